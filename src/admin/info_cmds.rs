@@ -1,9 +1,44 @@
 use std::collections::HashMap;
 
+use crate::storage::DiskInfo;
+
 // Information of underlying storage.
 pub struct StorageInfo {
     pub disks: Vec<Disk>,
     pub backend: BackendInfo,
+    // Usage of all the disks.
+    pub usage: StorageUsage,
+    // Usage of the disks of every pool, by pool index.
+    pub pools: Vec<StorageUsage>,
+}
+
+// Capacity and disk states of a set of disks.
+// Only online disks count towards the capacity.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct StorageUsage {
+    pub total: u64,
+    pub free: u64,
+    pub used: u64,
+    pub online_disks: usize,
+    pub offline_disks: usize,
+    // Online disks being healed.
+    pub healing_disks: usize,
+}
+
+impl StorageUsage {
+    fn add(&mut self, disk: &DiskInfo) {
+        if disk.error.is_some() {
+            self.offline_disks += 1;
+            return;
+        }
+        self.online_disks += 1;
+        if disk.healing {
+            self.healing_disks += 1;
+        }
+        self.total += disk.total;
+        self.free += disk.free;
+        self.used += disk.used;
+    }
 }
 
 // Contains info of the underlying backend.
@@ -25,7 +60,185 @@ pub struct BackendInfo {
     pub rr_sc_parity: usize, // Parity disks for currently configured Reduced Redundancy storage class.
 }
 
+// Number of disks by endpoint host, local paths being under an empty host.
+#[derive(Default, Debug, PartialEq, Eq)]
 pub struct BackendDisks(HashMap<String, usize>);
 
+impl BackendDisks {
+    pub fn get(&self, host: &str) -> usize {
+        self.0.get(host).copied().unwrap_or(0)
+    }
+
+    pub fn sum(&self) -> usize {
+        self.0.values().sum()
+    }
+}
+
 // Disk information.
-pub struct Disk {}
+pub struct Disk {
+    pub endpoint: String,
+    pub drive_path: String,
+    pub root_disk: bool,
+    pub healing: bool,
+    // Either "ok" or the error of an offline disk.
+    pub state: String,
+    pub uuid: String,
+    pub pool_index: isize,
+    pub total_space: u64,
+    pub used_space: u64,
+    pub available_space: u64,
+}
+
+const DISK_STATE_OK: &str = "ok";
+
+impl From<&DiskInfo> for Disk {
+    fn from(info: &DiskInfo) -> Self {
+        Disk {
+            endpoint: info.endpoint.clone(),
+            drive_path: info.mount_path.clone(),
+            root_disk: info.root_disk,
+            healing: info.healing,
+            state: info
+                .error
+                .clone()
+                .unwrap_or_else(|| DISK_STATE_OK.to_owned()),
+            uuid: info.id.clone(),
+            pool_index: info.pool_index,
+            total_space: info.total,
+            used_space: info.used,
+            available_space: info.free,
+        }
+    }
+}
+
+fn endpoint_host(endpoint: &str) -> String {
+    url::Url::parse(endpoint)
+        .ok()
+        .and_then(|url| {
+            url.host_str().map(|host| match url.port() {
+                Some(port) => format!("{}:{}", host, port),
+                None => host.to_owned(),
+            })
+        })
+        .unwrap_or_default()
+}
+
+/// Aggregates the per-disk information of an erasure backend, disks with an
+/// error being offline. The storage class fields of the backend are left to
+/// the caller, which knows the set layout.
+pub fn aggregate_storage_info(disks: &[DiskInfo]) -> StorageInfo {
+    let mut usage = StorageUsage::default();
+    let mut pools: Vec<StorageUsage> = Vec::new();
+    let mut online_disks = BackendDisks::default();
+    let mut offline_disks = BackendDisks::default();
+    for disk in disks {
+        usage.add(disk);
+        if disk.pool_index >= 0 {
+            let pool_index = disk.pool_index as usize;
+            if pools.len() <= pool_index {
+                pools.resize(pool_index + 1, StorageUsage::default());
+            }
+            pools[pool_index].add(disk);
+        }
+        let by_host = if disk.error.is_some() {
+            &mut offline_disks
+        } else {
+            &mut online_disks
+        };
+        *by_host.0.entry(endpoint_host(&disk.endpoint)).or_default() += 1;
+    }
+    StorageInfo {
+        disks: disks.iter().map(Disk::from).collect(),
+        backend: BackendInfo {
+            type_: crate::object::BackendType::Erasure,
+            gateway_online: false,
+            online_disks,
+            offline_disks,
+            standard_sc_data: Vec::new(),
+            standard_sc_parity: 0,
+            rr_sc_data: Vec::new(),
+            rr_sc_parity: 0,
+        },
+        usage,
+        pools,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disk_info(endpoint: &str, pool_index: isize, total: u64, used: u64) -> DiskInfo {
+        DiskInfo {
+            total,
+            free: total - used,
+            used,
+            used_inodes: 0,
+            free_inodes: 0,
+            fs_type: "xfs".to_owned(),
+            root_disk: false,
+            healing: false,
+            endpoint: endpoint.to_owned(),
+            mount_path: "/data".to_owned(),
+            id: uuid::Uuid::new_v4().to_string(),
+            pool_index,
+            metrics: None,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_aggregate_storage_info() {
+        let healthy = disk_info("http://node1:9000/data1", 0, 100, 40);
+        let mut faulty = disk_info("http://node1:9000/data2", 0, 100, 10);
+        faulty.error = Some("drive not found".to_owned());
+        let mut healing = disk_info("http://node2:9000/data1", 1, 200, 50);
+        healing.healing = true;
+        let other = disk_info("http://node2:9000/data2", 1, 200, 100);
+
+        let info = aggregate_storage_info(&[healthy, faulty, healing, other]);
+        assert_eq!(
+            info.usage,
+            StorageUsage {
+                total: 500,
+                free: 310,
+                used: 190,
+                online_disks: 3,
+                offline_disks: 1,
+                healing_disks: 1,
+            }
+        );
+        assert_eq!(
+            info.pools,
+            vec![
+                StorageUsage {
+                    total: 100,
+                    free: 60,
+                    used: 40,
+                    online_disks: 1,
+                    offline_disks: 1,
+                    healing_disks: 0,
+                },
+                StorageUsage {
+                    total: 400,
+                    free: 250,
+                    used: 150,
+                    online_disks: 2,
+                    offline_disks: 0,
+                    healing_disks: 1,
+                },
+            ]
+        );
+        assert_eq!(info.backend.online_disks.get("node1:9000"), 1);
+        assert_eq!(info.backend.online_disks.get("node2:9000"), 2);
+        assert_eq!(info.backend.offline_disks.get("node1:9000"), 1);
+        assert_eq!(info.backend.offline_disks.sum(), 1);
+        assert_eq!(info.disks.len(), 4);
+        assert_eq!(info.disks[0].state, DISK_STATE_OK);
+        assert_eq!(info.disks[1].state, "drive not found");
+
+        let info = aggregate_storage_info(&[]);
+        assert_eq!(info.usage, StorageUsage::default());
+        assert!(info.pools.is_empty());
+    }
+}
@@ -1,7 +1,9 @@
 mod config_history_cmds;
 mod info_cmds;
+mod scan;
 mod trace;
 
 pub use config_history_cmds::*;
 pub use info_cmds::*;
+pub use scan::*;
 pub use trace::*;
@@ -0,0 +1,234 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use anyhow::anyhow;
+use lazy_static::lazy_static;
+use serde::Serialize;
+
+use crate::utils;
+
+pub type ScanFuture = Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send>>;
+
+// Scans the namespace, reporting its progress to the given state.
+pub type ScanWorker = Arc<dyn Fn(Arc<ScanState>) -> ScanFuture + Send + Sync>;
+
+lazy_static! {
+    static ref SCANS: Scans = Scans::default();
+    static ref SCAN_WORKER: RwLock<Option<ScanWorker>> = RwLock::new(None);
+}
+
+/// Progress of a data scan.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanProgress {
+    pub buckets_done: u64,
+    pub objects_scanned: u64,
+    pub bytes_scanned: u64,
+    pub started_at: utils::DateTime,
+    pub done: bool,
+}
+
+// State of a scan, shared with the worker updating it.
+pub struct ScanState {
+    id: String,
+    started_at: utils::DateTime,
+    buckets_done: AtomicU64,
+    objects_scanned: AtomicU64,
+    bytes_scanned: AtomicU64,
+    done: AtomicBool,
+}
+
+impl ScanState {
+    fn new() -> ScanState {
+        ScanState {
+            id: uuid::Uuid::new_v4().to_string(),
+            started_at: utils::now(),
+            buckets_done: AtomicU64::new(0),
+            objects_scanned: AtomicU64::new(0),
+            bytes_scanned: AtomicU64::new(0),
+            done: AtomicBool::new(false),
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn bucket_done(&self) {
+        self.buckets_done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn object_scanned(&self, size: u64) {
+        self.objects_scanned.fetch_add(1, Ordering::Relaxed);
+        self.bytes_scanned.fetch_add(size, Ordering::Relaxed);
+    }
+
+    fn is_done(&self) -> bool {
+        self.done.load(Ordering::SeqCst)
+    }
+
+    pub fn progress(&self) -> ScanProgress {
+        ScanProgress {
+            buckets_done: self.buckets_done.load(Ordering::Relaxed),
+            objects_scanned: self.objects_scanned.load(Ordering::Relaxed),
+            bytes_scanned: self.bytes_scanned.load(Ordering::Relaxed),
+            started_at: self.started_at,
+            done: self.is_done(),
+        }
+    }
+}
+
+// Marks the scan done when dropped, even if its worker panicked.
+struct ScanDoneGuard(Arc<ScanState>);
+
+impl Drop for ScanDoneGuard {
+    fn drop(&mut self) {
+        self.0.done.store(true, Ordering::SeqCst);
+    }
+}
+
+// Runs at most one scan at a time, keeping the state of the latest one.
+#[derive(Default)]
+pub struct Scans {
+    latest: Mutex<Option<Arc<ScanState>>>,
+}
+
+impl Scans {
+    // Starts a scan with `worker` and returns its id, or the id of the scan
+    // in progress if any.
+    pub fn start(&self, worker: &ScanWorker) -> String {
+        let mut latest = self.latest.lock().unwrap();
+        if let Some(state) = latest.as_ref().filter(|state| !state.is_done()) {
+            return state.id.clone();
+        }
+        let state = Arc::new(ScanState::new());
+        *latest = Some(state.clone());
+        let done = ScanDoneGuard(state.clone());
+        let scan = worker(state.clone());
+        tokio::spawn(async move {
+            let _done = done;
+            if let Err(err) = scan.await {
+                crate::error!("data scan '{}' failed: {}", state.id, err);
+            }
+        });
+        state.id.clone()
+    }
+
+    pub fn progress(&self, id: &str) -> Option<ScanProgress> {
+        self.latest
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|state| state.id == id)
+            .map(|state| state.progress())
+    }
+}
+
+/// Sets the worker scanning the namespace on `start_scan`.
+pub fn set_scan_worker(worker: ScanWorker) {
+    *SCAN_WORKER.write().unwrap() = Some(worker);
+}
+
+/// Starts a data scan, returning its id. If a scan is already in progress,
+/// returns its id instead.
+pub fn start_scan() -> anyhow::Result<String> {
+    let worker = SCAN_WORKER.read().unwrap();
+    let worker = worker
+        .as_ref()
+        .ok_or_else(|| anyhow!("data scanner is not initialized"))?;
+    Ok(SCANS.start(worker))
+}
+
+/// Returns the progress of the scan, only the latest scan is tracked.
+pub fn scan_progress(id: &str) -> Option<ScanProgress> {
+    SCANS.progress(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::Notify;
+
+    use super::*;
+
+    // Scans two buckets of 3 objects, waiting on `step` before each bucket.
+    fn mock_worker(step: Arc<Notify>) -> ScanWorker {
+        Arc::new(move |state: Arc<ScanState>| {
+            let step = step.clone();
+            Box::pin(async move {
+                for _ in 0..2 {
+                    step.notified().await;
+                    for size in [10, 20, 30] {
+                        state.object_scanned(size);
+                    }
+                    state.bucket_done();
+                }
+                Ok(())
+            })
+        })
+    }
+
+    async fn wait_progress(scans: &Scans, id: &str, buckets_done: u64) -> ScanProgress {
+        loop {
+            let progress = scans.progress(id).unwrap();
+            if progress.buckets_done == buckets_done {
+                return progress;
+            }
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scan_progress() {
+        let scans = Scans::default();
+        let step = Arc::new(Notify::new());
+        let worker = mock_worker(step.clone());
+
+        let id = scans.start(&worker);
+        let progress = scans.progress(&id).unwrap();
+        assert_eq!(progress.objects_scanned, 0);
+        assert!(!progress.done);
+        assert_eq!(scans.progress("unknown"), None);
+
+        step.notify_one();
+        let progress = wait_progress(&scans, &id, 1).await;
+        assert_eq!(progress.objects_scanned, 3);
+        assert_eq!(progress.bytes_scanned, 60);
+
+        // Only one scan at a time.
+        assert_eq!(scans.start(&worker), id);
+
+        step.notify_one();
+        wait_progress(&scans, &id, 2).await;
+        while !scans.progress(&id).unwrap().done {
+            tokio::task::yield_now().await;
+        }
+        let progress = scans.progress(&id).unwrap();
+        assert_eq!(progress.objects_scanned, 6);
+        assert_eq!(progress.bytes_scanned, 120);
+
+        // A new scan once done.
+        let new_id = scans.start(&worker);
+        assert_ne!(new_id, id);
+        assert_eq!(scans.progress(&id), None);
+        assert_eq!(scans.progress(&new_id).unwrap().objects_scanned, 0);
+    }
+
+    async fn panicking_scan() -> anyhow::Result<()> {
+        panic!("scan failed")
+    }
+
+    #[tokio::test]
+    async fn test_scan_worker_panic() {
+        let scans = Scans::default();
+        let worker: ScanWorker = Arc::new(|_| Box::pin(panicking_scan()));
+
+        let id = scans.start(&worker);
+        while !scans.progress(&id).unwrap().done {
+            tokio::task::yield_now().await;
+        }
+        // The panicked scan does not block new ones.
+        assert_ne!(scans.start(&worker), id);
+    }
+}
@@ -1,10 +1,12 @@
 use actix_web::http::{HeaderMap, StatusCode};
 use derivative::Derivative;
 
+use crate::globals::GLOBALS;
+use crate::pubsub::BoundedReceiver;
 use crate::utils;
 use crate::utils::Duration;
 
-#[derive(Clone, Derivative)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Derivative)]
 #[derivative(Default)]
 pub enum TraceType {
     #[derivative(Default)]
@@ -70,3 +72,129 @@ pub struct TraceOsStats {
     pub path: String,
     pub duration: Duration,
 }
+
+impl TraceInfo {
+    // Path of the traced operation, if any.
+    pub fn path(&self) -> Option<&str> {
+        match self.trace_type {
+            TraceType::Http => self.req_info.as_ref().map(|r| r.path.as_str()),
+            TraceType::Os => self.os_stats.as_ref().map(|s| s.path.as_str()),
+            TraceType::Storage => self.storage_stats.as_ref().map(|s| s.path.as_str()),
+        }
+    }
+
+    // Duration of the traced operation, if any.
+    pub fn duration(&self) -> Option<Duration> {
+        match self.trace_type {
+            TraceType::Http => self.call_stats.as_ref().map(|s| s.latency),
+            TraceType::Os => self.os_stats.as_ref().map(|s| s.duration),
+            TraceType::Storage => self.storage_stats.as_ref().map(|s| s.duration),
+        }
+    }
+}
+
+// Filter of the traces sent to a subscriber, an empty `types` matches all types.
+#[derive(Clone, Default, Debug)]
+pub struct TraceFilter {
+    pub types: Vec<TraceType>,
+    pub path_prefix: Option<String>,
+    pub min_duration: Option<Duration>,
+}
+
+impl TraceFilter {
+    pub fn matches(&self, info: &TraceInfo) -> bool {
+        if !self.types.is_empty() && !self.types.contains(&info.trace_type) {
+            return false;
+        }
+        if let Some(prefix) = &self.path_prefix {
+            if !info
+                .path()
+                .map_or(false, |p| p.starts_with(prefix.as_str()))
+            {
+                return false;
+            }
+        }
+        if let Some(min_duration) = self.min_duration {
+            if info.duration().unwrap_or_default() < min_duration {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Subscribes to the traces matching `filter`, the rest are dropped
+/// before reaching the subscriber.
+pub fn subscribe_trace(filter: TraceFilter, capacity: usize) -> BoundedReceiver<TraceInfo> {
+    GLOBALS
+        .trace
+        .subscribe_bounded(move |info| filter.matches(info), capacity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pubsub::PubSub;
+
+    fn storage_trace(path: &str, duration: Duration) -> TraceInfo {
+        TraceInfo {
+            trace_type: TraceType::Storage,
+            storage_stats: Some(TraceStorageStats {
+                path: path.to_owned(),
+                duration,
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn os_trace(path: &str, duration: Duration) -> TraceInfo {
+        TraceInfo {
+            trace_type: TraceType::Os,
+            os_stats: Some(TraceOsStats {
+                path: path.to_owned(),
+                duration,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_trace_filter() {
+        let filter = TraceFilter {
+            types: vec![TraceType::Storage],
+            ..Default::default()
+        };
+        assert!(filter.matches(&storage_trace("bucket/object", Duration::ZERO)));
+        assert!(!filter.matches(&os_trace("bucket/object", Duration::ZERO)));
+        assert!(!filter.matches(&TraceInfo::default()));
+
+        let filter = TraceFilter {
+            path_prefix: Some("bucket/".to_owned()),
+            min_duration: Some(Duration::from_millis(10)),
+            ..Default::default()
+        };
+        assert!(filter.matches(&os_trace("bucket/object", Duration::from_millis(20))));
+        assert!(!filter.matches(&os_trace("bucket/object", Duration::from_millis(5))));
+        assert!(!filter.matches(&storage_trace("other/object", Duration::from_millis(20))));
+    }
+
+    #[tokio::test]
+    async fn test_trace_filter_subscribe() {
+        let ps = PubSub::new(16);
+        let filter = TraceFilter {
+            types: vec![TraceType::Storage],
+            min_duration: Some(Duration::from_millis(10)),
+            ..Default::default()
+        };
+        let mut rx = ps.subscribe_bounded(move |info| filter.matches(info), 8);
+
+        ps.publish(os_trace("slow", Duration::from_millis(20)));
+        ps.publish(storage_trace("fast", Duration::from_millis(1)));
+        ps.publish(storage_trace("slow", Duration::from_millis(20)));
+
+        let info = rx.recv().await.unwrap();
+        assert_eq!(info.trace_type, TraceType::Storage);
+        assert_eq!(info.path(), Some("slow"));
+        assert_eq!(rx.dropped_count(), 0);
+    }
+}
@@ -0,0 +1,59 @@
+/// Compares two byte slices in constant time with respect to their content,
+/// so that secrets and signatures don't leak through timing.
+/// Only the lengths are compared in variable time.
+pub fn secure_compare(a: &[u8], b: &[u8]) -> bool {
+    constant_time_eq::constant_time_eq(a, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    #[test]
+    fn test_secure_compare() {
+        let cases: Vec<(&[u8], &[u8], bool)> = vec![
+            (b"", b"", true),
+            (b"a", b"a", true),
+            (b"hulkadmin", b"hulkadmin", true),
+            (b"hulkadmin", b"hulkadmiN", false),
+            (b"hulkadmin", b"Hulkadmin", false),
+            (b"hulkadmin", b"hulkadmin1", false),
+            (b"", b"a", false),
+        ];
+        for (a, b, expected) in cases {
+            assert_eq!(secure_compare(a, b), expected);
+            assert_eq!(secure_compare(b, a), expected);
+        }
+    }
+
+    #[test]
+    fn test_secure_compare_timing() {
+        const SIZE: usize = 1 << 20;
+        const ROUNDS: usize = 50;
+
+        let a = vec![0u8; SIZE];
+        let mut first = a.clone();
+        first[0] = 1;
+        let mut last = a.clone();
+        last[SIZE - 1] = 1;
+
+        // Take the fastest run of each to filter out scheduling noise.
+        let measure = |b: &[u8]| {
+            (0..ROUNDS)
+                .map(|_| {
+                    let start = Instant::now();
+                    assert!(!secure_compare(&a, b));
+                    start.elapsed()
+                })
+                .min()
+                .unwrap_or(Duration::ZERO)
+        };
+        let first = measure(&first).as_nanos() as f64;
+        let last = measure(&last).as_nanos() as f64;
+
+        let ratio = first.max(last) / first.min(last).max(1.0);
+        assert!(ratio < 3.0, "timing ratio {} too large", ratio);
+    }
+}
@@ -1,13 +1,13 @@
 use std::collections::HashMap;
 use std::fmt;
 
-use constant_time_eq::constant_time_eq;
 use jsonwebtoken::{encode, Algorithm, DecodingKey, EncodingKey, Header};
 use lazy_static::lazy_static;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use super::secure_compare;
 use crate::jwt::MapClaims;
 use crate::utils::DateTimeExt;
 use crate::{jwt, utils};
@@ -127,8 +127,8 @@ impl PartialEq for Credentials {
             return false;
         }
         self.access_key == other.access_key
-            && constant_time_eq(self.secret_key.as_bytes(), other.secret_key.as_bytes())
-            && constant_time_eq(
+            && secure_compare(self.secret_key.as_bytes(), other.secret_key.as_bytes())
+            && secure_compare(
                 self.session_token.as_bytes(),
                 other.session_token.as_bytes(),
             )
@@ -256,7 +256,9 @@ pub fn jwt_sign_with_access_key(
 }
 
 pub fn extract_claims(token: &str, secret_key: &str) -> anyhow::Result<MapClaims> {
-    jwt::parse_with_claims(token, |_| {
-        DecodingKey::from_secret(secret_key.as_bytes()).into_static()
-    })
+    jwt::parse_with_claims(
+        token,
+        |_| DecodingKey::from_secret(secret_key.as_bytes()).into_static(),
+        *jwt::JWT_LEEWAY,
+    )
 }
@@ -1,3 +1,5 @@
+mod compare;
 mod credentials;
 
+pub use compare::*;
 pub use credentials::*;
@@ -16,6 +16,11 @@ pub async fn handle_common_cli_args(m: &ArgMatches) {
     global_cli_context.json = m
         .value_of("json")
         .map_or(false, |v| v.parse::<bool>().unwrap());
+    hulk::logger::init(if global_cli_context.json {
+        hulk::logger::LogFormat::Json
+    } else {
+        hulk::logger::LogFormat::Text
+    });
     global_cli_context.strict_s3_compatibility = m
         .value_of("no-s3-compatibility")
         .map_or(false, |v| v.parse::<bool>().unwrap());
@@ -88,7 +93,32 @@ pub async fn handle_common_env_vars() {
             domain_ips.add(endpoint);
         }
     }
-    hulk::endpoint::update_domain_ips(&domain_ips);
+    let domain_cidrs = std::env::var(config::ENV_DOMAIN_CIDRS).unwrap_or_default();
+    if !domain_cidrs.is_empty() {
+        *GLOBALS.domain_cidrs.guard() = domain_cidrs
+            .split(config::VALUE_SEPARATOR)
+            .map(|cidr| {
+                cidr.parse::<ipnet::IpNet>()
+                    .expect(&format!("Invalid {} env var", config::ENV_DOMAIN_CIDRS))
+            })
+            .collect();
+    }
+    hulk::endpoint::update_domain_ips(&domain_ips).await;
+
+    // Rejects an invalid leeway at startup rather than on the first token.
+    hulk::jwt::lookup_jwt_leeway().expect(&format!("Invalid {} env var", config::ENV_JWT_LEEWAY));
+
+    // Same for the shutdown grace period.
+    hulk::signals::lookup_shutdown_grace_period().expect(&format!(
+        "Invalid {} env var",
+        config::ENV_SHUTDOWN_GRACE_PERIOD
+    ));
+
+    // And for an unreadable cross domain policy file.
+    hulk::http::lookup_cross_domain_policy().expect(&format!(
+        "Invalid {} env var",
+        config::ENV_CROSSDOMAIN_POLICY
+    ));
 
     GLOBALS.inplace_update_disabled.set(
         !utils::parse_bool_ext(
@@ -89,7 +89,7 @@ pub async fn save_config(
         .put_object(
             object::SYSTEM_META_BUCKET,
             config_file,
-            &mut object::PutObjectReader {},
+            &mut object::PutObjectReader::new(hash_reader),
             Some(object::ObjectOptions {
                 max_parity: true,
                 ..Default::default()
@@ -34,8 +34,8 @@ impl EventHandler {
                     use Signal::*;
                     match signal {
                         Int | Term | Quit => {
-                            info!("Exiting on signal: {}", signal);
-                            exit(stop_process());
+                            // The server drains the in-flight requests and exits.
+                            info!("Shutting down on signal: {}", signal);
                         }
                         _ => {}
                     }
@@ -51,11 +51,3 @@ impl FromSignal for Event {
         Event::Signal(sig)
     }
 }
-
-fn exit(success: bool) {
-    std::process::exit(if success { 0 } else { 1 });
-}
-
-fn stop_process() -> bool {
-    true // TODO
-}
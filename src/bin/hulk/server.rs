@@ -3,7 +3,9 @@ use std::sync::Arc;
 use actix_web::{web, App, HttpResponse, HttpServer};
 use clap::ArgMatches;
 use hulk::globals::{self, Guard, ReadWriteGuard, GLOBALS};
+use hulk::signals;
 use rustls::{NoClientAuth, ResolvesServerCertUsingSNI, ServerConfig};
+use tokio_util::sync::CancellationToken;
 
 use super::*;
 
@@ -36,6 +38,9 @@ impl Server {
         let mut event_handler = EventHandler::new();
         let event_sender = event_handler.sender();
         tokio::spawn(async move { event_handler.handle_events().await });
+        let shutdown_token = CancellationToken::new();
+        let drained =
+            signals::install_graceful(shutdown_token.clone(), *signals::SHUTDOWN_GRACE_PERIOD);
 
         bitrot::bitrot_self_test();
         erasure::erasure_self_test();
@@ -67,11 +72,33 @@ impl Server {
         tokio::pin!(rpc_server);
 
         tokio::select! {
+            _ = shutdown_token.cancelled() => {
+                // Stop accepting new connections, then give the in-flight
+                // requests the grace period to complete before aborting them.
+                let graceful_stop = http_server.stop(true);
+                let _ = rpc_tx.send(());
+                // The RPC server drains its own calls, for the same grace period.
+                let (drained, rpc_stopped) = tokio::join!(
+                    drained,
+                    tokio::time::timeout(*signals::SHUTDOWN_GRACE_PERIOD, &mut rpc_server),
+                );
+                if !drained {
+                    hulk::warn!("Aborting in-flight requests after the shutdown grace period");
+                }
+                if rpc_stopped.is_err() {
+                    hulk::warn!("Aborting RPC calls after the shutdown grace period");
+                }
+                drop(graceful_stop);
+                http_server.stop(false).await;
+            }
             _ = &mut http_server => {
                 let _ = rpc_tx.send(());
                 let _ = rpc_server.await;
             }
-            _ = &mut rpc_server => {
+            res = &mut rpc_server => {
+                if let Err(err) = res {
+                    hulk::error!("RPC server failed: {}", err);
+                }
                 http_server.stop(true).await;
                 let _ = http_server.await;
             }
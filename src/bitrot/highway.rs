@@ -189,6 +189,7 @@ impl<'a> HighwayBitrotReader<'a> {
                             &path,
                             stream_offset,
                             till_offset - stream_offset,
+                            None,
                         )
                         .await
                 } else {
@@ -58,27 +58,156 @@ impl BitrotAlgorithm {
     }
 }
 
+/// Writes `hash(shard) || shard` for each `shard_size` block of the data, as
+/// read back by `bitrot_verify`. Data is buffered up to the shard size, so
+/// writes need not be aligned on shards. The last shard, which may be partial,
+/// is written on shutdown.
+pub struct BitrotWriter<W> {
+    writer: W,
+    hasher: Box<dyn BitrotHasher + Unpin>,
+    shard_size: usize,
+    // Data of the current shard.
+    shard: Vec<u8>,
+    // Hash and data of the last full shard, not fully written yet.
+    pending: Vec<u8>,
+    written: usize,
+}
+
+impl<W: AsyncWrite + Unpin> BitrotWriter<W> {
+    pub fn new(writer: W, shard_size: usize, algo: BitrotAlgorithm) -> Self {
+        BitrotWriter {
+            writer,
+            hasher: algo.hasher(),
+            shard_size,
+            shard: Vec::with_capacity(shard_size),
+            pending: Vec::with_capacity(algo.output_size() + shard_size),
+            written: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn seal_shard(&mut self) {
+        self.hasher.reset();
+        self.hasher.append(&self.shard);
+        self.pending.extend_from_slice(self.hasher.finish());
+        self.pending.extend_from_slice(&self.shard);
+        self.shard.clear();
+    }
+
+    fn poll_write_pending(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        while self.written < self.pending.len() {
+            let n =
+                ready!(Pin::new(&mut self.writer).poll_write(cx, &self.pending[self.written..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(std::io::ErrorKind::WriteZero.into()));
+            }
+            self.written += n;
+        }
+        self.pending.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for BitrotWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, Error>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_pending(cx))?;
+        let n = buf.len().min(this.shard_size - this.shard.len());
+        this.shard.extend_from_slice(&buf[..n]);
+        if this.shard.len() == this.shard_size {
+            this.seal_shard();
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_pending(cx))?;
+        Pin::new(&mut this.writer).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_pending(cx))?;
+        if !this.shard.is_empty() {
+            this.seal_shard();
+            ready!(this.poll_write_pending(cx))?;
+        }
+        Pin::new(&mut this.writer).poll_shutdown(cx)
+    }
+}
+
 pub struct BitrotVerifier {
     pub algorithm: BitrotAlgorithm,
     pub hash: [u8; 32],
 }
 
+/// Returns the size of a bitrot protected shard file holding `size` bytes of
+/// shard data, i.e. the data plus the hash of each `shard_size` block.
+pub fn bitrot_shard_file_size(size: u64, shard_size: u64, algo: BitrotAlgorithm) -> u64 {
+    crate::utils::ceil_frac(size, shard_size) * (algo.output_size() as u64) + size
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BitrotError {
+    #[error("bitrot file size mismatch, expected {want} but found {got}")]
+    SizeMismatch { want: u64, got: u64 },
+    #[error("bitrot hash mismatch of shard {shard} at offset {offset}")]
+    HashMismatch { shard: u64, offset: u64 },
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl From<std::io::Error> for BitrotError {
+    fn from(err: std::io::Error) -> Self {
+        BitrotError::Other(err.into())
+    }
+}
+
 pub async fn bitrot_verify<R: AsyncRead + Unpin>(
-    mut reader: R,
+    reader: R,
+    want_size: u64,
+    part_size: u64,
+    algo: BitrotAlgorithm,
+    want: &[u8],
+    shard_size: u64,
+) -> anyhow::Result<()> {
+    match bitrot_verify_detailed(reader, want_size, part_size, algo, want, shard_size).await {
+        Ok(()) => Ok(()),
+        Err(BitrotError::Other(err)) => Err(err),
+        Err(_) => Err(StorageError::FileCorrupt.into()),
+    }
+}
+
+/// Like `bitrot_verify`, but reports where the first corrupted shard is: its
+/// index, and its offset in the shard data, i.e. without the hashes.
+pub async fn bitrot_verify_detailed<R: AsyncRead + Unpin>(
+    reader: R,
     want_size: u64,
     part_size: u64,
     algo: BitrotAlgorithm,
     _want: &[u8],
     mut shard_size: u64,
-) -> anyhow::Result<()> {
+) -> Result<(), BitrotError> {
     let mut reader = Some(reader);
 
     // Calculate the size of the bitrot file and compare
     // it with the actual file size.
-    if want_size
-        != crate::utils::ceil_frac(part_size, shard_size) * (algo.output_size() as u64) + part_size
-    {
-        return Err(StorageError::FileCorrupt.into());
+    let expected_size = bitrot_shard_file_size(part_size, shard_size, algo);
+    if want_size != expected_size {
+        // `want_size` is the size of the file read.
+        return Err(BitrotError::SizeMismatch {
+            want: expected_size,
+            got: want_size,
+        });
     }
 
     let mut hasher = algo.hasher();
@@ -86,6 +215,8 @@ pub async fn bitrot_verify<R: AsyncRead + Unpin>(
     let mut buf_guard = Some(crate::xl_storage::XL_POOL_SMALL.get().await?);
 
     let mut left = want_size;
+    let mut shard = 0;
+    let mut offset = 0;
     while left > 0 {
         hasher.reset();
         let n = reader.as_mut().unwrap().read_full(&mut hash_buf).await?;
@@ -108,9 +239,185 @@ pub async fn bitrot_verify<R: AsyncRead + Unpin>(
         buf_guard.insert(g);
 
         if hasher.finish() != &hash_buf {
-            return Err(StorageError::FileCorrupt.into());
+            return Err(BitrotError::HashMismatch { shard, offset });
         }
+        shard += 1;
+        offset += n;
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl AsyncWrite for SharedBuf {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<Result<usize, Error>> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bitrot_shard_file_size() {
+        let algo = DEFAULT_BITROT_ALGORITHM;
+        let block_size = 64 * 1024;
+        let erasure = crate::erasure::Erasure::new::<
+            fn() -> reed_solomon_erasure::galois_8::ReedSolomon,
+        >(4, 2, block_size)
+        .unwrap();
+        let shard_size = erasure.shard_size() as u64;
+
+        for &size in &[1, 4 * 1024, block_size as u64, 3 * block_size as u64 + 4097] {
+            let shard_file_size = erasure.shard_file_size(size) as u64;
+            let want_size = bitrot_shard_file_size(shard_file_size, shard_size, algo);
+
+            // Write the shard of one disk block by block, as the erasure encoding does.
+            let buf = SharedBuf::default();
+            let mut writer = HighwayBitrotWriter::new(Box::new(buf.clone()));
+            let mut left = size;
+            while left > 0 {
+                let block = left.min(block_size as u64);
+                let shard = crate::utils::ceil_frac(block, 4) as usize;
+                writer.write_all(&vec![7u8; shard]).await.unwrap();
+                left -= block;
+            }
+            let written = buf.0.lock().unwrap().clone();
+            assert_eq!(written.len() as u64, want_size, "size {}", size);
+
+            bitrot_verify(
+                std::io::Cursor::new(written),
+                want_size,
+                shard_file_size,
+                algo,
+                &[],
+                shard_size,
+            )
+            .await
+            .unwrap();
+        }
+        assert_eq!(bitrot_shard_file_size(0, shard_size, algo), 0);
+    }
+
+    #[tokio::test]
+    async fn test_bitrot_verify_detailed() {
+        let algo = DEFAULT_BITROT_ALGORITHM;
+        let shard_size = 1024u64;
+        let size = 4 * shard_size + 904;
+
+        let buf = SharedBuf::default();
+        let mut writer = HighwayBitrotWriter::new(Box::new(buf.clone()));
+        let data: Vec<u8> = (0..size).map(|i| i as u8).collect();
+        for shard in data.chunks(shard_size as usize) {
+            writer.write_all(shard).await.unwrap();
+        }
+        let mut written = buf.0.lock().unwrap().clone();
+        let want_size = written.len() as u64;
+
+        let hash_size = algo.output_size() as u64;
+        written[(2 * (hash_size + shard_size) + hash_size + 10) as usize] ^= 0xff;
+        let err = bitrot_verify_detailed(
+            std::io::Cursor::new(&written),
+            want_size,
+            size,
+            algo,
+            &[],
+            shard_size,
+        )
+        .await
+        .unwrap_err();
+        assert!(
+            matches!(err, BitrotError::HashMismatch { shard: 2, offset } if offset == 2 * shard_size),
+            "{}",
+            err
+        );
+
+        let err = bitrot_verify_detailed(
+            std::io::Cursor::new(&written),
+            want_size - 1,
+            size,
+            algo,
+            &[],
+            shard_size,
+        )
+        .await
+        .unwrap_err();
+        assert!(
+            matches!(err, BitrotError::SizeMismatch { want, got } if want == want_size && got == want_size - 1),
+            "{}",
+            err
+        );
+
+        let err = bitrot_verify(
+            std::io::Cursor::new(&written),
+            want_size,
+            size,
+            algo,
+            &[],
+            shard_size,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<StorageError>(),
+            Some(&StorageError::FileCorrupt)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bitrot_writer() {
+        let algo = DEFAULT_BITROT_ALGORITHM;
+        let shard_size = 1024;
+        for &size in &[0usize, 100, shard_size, 3 * shard_size + 500] {
+            let data: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+            let mut writer = BitrotWriter::new(Vec::new(), shard_size, algo);
+            // Writes not aligned on shards.
+            for chunk in data.chunks(300) {
+                writer.write_all(chunk).await.unwrap();
+            }
+            writer.shutdown().await.unwrap();
+            let written = writer.into_inner();
+
+            let want_size = bitrot_shard_file_size(size as u64, shard_size as u64, algo);
+            assert_eq!(written.len() as u64, want_size, "size {}", size);
+            bitrot_verify(
+                std::io::Cursor::new(&written),
+                want_size,
+                size as u64,
+                algo,
+                &[],
+                shard_size as u64,
+            )
+            .await
+            .unwrap();
+
+            // Same format as the highway writer, given whole shards.
+            let buf = SharedBuf::default();
+            let mut highway_writer = HighwayBitrotWriter::new(Box::new(buf.clone()));
+            for shard in data.chunks(shard_size) {
+                highway_writer.write_all(shard).await.unwrap();
+            }
+            assert!(*buf.0.lock().unwrap() == written, "size {}", size);
+        }
+    }
+}
@@ -13,6 +13,10 @@ pub const TRANSITIONED_OBJECT_NAME: &str = "transitioned-object";
 pub const TRANSITIONED_VERSION_ID: &str = "transitioned-versionID";
 pub const TRANSITION_TIER: &str = "transition-tier";
 
+// Upper bound of the days of lifecycle actions, so that expiry times never
+// overflow.
+const MAX_LIFECYCLE_DAYS: i64 = 100_000;
+
 #[derive(Display)]
 pub enum TransitionStatus {
     #[strum(serialize = "complete")]
@@ -229,3 +233,424 @@ pub fn is_restored_object_on_disk(meta: &HashMap<String, String>) -> bool {
         None => false,
     }
 }
+
+// Max number of rules in a lifecycle configuration.
+const MAX_LIFECYCLE_RULES: usize = 1000;
+
+const RULE_STATUS_ENABLED: &str = "Enabled";
+const RULE_STATUS_DISABLED: &str = "Disabled";
+
+/// Action to apply on an object or upload by the lifecycle rules of its bucket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LifecycleAction {
+    None,
+    // Deletes the latest version, i.e. adds a delete marker if versioned.
+    Delete,
+    // Permanently deletes the version.
+    DeleteVersion,
+    AbortMultipartUpload,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct LifecycleFilter {
+    #[serde(default)]
+    pub prefix: String,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct Expiration {
+    /// Days after the object creation to expire it.
+    pub days: Option<i64>,
+    /// RFC 3339 date to expire objects at.
+    pub date: Option<String>,
+    /// Whether to remove the delete markers with no noncurrent versions.
+    #[serde(default)]
+    pub expired_object_delete_marker: bool,
+}
+
+impl Expiration {
+    fn parsed_date(&self) -> anyhow::Result<Option<DateTime>> {
+        match &self.date {
+            Some(date) => Ok(Some(
+                chrono::DateTime::parse_from_rfc3339(date.trim())?.with_timezone(&chrono::Utc),
+            )),
+            None => Ok(None),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct NoncurrentVersionExpiration {
+    /// Days after a version became noncurrent to expire it.
+    pub noncurrent_days: i64,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct AbortIncompleteMultipartUpload {
+    /// Days after the upload initiation to abort it.
+    pub days_after_initiation: i64,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct LifecycleRule {
+    #[serde(rename = "ID", default)]
+    pub id: String,
+    pub status: String,
+    pub filter: Option<LifecycleFilter>,
+    // Deprecated in favor of the filter, still accepted by S3.
+    pub prefix: Option<String>,
+    pub expiration: Option<Expiration>,
+    pub noncurrent_version_expiration: Option<NoncurrentVersionExpiration>,
+    pub abort_incomplete_multipart_upload: Option<AbortIncompleteMultipartUpload>,
+}
+
+impl LifecycleRule {
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.id.len() > 255 {
+            bail!("lifecycle rule ID must not exceed 255 characters");
+        }
+        if self.status != RULE_STATUS_ENABLED && self.status != RULE_STATUS_DISABLED {
+            bail!("invalid lifecycle rule status '{}'", self.status);
+        }
+        if self.filter.is_some() && self.prefix.is_some() {
+            bail!("lifecycle rule must not have both a filter and a prefix");
+        }
+        if self.expiration.is_none()
+            && self.noncurrent_version_expiration.is_none()
+            && self.abort_incomplete_multipart_upload.is_none()
+        {
+            bail!("lifecycle rule must have at least one action");
+        }
+        if let Some(expiration) = &self.expiration {
+            let set = [
+                expiration.days.is_some(),
+                expiration.date.is_some(),
+                expiration.expired_object_delete_marker,
+            ];
+            if set.iter().filter(|&&set| set).count() != 1 {
+                bail!("expiration must have exactly one of days, date or expired object delete marker");
+            }
+            if matches!(expiration.days, Some(days) if !valid_days(days)) {
+                bail!(
+                    "expiration days must be a positive integer up to {}",
+                    MAX_LIFECYCLE_DAYS
+                );
+            }
+            if expiration.parsed_date().is_err() {
+                bail!("expiration date must be in RFC 3339 format");
+            }
+        }
+        if let Some(noncurrent) = &self.noncurrent_version_expiration {
+            if !valid_days(noncurrent.noncurrent_days) {
+                bail!(
+                    "noncurrent version expiration days must be a positive integer up to {}",
+                    MAX_LIFECYCLE_DAYS
+                );
+            }
+        }
+        if let Some(abort) = &self.abort_incomplete_multipart_upload {
+            if !valid_days(abort.days_after_initiation) {
+                bail!(
+                    "abort incomplete multipart upload days must be a positive integer up to {}",
+                    MAX_LIFECYCLE_DAYS
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn prefix(&self) -> &str {
+        match (&self.filter, &self.prefix) {
+            (Some(filter), _) => &filter.prefix,
+            (None, Some(prefix)) => prefix,
+            (None, None) => "",
+        }
+    }
+
+    fn applies_to(&self, object: &str) -> bool {
+        self.status == RULE_STATUS_ENABLED && object.starts_with(self.prefix())
+    }
+}
+
+/// Lifecycle configuration of a bucket.
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(rename = "LifecycleConfiguration", rename_all = "PascalCase")]
+pub struct Lifecycle {
+    #[serde(rename = "Rule", default)]
+    pub rules: Vec<LifecycleRule>,
+}
+
+/// State of an object version lifecycle rules are evaluated against.
+pub struct LifecycleObject<'a> {
+    pub name: &'a str,
+    pub mod_time: DateTime,
+    pub is_latest: bool,
+    pub delete_marker: bool,
+    pub num_versions: isize,
+    // Time the version became noncurrent, i.e. mod time of the next version.
+    pub successor_mod_time: DateTime,
+}
+
+impl<'a> From<&'a crate::object::ObjectInfo> for LifecycleObject<'a> {
+    fn from(info: &'a crate::object::ObjectInfo) -> Self {
+        LifecycleObject {
+            name: &info.name,
+            mod_time: info.mod_time,
+            is_latest: info.is_latest,
+            delete_marker: info.delete_marker,
+            num_versions: info.num_versions,
+            successor_mod_time: info.successor_mod_time,
+        }
+    }
+}
+
+fn valid_days(days: i64) -> bool {
+    (1..=MAX_LIFECYCLE_DAYS).contains(&days)
+}
+
+// Returns the midnight UTC following `days` days after `time`, as S3 rounds
+// expirations up to the next day.
+fn expected_expiry_time(time: DateTime, days: i64) -> DateTime {
+    (time + utils::ChronoDuration::days(days + 1))
+        .date()
+        .and_hms(0, 0, 0)
+}
+
+impl Lifecycle {
+    /// Parses and validates a lifecycle configuration XML.
+    pub fn parse(xml: &str) -> anyhow::Result<Lifecycle> {
+        let lifecycle: Lifecycle = quick_xml::de::from_str(xml)?;
+        if lifecycle.rules.is_empty() {
+            bail!("lifecycle configuration must have at least one rule");
+        }
+        if lifecycle.rules.len() > MAX_LIFECYCLE_RULES {
+            bail!(
+                "lifecycle configuration must not have more than {} rules",
+                MAX_LIFECYCLE_RULES
+            );
+        }
+        for rule in &lifecycle.rules {
+            rule.validate()?;
+        }
+        Ok(lifecycle)
+    }
+
+    /// Returns the action the rules require on the object version at `now`.
+    pub fn compute_action(
+        &self,
+        info: &crate::object::ObjectInfo,
+        now: DateTime,
+    ) -> LifecycleAction {
+        self.compute_object_action(&LifecycleObject::from(info), now)
+    }
+
+    pub fn compute_object_action(&self, obj: &LifecycleObject, now: DateTime) -> LifecycleAction {
+        if obj.name.is_empty() {
+            return LifecycleAction::None;
+        }
+        for rule in self.rules.iter().filter(|rule| rule.applies_to(obj.name)) {
+            if !obj.is_latest {
+                if let Some(noncurrent) = &rule.noncurrent_version_expiration {
+                    let noncurrent_since = obj.successor_mod_time;
+                    if !noncurrent_since.is_zero()
+                        && now >= expected_expiry_time(noncurrent_since, noncurrent.noncurrent_days)
+                    {
+                        return LifecycleAction::DeleteVersion;
+                    }
+                }
+                continue;
+            }
+            let expiration = match &rule.expiration {
+                Some(expiration) => expiration,
+                None => continue,
+            };
+            if obj.delete_marker {
+                // A delete marker alone is expired once no noncurrent version is left.
+                if expiration.expired_object_delete_marker && obj.num_versions == 1 {
+                    return LifecycleAction::DeleteVersion;
+                }
+                continue;
+            }
+            if let Ok(Some(date)) = expiration.parsed_date() {
+                if now >= date {
+                    return LifecycleAction::Delete;
+                }
+            }
+            if let Some(days) = expiration.days {
+                if now >= expected_expiry_time(obj.mod_time, days) {
+                    return LifecycleAction::Delete;
+                }
+            }
+        }
+        LifecycleAction::None
+    }
+
+    /// Returns the action the rules require on a multipart upload of
+    /// `object` initiated at `initiated`.
+    pub fn compute_upload_action(
+        &self,
+        object: &str,
+        initiated: DateTime,
+        now: DateTime,
+    ) -> LifecycleAction {
+        let expired = self
+            .rules
+            .iter()
+            .filter(|rule| rule.applies_to(object))
+            .filter_map(|rule| rule.abort_incomplete_multipart_upload.as_ref())
+            .any(|abort| now >= expected_expiry_time(initiated, abort.days_after_initiation));
+        if expired {
+            LifecycleAction::AbortMultipartUpload
+        } else {
+            LifecycleAction::None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::ChronoDuration;
+
+    const LIFECYCLE_XML: &str = r#"<LifecycleConfiguration>
+    <Rule>
+        <ID>logs</ID>
+        <Status>Enabled</Status>
+        <Filter><Prefix>logs/</Prefix></Filter>
+        <Expiration><Days>30</Days></Expiration>
+        <NoncurrentVersionExpiration><NoncurrentDays>7</NoncurrentDays></NoncurrentVersionExpiration>
+        <AbortIncompleteMultipartUpload><DaysAfterInitiation>3</DaysAfterInitiation></AbortIncompleteMultipartUpload>
+    </Rule>
+    <Rule>
+        <ID>tmp</ID>
+        <Status>Enabled</Status>
+        <Prefix>tmp/</Prefix>
+        <Expiration><Date>2021-06-01T00:00:00Z</Date></Expiration>
+    </Rule>
+    <Rule>
+        <ID>disabled</ID>
+        <Status>Disabled</Status>
+        <Expiration><Days>1</Days></Expiration>
+    </Rule>
+</LifecycleConfiguration>"#;
+
+    fn object(name: &str, mod_time: DateTime) -> LifecycleObject<'_> {
+        LifecycleObject {
+            name,
+            mod_time,
+            is_latest: true,
+            delete_marker: false,
+            num_versions: 1,
+            successor_mod_time: DateTime::zero(),
+        }
+    }
+
+    #[test]
+    fn test_lifecycle_parse() {
+        let lifecycle = Lifecycle::parse(LIFECYCLE_XML).unwrap();
+        assert_eq!(lifecycle.rules.len(), 3);
+        assert_eq!(lifecycle.rules[0].id, "logs");
+        assert_eq!(lifecycle.rules[0].prefix(), "logs/");
+        assert_eq!(lifecycle.rules[1].prefix(), "tmp/");
+        assert_eq!(lifecycle.rules[2].prefix(), "");
+
+        let invalid = [
+            "<LifecycleConfiguration></LifecycleConfiguration>",
+            "<LifecycleConfiguration><Rule><Status>On</Status><Expiration><Days>1</Days></Expiration></Rule></LifecycleConfiguration>",
+            "<LifecycleConfiguration><Rule><Status>Enabled</Status></Rule></LifecycleConfiguration>",
+            "<LifecycleConfiguration><Rule><Status>Enabled</Status><Expiration><Days>0</Days></Expiration></Rule></LifecycleConfiguration>",
+            "<LifecycleConfiguration><Rule><Status>Enabled</Status><Expiration><Days>9223372036854775807</Days></Expiration></Rule></LifecycleConfiguration>",
+            "<LifecycleConfiguration><Rule><Status>Enabled</Status><NoncurrentVersionExpiration><NoncurrentDays>100001</NoncurrentDays></NoncurrentVersionExpiration></Rule></LifecycleConfiguration>",
+            "<LifecycleConfiguration><Rule><Status>Enabled</Status><AbortIncompleteMultipartUpload><DaysAfterInitiation>100001</DaysAfterInitiation></AbortIncompleteMultipartUpload></Rule></LifecycleConfiguration>",
+            "<LifecycleConfiguration><Rule><Status>Enabled</Status><Expiration><Days>1</Days><Date>2021-06-01T00:00:00Z</Date></Expiration></Rule></LifecycleConfiguration>",
+            "<LifecycleConfiguration><Rule><Status>Enabled</Status><Expiration><Date>yesterday</Date></Expiration></Rule></LifecycleConfiguration>",
+            "not xml",
+        ];
+        for xml in invalid {
+            assert!(Lifecycle::parse(xml).is_err(), "{}", xml);
+        }
+    }
+
+    #[test]
+    fn test_lifecycle_compute_action() {
+        let lifecycle = Lifecycle::parse(LIFECYCLE_XML).unwrap();
+        let now = utils::now();
+        let days_ago = |days| now - ChronoDuration::days(days);
+
+        // Expiration by days.
+        let obj = object("logs/old", days_ago(40));
+        assert_eq!(
+            lifecycle.compute_object_action(&obj, now),
+            LifecycleAction::Delete
+        );
+        let obj = object("logs/new", days_ago(10));
+        assert_eq!(
+            lifecycle.compute_object_action(&obj, now),
+            LifecycleAction::None
+        );
+        // Not matching any enabled rule.
+        let obj = object("data/old", days_ago(400));
+        assert_eq!(
+            lifecycle.compute_object_action(&obj, now),
+            LifecycleAction::None
+        );
+
+        // Expiration by date.
+        let obj = object("tmp/file", days_ago(1));
+        assert_eq!(
+            lifecycle.compute_object_action(&obj, now),
+            LifecycleAction::Delete
+        );
+        let before = chrono::DateTime::parse_from_rfc3339("2021-05-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        assert_eq!(
+            lifecycle.compute_object_action(&obj, before),
+            LifecycleAction::None
+        );
+
+        // Noncurrent version expiration.
+        let mut obj = object("logs/versioned", days_ago(100));
+        obj.is_latest = false;
+        obj.successor_mod_time = days_ago(10);
+        assert_eq!(
+            lifecycle.compute_object_action(&obj, now),
+            LifecycleAction::DeleteVersion
+        );
+        obj.successor_mod_time = days_ago(2);
+        assert_eq!(
+            lifecycle.compute_object_action(&obj, now),
+            LifecycleAction::None
+        );
+
+        // Abort incomplete multipart uploads.
+        assert_eq!(
+            lifecycle.compute_upload_action("logs/upload", days_ago(5), now),
+            LifecycleAction::AbortMultipartUpload
+        );
+        assert_eq!(
+            lifecycle.compute_upload_action("logs/upload", days_ago(1), now),
+            LifecycleAction::None
+        );
+        assert_eq!(
+            lifecycle.compute_upload_action("tmp/upload", days_ago(5), now),
+            LifecycleAction::None
+        );
+    }
+
+    #[test]
+    fn test_expected_expiry_time() {
+        let time = chrono::DateTime::parse_from_rfc3339("2021-06-01T15:30:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let expected = chrono::DateTime::parse_from_rfc3339("2021-06-03T00:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        assert_eq!(expected_expiry_time(time, 1), expected);
+    }
+}
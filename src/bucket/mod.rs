@@ -1,6 +1,12 @@
 pub mod encryption;
 mod lifecycle;
+mod naming;
+mod object_lock;
 pub mod policy;
 pub mod replication;
+mod versioning;
 
 pub use lifecycle::*;
+pub use naming::*;
+pub use object_lock::*;
+pub use versioning::*;
@@ -0,0 +1,58 @@
+use crate::s3utils;
+
+/// Reports whether `name` is a valid S3 bucket name. With `strict`, only
+/// DNS compatible names (lowercase letters, digits, dots and hyphens) pass.
+pub fn is_valid_bucket_name(name: &str, strict: bool) -> bool {
+    if strict {
+        s3utils::check_valid_bucket_name_strict(name).is_ok()
+    } else {
+        s3utils::check_valid_bucket_name(name).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_bucket_name() {
+        let cases = vec![
+            // (name, valid, valid_strict)
+            ("lol", true, true),
+            ("my-bucket", true, true),
+            ("my.bucket.01", true, true),
+            ("1-bucket", true, true),
+            ("bucket_name", true, false),
+            ("bucket:name", true, false),
+            ("MyBucket", true, false),
+            ("", false, false),
+            ("  ", false, false),
+            ("ab", false, false),
+            (".bucket", false, false),
+            ("-bucket", false, false),
+            ("bucket.", false, false),
+            ("bucket-", false, false),
+            ("my..bucket", false, false),
+            ("my.-bucket", false, false),
+            ("my-.bucket", false, false),
+            ("192.168.1.1", false, false),
+            ("bucket/name", false, false),
+            ("bucket name", false, false),
+        ];
+        for (i, (name, valid, valid_strict)) in cases.into_iter().enumerate() {
+            assert_eq!(is_valid_bucket_name(name, false), valid, "test {}", i + 1);
+            assert_eq!(
+                is_valid_bucket_name(name, true),
+                valid_strict,
+                "test {}",
+                i + 1
+            );
+        }
+
+        let name = "a".repeat(63);
+        assert!(is_valid_bucket_name(&name, true));
+        let name = "a".repeat(64);
+        assert!(!is_valid_bucket_name(&name, false));
+        assert!(!is_valid_bucket_name(&name, true));
+    }
+}
@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+
+use crate::http::{
+    AMZ_OBJECT_LOCK_LEGAL_HOLD, AMZ_OBJECT_LOCK_MODE, AMZ_OBJECT_LOCK_RETAIN_UNTIL_DATE,
+};
+use crate::utils::{self, DateTime};
+
+const OBJECT_LOCK_ENABLED: &str = "Enabled";
+const LEGAL_HOLD_ON: &str = "ON";
+// Longest default retention periods.
+const MAX_RETENTION_DAYS: i64 = 36500;
+const MAX_RETENTION_YEARS: i64 = 100;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Display, EnumString, PartialEq, Eq)]
+pub enum RetentionMode {
+    // Deletion is allowed to the users with the bypass governance permission.
+    #[serde(rename = "GOVERNANCE")]
+    #[strum(serialize = "GOVERNANCE")]
+    Governance,
+    // Deletion is not allowed to anyone, including the root user.
+    #[serde(rename = "COMPLIANCE")]
+    #[strum(serialize = "COMPLIANCE")]
+    Compliance,
+}
+
+/// Retention of an object version.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Retention {
+    pub mode: RetentionMode,
+    pub retain_until: DateTime,
+}
+
+impl Retention {
+    /// Reads the retention from the object metadata, if any. Malformed
+    /// retention metadata is an error.
+    pub fn from_metadata(
+        user_defined: &HashMap<String, String>,
+    ) -> anyhow::Result<Option<Retention>> {
+        let (mode, retain_until) = match (
+            user_defined.get(AMZ_OBJECT_LOCK_MODE),
+            user_defined.get(AMZ_OBJECT_LOCK_RETAIN_UNTIL_DATE),
+        ) {
+            (None, None) => return Ok(None),
+            (Some(mode), Some(retain_until)) => (mode, retain_until),
+            _ => bail!("object lock mode and retain until date must be set together"),
+        };
+        let mode = RetentionMode::from_str(mode)
+            .map_err(|_| anyhow!("invalid object lock mode '{}'", mode))?;
+        let retain_until = chrono::DateTime::parse_from_rfc3339(retain_until)
+            .map_err(|_| anyhow!("invalid object lock retain until date '{}'", retain_until))?
+            .with_timezone(&chrono::Utc);
+        Ok(Some(Retention { mode, retain_until }))
+    }
+
+    pub fn is_active(&self, now: DateTime) -> bool {
+        now < self.retain_until
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum RetentionError {
+    #[error("object is under compliance retention until {0}")]
+    Compliance(DateTime),
+    #[error("object is under governance retention until {0}")]
+    Governance(DateTime),
+    #[error("object is under legal hold")]
+    LegalHold,
+    #[error("object has malformed retention metadata")]
+    Malformed,
+}
+
+/// Checks whether the object version with metadata `user_defined` can be
+/// deleted or overwritten at `now`. `bypass_governance` is to be set only if
+/// the request has the bypass governance retention header and the caller is
+/// allowed `s3:BypassGovernanceRetention`.
+pub fn check_retention(
+    user_defined: &HashMap<String, String>,
+    bypass_governance: bool,
+    now: DateTime,
+) -> Result<(), RetentionError> {
+    if user_defined
+        .get(AMZ_OBJECT_LOCK_LEGAL_HOLD)
+        .map_or(false, |legal_hold| legal_hold == LEGAL_HOLD_ON)
+    {
+        return Err(RetentionError::LegalHold);
+    }
+    // Malformed retention metadata is not taken as the absence of retention.
+    match Retention::from_metadata(user_defined).map_err(|_| RetentionError::Malformed)? {
+        Some(retention) if retention.is_active(now) => match retention.mode {
+            RetentionMode::Compliance => Err(RetentionError::Compliance(retention.retain_until)),
+            RetentionMode::Governance if !bypass_governance => {
+                Err(RetentionError::Governance(retention.retain_until))
+            }
+            RetentionMode::Governance => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Checks whether the object version can be deleted, see `check_retention`.
+pub fn can_delete(
+    info: &crate::object::ObjectInfo,
+    bypass_governance: bool,
+    now: DateTime,
+) -> Result<(), RetentionError> {
+    check_retention(&info.user_defined, bypass_governance, now)
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct DefaultRetention {
+    pub mode: RetentionMode,
+    pub days: Option<i64>,
+    pub years: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct ObjectLockRule {
+    pub default_retention: DefaultRetention,
+}
+
+/// Object lock configuration of a bucket.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename = "ObjectLockConfiguration", rename_all = "PascalCase")]
+pub struct ObjectLockConfig {
+    pub object_lock_enabled: String,
+    pub rule: Option<ObjectLockRule>,
+}
+
+impl ObjectLockConfig {
+    /// Parses and validates an object lock configuration XML.
+    pub fn parse(xml: &str) -> anyhow::Result<ObjectLockConfig> {
+        let config: ObjectLockConfig = quick_xml::de::from_str(xml)?;
+        if config.object_lock_enabled != OBJECT_LOCK_ENABLED {
+            bail!("only 'Enabled' value is allowed to ObjectLockEnabled element");
+        }
+        if let Some(rule) = &config.rule {
+            let retention = &rule.default_retention;
+            match (retention.days, retention.years) {
+                (Some(days), None) if (1..=MAX_RETENTION_DAYS).contains(&days) => {}
+                (None, Some(years)) if (1..=MAX_RETENTION_YEARS).contains(&years) => {}
+                (Some(_), Some(_)) => bail!("either Days or Years must be specified, not both"),
+                _ => bail!(
+                    "default retention period must be a positive integer up to {} days or {} years",
+                    MAX_RETENTION_DAYS,
+                    MAX_RETENTION_YEARS
+                ),
+            }
+        }
+        Ok(config)
+    }
+
+    /// Returns the retention of an object version created at `now` without
+    /// explicit retention.
+    pub fn default_retention(&self, now: DateTime) -> Option<Retention> {
+        let retention = &self.rule.as_ref()?.default_retention;
+        let period = match (retention.days, retention.years) {
+            (Some(days), _) => utils::ChronoDuration::days(days),
+            (None, Some(years)) => utils::ChronoDuration::days(365 * years),
+            (None, None) => return None,
+        };
+        Some(Retention {
+            mode: retention.mode,
+            retain_until: now + period,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches::assert_matches;
+
+    use super::*;
+    use crate::utils::ChronoDuration;
+
+    fn metadata(mode: &str, retain_until: DateTime) -> HashMap<String, String> {
+        let mut user_defined = HashMap::new();
+        user_defined.insert(AMZ_OBJECT_LOCK_MODE.to_owned(), mode.to_owned());
+        user_defined.insert(
+            AMZ_OBJECT_LOCK_RETAIN_UNTIL_DATE.to_owned(),
+            retain_until.to_rfc3339(),
+        );
+        user_defined
+    }
+
+    #[test]
+    fn test_check_retention() {
+        let now = utils::now();
+        let future = now + ChronoDuration::days(1);
+        let past = now - ChronoDuration::days(1);
+
+        // No retention.
+        assert_eq!(check_retention(&HashMap::new(), false, now), Ok(()));
+
+        // Compliance, even with bypass.
+        let user_defined = metadata("COMPLIANCE", future);
+        for bypass in [false, true] {
+            assert_matches!(
+                check_retention(&user_defined, bypass, now),
+                Err(RetentionError::Compliance(_))
+            );
+        }
+
+        // Governance, unless bypassed.
+        let user_defined = metadata("GOVERNANCE", future);
+        assert_matches!(
+            check_retention(&user_defined, false, now),
+            Err(RetentionError::Governance(_))
+        );
+        assert_eq!(check_retention(&user_defined, true, now), Ok(()));
+
+        // Expired retention.
+        for mode in ["COMPLIANCE", "GOVERNANCE"] {
+            let user_defined = metadata(mode, past);
+            assert_eq!(check_retention(&user_defined, false, now), Ok(()));
+        }
+
+        // Legal hold.
+        let mut user_defined = HashMap::new();
+        user_defined.insert(AMZ_OBJECT_LOCK_LEGAL_HOLD.to_owned(), "ON".to_owned());
+        assert_eq!(
+            check_retention(&user_defined, true, now),
+            Err(RetentionError::LegalHold)
+        );
+
+        // Malformed retention.
+        let mut user_defined = metadata("COMPLIANCE", future);
+        user_defined.insert(
+            AMZ_OBJECT_LOCK_RETAIN_UNTIL_DATE.to_owned(),
+            "tomorrow".to_owned(),
+        );
+        assert!(Retention::from_metadata(&user_defined).is_err());
+        assert_eq!(
+            check_retention(&user_defined, true, now),
+            Err(RetentionError::Malformed)
+        );
+        let mut user_defined = metadata("FOREVER", future);
+        assert_eq!(
+            check_retention(&user_defined, true, now),
+            Err(RetentionError::Malformed)
+        );
+        user_defined.remove(AMZ_OBJECT_LOCK_MODE);
+        assert_eq!(
+            check_retention(&user_defined, true, now),
+            Err(RetentionError::Malformed)
+        );
+    }
+
+    #[test]
+    fn test_object_lock_config() {
+        let config = ObjectLockConfig::parse(
+            "<ObjectLockConfiguration>\
+                <ObjectLockEnabled>Enabled</ObjectLockEnabled>\
+                <Rule><DefaultRetention><Mode>GOVERNANCE</Mode><Days>3</Days></DefaultRetention></Rule>\
+            </ObjectLockConfiguration>",
+        )
+        .unwrap();
+        let now = utils::now();
+        assert_eq!(
+            config.default_retention(now),
+            Some(Retention {
+                mode: RetentionMode::Governance,
+                retain_until: now + ChronoDuration::days(3),
+            })
+        );
+
+        let config = ObjectLockConfig::parse(
+            "<ObjectLockConfiguration><ObjectLockEnabled>Enabled</ObjectLockEnabled></ObjectLockConfiguration>",
+        )
+        .unwrap();
+        assert_eq!(config.default_retention(now), None);
+
+        for xml in [
+            "<ObjectLockConfiguration><ObjectLockEnabled>Disabled</ObjectLockEnabled></ObjectLockConfiguration>",
+            "<ObjectLockConfiguration>\
+                <ObjectLockEnabled>Enabled</ObjectLockEnabled>\
+                <Rule><DefaultRetention><Mode>COMPLIANCE</Mode><Days>1</Days><Years>1</Years></DefaultRetention></Rule>\
+            </ObjectLockConfiguration>",
+            "<ObjectLockConfiguration>\
+                <ObjectLockEnabled>Enabled</ObjectLockEnabled>\
+                <Rule><DefaultRetention><Mode>COMPLIANCE</Mode><Days>0</Days></DefaultRetention></Rule>\
+            </ObjectLockConfiguration>",
+            "<ObjectLockConfiguration>\
+                <ObjectLockEnabled>Enabled</ObjectLockEnabled>\
+                <Rule><DefaultRetention><Mode>COMPLIANCE</Mode><Years>9223372036854775807</Years></DefaultRetention></Rule>\
+            </ObjectLockConfiguration>",
+            "<ObjectLockConfiguration>\
+                <ObjectLockEnabled>Enabled</ObjectLockEnabled>\
+                <Rule><DefaultRetention><Mode>COMPLIANCE</Mode><Days>36501</Days></DefaultRetention></Rule>\
+            </ObjectLockConfiguration>",
+        ] {
+            assert!(ObjectLockConfig::parse(xml).is_err(), "{}", xml);
+        }
+    }
+}
@@ -40,13 +40,10 @@ impl<'a> Function for IpAddressFunc<'a> {
         }
         match v {
             Some(v) => {
-                for s in v {
-                    let ip = std::net::IpAddr::from_str(s).unwrap();
-                    if self.values.iter().any(|net| net.contains(&ip)) {
-                        return true;
-                    }
-                }
-                return false;
+                // Malformed addresses never match.
+                v.iter()
+                    .filter_map(|s| std::net::IpAddr::from_str(s).ok())
+                    .any(|ip| self.values.iter().any(|net| net.contains(&ip)))
             }
             None => false,
         }
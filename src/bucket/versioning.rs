@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use anyhow::bail;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use tokio::io::AsyncReadExt;
+
+use crate::hash;
+use crate::object::{self, ObjectLayer, ObjectOptions, PutObjectReader};
+
+// Bucket metadata is stored under this prefix of the system bucket.
+const BUCKET_META_PREFIX: &str = "buckets";
+const BUCKET_VERSIONING_CONFIG: &str = "versioning.xml";
+
+lazy_static! {
+    // Versioning config of the buckets loaded or set so far, buckets missing
+    // are yet to be loaded from their metadata.
+    static ref BUCKET_VERSIONING: RwLock<HashMap<String, VersioningConfig>> =
+        RwLock::new(HashMap::new());
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Display, PartialEq, Eq)]
+pub enum VersioningStatus {
+    // Never versioned, a bucket cannot be brought back to it once versioned.
+    #[serde(skip)]
+    Disabled,
+    // Every write creates a new version.
+    Enabled,
+    // Writes overwrite the `null` version, existing versions are kept.
+    Suspended,
+}
+
+impl VersioningStatus {
+    fn is_disabled(&self) -> bool {
+        *self == VersioningStatus::Disabled
+    }
+}
+
+impl Default for VersioningStatus {
+    fn default() -> Self {
+        VersioningStatus::Disabled
+    }
+}
+
+/// Versioning configuration of a bucket.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename = "VersioningConfiguration", rename_all = "PascalCase")]
+pub struct VersioningConfig {
+    #[serde(default, skip_serializing_if = "VersioningStatus::is_disabled")]
+    pub status: VersioningStatus,
+}
+
+impl VersioningConfig {
+    /// Parses a versioning configuration XML.
+    pub fn parse(xml: &str) -> anyhow::Result<VersioningConfig> {
+        let config: VersioningConfig = quick_xml::de::from_str(xml)?;
+        if config.status.is_disabled() {
+            bail!("versioning status must be 'Enabled' or 'Suspended'");
+        }
+        Ok(config)
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.status == VersioningStatus::Enabled
+    }
+
+    pub fn suspended(&self) -> bool {
+        self.status == VersioningStatus::Suspended
+    }
+
+    /// Returns the options of a write, with `version_id` only to be set when
+    /// replicating a given version.
+    pub fn put_options(&self, version_id: &str) -> ObjectOptions {
+        let mut opts = ObjectOptions {
+            version_id: version_id.to_owned(),
+            ..Default::default()
+        };
+        self.apply(&mut opts);
+        opts
+    }
+
+    /// Sets the versioning options of a write to the bucket.
+    pub fn apply(&self, opts: &mut ObjectOptions) {
+        opts.versioned = self.enabled();
+        opts.version_suspended = self.suspended();
+    }
+}
+
+fn versioning_config_path(bucket: &str) -> String {
+    object::path_join(&[BUCKET_META_PREFIX, bucket, BUCKET_VERSIONING_CONFIG])
+}
+
+/// Returns the versioning config of the bucket.
+pub fn get_versioning(bucket: &str) -> VersioningConfig {
+    BUCKET_VERSIONING
+        .read()
+        .unwrap()
+        .get(bucket)
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Sets the versioning config of the bucket, a versioned bucket can only be
+/// suspended, not disabled.
+pub fn set_versioning(bucket: &str, config: VersioningConfig) -> anyhow::Result<()> {
+    let mut versioning = BUCKET_VERSIONING.write().unwrap();
+    if config.status.is_disabled() {
+        if versioning
+            .get(bucket)
+            .map_or(false, |current| !current.status.is_disabled())
+        {
+            bail!(
+                "versioning of bucket '{}' can only be suspended once enabled",
+                bucket
+            );
+        }
+        return Ok(());
+    }
+    versioning.insert(bucket.to_owned(), config);
+    Ok(())
+}
+
+/// Returns the versioning config of the bucket, read from the bucket
+/// metadata the first time.
+pub async fn load_versioning(api: &ObjectLayer, bucket: &str) -> anyhow::Result<VersioningConfig> {
+    if bucket == object::SYSTEM_META_BUCKET {
+        return Ok(VersioningConfig::default());
+    }
+    if let Some(config) = BUCKET_VERSIONING.read().unwrap().get(bucket) {
+        return Ok(*config);
+    }
+    let res = api
+        .get_object_and_info(
+            object::SYSTEM_META_BUCKET,
+            &versioning_config_path(bucket),
+            Default::default(),
+            &Default::default(),
+            object::LockType::Read,
+            None,
+        )
+        .await;
+    let config = match res {
+        Ok(mut r) => {
+            let mut buf = String::new();
+            r.reader.read_to_string(&mut buf).await?;
+            VersioningConfig::parse(&buf)?
+        }
+        // Never versioned.
+        Err(err) if object::is_object_not_found(&err) => VersioningConfig::default(),
+        Err(err) => return Err(err),
+    };
+    // Keep a config set concurrently.
+    Ok(*BUCKET_VERSIONING
+        .write()
+        .unwrap()
+        .entry(bucket.to_owned())
+        .or_insert(config))
+}
+
+/// Saves the versioning config of the bucket to its metadata, a versioned
+/// bucket can only be suspended, not disabled.
+pub async fn save_versioning(
+    api: &ObjectLayer,
+    bucket: &str,
+    config: VersioningConfig,
+) -> anyhow::Result<()> {
+    let current = load_versioning(api, bucket).await?;
+    if config.status.is_disabled() {
+        // Only checks that the bucket was never versioned.
+        return set_versioning(bucket, config);
+    }
+    if config == current {
+        return Ok(());
+    }
+    let data = crate::serde::xml::to_string(&config)?;
+    let data = data.as_bytes();
+    let reader = hash::Reader::new(
+        data,
+        data.len() as isize,
+        "",
+        &hash::sha256_hex(data),
+        data.len(),
+    )?;
+    api.put_object(
+        object::SYSTEM_META_BUCKET,
+        &versioning_config_path(bucket),
+        &mut PutObjectReader::new(reader),
+        Some(ObjectOptions {
+            max_parity: true,
+            ..Default::default()
+        }),
+    )
+    .await?;
+    set_versioning(bucket, config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::FileInfo;
+    use crate::utils;
+    use crate::xl_storage::{ErasureAlgo, ErasureInfo, XlMetaV2};
+
+    // Writes a new object version as the write path would, returning its version id.
+    fn put(xl: &mut XlMetaV2, bucket: &str) -> String {
+        let opts = get_versioning(bucket).put_options("");
+        let fi = FileInfo {
+            volume: bucket.to_owned(),
+            name: "object".to_owned(),
+            version_id: opts.put_version_id(),
+            mod_time: utils::now(),
+            erasure: Some(ErasureInfo {
+                algorithm: ErasureAlgo::ReedSolomon.to_string(),
+                data_blocks: 4,
+                parity_blocks: 2,
+                block_size: 10000,
+                index: 1,
+                distribution: vec![1, 2, 3, 4, 5, 6],
+                checksums: vec![],
+            }),
+            ..Default::default()
+        };
+        xl.add_version(&fi).unwrap();
+        fi.version_id
+    }
+
+    fn version_ids(xl: &XlMetaV2) -> Vec<String> {
+        let (versions, _) = xl.list_versions("bucket", "object").unwrap();
+        versions.into_iter().map(|fi| fi.version_id).collect()
+    }
+
+    #[test]
+    fn test_versioning_config() {
+        let config = VersioningConfig::parse(
+            "<VersioningConfiguration><Status>Enabled</Status></VersioningConfiguration>",
+        )
+        .unwrap();
+        assert!(config.enabled());
+        let config = VersioningConfig::parse(
+            "<VersioningConfiguration><Status>Suspended</Status></VersioningConfiguration>",
+        )
+        .unwrap();
+        assert!(config.suspended());
+        // Stored in the bucket metadata as XML.
+        let xml = crate::serde::xml::to_string(&config).unwrap();
+        assert_eq!(VersioningConfig::parse(&xml).unwrap(), config);
+        assert_eq!(
+            versioning_config_path("bucket"),
+            "buckets/bucket/versioning.xml"
+        );
+
+        for xml in [
+            "<VersioningConfiguration></VersioningConfiguration>",
+            "<VersioningConfiguration><Status>Disabled</Status></VersioningConfiguration>",
+        ] {
+            assert!(VersioningConfig::parse(xml).is_err(), "{}", xml);
+        }
+
+        let bucket = "test-versioning-config";
+        assert_eq!(get_versioning(bucket), VersioningConfig::default());
+        assert!(set_versioning(bucket, VersioningConfig::default()).is_ok());
+        set_versioning(bucket, config).unwrap();
+        assert!(get_versioning(bucket).suspended());
+        assert!(set_versioning(bucket, VersioningConfig::default()).is_err());
+        assert!(get_versioning(bucket).suspended());
+    }
+
+    #[test]
+    fn test_versioning_writes() {
+        let bucket = "test-versioning-writes";
+
+        // Disabled, every write overwrites the null version.
+        let mut xl = XlMetaV2::default();
+        assert_eq!(put(&mut xl, bucket), "");
+        assert_eq!(put(&mut xl, bucket), "");
+        assert_eq!(xl.versions.len(), 1);
+
+        // Enabled, every write creates a new version.
+        let enabled = VersioningConfig {
+            status: VersioningStatus::Enabled,
+        };
+        set_versioning(bucket, enabled).unwrap();
+        let v1 = put(&mut xl, bucket);
+        let v2 = put(&mut xl, bucket);
+        assert_ne!(v1, v2);
+        assert!(uuid::Uuid::parse_str(&v1).is_ok());
+        assert_eq!(xl.versions.len(), 3);
+
+        // Suspended, writes overwrite the null version and keep the others.
+        let suspended = VersioningConfig {
+            status: VersioningStatus::Suspended,
+        };
+        set_versioning(bucket, suspended).unwrap();
+        assert_eq!(put(&mut xl, bucket), "");
+        assert_eq!(put(&mut xl, bucket), "");
+        let ids = version_ids(&xl);
+        assert_eq!(ids.len(), 3);
+        assert!(ids.contains(&v1) && ids.contains(&v2));
+        assert_eq!(ids.iter().filter(|id| id.is_empty()).count(), 1);
+    }
+}
@@ -1,121 +1,347 @@
 use std::collections::HashMap;
 use std::io::BufReader;
-use std::sync::{Arc, RwLock};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use anyhow::anyhow;
-use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
+use anyhow::{anyhow, ensure};
+use arc_swap::ArcSwap;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use rustls::internal::pemfile::{certs as extract_certs, pkcs8_private_keys};
 use rustls::sign::{any_supported_type, CertifiedKey};
-use rustls::{ClientHello, ResolvesServerCert};
-use tokio::io::AsyncReadExt;
+use rustls::{ClientHello, NoClientAuth, ResolvesServerCert, ServerConfig};
 
 use crate::error;
-use crate::fs::File;
-use crate::utils::Path;
 
+pub const PUBLIC_CERT_FILE: &str = "public.crt";
+pub const PRIVATE_KEY_FILE: &str = "private.key";
+
+// Read on every handshake, so swapped as a whole on reload rather than locked.
+type CertMap = Arc<ArcSwap<HashMap<KeyCert, CertifiedKey>>>;
+type SniMap = Arc<ArcSwap<HashMap<String, KeyCert>>>;
+
+// Manager of the TLS certificates, which are reloaded when their files change.
+// The served certificate is looked up per handshake, so a reload takes effect
+// for new connections without rebuilding the server config.
 pub struct Manager {
-    certs: Arc<RwLock<HashMap<KeyCert, CertifiedKey>>>,
+    certs: CertMap,
     default_cert: KeyCert,
+    sni_certs: SniMap,
+    watcher: Option<RecommendedWatcher>,
 }
 
 #[derive(PartialEq, Eq, Hash, Clone)]
 struct KeyCert {
-    key_file: String,
-    cert_file: String,
+    key_file: PathBuf,
+    cert_file: PathBuf,
 }
 
 impl Manager {
-    pub fn new() -> Self {
-        todo!()
+    pub fn new(cert_file: impl AsRef<Path>, key_file: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let pair = KeyCert {
+            key_file: std::fs::canonicalize(key_file)?,
+            cert_file: std::fs::canonicalize(cert_file)?,
+        };
+        let cert = load_key_pair(&pair.cert_file, &pair.key_file)?;
+        let mut certs = HashMap::new();
+        certs.insert(pair.clone(), cert);
+        Ok(Manager {
+            certs: Arc::new(ArcSwap::from_pointee(certs)),
+            default_cert: pair,
+            sni_certs: Default::default(),
+            watcher: None,
+        })
+    }
+
+    /// Loads `public.crt` and `private.key` of the certs dir as the default pair,
+    /// and each `<name>/public.crt` and `<name>/private.key` as the pair served
+    /// for the server name `<name>`.
+    pub fn from_certs_dir(certs_dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let certs_dir = certs_dir.as_ref();
+        let manager = Manager::new(
+            certs_dir.join(PUBLIC_CERT_FILE),
+            certs_dir.join(PRIVATE_KEY_FILE),
+        )?;
+        for entry in std::fs::read_dir(certs_dir)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            let dir = entry.path();
+            let (cert_file, key_file) = (dir.join(PUBLIC_CERT_FILE), dir.join(PRIVATE_KEY_FILE));
+            // Directories without a key pair, e.g. the CAs dir, are not server names.
+            if !cert_file.is_file() || !key_file.is_file() {
+                continue;
+            }
+            let name = entry
+                .file_name()
+                .into_string()
+                .map_err(|_| anyhow!("invalid server name {:?}", dir))?;
+            manager.add_certificate(&name, cert_file, key_file)?;
+        }
+        Ok(manager)
+    }
+
+    /// Adds the key pair served for `server_name`.
+    pub fn add_certificate(
+        &self,
+        server_name: &str,
+        cert_file: impl AsRef<Path>,
+        key_file: impl AsRef<Path>,
+    ) -> anyhow::Result<()> {
+        let pair = KeyCert {
+            key_file: std::fs::canonicalize(key_file)?,
+            cert_file: std::fs::canonicalize(cert_file)?,
+        };
+        let cert = load_key_pair(&pair.cert_file, &pair.key_file)?;
+        insert_cert(&self.certs, pair.clone(), cert);
+        self.sni_certs.rcu(|sni_certs| {
+            let mut sni_certs = HashMap::clone(sni_certs);
+            sni_certs.insert(server_name.to_lowercase(), pair.clone());
+            sni_certs
+        });
+        Ok(())
+    }
+
+    /// Returns the currently loaded default certificate.
+    pub fn get_certificate(&self) -> CertifiedKey {
+        self.certs.load()[&self.default_cert].clone()
+    }
+
+    /// Returns a server config which always serves the currently loaded certificates.
+    pub fn server_config(&self) -> ServerConfig {
+        let mut config = ServerConfig::new(NoClientAuth::new());
+        config.cert_resolver = Arc::new(self.resolver());
+        config
+    }
+
+    fn resolver(&self) -> CertResolver {
+        CertResolver {
+            certs: Arc::clone(&self.certs),
+            default_cert: self.default_cert.clone(),
+            sni_certs: Arc::clone(&self.sni_certs),
+        }
     }
 
-    async fn watch_file_events(&mut self) -> anyhow::Result<()> {
+    /// Watches the certificate files and reloads them on change.
+    pub fn watch(&mut self) -> anyhow::Result<()> {
         let certs = Arc::clone(&self.certs);
-        let handler = move |res: notify::Result<notify::Event>| -> anyhow::Result<()> {
-            match res {
+        let mut watcher: RecommendedWatcher =
+            Watcher::new_immediate(move |res: notify::Result<notify::Event>| match res {
                 Ok(event) => match event.kind {
-                    notify::EventKind::Create(notify::event::CreateKind::File)
-                    | notify::EventKind::Modify(notify::event::ModifyKind::Data(_)) => {
-                        let path = event
-                            .paths
-                            .last()
-                            .ok_or_else(|| anyhow!("empty path"))?
-                            .as_path();
-                        let mut certs = certs.write().unwrap();
-                        let mut new_certs = HashMap::new();
-                        for pair in certs.keys() {
-                            if path != Path::new(&pair.key_file)
-                                && path != Path::new(&pair.cert_file)
-                            {
-                                continue;
-                            }
-                            let (cert_file, key_file) =
-                                tokio::task::block_in_place(|| -> std::io::Result<_> {
-                                    tokio::runtime::Handle::current().block_on(async {
-                                        let mut cert_file = Vec::new();
-                                        File::open(&pair.cert_file)
-                                            .await?
-                                            .read_to_end(&mut cert_file)
-                                            .await?;
-                                        let mut key_file = Vec::new();
-                                        File::open(&pair.key_file)
-                                            .await?
-                                            .read_to_end(&mut key_file)
-                                            .await?;
-                                        Ok((cert_file, key_file))
-                                    })
-                                })?;
-
-                            let cert_file = &mut BufReader::new(&cert_file[..]);
-                            let key_file = &mut BufReader::new(&key_file[..]);
-                            let cert_chain =
-                                extract_certs(cert_file).map_err(|_| anyhow!("invalid certs"))?;
-                            cert_chain.first().ok_or_else(|| anyhow!("invalid certs"))?;
-                            let mut keys = pkcs8_private_keys(key_file)
-                                .map_err(|_| anyhow!("invalid private key"))?;
-                            let key = any_supported_type(
-                                keys.first().ok_or_else(|| anyhow!("invalid private key"))?,
-                            )
-                            .map_err(|_| anyhow!("invalid private key"))?;
-                            new_certs
-                                .insert(pair.clone(), CertifiedKey::new(cert_chain, Arc::new(key)));
+                    notify::EventKind::Create(_) | notify::EventKind::Modify(_) => {
+                        for path in &event.paths {
+                            reload(&certs, path);
                         }
-                        certs.extend(new_certs.into_iter());
-                        Ok(())
                     }
-                    _ => Ok(()),
+                    _ => {}
                 },
-                Err(err) => Err(anyhow!(err)),
-            }
-        };
+                Err(err) => {
+                    error!("watch file events error: {:?}", err);
+                }
+            })?;
 
-        let mut watcher: RecommendedWatcher = Watcher::new_immediate(move |res| {
-            if let Err(err) = handler(res) {
-                error!("watch file events error: {:?}", err);
-            }
-        })?;
-        let conf_res = watcher.configure(Config::PreciseEvents(true))?;
-        if !conf_res {
-            anyhow::bail!("watcher does not support or implement the PreciseEvents config");
+        let mut dirs: Vec<_> = self
+            .certs
+            .load()
+            .keys()
+            .flat_map(|pair| vec![pair.cert_file.parent(), pair.key_file.parent()])
+            .flatten()
+            .map(Path::to_path_buf)
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+        for dir in dirs {
+            watcher.watch(&dir, RecursiveMode::NonRecursive)?;
         }
-        watcher.watch(".", RecursiveMode::Recursive)?;
-        watcher.unwatch(".");
+        self.watcher = Some(watcher);
         Ok(())
     }
 }
 
-struct ResolvesServerCertUsingSNI {
-    manager: &'static Manager,
-    resolver: rustls::ResolvesServerCertUsingSNI,
+// Reloads the key pairs which contain the changed file. A pair that fails
+// to load keeps serving its previous certificate.
+fn reload(certs: &CertMap, path: &Path) {
+    let pairs: Vec<_> = certs
+        .load()
+        .keys()
+        .filter(|pair| path == pair.key_file || path == pair.cert_file)
+        .cloned()
+        .collect();
+    for pair in pairs {
+        match load_key_pair(&pair.cert_file, &pair.key_file) {
+            Ok(cert) => insert_cert(certs, pair, cert),
+            Err(err) => {
+                error!(
+                    "unable to reload certificate {:?}, keeping the previous one: {}",
+                    pair.cert_file, err
+                );
+            }
+        }
+    }
+}
+
+fn insert_cert(certs: &CertMap, pair: KeyCert, cert: CertifiedKey) {
+    certs.rcu(|certs| {
+        let mut certs = HashMap::clone(certs);
+        certs.insert(pair.clone(), cert.clone());
+        certs
+    });
 }
 
-impl ResolvesServerCert for ResolvesServerCertUsingSNI {
+fn load_key_pair(cert_file: &Path, key_file: &Path) -> anyhow::Result<CertifiedKey> {
+    let cert_file = std::fs::read(cert_file)?;
+    let key_file = std::fs::read(key_file)?;
+    parse_key_pair(&cert_file, &key_file)
+}
+
+fn parse_key_pair(cert_file: &[u8], key_file: &[u8]) -> anyhow::Result<CertifiedKey> {
+    let cert_chain =
+        extract_certs(&mut BufReader::new(cert_file)).map_err(|_| anyhow!("invalid certs"))?;
+    cert_chain.first().ok_or_else(|| anyhow!("invalid certs"))?;
+    let keys = pkcs8_private_keys(&mut BufReader::new(key_file))
+        .map_err(|_| anyhow!("invalid private key"))?;
+    let key = keys.first().ok_or_else(|| anyhow!("invalid private key"))?;
+    // A key not matching the certificate would fail every handshake, e.g. when
+    // a reload happens between the writes of the new certificate and key.
+    let public_key = openssl::x509::X509::from_der(&cert_chain[0].0)?.public_key()?;
+    ensure!(
+        openssl::pkey::PKey::private_key_from_pkcs8(&key.0)?.public_eq(&public_key),
+        "private key does not match the certificate"
+    );
+    let key = any_supported_type(key).map_err(|_| anyhow!("invalid private key"))?;
+    Ok(CertifiedKey::new(cert_chain, Arc::new(key)))
+}
+
+// Resolves the certificate by SNI, falling back to the default pair when
+// there is no server name or no pair for it. Unlike
+// `rustls::ResolvesServerCertUsingSNI` it looks up the loaded certificates
+// on each handshake, so that reloaded pairs are served.
+struct CertResolver {
+    certs: CertMap,
+    default_cert: KeyCert,
+    sni_certs: SniMap,
+}
+
+impl CertResolver {
+    fn resolve_server_name(&self, server_name: Option<&str>) -> Option<CertifiedKey> {
+        let sni_certs = self.sni_certs.load();
+        let pair = server_name
+            .and_then(|name| sni_certs.get(&name.to_lowercase()))
+            .unwrap_or(&self.default_cert);
+        self.certs.load().get(pair).cloned()
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
     fn resolve(&self, client_hello: ClientHello) -> Option<CertifiedKey> {
-        if client_hello.server_name().is_none() {
-            let certs = self.manager.certs.read().unwrap();
-            Some(certs.get(&self.manager.default_cert).unwrap().clone())
-        } else {
-            self.resolver.resolve(client_hello)
+        self.resolve_server_name(client_hello.server_name().map(|name| name.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCALHOST_CRT: &[u8] = include_bytes!("testdata/localhost.crt");
+    const LOCALHOST_KEY: &[u8] = include_bytes!("testdata/localhost.key");
+    const ROTATED_CRT: &[u8] = include_bytes!("testdata/localhost-rotated.crt");
+    const ROTATED_KEY: &[u8] = include_bytes!("testdata/localhost-rotated.key");
+    const A_EXAMPLE_CRT: &[u8] = include_bytes!("testdata/a.example.com.crt");
+    const A_EXAMPLE_KEY: &[u8] = include_bytes!("testdata/a.example.com.key");
+    const B_EXAMPLE_CRT: &[u8] = include_bytes!("testdata/b.example.com.crt");
+    const B_EXAMPLE_KEY: &[u8] = include_bytes!("testdata/b.example.com.key");
+
+    fn leaf(cert_file: &[u8]) -> rustls::Certificate {
+        extract_certs(&mut BufReader::new(cert_file)).unwrap()[0].clone()
+    }
+
+    #[test]
+    fn test_manager_reload() {
+        let tmp_dir = tempfile::tempdir_in(".").unwrap();
+        let cert_file = tmp_dir.path().join("public.crt");
+        let key_file = tmp_dir.path().join("private.key");
+        std::fs::write(&cert_file, LOCALHOST_CRT).unwrap();
+        std::fs::write(&key_file, LOCALHOST_KEY).unwrap();
+
+        let manager = Manager::new(&cert_file, &key_file).unwrap();
+        assert!(manager.get_certificate().cert[0] == leaf(LOCALHOST_CRT));
+
+        // A new valid pair replaces the served certificate.
+        std::fs::write(&cert_file, ROTATED_CRT).unwrap();
+        std::fs::write(&key_file, ROTATED_KEY).unwrap();
+        reload(&manager.certs, &manager.default_cert.cert_file);
+        assert!(manager.get_certificate().cert[0] == leaf(ROTATED_CRT));
+
+        // An invalid certificate keeps the previous one.
+        std::fs::write(&cert_file, b"invalid").unwrap();
+        reload(&manager.certs, &manager.default_cert.cert_file);
+        assert!(manager.get_certificate().cert[0] == leaf(ROTATED_CRT));
+
+        // So does a certificate not matching the key.
+        std::fs::write(&cert_file, LOCALHOST_CRT).unwrap();
+        reload(&manager.certs, &manager.default_cert.cert_file);
+        assert!(manager.get_certificate().cert[0] == leaf(ROTATED_CRT));
+    }
+
+    #[test]
+    fn test_parse_key_pair() {
+        assert!(parse_key_pair(LOCALHOST_CRT, LOCALHOST_KEY).is_ok());
+        let err = parse_key_pair(LOCALHOST_CRT, ROTATED_KEY).unwrap_err();
+        assert!(err.to_string().contains("does not match"), "{}", err);
+        assert!(parse_key_pair(A_EXAMPLE_CRT, B_EXAMPLE_KEY).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_manager_watch() {
+        let tmp_dir = tempfile::tempdir_in(".").unwrap();
+        let cert_file = tmp_dir.path().join("public.crt");
+        let key_file = tmp_dir.path().join("private.key");
+        std::fs::write(&cert_file, LOCALHOST_CRT).unwrap();
+        std::fs::write(&key_file, LOCALHOST_KEY).unwrap();
+
+        let mut manager = Manager::new(&cert_file, &key_file).unwrap();
+        let _config = manager.server_config();
+        manager.watch().unwrap();
+
+        std::fs::write(&key_file, ROTATED_KEY).unwrap();
+        std::fs::write(&cert_file, ROTATED_CRT).unwrap();
+        for _ in 0..50 {
+            if manager.get_certificate().cert[0] == leaf(ROTATED_CRT) {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
         }
+        panic!("certificate is not reloaded");
+    }
+
+    #[test]
+    fn test_manager_sni() {
+        let tmp_dir = tempfile::tempdir_in(".").unwrap();
+        let write_pair = |dir: &Path, cert: &[u8], key: &[u8]| {
+            std::fs::create_dir_all(dir).unwrap();
+            std::fs::write(dir.join(PUBLIC_CERT_FILE), cert).unwrap();
+            std::fs::write(dir.join(PRIVATE_KEY_FILE), key).unwrap();
+        };
+        write_pair(tmp_dir.path(), LOCALHOST_CRT, LOCALHOST_KEY);
+        write_pair(
+            &tmp_dir.path().join("a.example.com"),
+            A_EXAMPLE_CRT,
+            A_EXAMPLE_KEY,
+        );
+        write_pair(
+            &tmp_dir.path().join("b.example.com"),
+            B_EXAMPLE_CRT,
+            B_EXAMPLE_KEY,
+        );
+        // Directories without a key pair are skipped.
+        std::fs::create_dir_all(tmp_dir.path().join("CAs")).unwrap();
+
+        let manager = Manager::from_certs_dir(tmp_dir.path()).unwrap();
+        let resolver = manager.resolver();
+        let resolve = |name| resolver.resolve_server_name(name).unwrap().cert[0].clone();
+        assert!(resolve(Some("a.example.com")) == leaf(A_EXAMPLE_CRT));
+        assert!(resolve(Some("B.example.com")) == leaf(B_EXAMPLE_CRT));
+        assert!(resolve(Some("c.example.com")) == leaf(LOCALHOST_CRT));
+        assert!(resolve(None) == leaf(LOCALHOST_CRT));
     }
 }
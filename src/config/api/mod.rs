@@ -73,14 +73,19 @@ lazy_static! {
     ]);
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
 pub struct Config {
     pub requests_max: usize,
+    #[serde(with = "crate::serde::humantime_duration")]
     pub requests_deadline: Duration,
+    #[serde(with = "crate::serde::humantime_duration")]
     pub cluster_deadline: Duration,
+    #[serde(with = "crate::serde::one_or_many")]
     pub cors_allow_origin: Vec<String>,
+    #[serde(with = "crate::serde::humantime_duration")]
     pub remote_transport_deadline: Duration,
     pub list_quorum: String,
+    #[serde(with = "crate::serde::humantime_duration")]
     pub extend_list_cache_life: Duration,
     pub replication_workers: usize,
     pub replication_failed_workers: usize,
@@ -450,4 +455,34 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_config_api_invalid_key_suggestion() {
+        let mut kvs = DEFAULT_KVS.clone();
+        kvs.set("requets_max".to_owned(), "10".to_owned());
+        let err = assert_err!(lookup_config(&kvs));
+        assert!(
+            err.to_string().contains("did you mean 'requests_max'?"),
+            "{}",
+            err
+        );
+
+        let mut kvs = DEFAULT_KVS.clone();
+        kvs.set("unrelated".to_owned(), "10".to_owned());
+        let err = assert_err!(lookup_config(&kvs));
+        assert!(!err.to_string().contains("did you mean"), "{}", err);
+    }
+
+    #[test]
+    fn test_config_api_cors_allow_origin_serde() {
+        let mut cfg = serde_json::to_value(Config::default()).unwrap();
+        for (origin, expected) in [
+            (serde_json::json!("*"), vec!["*"]),
+            (serde_json::json!(["a", "b"]), vec!["a", "b"]),
+        ] {
+            cfg["cors_allow_origin"] = origin;
+            let cfg: Config = assert_ok!(serde_json::from_value(cfg.clone()));
+            assert_eq!(cfg.cors_allow_origin, expected);
+        }
+    }
 }
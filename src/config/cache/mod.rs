@@ -83,7 +83,7 @@ lazy_static! {
     ]);
 }
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 pub struct Config {
     #[serde(skip)]
     pub enabled: bool,
@@ -41,7 +41,7 @@ lazy_static! {
     ]);
 }
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 pub struct Config {
     pub enabled: bool,
     pub allow_encrypted: bool,
@@ -12,6 +12,7 @@ use super::constants::*;
 use super::help::HelpKVS;
 use crate::auth;
 use crate::strset::StringSet;
+use crate::utils;
 
 pub const ENABLE_KEY: &str = "enable";
 pub const COMMENT_KEY: &str = "comment";
@@ -286,16 +287,40 @@ pub fn check_valid_keys(sub_sys: &str, kvs: &KVS, valid_kvs: &KVS) -> anyhow::Re
         }
     }
     if !nkvs.is_empty() {
+        let suggestions: Vec<_> = nkvs
+            .iter()
+            .filter_map(|kv| suggest_key(&kv.key, valid_kvs))
+            .map(|key| format!("'{}'", key))
+            .collect();
+        let hint = if suggestions.is_empty() {
+            "".to_owned()
+        } else {
+            format!(" (did you mean {}?)", suggestions.join(", "))
+        };
         bail!(
-            "found invalid keys ({}) for '{}' sub-system, use 'hc admin config reset myhulk {}' to fix invalid keys",
+            "found invalid keys ({}) for '{}' sub-system{}, use 'hc admin config reset myhulk {}' to fix invalid keys",
             nkvs.to_string(),
             sub_sys,
+            hint,
             sub_sys
         )
     }
     Ok(())
 }
 
+// Max edit distance for a valid key to be suggested in place of an invalid one.
+const MAX_KEY_SUGGESTION_DISTANCE: usize = 2;
+
+// Returns the valid key closest to the invalid `key`, if close enough.
+fn suggest_key<'a>(key: &str, valid_kvs: &'a KVS) -> Option<&'a str> {
+    valid_kvs
+        .iter()
+        .map(|kv| (utils::levenshtein_distance(key, &kv.key), kv.key.as_str()))
+        .filter(|&(distance, _)| distance <= MAX_KEY_SUGGESTION_DISTANCE)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, valid_key)| valid_key)
+}
+
 // Config structure at server.
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Config(HashMap<String, HashMap<String, KVS>>);
@@ -8,6 +8,7 @@ pub const ENV_BROWSER: &str = "HULK_BROWSER";
 pub const ENV_DOMAIN: &str = "HULK_DOMAIN";
 pub const ENV_REGION_NAME: &str = "HULK_REGION_NAME";
 pub const ENV_PUBLIC_IPS: &str = "HULK_PUBLIC_IPS";
+pub const ENV_DOMAIN_CIDRS: &str = "HULK_DOMAIN_CIDRS";
 pub const ENV_FS_OSYNC: &str = "HULK_FS_OSYNC";
 pub const ENV_ARGS: &str = "HULK_ARGS";
 pub const ENV_DNS_WEBHOOK: &str = "HULK_DNS_WEBHOOK_ENDPOINT";
@@ -16,6 +17,18 @@ pub const ENV_ROOT_DISK_THRESHOLD_SIZE: &str = "HULK_ROOTDISK_THRESHOLD_SIZE";
 
 pub const ENV_UPDATE: &str = "HULK_UPDATE";
 
+pub const ENV_JWT_LEEWAY: &str = "HULK_JWT_LEEWAY";
+
+pub const ENV_CROSSDOMAIN_POLICY: &str = "HULK_CROSSDOMAIN_POLICY";
+
+pub const ENV_SHUTDOWN_GRACE_PERIOD: &str = "HULK_SHUTDOWN_GRACE_PERIOD";
+
+pub const ENV_STORAGE_SMALL_FILE_THRESHOLD: &str = "HULK_STORAGE_SMALL_FILE_THRESHOLD";
+pub const ENV_STORAGE_REALLY_LARGE_FILE_THRESHOLD: &str =
+    "HULK_STORAGE_REALLY_LARGE_FILE_THRESHOLD";
+
+pub const ENV_API_SIGV2: &str = "HULK_API_SIGV2";
+
 pub const ENV_KMS_SECRET_KEY: &str = "HULK_KMS_SECRET_KEY";
 pub const ENV_KES_ENDPOINT: &str = "HULK_KMS_KES_ENDPOINT";
 pub const ENV_KES_KEY_NAME: &str = "HULK_KMS_KES_KEY_NAME";
@@ -57,9 +57,10 @@ lazy_static! {
     ]);
 }
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 pub struct Config {
     pub bitrot_scan: bool,
+    #[serde(with = "crate::serde::humantime_duration")]
     pub sleep: Duration,
     pub io_count: usize,
 }
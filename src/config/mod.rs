@@ -11,8 +11,10 @@ pub mod notify;
 pub mod openid;
 pub mod scanner;
 pub mod storageclass;
+mod validate;
 
 pub use boolflag::*;
 pub use config::*;
 pub use constants::*;
 pub use help::*;
+pub use validate::*;
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use anyhow::bail;
 use lazy_static::lazy_static;
@@ -37,12 +38,12 @@ pub async fn test_notification_targets(
     client: HttpClient,
     target_ids: Vec<event::TargetId>,
 ) -> anyhow::Result<()> {
-    match register_notification_targets(cfg, client, target_ids, true, true) {
+    match register_notification_targets(cfg, client, target_ids, true, true).await {
         Ok(targets) => {
             // Close all targets since we are only testing connections.
-            for (_, t) in targets.iter() {
+            for t in targets.targets() {
                 let mut t = t.lock().await;
-                t.close().await;
+                let _ = t.close().await;
             }
             Ok(())
         }
@@ -50,23 +51,29 @@ pub async fn test_notification_targets(
     }
 }
 
-pub fn get_notification_targets(
+pub async fn get_notification_targets(
     cfg: Config,
     client: HttpClient,
     target_ids: Vec<event::TargetId>,
     test: bool,
-) -> anyhow::Result<event::TargetList> {
-    register_notification_targets(cfg, client, target_ids, test, false)
+) -> anyhow::Result<Arc<event::TargetList>> {
+    let target_list =
+        Arc::new(register_notification_targets(cfg, client, target_ids, test, false).await?);
+    if !test {
+        // Sends the events queued while their target was offline.
+        target_list.start_retrier(event::QUEUE_RETRY_INTERVAL);
+    }
+    Ok(target_list)
 }
 
-fn register_notification_targets(
+async fn register_notification_targets(
     cfg: Config,
     client: HttpClient,
     target_ids: Vec<event::TargetId>,
     test: bool,
     return_on_target_error: bool,
 ) -> anyhow::Result<event::TargetList> {
-    let target_list = fetch_registered_targets(cfg, client, test, return_on_target_error)?;
+    let target_list = fetch_registered_targets(cfg, client, test, return_on_target_error).await?;
     if test {
         // Verify if user is trying to disable already configured
         // notification targets, based on their target IDs.
@@ -79,17 +86,342 @@ fn register_notification_targets(
     Ok(target_list)
 }
 
-fn fetch_registered_targets(
+async fn fetch_registered_targets(
     cfg: Config,
     client: HttpClient,
     test: bool,
     return_on_target_error: bool,
 ) -> anyhow::Result<event::TargetList> {
-    let mut target_list = event::TargetList::default();
-    // TODO
+    let target_list = event::TargetList::default();
+
+    if let Some(kafka_kvs) = cfg.get(NOTIFY_KAFKA_SUB_SYS) {
+        for (id, args) in get_notify_kafka(kafka_kvs)? {
+            if !args.enable {
+                continue;
+            }
+            let producer = match target::new_kafka_producer(&args).await {
+                Ok(producer) => producer,
+                Err(err) => {
+                    if return_on_target_error {
+                        return Err(err);
+                    }
+                    crate::error!("unable to create kafka target '{}': {}", id, err);
+                    continue;
+                }
+            };
+            let target = target::KafkaTarget::new(&id, args, producer)?;
+            target_list.add(Box::new(target))?;
+        }
+    }
+
+    if let Some(webhook_kvs) = cfg.get(NOTIFY_WEBHOOK_SUB_SYS) {
+        for (id, args) in get_notify_webhook(webhook_kvs)? {
+            if !args.enable {
+                continue;
+            }
+            let target = match target::WebhookTarget::new(&id, args, client.client.clone()) {
+                Ok(target) => target,
+                Err(err) => {
+                    if return_on_target_error {
+                        return Err(err);
+                    }
+                    crate::error!("unable to create webhook target '{}': {}", id, err);
+                    continue;
+                }
+            };
+            target_list.add(Box::new(target))?;
+        }
+    }
+
+    // TODO: other targets
     Ok(target_list)
 }
 
+// Looks up `key` of a notification target, preferring the environment.
+// Env names of targets other than the default one are suffixed by the target name.
+fn lookup_target_value(target: &str, kvs: &KVS, env: &str, key: &str) -> String {
+    let env = if target == DEFAULT {
+        env.to_owned()
+    } else {
+        format!("{}{}{}", env, ENV_WORD_DELIMITER, target.to_uppercase())
+    };
+    std::env::var(env).unwrap_or_else(|_| kvs.get(key).to_owned())
+}
+
+// Returns all Kafka notification targets configured, keyed by target name.
+pub fn get_notify_kafka(
+    kafka_kvs: &HashMap<String, KVS>,
+) -> anyhow::Result<HashMap<String, target::KafkaArgs>> {
+    let mut targets = HashMap::new();
+    for (name, kvs) in kafka_kvs {
+        let _ = check_valid_keys(NOTIFY_KAFKA_SUB_SYS, kvs, &DEFAULT_KAFKA_KVS)?;
+        let args = lookup_kafka_args(name, kvs)?;
+        args.validate()
+            .map_err(|e| anyhow::anyhow!("kafka target '{}' invalid: {}", name, e))?;
+        targets.insert(name.clone(), args);
+    }
+    Ok(targets)
+}
+
+fn lookup_kafka_args(name: &str, kvs: &KVS) -> anyhow::Result<target::KafkaArgs> {
+    let lookup = |env: &str, key: &str| lookup_target_value(name, kvs, env, key);
+    let parse_bool = |env: &str, key: &str| {
+        let value = lookup(env, key);
+        if value.is_empty() {
+            return Ok(false);
+        }
+        crate::utils::parse_bool_ext(&value)
+            .map_err(|e| anyhow::anyhow!("kafka '{}' value invalid: {}", key, e))
+    };
+
+    let brokers = lookup(target::ENV_KAFKA_BROKERS, target::KAFKA_BROKERS)
+        .split(VALUE_SEPARATOR)
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_owned())
+        .collect();
+    let batch_size = lookup(target::ENV_KAFKA_BATCH_SIZE, target::KAFKA_BATCH_SIZE);
+    let batch_size = if batch_size.is_empty() {
+        1
+    } else {
+        batch_size
+            .parse::<usize>()
+            .map_err(|e| anyhow::anyhow!("kafka 'batch_size' value invalid: {}", e))?
+    };
+
+    Ok(target::KafkaArgs {
+        enable: parse_bool(target::ENV_KAFKA_ENABLE, ENABLE_KEY)?,
+        brokers,
+        topic: lookup(target::ENV_KAFKA_TOPIC, target::KAFKA_TOPIC),
+        tls: target::KafkaTlsArgs {
+            enable: parse_bool(target::ENV_KAFKA_TLS, target::KAFKA_TLS)?,
+            skip_verify: parse_bool(
+                target::ENV_KAFKA_TLS_SKIP_VERIFY,
+                target::KAFKA_TLS_SKIP_VERIFY,
+            )?,
+        },
+        sasl: target::KafkaSaslArgs {
+            enable: parse_bool(target::ENV_KAFKA_SASL, target::KAFKA_SASL)?,
+            user: lookup(target::ENV_KAFKA_SASL_USERNAME, target::KAFKA_SASL_USERNAME),
+            password: lookup(target::ENV_KAFKA_SASL_PASSWORD, target::KAFKA_SASL_PASSWORD),
+            mechanism: lookup(
+                target::ENV_KAFKA_SASL_MECHANISM,
+                target::KAFKA_SASL_MECHANISM,
+            ),
+        },
+        batch_size,
+    })
+}
+
+fn parse_queue_limit(value: &str) -> anyhow::Result<u64> {
+    if value.is_empty() {
+        return Ok(0);
+    }
+    value
+        .parse()
+        .map_err(|e| anyhow::anyhow!("'queue_limit' value invalid: {}", e))
+}
+
+// Returns all webhook notification targets configured, keyed by target name.
+pub fn get_notify_webhook(
+    webhook_kvs: &HashMap<String, KVS>,
+) -> anyhow::Result<HashMap<String, target::WebhookArgs>> {
+    let mut targets = HashMap::new();
+    for (name, kvs) in webhook_kvs {
+        let _ = check_valid_keys(NOTIFY_WEBHOOK_SUB_SYS, kvs, &DEFAULT_WEBHOOK_KVS)?;
+        let lookup = |env: &str, key: &str| lookup_target_value(name, kvs, env, key);
+        let enable = lookup(target::ENV_WEBHOOK_ENABLE, ENABLE_KEY);
+        let args = target::WebhookArgs {
+            enable: !enable.is_empty()
+                && crate::utils::parse_bool_ext(&enable)
+                    .map_err(|e| anyhow::anyhow!("webhook 'enable' value invalid: {}", e))?,
+            endpoint: lookup(target::ENV_WEBHOOK_ENDPOINT, target::WEBHOOK_ENDPOINT),
+            auth_token: lookup(target::ENV_WEBHOOK_AUTH_TOKEN, target::WEBHOOK_AUTH_TOKEN),
+            queue_dir: lookup(target::ENV_WEBHOOK_QUEUE_DIR, target::WEBHOOK_QUEUE_DIR),
+            queue_limit: parse_queue_limit(&lookup(
+                target::ENV_WEBHOOK_QUEUE_LIMIT,
+                target::WEBHOOK_QUEUE_LIMIT,
+            ))?,
+        };
+        args.validate()
+            .map_err(|e| anyhow::anyhow!("webhook target '{}' invalid: {}", name, e))?;
+        targets.insert(name.clone(), args);
+    }
+    Ok(targets)
+}
+
 lazy_static! {
-    pub static ref DEFAULT_KVS: HashMap<String, KVS> = maplit::hashmap! {};
+    pub static ref DEFAULT_KAFKA_KVS: KVS = KVS(vec![
+        KV {
+            key: ENABLE_KEY.to_owned(),
+            value: ENABLE_OFF.to_owned(),
+        },
+        KV {
+            key: target::KAFKA_BROKERS.to_owned(),
+            value: "".to_owned(),
+        },
+        KV {
+            key: target::KAFKA_TOPIC.to_owned(),
+            value: "".to_owned(),
+        },
+        KV {
+            key: target::KAFKA_TLS.to_owned(),
+            value: ENABLE_OFF.to_owned(),
+        },
+        KV {
+            key: target::KAFKA_TLS_SKIP_VERIFY.to_owned(),
+            value: ENABLE_OFF.to_owned(),
+        },
+        KV {
+            key: target::KAFKA_SASL.to_owned(),
+            value: ENABLE_OFF.to_owned(),
+        },
+        KV {
+            key: target::KAFKA_SASL_USERNAME.to_owned(),
+            value: "".to_owned(),
+        },
+        KV {
+            key: target::KAFKA_SASL_PASSWORD.to_owned(),
+            value: "".to_owned(),
+        },
+        KV {
+            key: target::KAFKA_SASL_MECHANISM.to_owned(),
+            value: "plain".to_owned(),
+        },
+        KV {
+            key: target::KAFKA_BATCH_SIZE.to_owned(),
+            value: "1".to_owned(),
+        },
+    ]);
+    pub static ref DEFAULT_WEBHOOK_KVS: KVS = KVS(vec![
+        KV {
+            key: ENABLE_KEY.to_owned(),
+            value: ENABLE_OFF.to_owned(),
+        },
+        KV {
+            key: target::WEBHOOK_ENDPOINT.to_owned(),
+            value: "".to_owned(),
+        },
+        KV {
+            key: target::WEBHOOK_AUTH_TOKEN.to_owned(),
+            value: "".to_owned(),
+        },
+        KV {
+            key: target::WEBHOOK_QUEUE_DIR.to_owned(),
+            value: "".to_owned(),
+        },
+        KV {
+            key: target::WEBHOOK_QUEUE_LIMIT.to_owned(),
+            value: "0".to_owned(),
+        },
+    ]);
+    pub static ref DEFAULT_KVS: HashMap<String, KVS> = maplit::hashmap! {
+        NOTIFY_KAFKA_SUB_SYS.to_owned() => DEFAULT_KAFKA_KVS.clone(),
+        NOTIFY_WEBHOOK_SUB_SYS.to_owned() => DEFAULT_WEBHOOK_KVS.clone(),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kafka_kvs(kvs: &[(&str, &str)]) -> KVS {
+        let mut default_kvs = DEFAULT_KAFKA_KVS.clone();
+        for (key, value) in kvs {
+            default_kvs.set(key.to_string(), value.to_string());
+        }
+        default_kvs
+    }
+
+    #[test]
+    fn test_get_notify_kafka() {
+        let kvs = kafka_kvs(&[
+            (ENABLE_KEY, ENABLE_ON),
+            (target::KAFKA_BROKERS, "localhost:9092, 10.0.0.1:9093"),
+            (target::KAFKA_TOPIC, "events"),
+            (target::KAFKA_SASL_USERNAME, "user"),
+            (target::KAFKA_BATCH_SIZE, "10"),
+        ]);
+        let targets = get_notify_kafka(&maplit::hashmap! {"1".to_owned() => kvs}).unwrap();
+        let args = &targets["1"];
+        assert!(args.enable);
+        assert_eq!(args.brokers, vec!["localhost:9092", "10.0.0.1:9093"]);
+        assert_eq!(args.topic, "events");
+        assert!(!args.tls.enable);
+        assert!(!args.sasl.enable);
+        assert_eq!(args.sasl.user, "user");
+        assert_eq!(args.sasl.mechanism, "plain");
+        assert_eq!(args.batch_size, 10);
+
+        // Disabled targets are not validated.
+        let targets =
+            get_notify_kafka(&maplit::hashmap! {"1".to_owned() => kafka_kvs(&[])}).unwrap();
+        assert!(!targets["1"].enable);
+
+        let invalid = vec![
+            vec![(ENABLE_KEY, ENABLE_ON), (target::KAFKA_TOPIC, "events")],
+            vec![
+                (ENABLE_KEY, ENABLE_ON),
+                (target::KAFKA_BROKERS, "localhost:9092"),
+            ],
+            vec![
+                (ENABLE_KEY, ENABLE_ON),
+                (target::KAFKA_BROKERS, "localhost:9092"),
+                (target::KAFKA_TOPIC, "events"),
+                (target::KAFKA_BATCH_SIZE, "many"),
+            ],
+            vec![(target::KAFKA_TLS, "maybe")],
+            // SASL is not supported.
+            vec![
+                (ENABLE_KEY, ENABLE_ON),
+                (target::KAFKA_BROKERS, "localhost:9092"),
+                (target::KAFKA_TOPIC, "events"),
+                (target::KAFKA_SASL, ENABLE_ON),
+            ],
+            vec![("unknown_key", "value")],
+        ];
+        for (i, kvs) in invalid.into_iter().enumerate() {
+            let kvs = maplit::hashmap! {"1".to_owned() => kafka_kvs(&kvs)};
+            assert!(get_notify_kafka(&kvs).is_err(), "test {}", i + 1);
+        }
+    }
+
+    fn http_client() -> HttpClient {
+        HttpClient {
+            client: reqwest::Client::new(),
+            root_certs: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_registered_webhook_targets() {
+        let mut cfg = Config::new();
+        cfg.set_kvs(
+            "notify_webhook:1 endpoint=http://localhost:8080/events auth_token=secret",
+            &DEFAULT_KVS,
+        )
+        .unwrap();
+        let target_id = event::TargetId {
+            id: "1".to_owned(),
+            name: "webhook".to_owned(),
+        };
+
+        let target_list =
+            get_notification_targets(cfg.clone(), http_client(), vec![target_id.clone()], false)
+                .await
+                .unwrap();
+        assert!(target_list.target_ids() == vec![target_id.clone()]);
+        assert!(
+            test_notification_targets(cfg, http_client(), vec![target_id])
+                .await
+                .is_ok()
+        );
+
+        let mut cfg = Config::new();
+        cfg.set_kvs("notify_webhook:1 endpoint=localhost:8080", &DEFAULT_KVS)
+            .unwrap();
+        assert!(get_notification_targets(cfg, http_client(), vec![], false)
+            .await
+            .is_err());
+    }
 }
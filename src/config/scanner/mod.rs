@@ -52,18 +52,20 @@ lazy_static! {
     ]);
 }
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 pub struct Config {
     // The sleep multiplier.
     pub delay: f64,
     // The maximum wait time between operations.
+    #[serde(with = "crate::serde::humantime_duration")]
     pub max_wait: Duration,
     // The duration between each scanner cycles.
+    #[serde(with = "crate::serde::humantime_duration")]
     pub cycle: Duration,
 }
 
 pub fn lookup_config(kvs: &KVS) -> anyhow::Result<Config> {
-    let _ = check_valid_keys(API_SUB_SYS, kvs, &DEFAULT_KVS)?;
+    let _ = check_valid_keys(SCANNER_SUB_SYS, kvs, &DEFAULT_KVS)?;
 
     let delay = std::env::var(ENV_DELAY).unwrap_or_else(|_| kvs.get(DELAY).to_owned());
     let delay = delay.parse::<f64>()?;
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use anyhow::bail;
+
+use super::*;
+use crate::globals::{Snapshot, GLOBALS};
+
+// Typed config of the sub-systems.
+#[derive(Default, Clone)]
+pub struct ParsedConfig {
+    pub api: api::Config,
+    pub cache: cache::Config,
+    pub compress: compress::Config,
+    pub heal: heal::Config,
+    pub scanner: scanner::Config,
+    pub storage_class: storageclass::Config,
+}
+
+impl ParsedConfig {
+    // Looks up the config of every sub-system, a sub-system missing from
+    // `kvs_by_sub_sys` gets its default kvs.
+    // On failure, the error lists every failing sub-system.
+    pub fn lookup(
+        kvs_by_sub_sys: &HashMap<String, KVS>,
+        set_drive_count: u8,
+    ) -> anyhow::Result<ParsedConfig> {
+        let mut errs = Vec::new();
+        let cfg = ParsedConfig {
+            api: lookup_sub_sys(
+                &mut errs,
+                kvs_by_sub_sys,
+                API_SUB_SYS,
+                &api::DEFAULT_KVS,
+                api::lookup_config,
+            ),
+            cache: lookup_sub_sys(
+                &mut errs,
+                kvs_by_sub_sys,
+                CACHE_SUB_SYS,
+                &cache::DEFAULT_KVS,
+                cache::lookup_config,
+            ),
+            compress: lookup_sub_sys(
+                &mut errs,
+                kvs_by_sub_sys,
+                COMPRESSION_SUB_SYS,
+                &compress::DEFAULT_KVS,
+                compress::lookup_config,
+            ),
+            heal: lookup_sub_sys(
+                &mut errs,
+                kvs_by_sub_sys,
+                HEAL_SUB_SYS,
+                &heal::DEFAULT_KVS,
+                heal::lookup_config,
+            ),
+            scanner: lookup_sub_sys(
+                &mut errs,
+                kvs_by_sub_sys,
+                SCANNER_SUB_SYS,
+                &scanner::DEFAULT_KVS,
+                scanner::lookup_config,
+            ),
+            storage_class: lookup_sub_sys(
+                &mut errs,
+                kvs_by_sub_sys,
+                STORAGE_CLASS_SUB_SYS,
+                &storageclass::DEFAULT_KVS,
+                |kvs| storageclass::lookup_config(kvs, set_drive_count),
+            ),
+        };
+        if !errs.is_empty() {
+            bail!(
+                "invalid config for {} sub-system(s): {}",
+                errs.len(),
+                errs.join("; ")
+            );
+        }
+        Ok(cfg)
+    }
+}
+
+fn lookup_sub_sys<T: Default>(
+    errs: &mut Vec<String>,
+    kvs_by_sub_sys: &HashMap<String, KVS>,
+    sub_sys: &str,
+    default_kvs: &KVS,
+    lookup_config: impl FnOnce(&KVS) -> anyhow::Result<T>,
+) -> T {
+    let kvs = kvs_by_sub_sys.get(sub_sys).unwrap_or(default_kvs);
+    lookup_config(kvs).unwrap_or_else(|err| {
+        errs.push(format!("'{}': {:#}", sub_sys, err));
+        T::default()
+    })
+}
+
+/// Validates the config of every sub-system, and applies it only if all of them
+/// are valid, so a bad sub-system never leaves the server with a partially
+/// reloaded config.
+/// Only the storage class config has a global, the validated config is returned
+/// for the other sub-systems to be applied by the caller.
+pub fn validate_all(
+    kvs_by_sub_sys: &HashMap<String, KVS>,
+    set_drive_count: u8,
+) -> anyhow::Result<ParsedConfig> {
+    validate_all_with(kvs_by_sub_sys, set_drive_count, |cfg| {
+        GLOBALS.storage_class.update(cfg.storage_class.clone())
+    })
+}
+
+fn validate_all_with(
+    kvs_by_sub_sys: &HashMap<String, KVS>,
+    set_drive_count: u8,
+    apply: impl FnOnce(&ParsedConfig),
+) -> anyhow::Result<ParsedConfig> {
+    let cfg = ParsedConfig::lookup(kvs_by_sub_sys, set_drive_count)?;
+    apply(&cfg);
+    Ok(cfg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_all_atomic() {
+        let mut current = ParsedConfig::default();
+        current.scanner.delay = 42.0;
+
+        let mut kvs_by_sub_sys = HashMap::new();
+        let mut api_kvs = api::DEFAULT_KVS.clone();
+        api_kvs.set("list_quorum".to_owned(), "invalid".to_owned());
+        kvs_by_sub_sys.insert(API_SUB_SYS.to_owned(), api_kvs);
+        let mut scanner_kvs = scanner::DEFAULT_KVS.clone();
+        scanner_kvs.set(scanner::DELAY.to_owned(), "5".to_owned());
+        kvs_by_sub_sys.insert(SCANNER_SUB_SYS.to_owned(), scanner_kvs);
+        let mut storage_class_kvs = storageclass::DEFAULT_KVS.clone();
+        storage_class_kvs.set(storageclass::CLASS_STANDARD.to_owned(), "EC:4".to_owned());
+        kvs_by_sub_sys.insert(STORAGE_CLASS_SUB_SYS.to_owned(), storage_class_kvs);
+
+        let err = validate_all_with(&kvs_by_sub_sys, 16, |cfg| current = cfg.clone()).unwrap_err();
+        let err = err.to_string();
+        assert!(err.contains("'api'"), "{}", err);
+        assert!(!err.contains("'scanner'"), "{}", err);
+        assert!(!err.contains("'storage_class'"), "{}", err);
+        // The valid scanner config is not applied either.
+        assert_eq!(current.scanner.delay, 42.0);
+
+        kvs_by_sub_sys.remove(API_SUB_SYS);
+        let cfg = validate_all_with(&kvs_by_sub_sys, 16, |cfg| current = cfg.clone()).unwrap();
+        assert_eq!(cfg.scanner.delay, 5.0);
+        assert_eq!(current.scanner.delay, 5.0);
+    }
+}
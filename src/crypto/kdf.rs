@@ -0,0 +1,88 @@
+use ring::hkdf;
+
+// Info string of the keys derived with `derive_key`, changing it changes
+// every derived key.
+const DERIVE_KEY_INFO: &[u8] = b"Hulk-Key-Derivation-v1";
+
+// Maximum length of HKDF-SHA256 output keying material.
+pub const MAX_DERIVED_KEY_LEN: usize = 255 * 32;
+
+struct Len(usize);
+
+impl hkdf::KeyType for Len {
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+// HKDF-SHA256 (RFC 5869) of `len` bytes, at most `MAX_DERIVED_KEY_LEN`.
+fn hkdf_sha256(salt: &[u8], ikm: &[u8], info: &[u8], len: usize) -> Vec<u8> {
+    assert!(len <= MAX_DERIVED_KEY_LEN, "derived key too long");
+    let mut okm = vec![0u8; len];
+    hkdf::Salt::new(hkdf::HKDF_SHA256, salt)
+        .extract(ikm)
+        .expand(&[info], Len(len))
+        .and_then(|okm_material| okm_material.fill(&mut okm))
+        .unwrap();
+    okm
+}
+
+/// Derives a key of `len` bytes from the `master` key, unique to `context`
+/// (e.g. the bucket and object path), with HKDF-SHA256.
+///
+/// Panics if `len` is above `MAX_DERIVED_KEY_LEN`.
+pub fn derive_key(master: &[u8], context: &[u8], len: usize) -> Vec<u8> {
+    let info = [DERIVE_KEY_INFO, context].concat();
+    hkdf_sha256(&[], master, &info, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+
+    use super::*;
+
+    #[test]
+    fn test_hkdf_sha256() {
+        // RFC 5869 test cases 1 to 3.
+        assert_eq!(
+            hkdf_sha256(
+                &hex!("000102030405060708090a0b0c"),
+                &[0x0b; 22],
+                &hex!("f0f1f2f3f4f5f6f7f8f9"),
+                42
+            ),
+            hex!("3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865")
+        );
+        let ikm: Vec<u8> = (0x00..=0x4f).collect();
+        let salt: Vec<u8> = (0x60..=0xaf).collect();
+        let info: Vec<u8> = (0xb0..=0xff).collect();
+        assert_eq!(
+            hkdf_sha256(&salt, &ikm, &info, 82),
+            hex!("b11e398dc80327a1c8e7f78c596a49344f012eda2d4efad8a050cc4c19afa97c59045a99cac7827271cb41c65e590e09da3275600c2f09b8367793a9aca3db71cc30c58179ec3e87c14c01d5c1f3434f1d87")
+        );
+        assert_eq!(
+            hkdf_sha256(&[], &[0x0b; 22], &[], 42),
+            hex!("8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d9d201395faa4b61a96c8")
+        );
+    }
+
+    #[test]
+    fn test_derive_key() {
+        let master = [0x07; 32];
+        let key = derive_key(&master, b"bucket/object", 32);
+        assert_eq!(
+            key,
+            hex!("eb658d1a14045dfe6ca10a4025c94245701dc67f269fe881872416c5a3b4675a")
+        );
+        assert_eq!(derive_key(&master, b"bucket/object", 32), key);
+        assert_ne!(derive_key(&master, b"bucket/object2", 32), key);
+        assert_ne!(derive_key(&[0x08; 32], b"bucket/object", 32), key);
+        // Shorter keys are prefixes of longer ones.
+        assert_eq!(derive_key(&master, b"bucket/object", 12), key[..12]);
+        assert_eq!(
+            derive_key(&master, b"bucket/object", MAX_DERIVED_KEY_LEN).len(),
+            MAX_DERIVED_KEY_LEN
+        );
+    }
+}
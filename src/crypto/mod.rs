@@ -98,10 +98,12 @@
 /// - ObjectKey := DAREv2_Dec(KeyEncKey, SealedKey)
 /// - object_data := DAREv2_Dec(ObjectKey, enc_object_data)
 /// Output: object_data
+mod kdf;
 mod sse;
 mod sse_c;
 mod sse_c_copy;
 
+pub use kdf::*;
 pub use sse::*;
 pub use sse_c::*;
 pub use sse_c_copy::*;
@@ -3,14 +3,15 @@ use std::sync::Arc;
 use anyhow::anyhow;
 use tokio::select;
 use tokio::sync::mpsc::channel;
-use tokio::sync::RwLock;
+use tokio::sync::{watch, RwLock};
 use tokio::time::{timeout, timeout_at, Duration, Instant};
 use tokio_util::sync::CancellationToken;
 
 use super::*;
 use crate::dsync::Dsync;
 use crate::trace;
-use crate::utils::{rng_seed_now, sleep, sleep_until};
+use crate::utils;
+use crate::utils::{rng_seed_now, sleep_jitter, sleep_until};
 
 // Tolerance limit to wait for lock acquisition before.
 const DRW_MUTEX_ACQUIRE_TIMEOUT: Duration = Duration::from_secs(1);
@@ -36,6 +37,9 @@ pub struct DRWLock<L: NetLocker + Send + Sync + 'static, D: Dsync<L>> {
     owner: String,
     write_locks: Arc<RwLock<Vec<String>>>, // Array of nodes that granted a write lock
     readers_locks: Arc<RwLock<Vec<Vec<String>>>>, // Array of array of nodes that granted reader locks
+    active_locks: std::sync::Mutex<Vec<LockInfo>>, // Locks currently held by this instance
+    refresh_interval: Duration,
+    refresh_guards: Vec<RefreshGuard>, // Refresh loops of the locks not returned to the caller
     token: CancellationToken,
     _phantom: std::marker::PhantomData<L>,
 }
@@ -45,6 +49,42 @@ pub struct Options {
     pub timeout: Duration,
 }
 
+// Describes a lock held on a resource.
+#[derive(Clone, Debug)]
+pub struct LockInfo {
+    pub resource: String,
+    pub owner: String,
+    pub uid: String,
+    pub is_read_lock: bool,
+    pub acquired_at: utils::DateTime,
+}
+
+// Keeps a held lock refreshed in the background. The refresh loop stops
+// when the guard is dropped or the lock is released.
+#[must_use = "the lock stops being refreshed when the guard is dropped"]
+pub struct RefreshGuard {
+    token: CancellationToken,
+    lost: watch::Receiver<bool>,
+}
+
+impl RefreshGuard {
+    /// Returns a receiver which turns true when the lock is lost,
+    /// i.e. it could not be refreshed on a quorum of the lockers.
+    pub fn lost(&self) -> watch::Receiver<bool> {
+        self.lost.clone()
+    }
+
+    pub fn is_lost(&self) -> bool {
+        *self.lost.borrow()
+    }
+}
+
+impl Drop for RefreshGuard {
+    fn drop(&mut self) {
+        self.token.cancel();
+    }
+}
+
 // Represents a structure of a granted lock.
 pub struct Granted {
     index: usize,
@@ -62,48 +102,142 @@ impl<L: NetLocker + Send + Sync + 'static, D: Dsync<L>> DRWLock<L, D> {
             owner,
             write_locks,
             readers_locks: Default::default(),
+            active_locks: Default::default(),
+            refresh_interval: DRW_MUTEX_REFRESH_INTERVAL,
+            refresh_guards: Vec::new(),
             token: CancellationToken::new(),
             _phantom: Default::default(),
         }
     }
 
-    pub async fn try_lock<F: FnOnce() + Send + 'static>(
-        &mut self,
-        lock_loss_callback: F,
-        id: &str,
-        source: &str,
-        opts: Options,
-    ) -> bool {
-        self.lock_blocking(Some(lock_loss_callback), id, source, false, opts)
-            .await
+    /// Sets the interval between two refresh calls of the held locks.
+    pub fn set_refresh_interval(&mut self, interval: Duration) {
+        self.refresh_interval = interval;
     }
 
-    pub async fn rlock(&mut self, id: &str, source: &str) {
+    /// Acquires the write lock, blocking until it succeeds. The lock is refreshed
+    /// until it is released or the returned guard is dropped.
+    #[must_use]
+    pub async fn lock(&mut self, id: &str, source: &str) -> RefreshGuard {
         self.lock_blocking(
-            Some(|| {}),
+            None::<fn()>,
             id,
             source,
-            true,
+            false,
             Options {
                 timeout: DRW_MUTEX_INFINITE,
             },
         )
-        .await;
+        .await
+        .expect("lock without timeout is always granted")
     }
 
-    pub async fn try_rlock<F: FnOnce() + Send + 'static>(
+    /// Tries to acquire the write lock until it succeeds or `opts.timeout` expires.
+    pub async fn get_lock<F: FnOnce() + Send + 'static>(
         &mut self,
         lock_loss_callback: F,
         id: &str,
         source: &str,
         opts: Options,
     ) -> bool {
-        self.lock_blocking(Some(lock_loss_callback), id, source, true, opts)
-            .await
+        let guard = self
+            .lock_blocking(Some(lock_loss_callback), id, source, false, opts)
+            .await;
+        self.keep_refreshing(guard)
+    }
+
+    pub async fn rlock(&mut self, id: &str, source: &str) {
+        let guard = self
+            .lock_blocking(
+                Some(|| {}),
+                id,
+                source,
+                true,
+                Options {
+                    timeout: DRW_MUTEX_INFINITE,
+                },
+            )
+            .await;
+        self.keep_refreshing(guard);
+    }
+
+    /// Tries to acquire the read lock until it succeeds or `opts.timeout` expires.
+    pub async fn get_rlock<F: FnOnce() + Send + 'static>(
+        &mut self,
+        lock_loss_callback: F,
+        id: &str,
+        source: &str,
+        opts: Options,
+    ) -> bool {
+        let guard = self
+            .lock_blocking(Some(lock_loss_callback), id, source, true, opts)
+            .await;
+        self.keep_refreshing(guard)
+    }
+
+    /// Tries to acquire the write lock once, failing immediately unless all
+    /// the lockers grant it. Any partially granted locks are released.
+    pub async fn try_lock(&mut self, id: &str, source: &str) -> bool {
+        let guard = self.lock_once(id, source, false).await;
+        self.keep_refreshing(guard)
+    }
+
+    /// Tries to acquire the read lock once, failing immediately unless all
+    /// the lockers grant it. Any partially granted locks are released.
+    pub async fn try_rlock(&mut self, id: &str, source: &str) -> bool {
+        let guard = self.lock_once(id, source, true).await;
+        self.keep_refreshing(guard)
+    }
+
+    // Keeps the refresh loop of a granted lock until it is released.
+    fn keep_refreshing(&mut self, guard: Option<RefreshGuard>) -> bool {
+        match guard {
+            Some(guard) => {
+                self.refresh_guards.push(guard);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Stops the refresh loops of the held locks.
+    fn stop_refreshing(&mut self) {
+        std::mem::replace(&mut self.token, CancellationToken::new()).cancel();
+        self.refresh_guards.clear();
+    }
+
+    /// Returns the locks currently held by this instance.
+    pub fn active_locks(&self) -> Vec<LockInfo> {
+        self.active_locks.lock().unwrap().clone()
+    }
+
+    fn add_active_locks(&self, uid: &str, is_read_lock: bool) {
+        let acquired_at = utils::now();
+        self.active_locks
+            .lock()
+            .unwrap()
+            .extend(self.names.iter().map(|resource| LockInfo {
+                resource: resource.clone(),
+                owner: self.owner.clone(),
+                uid: uid.to_owned(),
+                is_read_lock,
+                acquired_at,
+            }));
+    }
+
+    fn remove_active_locks(&self, uid: &str, is_read_lock: bool) {
+        let mut active_locks = self.active_locks.lock().unwrap();
+        for resource in &self.names {
+            if let Some(i) = active_locks.iter().position(|l| {
+                &l.resource == resource && l.uid == uid && l.is_read_lock == is_read_lock
+            }) {
+                active_locks.remove(i);
+            }
+        }
     }
 
     pub async fn unlock(&mut self) {
-        self.token.cancel();
+        self.stop_refreshing();
 
         // Check if minimally a single bool is set in the write_locks array
         let write_locks = self.write_locks.read().await;
@@ -112,6 +246,9 @@ impl<L: NetLocker + Send + Sync + 'static, D: Dsync<L>> DRWLock<L, D> {
             panic!("Trying to unlock while no lock is active");
         }
         let mut locks = Arc::new(RwLock::new(write_locks.clone()));
+        if let Some(uid) = write_locks.iter().find(|l| is_locked(l)) {
+            self.remove_active_locks(uid, false);
+        }
 
         // Tolerance is not set, defaults to half of the locker clients.
         let tolerance = self.lockers.len() / 2;
@@ -127,12 +264,12 @@ impl<L: NetLocker + Send + Sync + 'static, D: Dsync<L>> DRWLock<L, D> {
         )
         .await
         {
-            sleep(LOCK_RETRY_INTERVAL, Some(&mut rng)).await;
+            sleep_jitter(LOCK_RETRY_INTERVAL, 0.5, 1.5, Some(&mut rng)).await;
         }
     }
 
     pub async fn runlock(&mut self) {
-        self.token.cancel();
+        self.stop_refreshing();
 
         let mut readers_locks = self.readers_locks.write().await;
         if readers_locks.is_empty() {
@@ -140,6 +277,9 @@ impl<L: NetLocker + Send + Sync + 'static, D: Dsync<L>> DRWLock<L, D> {
         }
         // Take away and remove first element from array.
         let locks = readers_locks.remove(0);
+        if let Some(uid) = locks.iter().find(|l| is_locked(l)) {
+            self.remove_active_locks(uid, true);
+        }
         let mut locks = Arc::new(RwLock::new(locks));
 
         // Tolerance is not set, defaults to half of the locker clients.
@@ -156,10 +296,11 @@ impl<L: NetLocker + Send + Sync + 'static, D: Dsync<L>> DRWLock<L, D> {
         )
         .await
         {
-            sleep(LOCK_RETRY_INTERVAL, Some(&mut rng)).await;
+            sleep_jitter(LOCK_RETRY_INTERVAL, 0.5, 1.5, Some(&mut rng)).await;
         }
     }
 
+    #[must_use]
     async fn lock_blocking<F: FnOnce() + Send + 'static>(
         &mut self,
         lock_loss_callback: Option<F>,
@@ -167,7 +308,7 @@ impl<L: NetLocker + Send + Sync + 'static, D: Dsync<L>> DRWLock<L, D> {
         source: &str,
         is_read_lock: bool,
         opts: Options,
-    ) -> bool {
+    ) -> Option<RefreshGuard> {
         let (lockers, _) = self.dsync.get_lockers();
 
         let mut rng = rng_seed_now();
@@ -181,18 +322,7 @@ impl<L: NetLocker + Send + Sync + 'static, D: Dsync<L>> DRWLock<L, D> {
             opts
         );
 
-        // Tolerance is not set, defaults to half of the locker clients.
-        let mut tolerance = lockers.len() / 2;
-        let mut quorum = lockers.len() - tolerance;
-        if !is_read_lock && quorum == tolerance {
-            // In situations for write locks, as a special case
-            // to avoid split brains we make sure to acquire
-            // quorum + 1 when tolerance is exactly half of the
-            // total locker clients.
-            quorum += 1;
-            // So tolerance - 1.
-            tolerance -= 1;
-        }
+        let (tolerance, quorum) = lock_quorum(lockers.len(), is_read_lock);
 
         let (lockers, owner) = self.dsync.get_lockers();
 
@@ -241,27 +371,85 @@ impl<L: NetLocker + Send + Sync + 'static, D: Dsync<L>> DRWLock<L, D> {
             match locked {
                 Ok(locked) => {
                     if locked {
+                        self.add_active_locks(id, is_read_lock);
                         // Refresh lock continuously and cancel if there is no quorum in the lock anymore
                         let lockers = lockers.clone();
-                        self.start_continuous_lock_refresh(
+                        return Some(self.start_continuous_lock_refresh(
                             lock_loss_callback,
                             lockers,
                             owner,
                             id.to_owned(),
                             source.to_owned(),
                             quorum,
-                        );
-                        return locked;
+                        ));
                     }
                     sleep_until(deadline, LOCK_RETRY_INTERVAL, Some(&mut rng)).await;
                 }
                 Err(_) => {
-                    return false;
+                    return None;
                 }
             }
         }
     }
 
+    #[must_use]
+    async fn lock_once(
+        &mut self,
+        id: &str,
+        source: &str,
+        is_read_lock: bool,
+    ) -> Option<RefreshGuard> {
+        let (lockers, owner) = self.dsync.get_lockers();
+
+        trace!(
+            "lock_once {}/{} for {:?}: lock type {}",
+            id,
+            source,
+            self.names,
+            if is_read_lock { "Read" } else { "Write" },
+        );
+
+        // All the lockers have to grant the lock, so no failure is tolerated.
+        let mut locks = Arc::new(RwLock::new(vec!["".to_owned(); lockers.len()]));
+        let locked = lock(
+            lockers.clone(),
+            &owner,
+            &mut locks,
+            id,
+            source,
+            is_read_lock,
+            0,
+            lockers.len(),
+            &self.names,
+            Instant::now() + DRW_MUTEX_ACQUIRE_TIMEOUT,
+        )
+        .await;
+        if !locked {
+            return None;
+        }
+
+        let locks = locks.read().await.clone();
+        if is_read_lock {
+            self.readers_locks.write().await.push(locks);
+        } else {
+            *self.write_locks.write().await = locks;
+        }
+        trace!("lock_once {}/{} for {:?}: granted", id, source, self.names);
+        self.add_active_locks(id, is_read_lock);
+
+        // Once held, the lock is kept as long as the usual quorum refreshes it.
+        let (_, quorum) = lock_quorum(lockers.len(), is_read_lock);
+        Some(self.start_continuous_lock_refresh(
+            None::<fn()>,
+            lockers,
+            owner,
+            id.to_owned(),
+            source.to_owned(),
+            quorum,
+        ))
+    }
+
+    #[must_use]
     fn start_continuous_lock_refresh<F: FnOnce() + Send + 'static>(
         &mut self,
         lock_loss_callback: Option<F>,
@@ -270,11 +458,19 @@ impl<L: NetLocker + Send + Sync + 'static, D: Dsync<L>> DRWLock<L, D> {
         id: String,
         source: String,
         quorum: usize,
-    ) {
-        let token = self.token.clone();
+    ) -> RefreshGuard {
+        let token = self.token.child_token();
+        let (lost_tx, lost) = watch::channel(false);
+        let guard = RefreshGuard {
+            token: token.clone(),
+            lost,
+        };
         let names = self.names.clone();
+        let refresh_interval = self.refresh_interval;
         tokio::spawn(async move {
-            let mut interval = tokio::time::interval(DRW_MUTEX_REFRESH_INTERVAL);
+            // The lock has just been acquired, so the first refresh is due after an interval.
+            let mut interval =
+                tokio::time::interval_at(Instant::now() + refresh_interval, refresh_interval);
             loop {
                 let lockers = lockers.clone();
                 let owner = owner.clone();
@@ -287,6 +483,7 @@ impl<L: NetLocker + Send + Sync + 'static, D: Dsync<L>> DRWLock<L, D> {
                     _ = interval.tick() => {
                         if let Ok(refreshed) = refresh(token.clone(), lockers, &owner, &id, &source, quorum, &names).await {
                             if !refreshed {
+                                let _ = lost_tx.send(true);
                                 if let Some(lock_loss_callback) = lock_loss_callback {
                                     lock_loss_callback();
                                 }
@@ -297,9 +494,27 @@ impl<L: NetLocker + Send + Sync + 'static, D: Dsync<L>> DRWLock<L, D> {
                 }
             }
         }); // do not await
+        guard
     }
 }
 
+// Returns the tolerance and quorum of a lock over `lockers` lockers.
+fn lock_quorum(lockers: usize, is_read_lock: bool) -> (usize, usize) {
+    // Tolerance is not set, defaults to half of the locker clients.
+    let mut tolerance = lockers / 2;
+    let mut quorum = lockers - tolerance;
+    if !is_read_lock && quorum == tolerance {
+        // In situations for write locks, as a special case
+        // to avoid split brains we make sure to acquire
+        // quorum + 1 when tolerance is exactly half of the
+        // total locker clients.
+        quorum += 1;
+        // So tolerance - 1.
+        tolerance -= 1;
+    }
+    (tolerance, quorum)
+}
+
 impl Granted {
     fn is_locked(&self) -> bool {
         is_locked(&self.lock_uid)
@@ -361,10 +576,11 @@ async fn refresh<L: NetLocker + Send + Sync + 'static>(
                                 })
                                 .await;
                         } else {
+                            // The locker does not hold the lock anymore.
                             let _ = tx
                                 .send(RefreshResult {
                                     succeeded: false,
-                                    offline: true,
+                                    offline: false,
                                 })
                                 .await;
                             trace!(
@@ -383,10 +599,11 @@ async fn refresh<L: NetLocker + Send + Sync + 'static>(
                     err = e.into();
                 }
             }
+            // The locker could not be reached.
             let _ = tx
                 .send(RefreshResult {
                     succeeded: false,
-                    offline: false,
+                    offline: true,
                 })
                 .await;
             trace!(
@@ -596,7 +813,7 @@ async fn lock<L: NetLocker + Send + Sync + 'static>(
         }
     });
 
-    true
+    quorum_locked
 }
 
 async fn release_all<L: NetLocker + Send + Sync + 'static>(
@@ -628,7 +845,7 @@ async fn release_all<L: NetLocker + Send + Sync + 'static>(
         r.unwrap(); // no task should panic
     }
 
-    check_failed_unlocks(&locks.read().await, tolerance)
+    !check_failed_unlocks(&locks.read().await, tolerance)
 }
 
 async fn send_release<L: NetLocker + Send + Sync + 'static>(
@@ -683,7 +900,7 @@ async fn send_release<L: NetLocker + Send + Sync + 'static>(
 
 // Determines whether we have locked the required quorum of underlying locks or not.
 fn check_quorum_locked(locks: &[String], quorum: usize) -> bool {
-    locks.iter().filter(|&uid| is_locked(uid)).count() > quorum
+    locks.iter().filter(|&uid| is_locked(uid)).count() >= quorum
 }
 
 // Determines whether we have sufficiently unlocked all
@@ -696,3 +913,237 @@ fn check_failed_unlocks(locks: &[String], tolerance: usize) -> bool {
         unlocks_failed > tolerance
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::fmt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    struct MockLocker {
+        index: usize,
+        refuse: bool,
+        held: Arc<std::sync::Mutex<HashSet<String>>>,
+        refreshes: Arc<AtomicUsize>,
+    }
+
+    impl fmt::Display for MockLocker {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "mock-locker-{}", self.index)
+        }
+    }
+
+    impl MockLocker {
+        fn grant(&self, args: &LockArgs) -> anyhow::Result<bool> {
+            if self.refuse {
+                return Ok(false);
+            }
+            let mut held = self.held.lock().unwrap();
+            if args.resources.iter().any(|r| held.contains(r)) {
+                return Ok(false);
+            }
+            held.extend(args.resources.iter().cloned());
+            Ok(true)
+        }
+
+        fn release(&self, args: &LockArgs) -> anyhow::Result<bool> {
+            let mut held = self.held.lock().unwrap();
+            args.resources.iter().for_each(|r| {
+                held.remove(r);
+            });
+            Ok(true)
+        }
+    }
+
+    #[async_trait]
+    impl NetLocker for MockLocker {
+        async fn rlock(&mut self, args: &LockArgs) -> anyhow::Result<bool> {
+            self.grant(args)
+        }
+
+        async fn lock(&mut self, args: &LockArgs) -> anyhow::Result<bool> {
+            self.grant(args)
+        }
+
+        async fn runlock(&mut self, args: &LockArgs) -> anyhow::Result<bool> {
+            self.release(args)
+        }
+
+        async fn unlock(&mut self, args: &LockArgs) -> anyhow::Result<bool> {
+            self.release(args)
+        }
+
+        async fn refresh(
+            &mut self,
+            _token: CancellationToken,
+            args: &LockArgs,
+        ) -> anyhow::Result<bool> {
+            self.refreshes.fetch_add(1, Ordering::SeqCst);
+            let held = self.held.lock().unwrap();
+            Ok(args.resources.iter().all(|r| held.contains(r)))
+        }
+
+        async fn force_unlock(&mut self, args: &LockArgs) -> anyhow::Result<bool> {
+            self.release(args)
+        }
+
+        async fn close(&mut self) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn is_online(&self) -> bool {
+            true
+        }
+
+        fn is_local(&self) -> bool {
+            true
+        }
+    }
+
+    struct MockDsync(Vec<Arc<RwLock<MockLocker>>>);
+
+    impl Dsync<MockLocker> for MockDsync {
+        fn get_lockers(&self) -> (Vec<Arc<RwLock<MockLocker>>>, String) {
+            (self.0.clone(), "owner".to_owned())
+        }
+    }
+
+    fn new_lockers(
+        refuse: &[bool],
+    ) -> Vec<(
+        Arc<RwLock<MockLocker>>,
+        Arc<std::sync::Mutex<HashSet<String>>>,
+    )> {
+        refuse
+            .iter()
+            .enumerate()
+            .map(|(index, &refuse)| {
+                let held: Arc<std::sync::Mutex<HashSet<String>>> = Default::default();
+                let locker = MockLocker {
+                    index,
+                    refuse,
+                    held: held.clone(),
+                    refreshes: Default::default(),
+                };
+                (Arc::new(RwLock::new(locker)), held)
+            })
+            .collect()
+    }
+
+    async fn wait_released(held: &[Arc<std::sync::Mutex<HashSet<String>>>]) -> bool {
+        for _ in 0..100 {
+            if held.iter().all(|h| h.lock().unwrap().is_empty()) {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn test_drwlock_try_lock() {
+        let lockers = new_lockers(&[false, false, false]);
+        let held: Vec<_> = lockers.iter().map(|(_, h)| h.clone()).collect();
+        let dsync = MockDsync(lockers.into_iter().map(|(l, _)| l).collect());
+        let mut lock = DRWLock::new(dsync, vec!["bucket/object".to_owned()]);
+
+        assert!(lock.try_lock("id", "source").await);
+        assert!(held
+            .iter()
+            .all(|h| h.lock().unwrap().contains("bucket/object")));
+        lock.unlock().await;
+        assert!(lock.active_locks().is_empty());
+        assert!(wait_released(&held).await);
+    }
+
+    #[tokio::test]
+    async fn test_drwlock_try_lock_refused() {
+        let lockers = new_lockers(&[false, false, true]);
+        let held: Vec<_> = lockers.iter().map(|(_, h)| h.clone()).collect();
+        let dsync = MockDsync(lockers.into_iter().map(|(l, _)| l).collect());
+        let mut lock = DRWLock::new(dsync, vec!["bucket/object".to_owned()]);
+
+        assert!(!lock.try_lock("id", "source").await);
+        assert!(
+            wait_released(&held).await,
+            "partially granted locks are not released"
+        );
+        assert!(!lock.try_rlock("id", "source").await);
+        assert!(wait_released(&held).await);
+    }
+
+    #[tokio::test]
+    async fn test_drwlock_active_locks() {
+        let lockers = new_lockers(&[false, false, false, false]);
+        let dsync = MockDsync(lockers.into_iter().map(|(l, _)| l).collect());
+        let mut lock = DRWLock::new(dsync, vec!["bucket/object".to_owned()]);
+        assert!(lock.active_locks().is_empty());
+
+        let before = utils::now();
+        assert!(
+            lock.get_lock(
+                || {},
+                "id",
+                "source",
+                Options {
+                    timeout: Duration::from_secs(5),
+                },
+            )
+            .await
+        );
+        let active_locks = lock.active_locks();
+        assert_eq!(active_locks.len(), 1);
+        let info = &active_locks[0];
+        assert_eq!(info.resource, "bucket/object");
+        assert_eq!(info.owner, "owner");
+        assert_eq!(info.uid, "id");
+        assert!(!info.is_read_lock);
+        assert!(info.acquired_at >= before && info.acquired_at <= utils::now());
+
+        lock.unlock().await;
+        assert!(lock.active_locks().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_drwlock_refresh() {
+        let lockers = new_lockers(&[false, false, false]);
+        let held: Vec<_> = lockers.iter().map(|(_, h)| h.clone()).collect();
+        let mut refreshes = Vec::new();
+        for (locker, _) in &lockers {
+            refreshes.push(locker.read().await.refreshes.clone());
+        }
+        let refresh_count = || {
+            refreshes
+                .iter()
+                .map(|r| r.load(Ordering::SeqCst))
+                .min()
+                .unwrap()
+        };
+        let dsync = MockDsync(lockers.into_iter().map(|(l, _)| l).collect());
+        let mut lock = DRWLock::new(dsync, vec!["bucket/object".to_owned()]);
+        lock.set_refresh_interval(Duration::from_millis(20));
+
+        let guard = lock.lock("id", "source").await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(refresh_count() >= 3, "refreshed {} times", refresh_count());
+        assert!(!guard.is_lost());
+
+        lock.unlock().await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let count = refresh_count();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(refresh_count(), count);
+
+        // Losing the lock on the lockers is signaled to the holder.
+        let guard = lock.lock("id", "source").await;
+        let mut lost = guard.lost();
+        held.iter().for_each(|h| h.lock().unwrap().clear());
+        tokio::time::timeout(Duration::from_secs(1), lost.changed())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(guard.is_lost());
+    }
+}
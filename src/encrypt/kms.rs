@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, ensure};
+use const_format::concatcp;
+use rand::Rng;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+
+use crate::config::ENV_KMS_SECRET_KEY;
+use crate::globals::RESERVED_METADATA_PREFIX;
+
+// Metadata of SSE-KMS objects: the KMS key id and the ciphertext blob of the
+// data key the object is encrypted with.
+pub const META_KMS_KEY_ID: &str = concatcp!(
+    RESERVED_METADATA_PREFIX,
+    "Server-Side-Encryption-Kms-Key-Id"
+);
+pub const META_KMS_SEALED_KEY: &str = concatcp!(
+    RESERVED_METADATA_PREFIX,
+    "Server-Side-Encryption-Kms-Sealed-Key"
+);
+
+pub const DATA_KEY_LEN: usize = 32;
+
+/// Data key generated by a KMS.
+pub struct DataKey {
+    pub plaintext: [u8; DATA_KEY_LEN],
+    // The plaintext key encrypted by the KMS, only the KMS can decrypt it.
+    pub ciphertext: Vec<u8>,
+}
+
+/// Key management service, encrypting the data keys of objects with its
+/// master keys.
+pub trait Kms: Send + Sync {
+    /// Generates a new data key, encrypted with the master key `key_id`.
+    fn generate_data_key(&self, key_id: &str) -> anyhow::Result<DataKey>;
+
+    /// Decrypts a data key generated by `generate_data_key`.
+    fn decrypt(&self, ciphertext: &[u8]) -> anyhow::Result<[u8; DATA_KEY_LEN]>;
+}
+
+/// KMS with a single master key, e.g. set with `HULK_KMS_SECRET_KEY`.
+/// Data keys are sealed with AES-256-GCM, the key id being authenticated.
+///
+/// Ciphertext blob: key id length (1 byte) || key id || nonce || sealed key.
+pub struct LocalKms {
+    key_id: String,
+    master_key: LessSafeKey,
+}
+
+impl LocalKms {
+    pub fn new(key_id: &str, master_key: &[u8]) -> anyhow::Result<LocalKms> {
+        ensure!(
+            !key_id.is_empty() && key_id.len() <= u8::MAX as usize,
+            "invalid KMS key id '{}'",
+            key_id
+        );
+        let master_key = UnboundKey::new(&AES_256_GCM, master_key)
+            .map_err(|_| anyhow!("KMS master key must be {} bytes", DATA_KEY_LEN))?;
+        Ok(LocalKms {
+            key_id: key_id.to_owned(),
+            master_key: LessSafeKey::new(master_key),
+        })
+    }
+
+    /// Parses the master key from `<key-id>:<base64 key>`.
+    pub fn parse(secret_key: &str) -> anyhow::Result<LocalKms> {
+        let (key_id, key) = secret_key
+            .split_once(':')
+            .ok_or_else(|| anyhow!("KMS secret key must be '<key-id>:<base64 key>'"))?;
+        let key = base64::decode(key).map_err(|err| anyhow!("invalid KMS secret key: {}", err))?;
+        LocalKms::new(key_id, &key)
+    }
+
+    /// Creates the KMS from `HULK_KMS_SECRET_KEY`, if set.
+    pub fn from_env() -> anyhow::Result<Option<LocalKms>> {
+        match std::env::var(ENV_KMS_SECRET_KEY) {
+            Ok(secret_key) => Ok(Some(LocalKms::parse(&secret_key)?)),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+impl Kms for LocalKms {
+    fn generate_data_key(&self, key_id: &str) -> anyhow::Result<DataKey> {
+        ensure!(key_id == self.key_id, "KMS key '{}' does not exist", key_id);
+        let mut rng = rand::thread_rng();
+        let mut plaintext = [0u8; DATA_KEY_LEN];
+        rng.fill(&mut plaintext);
+        let mut nonce = [0u8; NONCE_LEN];
+        rng.fill(&mut nonce);
+
+        let mut sealed = plaintext.to_vec();
+        self.master_key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(key_id.as_bytes()),
+                &mut sealed,
+            )
+            .map_err(|_| anyhow!("failed to seal data key"))?;
+
+        let mut ciphertext = Vec::with_capacity(1 + key_id.len() + NONCE_LEN + sealed.len());
+        ciphertext.push(key_id.len() as u8);
+        ciphertext.extend_from_slice(key_id.as_bytes());
+        ciphertext.extend_from_slice(&nonce);
+        ciphertext.extend_from_slice(&sealed);
+        Ok(DataKey {
+            plaintext,
+            ciphertext,
+        })
+    }
+
+    fn decrypt(&self, ciphertext: &[u8]) -> anyhow::Result<[u8; DATA_KEY_LEN]> {
+        let (&key_id_len, rest) = ciphertext
+            .split_first()
+            .ok_or_else(|| anyhow!("malformed KMS ciphertext"))?;
+        let key_id_len = key_id_len as usize;
+        ensure!(
+            rest.len() == key_id_len + NONCE_LEN + DATA_KEY_LEN + AES_256_GCM.tag_len(),
+            "malformed KMS ciphertext"
+        );
+        let (key_id, rest) = rest.split_at(key_id_len);
+        ensure!(
+            key_id == self.key_id.as_bytes(),
+            "KMS key '{}' does not exist",
+            String::from_utf8_lossy(key_id)
+        );
+        let (nonce, sealed) = rest.split_at(NONCE_LEN);
+
+        let mut sealed = sealed.to_vec();
+        let plaintext = self
+            .master_key
+            .open_in_place(
+                Nonce::try_assume_unique_for_key(nonce).unwrap(),
+                Aad::from(key_id),
+                &mut sealed,
+            )
+            .map_err(|_| anyhow!("failed to decrypt data key"))?;
+        let mut key = [0u8; DATA_KEY_LEN];
+        key.copy_from_slice(plaintext);
+        Ok(key)
+    }
+}
+
+/// Generates the data key of a new SSE-KMS object, storing its ciphertext
+/// blob in the object metadata, and returns the plaintext key to encrypt
+/// the object with.
+pub fn seal_object_key(
+    kms: &dyn Kms,
+    key_id: &str,
+    metadata: &mut HashMap<String, String>,
+) -> anyhow::Result<[u8; DATA_KEY_LEN]> {
+    let key = kms.generate_data_key(key_id)?;
+    metadata.insert(META_KMS_KEY_ID.to_owned(), key_id.to_owned());
+    metadata.insert(
+        META_KMS_SEALED_KEY.to_owned(),
+        base64::encode(&key.ciphertext),
+    );
+    Ok(key.plaintext)
+}
+
+/// Returns the plaintext data key of an SSE-KMS object from its metadata.
+pub fn unseal_object_key(
+    kms: &dyn Kms,
+    metadata: &HashMap<String, String>,
+) -> anyhow::Result<[u8; DATA_KEY_LEN]> {
+    let sealed = match metadata.get(META_KMS_SEALED_KEY) {
+        Some(sealed) => sealed,
+        None => bail!("object is not encrypted with a KMS key"),
+    };
+    let ciphertext =
+        base64::decode(sealed).map_err(|err| anyhow!("invalid sealed object key: {}", err))?;
+    kms.decrypt(&ciphertext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_ID: &str = "my-key";
+
+    fn kms() -> LocalKms {
+        LocalKms::parse(&format!("{}:{}", KEY_ID, base64::encode([7u8; 32]))).unwrap()
+    }
+
+    #[test]
+    fn test_local_kms() {
+        let kms = kms();
+        let key = kms.generate_data_key(KEY_ID).unwrap();
+        assert_ne!(key.ciphertext[..], key.plaintext[..]);
+        assert_eq!(kms.decrypt(&key.ciphertext).unwrap(), key.plaintext);
+        // Every data key is unique.
+        let other = kms.generate_data_key(KEY_ID).unwrap();
+        assert_ne!(other.plaintext, key.plaintext);
+
+        assert!(kms.generate_data_key("other-key").is_err());
+
+        // Tampered ciphertext blobs.
+        let mut tampered = key.ciphertext.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(kms.decrypt(&tampered).is_err());
+        let mut tampered = key.ciphertext.clone();
+        tampered[1] = b'M';
+        assert!(kms.decrypt(&tampered).is_err());
+        assert!(kms
+            .decrypt(&key.ciphertext[..key.ciphertext.len() - 1])
+            .is_err());
+        assert!(kms.decrypt(&[]).is_err());
+
+        // Another master key.
+        let kms2 = LocalKms::new(KEY_ID, &[8u8; 32]).unwrap();
+        assert!(kms2.decrypt(&key.ciphertext).is_err());
+
+        assert!(LocalKms::parse("my-key").is_err());
+        assert!(LocalKms::parse("my-key:c2hvcnQ=").is_err());
+    }
+
+    #[test]
+    fn test_object_key_metadata() {
+        let kms = kms();
+        let mut metadata = HashMap::new();
+        assert!(unseal_object_key(&kms, &metadata).is_err());
+
+        let key = seal_object_key(&kms, KEY_ID, &mut metadata).unwrap();
+        assert_eq!(metadata.get(META_KMS_KEY_ID).unwrap(), KEY_ID);
+        assert_eq!(unseal_object_key(&kms, &metadata).unwrap(), key);
+
+        let sealed = metadata.get_mut(META_KMS_SEALED_KEY).unwrap();
+        let mut ciphertext = base64::decode(&sealed).unwrap();
+        ciphertext[20] ^= 0xff;
+        *sealed = base64::encode(&ciphertext);
+        assert!(unseal_object_key(&kms, &metadata).is_err());
+    }
+}
@@ -1,3 +1,5 @@
+mod kms;
 mod server_side;
 
+pub use kms::*;
 pub use server_side::*;
@@ -257,6 +257,29 @@ fn get_all_sets(args: &[&str]) -> anyhow::Result<Vec<Vec<String>>> {
     Ok(set_args)
 }
 
+impl PoolEndpoints {
+    /// Expands the args of a pool, with or without ellipses, into its
+    /// endpoints, divided in sets of the optimal size.
+    ///
+    /// Hosts are not resolved, so URL endpoints are not flagged as local,
+    /// see `Endpoints::update_is_local`.
+    pub fn from_ellipses(args: &[String]) -> anyhow::Result<PoolEndpoints> {
+        ensure!(!args.is_empty(), TypedError::InvalidArgument);
+        let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+        let set_args = get_all_sets(&args)?;
+        let endpoints = set_args
+            .iter()
+            .flatten()
+            .map(|arg| Endpoint::new(arg))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(PoolEndpoints {
+            set_count: set_args.len(),
+            drives_per_set: set_args[0].len(),
+            endpoints: Endpoints(endpoints),
+        })
+    }
+}
+
 pub async fn create_server_endpoints(
     server_addr: &str,
     args: &[&str],
@@ -738,4 +761,31 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_pool_endpoints_from_ellipses() {
+        let cases = vec![
+            ("http://h{1...4}/d{1...8}", 2, 16),
+            ("http://host{1...2}/data{1...180}", 30, 12),
+            ("/data{1...64}", 4, 16),
+        ];
+        for (i, (arg, set_count, drives_per_set)) in cases.into_iter().enumerate() {
+            let pool = PoolEndpoints::from_ellipses(&[arg.to_owned()]).unwrap();
+            assert_eq!(pool.set_count, set_count, "test {}", i + 1);
+            assert_eq!(pool.drives_per_set, drives_per_set, "test {}", i + 1);
+            assert_eq!(
+                pool.endpoints.iter().count(),
+                set_count * drives_per_set,
+                "test {}",
+                i + 1
+            );
+        }
+
+        let pool = PoolEndpoints::from_ellipses(&["http://h1/d1".to_owned()]).unwrap();
+        assert_eq!((pool.set_count, pool.drives_per_set), (1, 1));
+        assert_eq!(pool.endpoints.get_string(0), "http://h1/d1");
+
+        assert!(PoolEndpoints::from_ellipses(&[]).is_err());
+        assert!(PoolEndpoints::from_ellipses(&["/data{1...3}".to_owned()]).is_err());
+    }
 }
@@ -157,6 +157,14 @@ impl Endpoint {
         }
     }
 
+    // Tells whether the endpoints are on the same host, and the path of one
+    // is the path of the other or within it.
+    fn path_overlaps(&self, other: &Endpoint) -> bool {
+        let (path, other_path) = (Path::new(self.path()), Path::new(other.path()));
+        self.host() == other.host()
+            && (path.starts_with(other_path) || other_path.starts_with(path))
+    }
+
     pub fn is_local(&self) -> bool {
         match self {
             Endpoint::Path(_) => true,
@@ -267,18 +275,64 @@ impl Endpoints {
         return false;
     }
 
-    async fn check_cross_device_mounts(&self) -> anyhow::Result<()> {
+    async fn check_cross_device_mounts(
+        &self,
+        checker: &mut crate::mount::CrossDeviceChecker,
+    ) -> anyhow::Result<()> {
         let mut abs_paths = Vec::new();
         for e in &self.0 {
             if e.is_local() {
                 abs_paths.push(Path::new(e.path()).absolutize()?)
             }
         }
-        crate::mount::check_cross_device(&abs_paths)
+        checker.check(&abs_paths)
+    }
+
+    pub async fn update_is_local(&mut self, found_prev_local: bool) -> anyhow::Result<()> {
+        let local_port = GLOBALS.rpc_port.guard().clone();
+        self.update_is_local_with(found_prev_local, |host, port| {
+            let local_port = local_port.clone();
+            async move { is_local_host(&host, &port, &local_port).await }
+        })
+        .await
     }
 
-    pub fn update_is_local(&mut self, found_prev_local: bool) -> anyhow::Result<()> {
-        todo!()
+    async fn update_is_local_with<F, Fut>(
+        &mut self,
+        found_prev_local: bool,
+        is_local_host: F,
+    ) -> anyhow::Result<()>
+    where
+        F: Fn(String, String) -> Fut,
+        Fut: Future<Output = anyhow::Result<bool>>,
+    {
+        // Resolve each host and port only once.
+        let mut resolved: HashMap<(String, String), bool> = HashMap::new();
+        for endpoint in self.0.iter_mut() {
+            let (url, is_local) = match endpoint {
+                Endpoint::Url(url, is_local) if !*is_local => (url, is_local),
+                _ => continue,
+            };
+            let key = (
+                url.host_str().unwrap_or_default().to_owned(),
+                url.port().map(|p| p.to_string()).unwrap_or_default(),
+            );
+            *is_local = match resolved.get(&key) {
+                Some(&local) => local,
+                None => {
+                    let local = match is_local_host(key.0.clone(), key.1.clone()).await {
+                        Ok(local) => local,
+                        // A local endpoint was already found in a previous pool,
+                        // so a host which cannot be resolved is a remote one.
+                        Err(_) if found_prev_local => false,
+                        Err(err) => return Err(err),
+                    };
+                    resolved.insert(key, local);
+                    local
+                }
+            };
+        }
+        Ok(())
     }
 }
 
@@ -311,6 +365,17 @@ impl EndpointServerPools {
                 "duplicate endpoints found"
             );
         }
+        // Nested paths on the same host would have pools store data in each other.
+        for e in &p.endpoints.0 {
+            for other in self.0.iter().flat_map(|p| p.endpoints.0.iter()) {
+                ensure!(
+                    !e.path_overlaps(other),
+                    "endpoint '{}' overlaps with endpoint '{}'",
+                    e,
+                    other
+                );
+            }
+        }
         self.0.push(p);
         Ok(())
     }
@@ -423,6 +488,7 @@ pub(self) async fn create_endpoints(
     let (_, server_addr_port) = split_host_port(server_addr).unwrap();
 
     let mut endpoints = Vec::new();
+    let mut cross_device_checker = crate::mount::CrossDeviceChecker::new()?;
 
     // For single arg, return FS setup.
     if args_list.len() == 1 && args_list[0].len() == 1 {
@@ -436,7 +502,7 @@ pub(self) async fn create_endpoints(
         let endpoints = Endpoints(endpoints);
         // Check for cross device mounts if any.
         endpoints
-            .check_cross_device_mounts()
+            .check_cross_device_mounts(&mut cross_device_checker)
             .await
             .map_err(|e| UiError::InvalidFSEndpoint.msg(e.to_string()))?;
 
@@ -446,7 +512,7 @@ pub(self) async fn create_endpoints(
     for args in args_list {
         let eps = Endpoints::new(args)
             .map_err(|e| UiError::InvalidErasureEndpoints.msg(e.to_string()))?;
-        eps.check_cross_device_mounts()
+        eps.check_cross_device_mounts(&mut cross_device_checker)
             .await
             .map_err(|e| UiError::InvalidErasureEndpoints.msg(e.to_string()))?;
         endpoints.extend(eps.0.into_iter());
@@ -464,6 +530,7 @@ pub(self) async fn create_endpoints(
     let mut endpoints = Endpoints(endpoints);
     endpoints
         .update_is_local(found_local)
+        .await
         .map_err(|e| UiError::InvalidErasureEndpoints.msg(e.to_string()))?;
 
     let mut endpoint_path_set = StringSet::new();
@@ -581,16 +648,26 @@ pub async fn update_domain_ips(endpoints: &StringSet) {
         }
     }
 
-    *GLOBALS.domain_ips.guard() = ip_list.match_fn(|ip| {
-        let host_port = split_host_port(ip);
-        let ip = if let Ok((ref host, _)) = host_port {
-            host
-        } else {
-            ip
-        };
-        let ip_res = ip.parse::<IpAddr>();
-        ip_res.is_ok() && !ip_res.unwrap().is_loopback() && ip != "localhost"
-    });
+    let domain_cidrs = GLOBALS.domain_cidrs.guard().clone();
+    *GLOBALS.domain_ips.guard() = ip_list.match_fn(|ip| domain_ip_matches(ip, &domain_cidrs));
+}
+
+// Reports whether `ip` (optionally with a port) is a non-loopback address
+// inside one of `cidrs`, or any non-loopback address if `cidrs` is empty.
+fn domain_ip_matches(ip: &str, cidrs: &[ipnet::IpNet]) -> bool {
+    let host_port = split_host_port(ip);
+    let ip = if let Ok((ref host, _)) = host_port {
+        host
+    } else {
+        ip
+    };
+    if ip == "localhost" {
+        return false;
+    }
+    match ip.parse::<IpAddr>() {
+        Ok(ip) => !ip.is_loopback() && (cidrs.is_empty() || cidrs.iter().any(|c| c.contains(&ip))),
+        Err(_) => false,
+    }
 }
 
 #[cfg(test)]
@@ -928,4 +1005,96 @@ mod tests {
         *GLOBALS.rpc_port.guard() = temp_global_port;
         *GLOBALS.domain_ips.guard() = temp_global_domain_ips;
     }
+
+    #[tokio::test]
+    async fn test_endpoints_update_is_local() {
+        let args = vec![
+            "http://host-a:9000/d1".to_owned(),
+            "http://host-a:9000/d2".to_owned(),
+            "http://host-b:9000/d1".to_owned(),
+            "http://host-c:9000/d1".to_owned(),
+        ];
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let stub = |host: String, _port: String| {
+            calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            async move {
+                match host.as_str() {
+                    "host-a" => Ok(true),
+                    "host-b" => Ok(false),
+                    _ => Err(anyhow!("no such host")),
+                }
+            }
+        };
+
+        let mut endpoints = Endpoints::new(&args).unwrap();
+        assert!(endpoints.update_is_local_with(false, &stub).await.is_err());
+
+        calls.store(0, std::sync::atomic::Ordering::SeqCst);
+        let mut endpoints = Endpoints::new(&args).unwrap();
+        endpoints.update_is_local_with(true, &stub).await.unwrap();
+        let is_local: Vec<bool> = endpoints.iter().map(|e| e.is_local()).collect();
+        assert_eq!(is_local, vec![true, true, false, false]);
+        // Each host is resolved only once.
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_endpoint_server_pools_add_overlap() {
+        let pool = |args: &[&str]| PoolEndpoints {
+            set_count: 1,
+            drives_per_set: args.len(),
+            endpoints: Endpoints(args.iter().map(|a| Endpoint::new(a).unwrap()).collect()),
+        };
+
+        let mut pools = EndpointServerPools::default();
+        pools.add(pool(&["/data/d1", "/data/d2"])).unwrap();
+        assert!(pools.add(pool(&["/data/d1/sub", "/data/d3"])).is_err());
+        assert!(pools.add(pool(&["/data", "/other"])).is_err());
+        assert!(pools.add(pool(&["/data/d2"])).is_err());
+        // Not nested, only sharing a prefix.
+        pools.add(pool(&["/data/d10", "/data/d20"])).unwrap();
+
+        let mut pools = EndpointServerPools::default();
+        pools
+            .add(pool(&[
+                "http://host-a:9000/data",
+                "http://host-b:9000/data",
+            ]))
+            .unwrap();
+        // Same paths on different hosts.
+        pools
+            .add(pool(&[
+                "http://host-c:9000/data",
+                "http://host-d:9000/data",
+            ]))
+            .unwrap();
+        assert!(pools
+            .add(pool(&[
+                "http://host-a:9000/data/sub",
+                "http://host-e:9000/data"
+            ]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_domain_ip_matches() {
+        let cidrs = vec!["10.0.0.0/8".parse::<ipnet::IpNet>().unwrap()];
+        let cases = vec![
+            ("10.0.0.1", true, true),
+            ("10.0.0.1:9000", true, true),
+            ("192.168.1.1", true, false),
+            ("192.168.1.1:9000", true, false),
+            ("127.0.0.1", false, false),
+            ("localhost", false, false),
+        ];
+        for (i, (ip, expected, expected_with_cidrs)) in cases.into_iter().enumerate() {
+            assert_eq!(domain_ip_matches(ip, &[]), expected, "test {}", i + 1);
+            assert_eq!(
+                domain_ip_matches(ip, &cidrs),
+                expected_with_cidrs,
+                "test {}",
+                i + 1
+            );
+        }
+    }
 }
@@ -40,7 +40,21 @@ pub fn split_host_port(host_port: &str) -> anyhow::Result<(String, String)> {
         UiError::InvalidAddressFlag
             .msg(format!("redundant path/query '{:?}'", url.path_and_query()))
     ); // no path/query/fragment
-    let host = url.host().unwrap_or("").to_owned();
+
+    // IPv6 literals come back bracketed, e.g. `[::1]`.
+    let host = url.host().unwrap_or("");
+    let host = match host.strip_prefix('[') {
+        Some(host) => host.strip_suffix(']').ok_or_else(|| {
+            UiError::InvalidAddressFlag.msg(format!("missing ']' in address '{}'", host_port))
+        })?,
+        None => host,
+    };
+    ensure!(
+        !host.contains(|c| c == '[' || c == ']'),
+        UiError::InvalidAddressFlag
+            .msg(format!("unexpected '[' or ']' in address '{}'", host_port))
+    );
+    let host = host.to_owned();
     let port = url.port().map_or_else(
         || {
             if url.scheme() == Some(&Scheme::HTTP) {
@@ -199,6 +213,12 @@ mod tests {
             (":0", "", "0"),
             ("https://server", "server", "443"),
             ("http://server", "server", "80"),
+            ("server", "server", ""),
+            ("10.0.0.1:9000", "10.0.0.1", "9000"),
+            ("10.0.0.1", "10.0.0.1", ""),
+            ("[::1]:9000", "::1", "9000"),
+            ("[fe80::1]", "fe80::1", ""),
+            ("http://[::1]", "::1", "80"),
         ];
         for (host_port, expected_host, expected_port) in cases {
             match split_host_port(host_port) {
@@ -211,5 +231,33 @@ mod tests {
                 }
             }
         }
+
+        for host_port in ["[::1", "::1]:9000", "[::1]]:9000", "ftp://server"] {
+            assert!(
+                split_host_port(host_port).is_err(),
+                "expected error for '{}'",
+                host_port
+            );
+        }
+    }
+
+    #[test]
+    fn test_join_host_port() {
+        let cases = vec![
+            ("server", "9000", "server:9000"),
+            ("10.0.0.1", "9000", "10.0.0.1:9000"),
+            ("::1", "9000", "[::1]:9000"),
+            ("fe80::1", "", "[fe80::1]:"),
+        ];
+        for (host, port, expected) in cases {
+            let host_port = join_host_port(host, port);
+            assert_eq!(host_port, expected);
+            if !port.is_empty() {
+                assert_eq!(
+                    split_host_port(&host_port).unwrap(),
+                    (host.to_owned(), port.to_owned())
+                );
+            }
+        }
     }
 }
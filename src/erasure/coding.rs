@@ -91,6 +91,34 @@ impl Erasure {
         Ok(())
     }
 
+    /// Rebuilds the missing (`None`) data and parity shards from the
+    /// present ones, at least `data_blocks` shards have to be present.
+    pub fn reconstruct(&self, shards: &mut [Option<Vec<u8>>]) -> anyhow::Result<()> {
+        let total = self.data_blocks + self.parity_blocks;
+        if shards.len() != total {
+            bail!(
+                "expected {} shards to reconstruct, got {}",
+                total,
+                shards.len()
+            );
+        }
+        let present = shards.iter().filter(|shard| shard.is_some()).count();
+        if present == total {
+            // No need to reconstruct.
+            return Ok(());
+        }
+        if present < self.data_blocks {
+            bail!(
+                "insufficient shards to reconstruct: {} of {} present, at least {} required",
+                present,
+                total,
+                self.data_blocks
+            );
+        }
+        self.encoder.reconstruct(shards)?;
+        Ok(())
+    }
+
     fn split<'a>(&self, data: &'a mut Vec<u8>) -> anyhow::Result<Vec<&'a mut [u8]>> {
         if data.len() == 0 {
             bail!("Not enough data to fill the number of requested shards");
@@ -116,3 +144,87 @@ impl Erasure {
         Ok(dst)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_erasure(data_blocks: usize, parity_blocks: usize) -> Erasure {
+        Erasure::new::<fn() -> ReedSolomon>(data_blocks, parity_blocks, super::super::BLOCK_SIZE_V2)
+            .unwrap()
+    }
+
+    fn encode(erasure: &Erasure, data: &[u8]) -> Vec<Option<Vec<u8>>> {
+        let mut buf = data.to_vec();
+        erasure
+            .encode_data(&mut buf)
+            .unwrap()
+            .into_iter()
+            .map(|shard| Some(shard.to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn test_erasure_reconstruct() {
+        let data: Vec<u8> = (0..10007).map(|i| (i % 251) as u8).collect();
+        for &(data_blocks, parity_blocks) in &[(2, 2), (4, 2), (5, 3), (8, 8)] {
+            let erasure = new_erasure(data_blocks, parity_blocks);
+            let shards = encode(&erasure, &data);
+
+            // Missing up to `parity_blocks` shards, first data then parity shards.
+            for missing in 0..=parity_blocks {
+                for start in 0..(data_blocks + parity_blocks - missing) {
+                    let mut damaged = shards.clone();
+                    for shard in &mut damaged[start..start + missing] {
+                        *shard = None;
+                    }
+                    erasure.reconstruct(&mut damaged).unwrap();
+                    assert_eq!(damaged, shards);
+
+                    let recovered: Vec<u8> = damaged[..data_blocks]
+                        .iter()
+                        .flat_map(|shard| shard.as_ref().unwrap().iter().copied())
+                        .take(data.len())
+                        .collect();
+                    assert_eq!(recovered, data);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_erasure_reconstruct_insufficient() {
+        let erasure = new_erasure(4, 2);
+        let mut shards = encode(&erasure, b"hello world");
+        for shard in &mut shards[..3] {
+            *shard = None;
+        }
+        let err = erasure.reconstruct(&mut shards).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("3 of 6 present, at least 4 required"),
+            "{}",
+            err
+        );
+
+        let mut shards = encode(&erasure, b"hello world");
+        assert!(erasure.reconstruct(&mut shards[..5]).is_err());
+    }
+
+    #[test]
+    fn test_erasure_shard_file_size() {
+        let block_size = super::super::BLOCK_SIZE_V2;
+        let erasure = new_erasure(4, 4);
+        assert_eq!(erasure.shard_size(), block_size / 4);
+        assert_eq!(new_erasure(3, 3).shard_size(), (block_size + 2) / 3);
+
+        let shard_size = erasure.shard_size();
+        let block_size = block_size as u64;
+        assert_eq!(erasure.shard_file_size(0), 0);
+        assert_eq!(erasure.shard_file_size(1), 1);
+        assert_eq!(erasure.shard_file_size(block_size), shard_size);
+        assert_eq!(erasure.shard_file_size(2 * block_size), 2 * shard_size);
+        assert_eq!(erasure.shard_file_size(block_size + 1), shard_size + 1);
+        assert_eq!(erasure.shard_file_size(block_size + 9), shard_size + 3);
+    }
+}
@@ -18,6 +18,9 @@ pub use ui_errors::*;
 pub trait AsError {
     fn as_error<E: std::error::Error + 'static>(&self) -> Option<&E>;
 
+    /// Like `as_error`, but returns the deepest matching cause in the chain.
+    fn root_error<E: std::error::Error + 'static>(&self) -> Option<&E>;
+
     fn is_error<E: std::error::Error + PartialEq + 'static>(&self, err: &E) -> bool {
         if let Some(e) = self.as_error::<E>() {
             e == err
@@ -58,6 +61,13 @@ impl<T: std::error::Error + 'static> AsError for T {
         }
         None
     }
+
+    fn root_error<E: Error + 'static>(&self) -> Option<&E> {
+        self.as_dyn_error()
+            .chain()
+            .filter_map(|cause| cause.downcast_ref::<E>())
+            .last()
+    }
 }
 
 impl AsError for dyn std::error::Error + 'static {
@@ -69,10 +79,56 @@ impl AsError for dyn std::error::Error + 'static {
         }
         None
     }
+
+    fn root_error<E: std::error::Error + 'static>(&self) -> Option<&E> {
+        self.chain()
+            .filter_map(|cause| cause.downcast_ref::<E>())
+            .last()
+    }
 }
 
 impl AsError for dyn std::error::Error + Send + Sync + 'static {
     fn as_error<E: std::error::Error + 'static>(&self) -> Option<&E> {
         (self as &(dyn std::error::Error + 'static)).as_error::<E>()
     }
+
+    fn root_error<E: std::error::Error + 'static>(&self) -> Option<&E> {
+        (self as &(dyn std::error::Error + 'static)).root_error::<E>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use thiserror::Error;
+
+    use super::*;
+
+    #[derive(Debug, Error)]
+    #[error("wrapped")]
+    struct Wrapped(#[source] io::Error);
+
+    #[test]
+    fn test_root_error() {
+        let inner = io::Error::new(io::ErrorKind::NotFound, "inner");
+        let outer = io::Error::new(io::ErrorKind::Other, Wrapped(inner));
+        let err = Wrapped(outer);
+
+        assert_eq!(
+            err.as_error::<io::Error>().unwrap().kind(),
+            io::ErrorKind::Other
+        );
+        assert_eq!(
+            err.root_error::<io::Error>().unwrap().kind(),
+            io::ErrorKind::NotFound
+        );
+
+        let err = &err as &(dyn std::error::Error + Send + Sync + 'static);
+        assert_eq!(
+            err.root_error::<io::Error>().unwrap().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert!(err.root_error::<StorageError>().is_none());
+    }
 }
@@ -2,6 +2,8 @@ use std::convert::TryFrom;
 
 use thiserror::Error;
 
+use super::ApiError;
+
 #[derive(Debug, Error, Eq, PartialEq, Hash, Clone)]
 #[non_exhaustive]
 pub enum StorageError {
@@ -29,6 +31,11 @@ pub enum StorageError {
     #[error("disk not found")]
     DiskNotFound,
 
+    // The disk id in the format file is not the expected one, e.g. the disk
+    // has been swapped.
+    #[error("drive id mismatch, expected '{0}' but found '{1}'")]
+    DriveIdMismatch(String, String),
+
     #[error("remote disk is faulty")]
     FaultyRemoteDisk,
 
@@ -117,6 +124,47 @@ pub const BASE_STORAGE_ERRORS: [StorageError; 3] = [
     StorageError::FaultyRemoteDisk,
 ];
 
+impl StorageError {
+    /// Maps the storage error to the S3 API error returned to clients.
+    pub fn to_api_error(&self) -> ApiError {
+        use StorageError::*;
+        // Keep this match exhaustive, so that new variants must be mapped explicitly.
+        match self {
+            FileNotFound | PathNotFound => ApiError::NoSuchKey,
+            FileVersionNotFound => ApiError::NoSuchVersion,
+            VolumeNotFound => ApiError::NoSuchBucket,
+            VolumeExists => ApiError::BucketAlreadyOwnedByYou,
+            VolumeNotEmpty => ApiError::BucketNotEmpty,
+            DiskAccessDenied | VolumeAccessDenied | FileAccessDenied => ApiError::AccessDenied,
+            FileNameTooLong => ApiError::KeyTooLongError,
+            IsNotRegular => ApiError::ObjectExistsAsDirectory,
+            FileParentIsFile => ApiError::ParentIsObject,
+            DiskFull | MinDiskSize => ApiError::StorageFull,
+            TooManyOpenFiles => ApiError::SlowDown,
+            LessData => ApiError::IncompleteBody,
+            MoreData => ApiError::EntityTooLarge,
+            ErasureReadQuorum => ApiError::ReadQuorum,
+            ErasureWriteQuorum => ApiError::WriteQuorum,
+            Unexpected
+            | CorruptedFormat
+            | UnformattedDisk
+            | InconsistentDisk
+            | UnsupportedDisk
+            | DiskNotDir
+            | DiskNotFound
+            | DriveIdMismatch(..)
+            | FaultyRemoteDisk
+            | FaultyDisk
+            | FileCorrupt
+            | BitrotHashAlgoInvalid
+            | CrossDeviceLink(..)
+            | DoneForNow
+            | SkipFile
+            | NoHealRequired => ApiError::InternalError,
+        }
+    }
+}
+
 impl TryFrom<std::io::Error> for StorageError {
     type Error = std::io::Error;
 
@@ -138,3 +186,57 @@ impl TryFrom<std::io::Error> for StorageError {
         Err(err)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches::assert_matches;
+
+    use actix_web::http::StatusCode;
+
+    use super::*;
+
+    #[test]
+    fn test_storage_error_to_api_error() {
+        let cases = vec![
+            (
+                StorageError::FileNotFound,
+                "NoSuchKey",
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                StorageError::VolumeNotFound,
+                "NoSuchBucket",
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                StorageError::DiskAccessDenied,
+                "AccessDenied",
+                StatusCode::FORBIDDEN,
+            ),
+            (
+                StorageError::FaultyDisk,
+                "InternalError",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                StorageError::FileCorrupt,
+                "InternalError",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        ];
+        for (err, code, status) in cases {
+            let api_err = err.to_api_error().value();
+            assert_eq!(api_err.code, code);
+            assert_eq!(api_err.http_status_code, status);
+        }
+
+        assert_matches!(
+            StorageError::FileVersionNotFound.to_api_error(),
+            ApiError::NoSuchVersion
+        );
+        assert_matches!(
+            StorageError::CrossDeviceLink("a".into(), "b".into()).to_api_error(),
+            ApiError::InternalError
+        );
+    }
+}
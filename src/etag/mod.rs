@@ -116,6 +116,20 @@ impl ETag {
         parse(s, false)
     }
 
+    // Computes the ETag of a multipart object from the
+    // MD5 checksums of its parts:
+    //   ETag := MD5(e1 || e2 || e3 ... || eN) || -N
+    pub fn multipart(part_md5s: &[[u8; 16]]) -> ETag {
+        use md5::Digest;
+        let mut md5 = md5::Md5::new();
+        for part in part_md5s {
+            md5.update(part);
+        }
+        let mut etag = md5.finalize().to_vec();
+        etag.extend_from_slice(format!("-{}", part_md5s.len()).as_bytes());
+        ETag(etag)
+    }
+
     // Decodes and returns the Content-MD5
     // as ETag, if set. If no Content-MD5 header is set
     // it returns an empty ETag and no error.
@@ -237,3 +251,27 @@ fn parse(mut s: &str, strict: bool) -> anyhow::Result<ETag> {
     etag.extend_from_slice(suffix.as_bytes());
     Ok(ETag(etag))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_etag_multipart() {
+        let parts: Vec<[u8; 16]> = (0..5u8).map(|i| [i; 16]).collect();
+        let etag = ETag::multipart(&parts);
+        assert!(etag.is_multipart());
+        assert_eq!(etag.parts(), 5);
+
+        let s = etag.to_string();
+        assert_eq!(s.len(), "ceb8853ddc5086cc4ab9e149f8f09c88-5".len());
+        assert!(s.ends_with("-5"));
+        assert!(ETag::parse(&s).unwrap() == etag);
+
+        let mut concat = Vec::new();
+        for part in &parts {
+            concat.extend_from_slice(part);
+        }
+        assert_eq!(&s[..32], crate::hash::md5_hex(&concat));
+    }
+}
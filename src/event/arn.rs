@@ -56,7 +56,7 @@ impl<'de> Deserialize<'de> for Arn {
     }
 }
 
-fn parse_arn(s: &str) -> anyhow::Result<Arn> {
+pub(super) fn parse_arn(s: &str) -> anyhow::Result<Arn> {
     // ARN must be in the format of arn:hulk:sqs:<REGION>:<ID>:<TYPE>
     ensure!(
         s.starts_with("arn:hulk:sqs:"),
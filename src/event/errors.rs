@@ -26,4 +26,6 @@ pub enum EventError {
     InvalidTargetId(String),
     #[error("invalid event name '{0}'")]
     InvalidEventName(String),
+    #[error("queue store limit of {0} events exceeded")]
+    QueueLimitExceeded(u64),
 }
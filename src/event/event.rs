@@ -5,14 +5,14 @@ use serde::{Deserialize, Serialize};
 use super::*;
 
 // Represents access key who caused the event.
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Identity {
     pub principal_id: String,
 }
 
 // Represents bucket metadata of the event.
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Bucket {
     pub name: String,
@@ -21,7 +21,7 @@ pub struct Bucket {
 }
 
 // Represents object metadata of the event.
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Object {
     pub key: String,
@@ -35,7 +35,7 @@ pub struct Object {
 }
 
 // Represents event metadata.
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Metadata {
     #[serde(rename = "s3SchemaVersion")]
@@ -46,7 +46,7 @@ pub struct Metadata {
 }
 
 // Represents client information who triggered the event.
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Source {
     pub host: String,
@@ -56,7 +56,7 @@ pub struct Source {
 
 // Represents event notification information defined in
 // http://docs.aws.amazon.com/AmazonS3/latest/dev/notification-content-structure.html.
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Event {
     pub event_version: String,
@@ -72,6 +72,8 @@ pub struct Event {
 }
 
 // Represents event information for some event targets.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub struct Log {
     pub event_name: Name,
     pub key: String,
@@ -4,6 +4,7 @@ mod errors;
 mod event;
 mod name;
 mod rules;
+mod store;
 pub mod target;
 mod targetid;
 mod targetlist;
@@ -14,5 +15,6 @@ pub use errors::*;
 pub use event::*;
 pub use name::*;
 pub use rules::*;
+pub use store::*;
 pub use targetid::*;
 pub use targetlist::*;
@@ -0,0 +1,151 @@
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+use anyhow::bail;
+
+use super::*;
+use crate::utils::{self, Duration, Path, PathBuf};
+
+pub const DEFAULT_QUEUE_LIMIT: u64 = 100000;
+
+/// Interval between two attempts to send the queued events.
+pub const QUEUE_RETRY_INTERVAL: Duration = Duration::from_secs(3);
+
+const EVENT_EXT: &str = ".event";
+// Events are written to a temporary file first, then renamed into place, so
+// a crash never leaves a partial event behind.
+const TMP_EXT: &str = ".tmp";
+
+/// Persists the events which could not be delivered to a target, so they
+/// are sent again once the target is back online, in the order they came in.
+pub struct QueueStore {
+    dir: PathBuf,
+    limit: u64,
+    // Keys of the queued events, ordered by arrival.
+    entries: Mutex<BTreeSet<String>>,
+}
+
+impl QueueStore {
+    /// Opens the queue store in `dir`, with the events queued before if any.
+    /// A `limit` of 0 stands for `DEFAULT_QUEUE_LIMIT`.
+    pub fn new(dir: &Path, limit: u64) -> anyhow::Result<QueueStore> {
+        let limit = if limit > 0 {
+            limit
+        } else {
+            DEFAULT_QUEUE_LIMIT
+        };
+        std::fs::create_dir_all(dir)?;
+        let mut entries = BTreeSet::new();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let name = match name.to_str() {
+                Some(name) => name,
+                None => continue,
+            };
+            if name.ends_with(TMP_EXT) {
+                // Interrupted write.
+                std::fs::remove_file(entry.path())?;
+            } else if let Some(key) = name.strip_suffix(EVENT_EXT) {
+                entries.insert(key.to_owned());
+            }
+        }
+        Ok(QueueStore {
+            dir: dir.to_owned(),
+            limit,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}{}", key, EVENT_EXT))
+    }
+
+    pub async fn put(&self, event: &Event) -> anyhow::Result<()> {
+        if self.len() as u64 >= self.limit {
+            bail!(EventError::QueueLimitExceeded(self.limit));
+        }
+        // Keys sort in arrival order.
+        let key = format!(
+            "{:020}-{}",
+            utils::now().timestamp_nanos(),
+            uuid::Uuid::new_v4()
+        );
+        let path = self.path(&key);
+        let tmp_path = PathBuf::from(format!("{}{}", path, TMP_EXT));
+        tokio::fs::write(&tmp_path, serde_json::to_vec(event)?).await?;
+        if let Err(err) = tokio::fs::rename(&tmp_path, &path).await {
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+            return Err(err.into());
+        }
+        self.entries.lock().unwrap().insert(key);
+        Ok(())
+    }
+
+    pub async fn get(&self, key: &str) -> anyhow::Result<Event> {
+        let data = tokio::fs::read(self.path(key)).await?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    pub async fn del(&self, key: &str) -> anyhow::Result<()> {
+        match tokio::fs::remove_file(self.path(key)).await {
+            Ok(_) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+        self.entries.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    /// Returns the keys of the queued events, oldest first.
+    pub fn list(&self) -> Vec<String> {
+        self.entries.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_event(key: &str) -> Event {
+        let mut event = Event::default();
+        event.s3.object.key = key.to_owned();
+        event
+    }
+
+    #[tokio::test]
+    async fn test_queue_store() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let dir = Path::from_path(dir.path()).unwrap().join("queue");
+        let store = QueueStore::new(&dir, 3).unwrap();
+        for key in &["a", "b", "c"] {
+            store.put(&new_event(key)).await.unwrap();
+        }
+        assert!(store.put(&new_event("d")).await.is_err());
+
+        // Queued events survive a restart, interrupted writes do not.
+        std::fs::write(dir.join(format!("x{}{}", EVENT_EXT, TMP_EXT)), b"{").unwrap();
+        let store = QueueStore::new(&dir, 3).unwrap();
+        let keys = store.list();
+        assert_eq!(keys.len(), 3);
+        let event = store.get(&keys[0]).await.unwrap();
+        assert_eq!(event.s3.object.key, "a");
+        store.del(&keys[0]).await.unwrap();
+        store.del(&keys[0]).await.unwrap();
+        assert_eq!(store.len(), 2);
+        assert_eq!(
+            store.get(&store.list()[0]).await.unwrap().s3.object.key,
+            "b"
+        );
+        store.put(&new_event("d")).await.unwrap();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3);
+    }
+}
@@ -0,0 +1,527 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+
+use anyhow::{anyhow, bail, ensure};
+use async_trait::async_trait;
+use kafka::client::{KafkaClient, SecurityConfig};
+use kafka::producer::{Producer, Record, RequiredAcks};
+use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::event::{Event, Log, Target, TargetId};
+use crate::utils::Duration;
+
+pub const KAFKA_BROKERS: &str = "brokers";
+pub const KAFKA_TOPIC: &str = "topic";
+pub const KAFKA_TLS: &str = "tls";
+pub const KAFKA_TLS_SKIP_VERIFY: &str = "tls_skip_verify";
+pub const KAFKA_SASL: &str = "sasl";
+pub const KAFKA_SASL_USERNAME: &str = "sasl_username";
+pub const KAFKA_SASL_PASSWORD: &str = "sasl_password";
+pub const KAFKA_SASL_MECHANISM: &str = "sasl_mechanism";
+pub const KAFKA_BATCH_SIZE: &str = "batch_size";
+
+pub const ENV_KAFKA_ENABLE: &str = "HULK_NOTIFY_KAFKA_ENABLE";
+pub const ENV_KAFKA_BROKERS: &str = "HULK_NOTIFY_KAFKA_BROKERS";
+pub const ENV_KAFKA_TOPIC: &str = "HULK_NOTIFY_KAFKA_TOPIC";
+pub const ENV_KAFKA_TLS: &str = "HULK_NOTIFY_KAFKA_TLS";
+pub const ENV_KAFKA_TLS_SKIP_VERIFY: &str = "HULK_NOTIFY_KAFKA_TLS_SKIP_VERIFY";
+pub const ENV_KAFKA_SASL: &str = "HULK_NOTIFY_KAFKA_SASL";
+pub const ENV_KAFKA_SASL_USERNAME: &str = "HULK_NOTIFY_KAFKA_SASL_USERNAME";
+pub const ENV_KAFKA_SASL_PASSWORD: &str = "HULK_NOTIFY_KAFKA_SASL_PASSWORD";
+pub const ENV_KAFKA_SASL_MECHANISM: &str = "HULK_NOTIFY_KAFKA_SASL_MECHANISM";
+pub const ENV_KAFKA_BATCH_SIZE: &str = "HULK_NOTIFY_KAFKA_BATCH_SIZE";
+
+const KAFKA_ACK_TIMEOUT: Duration = Duration::from_secs(5);
+// Saved events are sent at most this long after, even if their batch is
+// not full yet.
+const KAFKA_BATCH_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct KafkaTlsArgs {
+    pub enable: bool,
+    pub skip_verify: bool,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct KafkaSaslArgs {
+    pub enable: bool,
+    pub user: String,
+    pub password: String,
+    pub mechanism: String,
+}
+
+// Kafka target arguments.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct KafkaArgs {
+    pub enable: bool,
+    pub brokers: Vec<String>,
+    pub topic: String,
+    pub tls: KafkaTlsArgs,
+    pub sasl: KafkaSaslArgs,
+    // Number of events sent to the brokers in one request.
+    pub batch_size: usize,
+}
+
+impl KafkaArgs {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !self.enable {
+            return Ok(());
+        }
+        ensure!(!self.brokers.is_empty(), "no broker address found");
+        for broker in &self.brokers {
+            let (host, port) = crate::endpoint::split_host_port(broker)?;
+            ensure!(
+                !host.is_empty() && !port.is_empty(),
+                "invalid broker address '{}'",
+                broker
+            );
+        }
+        ensure!(!self.topic.is_empty(), "empty topic");
+        ensure!(self.batch_size > 0, "batch size must be greater than 0");
+        ensure!(
+            !self.sasl.enable,
+            "SASL authentication is not supported by the kafka client"
+        );
+        Ok(())
+    }
+}
+
+/// Kafka client used by [`KafkaTarget`] to publish messages.
+#[async_trait]
+pub trait KafkaProducer: Send + Sync {
+    fn is_connected(&self) -> bool;
+    /// Publishes `(key, value)` messages to `topic` in one request.
+    async fn send_messages(
+        &self,
+        topic: &str,
+        messages: Vec<(String, String)>,
+    ) -> anyhow::Result<()>;
+    async fn close(&self) -> anyhow::Result<()>;
+}
+
+// Producer of the kafka crate, whose calls block, so they are run on the
+// blocking thread pool.
+struct KafkaClientProducer {
+    producer: Arc<std::sync::Mutex<Producer>>,
+    connected: Arc<AtomicBool>,
+}
+
+#[async_trait]
+impl KafkaProducer for KafkaClientProducer {
+    fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    async fn send_messages(
+        &self,
+        topic: &str,
+        messages: Vec<(String, String)>,
+    ) -> anyhow::Result<()> {
+        let producer = self.producer.clone();
+        let topic = topic.to_owned();
+        let confirms = tokio::task::spawn_blocking(move || {
+            let records: Vec<_> = messages
+                .iter()
+                .map(|(key, value)| Record::from_key_value(&topic, key.as_str(), value.as_str()))
+                .collect();
+            producer.lock().unwrap().send_all(&records)
+        })
+        .await?;
+        self.connected.store(confirms.is_ok(), Ordering::Relaxed);
+        let confirms = confirms.map_err(|err| anyhow!("unable to send to kafka: {}", err))?;
+        for confirm in confirms {
+            for partition in confirm.partition_confirms {
+                if let Err(code) = partition.offset {
+                    bail!(
+                        "kafka topic '{}' partition {} rejected messages: {:?}",
+                        confirm.topic,
+                        partition.partition,
+                        code
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn close(&self) -> anyhow::Result<()> {
+        // Connections are closed with the producer.
+        Ok(())
+    }
+}
+
+/// Connects to the brokers of the target.
+pub async fn new_kafka_producer(args: &KafkaArgs) -> anyhow::Result<Box<dyn KafkaProducer>> {
+    let args = args.clone();
+    let producer = tokio::task::spawn_blocking(move || connect_kafka_producer(&args)).await??;
+    Ok(Box::new(KafkaClientProducer {
+        producer: Arc::new(std::sync::Mutex::new(producer)),
+        connected: Arc::new(AtomicBool::new(true)),
+    }))
+}
+
+// Loads the metadata of the brokers, which blocks until they answer.
+fn connect_kafka_producer(args: &KafkaArgs) -> anyhow::Result<Producer> {
+    let mut client = if args.tls.enable {
+        let mut connector = SslConnector::builder(SslMethod::tls())?;
+        if args.tls.skip_verify {
+            connector.set_verify(SslVerifyMode::NONE);
+        }
+        let security = SecurityConfig::new(connector.build())
+            .with_hostname_verification(!args.tls.skip_verify);
+        KafkaClient::new_secure(args.brokers.clone(), security)
+    } else {
+        KafkaClient::new(args.brokers.clone())
+    };
+    client.load_metadata_all().map_err(|err| {
+        anyhow!(
+            "unable to connect to kafka brokers {:?}: {}",
+            args.brokers,
+            err
+        )
+    })?;
+    Producer::from_client(client)
+        .with_ack_timeout(KAFKA_ACK_TIMEOUT)
+        .with_required_acks(RequiredAcks::One)
+        .create()
+        .map_err(|err| anyhow!("unable to create kafka producer: {}", err))
+}
+
+// Events saved but not sent yet.
+#[derive(Default)]
+struct KafkaBatch {
+    messages: Vec<(String, String)>,
+    // Whether a flush of the batch is pending.
+    flush_scheduled: bool,
+}
+
+// Sends the batch, which is only emptied once the brokers took it.
+async fn flush_batch(
+    producer: &dyn KafkaProducer,
+    topic: &str,
+    batch: &mut KafkaBatch,
+) -> anyhow::Result<()> {
+    if batch.messages.is_empty() {
+        return Ok(());
+    }
+    producer
+        .send_messages(topic, batch.messages.clone())
+        .await?;
+    batch.messages.clear();
+    Ok(())
+}
+
+// Sends the batch once `interval` elapsed, unless the target is gone by then.
+// The flush is retried after another interval until it succeeds.
+fn schedule_flush(
+    batch: Weak<Mutex<KafkaBatch>>,
+    producer: Arc<dyn KafkaProducer>,
+    topic: String,
+    interval: Duration,
+) {
+    tokio::spawn(async move {
+        tokio::time::sleep(interval).await;
+        let batch_ref = match batch.upgrade() {
+            Some(batch_ref) => batch_ref,
+            None => return,
+        };
+        let mut pending = batch_ref.lock().await;
+        if let Err(err) = flush_batch(&*producer, &topic, &mut pending).await {
+            crate::error!("unable to send events to kafka topic '{}': {}", topic, err);
+            schedule_flush(batch, producer, topic, interval);
+            return;
+        }
+        pending.flush_scheduled = false;
+    });
+}
+
+// Kafka event notification target.
+pub struct KafkaTarget {
+    id: TargetId,
+    args: KafkaArgs,
+    producer: Arc<dyn KafkaProducer>,
+    batch: Arc<Mutex<KafkaBatch>>,
+    flush_interval: Duration,
+}
+
+impl KafkaTarget {
+    pub fn new(
+        id: &str,
+        args: KafkaArgs,
+        producer: Box<dyn KafkaProducer>,
+    ) -> anyhow::Result<KafkaTarget> {
+        args.validate()?;
+        Ok(KafkaTarget {
+            id: TargetId {
+                id: id.to_owned(),
+                name: "kafka".to_owned(),
+            },
+            batch: Arc::new(Mutex::new(KafkaBatch {
+                messages: Vec::with_capacity(args.batch_size),
+                flush_scheduled: false,
+            })),
+            args,
+            producer: producer.into(),
+            flush_interval: KAFKA_BATCH_FLUSH_INTERVAL,
+        })
+    }
+
+    fn schedule_batch_flush(&self, batch: &mut KafkaBatch) {
+        if batch.flush_scheduled {
+            return;
+        }
+        batch.flush_scheduled = true;
+        schedule_flush(
+            Arc::downgrade(&self.batch),
+            self.producer.clone(),
+            self.args.topic.clone(),
+            self.flush_interval,
+        );
+    }
+}
+
+#[async_trait]
+impl Target for KafkaTarget {
+    fn id(&self) -> &TargetId {
+        &self.id
+    }
+
+    fn is_active(&self) -> anyhow::Result<bool> {
+        Ok(self.producer.is_connected())
+    }
+
+    async fn save(&self, event: &Event) -> anyhow::Result<()> {
+        let key = format!("{}/{}", event.s3.bucket.name, event.s3.object.key);
+        let log = Log {
+            event_name: event.event_name.clone(),
+            key: key.clone(),
+            records: vec![event.clone()],
+        };
+        let value = serde_json::to_string(&log)?;
+        let mut batch = self.batch.lock().await;
+        batch.messages.push((key, value));
+        if batch.messages.len() >= self.args.batch_size {
+            let flushed = flush_batch(&*self.producer, &self.args.topic, &mut batch).await;
+            if flushed.is_err() {
+                // The events stay in the batch until a later flush succeeds.
+                self.schedule_batch_flush(&mut batch);
+            }
+            return flushed;
+        }
+        self.schedule_batch_flush(&mut batch);
+        Ok(())
+    }
+
+    async fn send(&self, s: &str) -> anyhow::Result<()> {
+        let log: serde_json::Value = serde_json::from_str(s)?;
+        let key = log
+            .get("Key")
+            .and_then(|k| k.as_str())
+            .unwrap_or_default()
+            .to_owned();
+        self.producer
+            .send_messages(&self.args.topic, vec![(key, s.to_owned())])
+            .await
+    }
+
+    async fn close(&mut self) -> anyhow::Result<()> {
+        let mut batch = self.batch.lock().await;
+        let flushed = flush_batch(&*self.producer, &self.args.topic, &mut batch).await;
+        let closed = self.producer.close().await;
+        flushed.and(closed)
+    }
+
+    fn has_queue_store(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex as StdMutex};
+
+    use tokio::sync::Notify;
+
+    use super::*;
+
+    #[derive(Default, Clone)]
+    struct MockProducer {
+        sent: Arc<StdMutex<Vec<(String, Vec<(String, String)>)>>>,
+        // Notified after each successful send.
+        notify: Arc<Notify>,
+        fail: Arc<AtomicBool>,
+    }
+
+    #[async_trait]
+    impl KafkaProducer for MockProducer {
+        fn is_connected(&self) -> bool {
+            true
+        }
+
+        async fn send_messages(
+            &self,
+            topic: &str,
+            messages: Vec<(String, String)>,
+        ) -> anyhow::Result<()> {
+            ensure!(!self.fail.load(Ordering::Relaxed), "broker unavailable");
+            self.sent.lock().unwrap().push((topic.to_owned(), messages));
+            self.notify.notify_one();
+            Ok(())
+        }
+
+        async fn close(&self) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn new_event(key: &str) -> Event {
+        let mut event = Event::default();
+        event.s3.bucket.name = "bucket".to_owned();
+        event.s3.object.key = key.to_owned();
+        event
+    }
+
+    #[tokio::test]
+    async fn test_kafka_target_batching() {
+        let args = KafkaArgs {
+            enable: true,
+            brokers: vec!["localhost:9092".to_owned()],
+            topic: "events".to_owned(),
+            batch_size: 2,
+            ..Default::default()
+        };
+        let producer = MockProducer::default();
+        let sent = producer.sent.clone();
+        let mut target = KafkaTarget::new("1", args, Box::new(producer)).unwrap();
+
+        for key in &["a", "b", "c"] {
+            target.save(&new_event(key)).await.unwrap();
+        }
+        {
+            let sent = sent.lock().unwrap();
+            assert_eq!(sent.len(), 1);
+            assert_eq!(sent[0].0, "events");
+            let keys: Vec<&str> = sent[0].1.iter().map(|(k, _)| k.as_str()).collect();
+            assert_eq!(keys, vec!["bucket/a", "bucket/b"]);
+            let log: serde_json::Value = serde_json::from_str(&sent[0].1[0].1).unwrap();
+            assert_eq!(log["Key"], "bucket/a");
+        }
+
+        // Pending events are flushed on close.
+        target.close().await.unwrap();
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[1].1[0].0, "bucket/c");
+    }
+
+    #[tokio::test]
+    async fn test_kafka_target_flush_interval() {
+        let args = KafkaArgs {
+            enable: true,
+            brokers: vec!["localhost:9092".to_owned()],
+            topic: "events".to_owned(),
+            batch_size: 10,
+            ..Default::default()
+        };
+        let producer = MockProducer::default();
+        let sent = producer.sent.clone();
+        let notify = producer.notify.clone();
+        let mut target = KafkaTarget::new("1", args, Box::new(producer)).unwrap();
+        target.flush_interval = Duration::from_millis(50);
+
+        target.save(&new_event("a")).await.unwrap();
+        target.save(&new_event("b")).await.unwrap();
+        assert!(sent.lock().unwrap().is_empty());
+
+        // The batch is not full, it is sent once the interval elapsed.
+        tokio::time::timeout(Duration::from_secs(5), notify.notified())
+            .await
+            .unwrap();
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].1.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_kafka_target_send_failure() {
+        let args = KafkaArgs {
+            enable: true,
+            brokers: vec!["localhost:9092".to_owned()],
+            topic: "events".to_owned(),
+            batch_size: 2,
+            ..Default::default()
+        };
+        let producer = MockProducer::default();
+        let sent = producer.sent.clone();
+        let notify = producer.notify.clone();
+        let fail = producer.fail.clone();
+        let mut target = KafkaTarget::new("1", args, Box::new(producer)).unwrap();
+        target.flush_interval = Duration::from_millis(50);
+
+        fail.store(true, Ordering::Relaxed);
+        target.save(&new_event("a")).await.unwrap();
+        assert!(target.save(&new_event("b")).await.is_err());
+        assert!(sent.lock().unwrap().is_empty());
+
+        // The failed batch is kept and retried once the brokers are back.
+        fail.store(false, Ordering::Relaxed);
+        tokio::time::timeout(Duration::from_secs(5), notify.notified())
+            .await
+            .unwrap();
+        {
+            let sent = sent.lock().unwrap();
+            assert_eq!(sent.len(), 1);
+            let keys: Vec<&str> = sent[0].1.iter().map(|(k, _)| k.as_str()).collect();
+            assert_eq!(keys, vec!["bucket/a", "bucket/b"]);
+        }
+
+        target.close().await.unwrap();
+        assert_eq!(sent.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_kafka_args_validate() {
+        let args = KafkaArgs {
+            enable: true,
+            brokers: vec!["localhost:9092".to_owned()],
+            topic: "events".to_owned(),
+            batch_size: 1,
+            ..Default::default()
+        };
+        assert!(args.validate().is_ok());
+        assert!(KafkaArgs::default().validate().is_ok());
+
+        let invalid = vec![
+            KafkaArgs {
+                brokers: vec![],
+                ..args.clone()
+            },
+            KafkaArgs {
+                brokers: vec!["localhost".to_owned()],
+                ..args.clone()
+            },
+            KafkaArgs {
+                topic: "".to_owned(),
+                ..args.clone()
+            },
+            KafkaArgs {
+                batch_size: 0,
+                ..args.clone()
+            },
+            KafkaArgs {
+                sasl: KafkaSaslArgs {
+                    enable: true,
+                    user: "user".to_owned(),
+                    password: "password".to_owned(),
+                    mechanism: "plain".to_owned(),
+                },
+                ..args.clone()
+            },
+        ];
+        for (i, args) in invalid.into_iter().enumerate() {
+            assert!(args.validate().is_err(), "test {}", i + 1);
+        }
+    }
+}
@@ -0,0 +1,13 @@
+pub use elasticsearch::*;
+pub use kafka::*;
+pub use mysql::*;
+pub use nats::*;
+pub use redis::*;
+pub use webhook::*;
+
+mod elasticsearch;
+mod kafka;
+mod mysql;
+mod nats;
+mod redis;
+mod webhook;
@@ -0,0 +1,5 @@
+use serde::{Deserialize, Serialize};
+
+// NATS target arguments.
+#[derive(Serialize, Deserialize, Default)]
+pub struct NatsArgs {}
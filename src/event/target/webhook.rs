@@ -0,0 +1,188 @@
+use anyhow::{bail, ensure};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::event::{Event, Log, QueueStore, Target, TargetId};
+use crate::utils::Path;
+
+pub const WEBHOOK_ENDPOINT: &str = "endpoint";
+pub const WEBHOOK_AUTH_TOKEN: &str = "auth_token";
+pub const WEBHOOK_QUEUE_DIR: &str = "queue_dir";
+pub const WEBHOOK_QUEUE_LIMIT: &str = "queue_limit";
+
+pub const ENV_WEBHOOK_ENABLE: &str = "HULK_NOTIFY_WEBHOOK_ENABLE";
+pub const ENV_WEBHOOK_ENDPOINT: &str = "HULK_NOTIFY_WEBHOOK_ENDPOINT";
+pub const ENV_WEBHOOK_AUTH_TOKEN: &str = "HULK_NOTIFY_WEBHOOK_AUTH_TOKEN";
+pub const ENV_WEBHOOK_QUEUE_DIR: &str = "HULK_NOTIFY_WEBHOOK_QUEUE_DIR";
+pub const ENV_WEBHOOK_QUEUE_LIMIT: &str = "HULK_NOTIFY_WEBHOOK_QUEUE_LIMIT";
+
+// Webhook target arguments.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct WebhookArgs {
+    pub enable: bool,
+    pub endpoint: String,
+    pub auth_token: String,
+    // Directory where undelivered events are persisted, none if empty.
+    pub queue_dir: String,
+    pub queue_limit: u64,
+}
+
+impl WebhookArgs {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !self.enable {
+            return Ok(());
+        }
+        ensure!(!self.endpoint.is_empty(), "endpoint empty");
+        let url = url::Url::parse(&self.endpoint)?;
+        ensure!(
+            url.scheme() == "http" || url.scheme() == "https",
+            "invalid endpoint scheme '{}'",
+            url.scheme()
+        );
+        if !self.queue_dir.is_empty() {
+            ensure!(
+                Path::new(&self.queue_dir).is_absolute(),
+                "queue dir '{}' must be an absolute path",
+                self.queue_dir
+            );
+        }
+        Ok(())
+    }
+}
+
+// Webhook event notification target, posting events as JSON.
+pub struct WebhookTarget {
+    id: TargetId,
+    args: WebhookArgs,
+    client: reqwest::Client,
+    store: Option<QueueStore>,
+}
+
+impl WebhookTarget {
+    pub fn new(
+        id: &str,
+        args: WebhookArgs,
+        client: reqwest::Client,
+    ) -> anyhow::Result<WebhookTarget> {
+        args.validate()?;
+        let id = TargetId {
+            id: id.to_owned(),
+            name: "webhook".to_owned(),
+        };
+        let store = if !args.queue_dir.is_empty() {
+            let dir = Path::new(&args.queue_dir).join(format!("webhook-{}", id.id));
+            Some(QueueStore::new(&dir, args.queue_limit)?)
+        } else {
+            None
+        };
+        Ok(WebhookTarget {
+            id,
+            args,
+            client,
+            store,
+        })
+    }
+}
+
+#[async_trait]
+impl Target for WebhookTarget {
+    fn id(&self) -> &TargetId {
+        &self.id
+    }
+
+    fn is_active(&self) -> anyhow::Result<bool> {
+        // The endpoint is only reached when sending events.
+        Ok(true)
+    }
+
+    async fn save(&self, event: &Event) -> anyhow::Result<()> {
+        let log = Log {
+            event_name: event.event_name.clone(),
+            key: format!("{}/{}", event.s3.bucket.name, event.s3.object.key),
+            records: vec![event.clone()],
+        };
+        self.send(&serde_json::to_string(&log)?).await
+    }
+
+    async fn send(&self, s: &str) -> anyhow::Result<()> {
+        let mut req = self
+            .client
+            .post(&self.args.endpoint)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(s.to_owned());
+        if !self.args.auth_token.is_empty() {
+            // Tokens may come with their scheme, otherwise they are bearer tokens.
+            let token = &self.args.auth_token;
+            req = if token.contains(' ') {
+                req.header(reqwest::header::AUTHORIZATION, token)
+            } else {
+                req.bearer_auth(token)
+            };
+        }
+        let resp = req.send().await?;
+        if !resp.status().is_success() {
+            bail!(
+                "sending event to webhook '{}' failed with {}",
+                self.args.endpoint,
+                resp.status()
+            );
+        }
+        Ok(())
+    }
+
+    async fn close(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn has_queue_store(&self) -> bool {
+        self.store.is_some()
+    }
+
+    fn queue_store(&self) -> Option<&QueueStore> {
+        self.store.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_webhook_args_validate() {
+        let args = WebhookArgs {
+            enable: true,
+            endpoint: "http://localhost:8080/events".to_owned(),
+            ..Default::default()
+        };
+        assert!(args.validate().is_ok());
+        assert!(WebhookArgs::default().validate().is_ok());
+
+        for endpoint in &["", "localhost:8080", "ftp://localhost/events"] {
+            let args = WebhookArgs {
+                endpoint: endpoint.to_string(),
+                ..args.clone()
+            };
+            assert!(args.validate().is_err(), "{}", endpoint);
+        }
+        let relative_queue_dir = WebhookArgs {
+            queue_dir: "events".to_owned(),
+            ..args.clone()
+        };
+        assert!(relative_queue_dir.validate().is_err());
+    }
+
+    #[test]
+    fn test_webhook_target_queue_store() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let args = WebhookArgs {
+            enable: true,
+            endpoint: "http://localhost:8080/events".to_owned(),
+            auth_token: "".to_owned(),
+            queue_dir: dir.path().to_str().unwrap().to_owned(),
+            queue_limit: 10,
+        };
+        let target = WebhookTarget::new("1", args, reqwest::Client::new()).unwrap();
+        assert!(target.has_queue_store());
+        assert!(dir.path().join("webhook-1").is_dir());
+    }
+}
@@ -23,6 +23,16 @@ impl TargetId {
             region,
         }
     }
+
+    /// Parses the target ID out of an ARN such as `arn:hulk:sqs:<REGION>:<ID>:<TYPE>`,
+    /// as referenced by bucket notification configs.
+    pub fn from_arn(s: &str) -> anyhow::Result<TargetId> {
+        Ok(parse_arn(s)?.target_id)
+    }
+
+    pub fn to_arn(&self, region: &str) -> String {
+        self.clone().into_arn(region.to_owned()).to_string()
+    }
 }
 
 fn parse_target_id(s: &str) -> anyhow::Result<TargetId> {
@@ -33,3 +43,34 @@ fn parse_target_id(s: &str) -> anyhow::Result<TargetId> {
         name: tokens[1].to_owned(),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_id_arn() {
+        let target_id = TargetId {
+            id: "1".to_owned(),
+            name: "webhook".to_owned(),
+        };
+        let arn = target_id.to_arn("us-east-1");
+        assert_eq!(arn, "arn:hulk:sqs:us-east-1:1:webhook");
+        assert!(TargetId::from_arn(&arn).unwrap() == target_id);
+        // The region is optional.
+        assert!(TargetId::from_arn(&target_id.to_arn("")).unwrap() == target_id);
+
+        let invalid = [
+            "",
+            "arn:hulk:sqs:us-east-1:1",
+            "arn:hulk:sqs:us-east-1:1:webhook:extra",
+            "arn:hulk:sqs:us-east-1::webhook",
+            "arn:hulk:sqs:us-east-1:1:",
+            "arn:hulk:sns:us-east-1:1:webhook",
+            "arn:aws:sqs:us-east-1:1:webhook",
+        ];
+        for arn in &invalid {
+            assert!(TargetId::from_arn(arn).is_err(), "{}", arn);
+        }
+    }
+}
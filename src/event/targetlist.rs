@@ -1,5 +1,6 @@
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 use anyhow::ensure;
 use async_trait::async_trait;
@@ -7,83 +8,421 @@ use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::Mutex;
 
 use super::*;
+use crate::utils::{self, Duration};
 
 // Event target trait.
 #[async_trait]
-pub trait Target {
+pub trait Target: Send + Sync {
     fn id(&self) -> &TargetId;
     fn is_active(&self) -> anyhow::Result<bool>;
     async fn save(&self, event: &Event) -> anyhow::Result<()>;
     async fn send(&self, s: &str) -> anyhow::Result<()>;
     async fn close(&mut self) -> anyhow::Result<()>;
     fn has_queue_store(&self) -> bool;
+
+    // Store of the events not delivered yet, if any.
+    fn queue_store(&self) -> Option<&QueueStore> {
+        None
+    }
+}
+
+type TargetRef = Arc<Mutex<Box<dyn Target>>>;
+
+// Key filter of a target, events for non-matching objects are not sent to it.
+#[derive(Default, Clone, Debug)]
+pub struct KeyFilter {
+    pub prefix: Option<String>,
+    pub suffix: Option<String>,
+}
+
+impl KeyFilter {
+    /// Matches the unescaped object name, event keys being query escaped.
+    pub fn matches(&self, object_name: &str) -> bool {
+        let object_name = unescape_key(object_name);
+        self.prefix
+            .as_ref()
+            .map_or(true, |prefix| object_name.starts_with(prefix.as_str()))
+            && self
+                .suffix
+                .as_ref()
+                .map_or(true, |suffix| object_name.ends_with(suffix.as_str()))
+    }
+}
+
+// Reverts the query escaping of the object key of events, invalid escapes
+// being kept as is.
+fn unescape_key(key: &str) -> Cow<str> {
+    if !key.contains(&['%', '+'][..]) {
+        return Cow::Borrowed(key);
+    }
+    let key = key.replace('+', " ");
+    Cow::Owned(
+        percent_encoding::percent_decode_str(&key)
+            .decode_utf8_lossy()
+            .into_owned(),
+    )
 }
 
+// Targets are shared with in-flight sends, so the list itself only
+// holds the map lock for lookups and never across an await point.
 #[derive(Default)]
-pub struct TargetList(HashMap<TargetId, Arc<Mutex<Box<dyn Target>>>>);
+pub struct TargetList(RwLock<HashMap<TargetId, (TargetRef, KeyFilter)>>);
 
 impl TargetList {
-    pub fn add(&mut self, target: Box<dyn Target>) -> anyhow::Result<()> {
+    pub fn add(&self, target: Box<dyn Target>) -> anyhow::Result<()> {
+        self.add_with_filter(target, KeyFilter::default())
+    }
+
+    pub fn add_with_filter(
+        &self,
+        target: Box<dyn Target>,
+        filter: KeyFilter,
+    ) -> anyhow::Result<()> {
+        let mut targets = self.0.write().unwrap();
         ensure!(
-            self.0.contains_key(target.id()),
+            !targets.contains_key(target.id()),
             "target {} already exists",
             target.id()
         );
-        self.0
-            .insert(target.id().clone(), Arc::new(Mutex::new(target)));
+        targets.insert(target.id().clone(), (Arc::new(Mutex::new(target)), filter));
         Ok(())
     }
 
     pub fn contains(&self, id: &TargetId) -> bool {
-        self.0.contains_key(id)
+        self.0.read().unwrap().contains_key(id)
     }
 
-    pub async fn remove(&mut self, id: &TargetId) {
-        if let Some(mut target) = self.0.remove(id) {
-            let _ = target.lock().await.close().await;
+    /// Removes and closes the target, returning whether it existed.
+    /// A send already in progress to the target completes before it is closed.
+    pub async fn remove(&self, id: &TargetId) -> bool {
+        let target = self.0.write().unwrap().remove(id);
+        match target {
+            Some((target, _)) => {
+                let _ = target.lock().await.close().await;
+                true
+            }
+            None => false,
         }
     }
 
-    pub fn targets(&self) -> Vec<Arc<Mutex<Box<dyn Target>>>> {
-        self.0.values().cloned().collect()
+    pub fn target_ids(&self) -> Vec<TargetId> {
+        self.0.read().unwrap().keys().cloned().collect()
     }
 
-    pub fn iter(
-        &self,
-    ) -> std::collections::hash_map::Iter<'_, TargetId, Arc<Mutex<Box<dyn Target>>>> {
-        self.0.iter()
-    }
-
-    pub fn iter_mut(
-        &mut self,
-    ) -> std::collections::hash_map::IterMut<'_, TargetId, Arc<Mutex<Box<dyn Target>>>> {
-        self.0.iter_mut()
+    pub fn targets(&self) -> Vec<TargetRef> {
+        self.0
+            .read()
+            .unwrap()
+            .values()
+            .map(|(target, _)| target.clone())
+            .collect()
     }
 
     pub async fn send(
-        &mut self,
+        &self,
         event: Event,
         targets: HashSet<TargetId>,
         tx: UnboundedSender<(TargetId, Option<anyhow::Error>)>,
     ) {
-        let targets: Vec<_> = targets.into_iter().collect();
+        let targets: Vec<_> = {
+            let target_map = self.0.read().unwrap();
+            targets
+                .into_iter()
+                .map(|id| {
+                    // Events for objects not matching the filter of a target are dropped.
+                    let target = target_map
+                        .get(&id)
+                        .filter(|(_, filter)| filter.matches(&event.s3.object.key))
+                        .map(|(target, _)| target.clone());
+                    (id, target)
+                })
+                .collect()
+        };
         let mut results = Vec::new();
-        for id in &targets {
-            match self.0.get_mut(id) {
+        for (id, target) in targets {
+            match target {
                 Some(target) => {
-                    let target = target.clone();
                     let event = &event;
                     let tx = tx.clone();
                     results.push(async move {
-                        let r = target.lock().await.save(event).await;
-                        let _ = tx.send((id.clone(), r.err()));
+                        let target = target.lock().await;
+                        // Events of targets with a store always go through it, so
+                        // they are delivered in order even while some are queued.
+                        let r = match target.queue_store() {
+                            Some(store) => match store.put(event).await {
+                                Ok(_) => {
+                                    let _ = replay_target(&**target, store).await;
+                                    Ok(())
+                                }
+                                Err(err) => Err(err),
+                            },
+                            None => target.save(event).await,
+                        };
+                        let _ = tx.send((id, r.err()));
                     });
                 }
                 None => {
-                    let _ = tx.send((id.clone(), None));
+                    let _ = tx.send((id, None));
                 }
             }
         }
         let _ = futures_util::future::join_all(results).await;
     }
+
+    /// Sends the queued events of the targets again, oldest first, returning
+    /// how many were delivered. Events stay queued while their target fails.
+    pub async fn replay_queued(&self) -> usize {
+        let mut replayed = 0;
+        for target in self.targets() {
+            let target = target.lock().await;
+            if let Some(store) = target.queue_store() {
+                replayed += replay_target(&**target, store).await;
+            }
+        }
+        replayed
+    }
+
+    /// Replays the queued events every `interval` in the background, until
+    /// the list is dropped.
+    pub fn start_retrier(self: &Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let target_list = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut rng = utils::rng_seed_now();
+            loop {
+                utils::sleep_jitter(interval, 0.5, 1.5, Some(&mut rng)).await;
+                match target_list.upgrade() {
+                    Some(target_list) => {
+                        let _ = target_list.replay_queued().await;
+                    }
+                    None => break,
+                }
+            }
+        })
+    }
+}
+
+// Sends the queued events of `target` oldest first, until one fails.
+async fn replay_target(target: &dyn Target, store: &QueueStore) -> usize {
+    let mut replayed = 0;
+    for key in store.list() {
+        let event = match store.get(&key).await {
+            Ok(event) => event,
+            Err(err) => {
+                crate::error!("dropping invalid queued event '{}': {}", key, err);
+                let _ = store.del(&key).await;
+                continue;
+            }
+        };
+        if target.save(&event).await.is_err() {
+            break;
+        }
+        let _ = store.del(&key).await;
+        replayed += 1;
+    }
+    replayed
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    use super::*;
+
+    struct MockTarget {
+        id: TargetId,
+        saved: Arc<AtomicUsize>,
+        closed: Arc<AtomicBool>,
+        offline: Arc<AtomicBool>,
+        store: Option<QueueStore>,
+    }
+
+    impl MockTarget {
+        fn new(id: &str) -> MockTarget {
+            MockTarget {
+                id: TargetId {
+                    id: id.to_owned(),
+                    name: "mock".to_owned(),
+                },
+                saved: Arc::new(AtomicUsize::new(0)),
+                closed: Arc::new(AtomicBool::new(false)),
+                offline: Arc::new(AtomicBool::new(false)),
+                store: None,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Target for MockTarget {
+        fn id(&self) -> &TargetId {
+            &self.id
+        }
+
+        fn is_active(&self) -> anyhow::Result<bool> {
+            Ok(true)
+        }
+
+        async fn save(&self, _event: &Event) -> anyhow::Result<()> {
+            anyhow::ensure!(!self.offline.load(Ordering::SeqCst), "target offline");
+            self.saved.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn send(&self, _s: &str) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn close(&mut self) -> anyhow::Result<()> {
+            self.closed.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn has_queue_store(&self) -> bool {
+            self.store.is_some()
+        }
+
+        fn queue_store(&self) -> Option<&QueueStore> {
+            self.store.as_ref()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_target_list_remove() {
+        let target_list = TargetList::default();
+        let target1 = MockTarget::new("1");
+        let target2 = MockTarget::new("2");
+        let (id1, id2) = (target1.id.clone(), target2.id.clone());
+        let closed1 = target1.closed.clone();
+        target_list.add(Box::new(target1)).unwrap();
+        target_list.add(Box::new(target2)).unwrap();
+        assert!(target_list.add(Box::new(MockTarget::new("1"))).is_err());
+
+        let mut ids = target_list.target_ids();
+        ids.sort_by(|a, b| a.id.cmp(&b.id));
+        assert!(ids == vec![id1.clone(), id2.clone()]);
+
+        assert!(target_list.remove(&id1).await);
+        assert!(closed1.load(Ordering::SeqCst));
+        assert!(!target_list.remove(&id1).await);
+        assert!(target_list.target_ids() == vec![id2.clone()]);
+        assert!(!target_list.contains(&id1));
+
+        // Sending to a removed target reports no error.
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let targets: HashSet<_> = vec![id1.clone()].into_iter().collect();
+        target_list.send(Event::default(), targets, tx).await;
+        let (id, err) = rx.recv().await.unwrap();
+        assert!(id == id1 && err.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_target_list_send_filter() {
+        let target_list = TargetList::default();
+        let logs_target = MockTarget::new("1");
+        let json_target = MockTarget::new("2");
+        let (logs_id, json_id) = (logs_target.id.clone(), json_target.id.clone());
+        let (logs_saved, json_saved) = (logs_target.saved.clone(), json_target.saved.clone());
+        target_list
+            .add_with_filter(
+                Box::new(logs_target),
+                KeyFilter {
+                    prefix: Some("logs/".to_owned()),
+                    suffix: None,
+                },
+            )
+            .unwrap();
+        target_list
+            .add_with_filter(
+                Box::new(json_target),
+                KeyFilter {
+                    prefix: None,
+                    suffix: Some(".json".to_owned()),
+                },
+            )
+            .unwrap();
+
+        let mut event = Event::default();
+        event.s3.object.key = "logs/a.txt".to_owned();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let targets: HashSet<_> = vec![logs_id, json_id].into_iter().collect();
+        target_list.send(event, targets, tx).await;
+        while let Some((_, err)) = rx.recv().await {
+            assert!(err.is_none());
+        }
+
+        assert_eq!(logs_saved.load(Ordering::SeqCst), 1);
+        assert_eq!(json_saved.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_target_list_queue_store() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let dir = crate::utils::Path::from_path(dir.path()).unwrap();
+        let target_list = Arc::new(TargetList::default());
+        let mut target = MockTarget::new("1");
+        target.store = Some(QueueStore::new(dir, 2).unwrap());
+        target.offline.store(true, Ordering::SeqCst);
+        let (id, saved, offline) = (
+            target.id.clone(),
+            target.saved.clone(),
+            target.offline.clone(),
+        );
+        target_list.add(Box::new(target)).unwrap();
+
+        let send = |key: &str| {
+            let mut event = Event::default();
+            event.s3.object.key = key.to_owned();
+            let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+            let targets: HashSet<_> = vec![id.clone()].into_iter().collect();
+            let target_list = target_list.clone();
+            async move {
+                target_list.send(event, targets, tx).await;
+                rx.recv().await.unwrap().1
+            }
+        };
+        // Events are queued while the target is offline, up to the limit.
+        assert!(send("a").await.is_none());
+        assert!(send("b").await.is_none());
+        assert!(send("c").await.is_some());
+        assert_eq!(std::fs::read_dir(dir).unwrap().count(), 2);
+        assert_eq!(target_list.replay_queued().await, 0);
+
+        offline.store(false, Ordering::SeqCst);
+        let retrier = target_list.start_retrier(Duration::from_millis(10));
+        for _ in 0..100 {
+            if saved.load(Ordering::SeqCst) == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(saved.load(Ordering::SeqCst), 2);
+        assert_eq!(std::fs::read_dir(dir).unwrap().count(), 0);
+
+        // Once online, events are delivered through the store right away.
+        assert!(send("d").await.is_none());
+        assert_eq!(saved.load(Ordering::SeqCst), 3);
+        assert_eq!(std::fs::read_dir(dir).unwrap().count(), 0);
+
+        drop(target_list);
+        retrier.await.unwrap();
+    }
+
+    #[test]
+    fn test_key_filter_matches() {
+        let rule = KeyFilter {
+            prefix: Some("logs/".to_owned()),
+            suffix: Some(".txt".to_owned()),
+        };
+        assert!(rule.matches("logs/a.txt"));
+        assert!(!rule.matches("logs/a.json"));
+        assert!(!rule.matches("data/a.txt"));
+        assert!(KeyFilter::default().matches("anything"));
+
+        // Event keys are query escaped.
+        let rule = KeyFilter {
+            prefix: Some("my logs/".to_owned()),
+            suffix: Some("+1.txt".to_owned()),
+        };
+        assert!(rule.matches("my+logs%2Fa%2B1.txt"));
+        assert!(!rule.matches("my+logs%2Fa+1.txt"));
+    }
 }
@@ -1,3 +1,4 @@
+use anyhow::{bail, ensure};
 use serde::{Deserialize, Serialize};
 
 use super::*;
@@ -5,8 +6,11 @@ use super::*;
 // Represents Erasure backend.
 const FORMAT_BACKEND_ERASURE: &str = "xl";
 
+// formatErasureV2.Erasure.Version - version '2'.
+const FORMAT_ERASURE_VERSION_V2: &str = "2";
+
 // formatErasureV3.Erasure.Version - version '3'.
-const FORMAT_ERASURE_VERSION_V3: &str = "3";
+pub const FORMAT_ERASURE_VERSION_V3: &str = "3";
 
 // Distributed algorithm used, with N/2 default parity
 const FORMAT_ERASURE_VERSION_V3DISTRIBUTION_ALGO_V2: &str = "SIPMOD";
@@ -17,16 +21,245 @@ const FORMAT_ERASURE_VERSION_V3DISTRIBUTION_ALGO_V3: &str = "SIPMOD+PARITY";
 // Offline disk UUID represents an offline disk.
 const OFFLINE_DISK_UUID: &str = "ffffffff-ffff-ffff-ffff-ffffffffffff";
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct FormatErasureV3 {
+    #[serde(flatten)]
     pub meta: FormatMetaV1,
+    #[serde(rename = "xl")]
     pub erasure: ErasureV3,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct ErasureV3 {
     pub version: String,
     pub this: String,
     pub sets: Vec<Vec<String>>,
     pub distribution_algo: String,
 }
+
+// The V2 layout only differs from V3 by its version.
+type ErasureV2 = ErasureV3;
+
+#[derive(Deserialize)]
+struct FormatErasureV2 {
+    #[serde(flatten)]
+    meta: FormatMetaV1,
+    #[serde(rename = "xl")]
+    erasure: ErasureV2,
+}
+
+// Just the versions of a format file, to decide how to load it.
+#[derive(Deserialize)]
+struct FormatErasureVersion {
+    #[serde(flatten)]
+    meta: FormatMetaV1,
+    #[serde(rename = "xl")]
+    erasure: ErasureVersion,
+}
+
+#[derive(Deserialize)]
+struct ErasureVersion {
+    version: String,
+}
+
+/// Returns the erasure version of the format file content.
+pub fn format_erasure_version(raw: &[u8]) -> anyhow::Result<String> {
+    let format: FormatErasureVersion = serde_json::from_slice(raw)?;
+    ensure!(
+        format.meta.format == FORMAT_BACKEND_ERASURE,
+        "unsupported backend format '{}'",
+        format.meta.format
+    );
+    Ok(format.erasure.version)
+}
+
+/// Loads the format file content, upgrading older erasure versions to V3.
+pub fn migrate_to_v3(raw: &[u8]) -> anyhow::Result<FormatErasureV3> {
+    match format_erasure_version(raw)?.as_str() {
+        FORMAT_ERASURE_VERSION_V3 => Ok(serde_json::from_slice(raw)?),
+        FORMAT_ERASURE_VERSION_V2 => {
+            let format: FormatErasureV2 = serde_json::from_slice(raw)?;
+            Ok(FormatErasureV3 {
+                meta: format.meta,
+                erasure: ErasureV3 {
+                    version: FORMAT_ERASURE_VERSION_V3.to_owned(),
+                    ..format.erasure
+                },
+            })
+        }
+        version => bail!("unsupported erasure format version '{}'", version),
+    }
+}
+
+impl FormatErasureV3 {
+    // Number of drives in each set, if all the sets have the same number.
+    fn drives_per_set(&self) -> Option<usize> {
+        let drives = self.erasure.sets.first()?.len();
+        if self.erasure.sets.iter().all(|set| set.len() == drives) {
+            Some(drives)
+        } else {
+            None
+        }
+    }
+}
+
+/// Verifies the formats of the disks of an erasure set agree on the deployment
+/// and the layout, naming the index of the first disk which disagrees.
+pub fn verify_set_consistency(formats: &[FormatErasureV3]) -> anyhow::Result<()> {
+    let reference = match formats.first() {
+        Some(format) => format,
+        None => return Ok(()),
+    };
+    let drives_per_set = reference
+        .drives_per_set()
+        .ok_or_else(|| anyhow::anyhow!("disk 0: sets have different numbers of drives"))?;
+    for (i, format) in formats.iter().enumerate().skip(1) {
+        ensure!(
+            format.meta.id == reference.meta.id,
+            "disk {}: deployment id '{}' does not match '{}'",
+            i,
+            format.meta.id,
+            reference.meta.id
+        );
+        ensure!(
+            format.erasure.sets.len() == reference.erasure.sets.len(),
+            "disk {}: set count {} does not match {}",
+            i,
+            format.erasure.sets.len(),
+            reference.erasure.sets.len()
+        );
+        match format.drives_per_set() {
+            Some(drives) if drives == drives_per_set => {}
+            Some(drives) => bail!(
+                "disk {}: drives per set {} does not match {}",
+                i,
+                drives,
+                drives_per_set
+            ),
+            None => bail!("disk {}: sets have different numbers of drives", i),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORMAT_V2: &str = r#"{
+        "version": "1",
+        "format": "xl",
+        "id": "bd7a3bd3-8d5b-4b5e-9aa4-0d1b76d8e5a3",
+        "xl": {
+            "version": "2",
+            "this": "4d9b1a45-ef8b-4fd2-a0b5-6e1ba2ef7b1c",
+            "sets": [
+                [
+                    "4d9b1a45-ef8b-4fd2-a0b5-6e1ba2ef7b1c",
+                    "8e0c3a4f-1ad5-4c4e-b6f5-9b7f2e1a0c3d"
+                ]
+            ],
+            "distributionAlgo": "CRCMOD"
+        }
+    }"#;
+
+    #[test]
+    fn test_migrate_to_v3() {
+        assert_eq!(format_erasure_version(FORMAT_V2.as_bytes()).unwrap(), "2");
+
+        let format = migrate_to_v3(FORMAT_V2.as_bytes()).unwrap();
+        assert_eq!(format.meta.version, "1");
+        assert_eq!(format.meta.format, FORMAT_BACKEND_ERASURE);
+        assert_eq!(format.meta.id, "bd7a3bd3-8d5b-4b5e-9aa4-0d1b76d8e5a3");
+        assert_eq!(format.erasure.version, FORMAT_ERASURE_VERSION_V3);
+        assert_eq!(format.erasure.this, "4d9b1a45-ef8b-4fd2-a0b5-6e1ba2ef7b1c");
+        assert_eq!(
+            format.erasure.sets,
+            vec![vec![
+                "4d9b1a45-ef8b-4fd2-a0b5-6e1ba2ef7b1c".to_owned(),
+                "8e0c3a4f-1ad5-4c4e-b6f5-9b7f2e1a0c3d".to_owned(),
+            ]]
+        );
+        assert_eq!(format.erasure.distribution_algo, "CRCMOD");
+
+        // A migrated format loads as is.
+        let raw = serde_json::to_vec(&format).unwrap();
+        assert_eq!(format_erasure_version(&raw).unwrap(), "3");
+        let loaded = migrate_to_v3(&raw).unwrap();
+        assert_eq!(loaded.erasure.this, format.erasure.this);
+        assert_eq!(loaded.erasure.sets, format.erasure.sets);
+    }
+
+    #[test]
+    fn test_migrate_to_v3_unsupported() {
+        let v1 = FORMAT_V2.replace(r#""version": "2""#, r#""version": "1""#);
+        assert!(migrate_to_v3(v1.as_bytes()).is_err());
+        let fs = FORMAT_V2.replace(r#""format": "xl""#, r#""format": "fs""#);
+        assert!(migrate_to_v3(fs.as_bytes()).is_err());
+        assert!(migrate_to_v3(b"{}").is_err());
+    }
+
+    fn new_format(
+        id: &str,
+        this: &str,
+        set_count: usize,
+        drives_per_set: usize,
+    ) -> FormatErasureV3 {
+        FormatErasureV3 {
+            meta: FormatMetaV1 {
+                version: "1".to_owned(),
+                format: FORMAT_BACKEND_ERASURE.to_owned(),
+                id: id.to_owned(),
+            },
+            erasure: ErasureV3 {
+                version: FORMAT_ERASURE_VERSION_V3.to_owned(),
+                this: this.to_owned(),
+                sets: (0..set_count)
+                    .map(|i| {
+                        (0..drives_per_set)
+                            .map(|j| format!("disk-{}-{}", i, j))
+                            .collect()
+                    })
+                    .collect(),
+                distribution_algo: FORMAT_ERASURE_VERSION_V3DISTRIBUTION_ALGO_V3.to_owned(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_verify_set_consistency() {
+        let formats: Vec<_> = (0..4)
+            .map(|j| new_format("deployment", &format!("disk-0-{}", j), 2, 4))
+            .collect();
+        verify_set_consistency(&formats).unwrap();
+        verify_set_consistency(&[]).unwrap();
+
+        let mut mismatched = formats.clone();
+        mismatched[2].meta.id = "other-deployment".to_owned();
+        let err = verify_set_consistency(&mismatched).unwrap_err().to_string();
+        assert!(
+            err.contains("disk 2") && err.contains("deployment id"),
+            "{}",
+            err
+        );
+
+        let mut mismatched = formats.clone();
+        mismatched[3] = new_format("deployment", "disk-0-3", 1, 4);
+        let err = verify_set_consistency(&mismatched).unwrap_err().to_string();
+        assert!(
+            err.contains("disk 3") && err.contains("set count"),
+            "{}",
+            err
+        );
+
+        let mut mismatched = formats;
+        mismatched[1] = new_format("deployment", "disk-0-1", 2, 3);
+        let err = verify_set_consistency(&mismatched).unwrap_err().to_string();
+        assert!(
+            err.contains("disk 1") && err.contains("drives per set"),
+            "{}",
+            err
+        );
+    }
+}
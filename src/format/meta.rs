@@ -20,9 +20,9 @@ const FORMAT_META_VERSION_V1: &str = "1";
 
 // Ideally we will never have a situation where we will have to change the
 // fields of this struct and deal with related migration.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct FormatMetaV1 {
-    version: String, // Version of the format config.
-    format: String,  // The backend format type, supports two values 'xl' and 'fs'.
-    id: String,      // The identifier for the deployment.
+    pub version: String, // Version of the format config.
+    pub format: String,  // The backend format type, supports two values 'xl' and 'fs'.
+    pub id: String,      // The identifier for the deployment.
 }
@@ -1,5 +1,5 @@
 use std::future::Future;
-use std::io::ErrorKind;
+use std::io::{ErrorKind, Seek, SeekFrom};
 use std::sync::Arc;
 
 use bytes::BufMut;
@@ -15,6 +15,7 @@ use crate::utils::BufGuardMut;
 pub struct AlignedWriter<G: BufGuardMut> {
     std: Arc<std::fs::File>,
     total_size: Option<u64>,
+    capacity: usize,
     buffer: &'static mut [u8],
     buffer_cursor: &'static mut [u8],
     #[pin]
@@ -44,6 +45,7 @@ impl<G: BufGuardMut> AlignedWriter<G> {
         AlignedWriter {
             std: Arc::new(f),
             total_size,
+            capacity: buffer.len(),
             buffer,
             buffer_cursor: &mut [],
             buf_guard: aligned_buf_guard,
@@ -67,22 +69,37 @@ impl<G: BufGuardMut> AlignedWriter<G> {
     }
 
     fn write(
-        this: &AlignedWriterProj<'_, G>,
+        this: &mut AlignedWriterProj<'_, G>,
         size: usize,
     ) -> std::io::Result<tokio::task::JoinHandle<std::io::Result<()>>> {
-        if this.buffer.len() % DIRECTIO_ALIGN_SIZE != 0 {
-            this.std.disable_direct_io()?; // TODO: async
-        }
+        // Only the final block may be partial. It is padded with zeros to
+        // be written directly, and the file is then truncated to its size
+        // with direct I/O turned off, as later I/O is no longer aligned.
+        let padded_size =
+            (size + DIRECTIO_ALIGN_SIZE - 1) / DIRECTIO_ALIGN_SIZE * DIRECTIO_ALIGN_SIZE;
+        debug_assert!(padded_size <= *this.capacity);
+        let file_size = if padded_size != size {
+            Some(*this.written)
+        } else {
+            None
+        };
 
         // TODO: should we use std file blocking read or tokio-uring?
-        let mut std = this.std.clone();
-        let buf_ptr = utils::SendRawPtr::new(this.buffer.as_ptr());
+        let std = this.std.clone();
+        let buf_ptr = utils::SendRawPtr::new(this.buffer.as_mut_ptr());
         let rx = tokio::task::spawn_blocking(move || {
             // Safety: buffer may be invalidated somewhere,
             // which may lead to dirty stuff to be written to file,
             // but even without this, the file is broken anyway, since writing has been cancelled.
-            let mut buffer = unsafe { std::slice::from_raw_parts(buf_ptr.to(), size) };
-            (&mut &*std).write_all(buffer)
+            let buffer = unsafe { std::slice::from_raw_parts_mut(buf_ptr.to(), padded_size) };
+            buffer[size..].fill(0);
+            (&mut &*std).write_all(buffer)?;
+            if let Some(file_size) = file_size {
+                std.disable_direct_io()?;
+                std.set_len(file_size)?;
+                (&mut &*std).seek(SeekFrom::Start(file_size))?;
+            }
+            Ok(())
         });
         Ok(rx)
     }
@@ -94,7 +111,7 @@ impl<G: BufGuardMut> AsyncWrite for AlignedWriter<G> {
         cx: &mut Context<'_>,
         mut buf: &[u8],
     ) -> Poll<std::io::Result<usize>> {
-        let this = self.project();
+        let mut this = self.project();
         if buf.is_empty() {
             return Poll::Ready(Ok(0));
         }
@@ -135,7 +152,7 @@ impl<G: BufGuardMut> AsyncWrite for AlignedWriter<G> {
                     }
 
                     // Buffer is full, so write it.
-                    let rx = Self::write(&this, this.buffer.len())?;
+                    let rx = Self::write(&mut this, this.buffer.len())?;
 
                     *this.state = State::Busy(consume, rx);
                 }
@@ -151,14 +168,14 @@ impl<G: BufGuardMut> AsyncWrite for AlignedWriter<G> {
     }
 
     fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
-        let this = self.project();
+        let mut this = self.project();
         loop {
             match this.state {
                 State::Idle => break,
                 State::Buffering => {
                     let buf_size = this.buffer.len() - this.buffer_cursor.remaining_mut();
                     if buf_size > 0 {
-                        let rx = Self::write(&this, buf_size)?;
+                        let rx = Self::write(&mut this, buf_size)?;
 
                         *this.state = State::Busy(0, rx);
                     } else {
@@ -234,6 +251,15 @@ mod tests {
                 None,
             )
             .await;
+            // Final partial block with a known total size.
+            aligned_write_and_read_numbers(
+                &tmp_file,
+                utils::MIB + utils::KIB + 3,
+                4 * DIRECTIO_ALIGN_SIZE,
+                DIRECTIO_ALIGN_SIZE,
+                Some((utils::MIB + utils::KIB + 3) * 8),
+            )
+            .await;
         });
     }
 
@@ -273,7 +299,11 @@ mod tests {
             });
         }
 
-        let mut writer = AlignedWriter::new(file.into_std().await, aligned_buf, None);
+        let mut writer = AlignedWriter::new(
+            file.into_std().await,
+            aligned_buf,
+            total_size.map(|size| size as u64),
+        );
 
         let content = (0..numbers)
             .map(|n| format!("{:08}", n).into_bytes())
@@ -37,6 +37,11 @@ pub async fn remove_all(path: impl AsRef<Path>) -> std::io::Result<()> {
     }
 }
 
+pub async fn fsync_dir(path: impl AsRef<Path>) -> std::io::Result<()> {
+    let path = path.as_ref().to_owned();
+    super::asyncify(move || std::fs::File::open(path)?.sync_all()).await
+}
+
 pub async fn access(path: impl AsRef<Path>) -> std::io::Result<()> {
     use faccess::{AccessMode, PathExt};
     let path = path.as_ref().to_owned();
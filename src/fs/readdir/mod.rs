@@ -7,9 +7,11 @@ use std::fs::{FileType, Metadata};
 use std::io;
 use std::io::Error;
 use std::path::{Path, PathBuf};
+use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use futures_core::Stream;
+use futures_core::{ready, Stream};
+use futures_util::future::{BoxFuture, FutureExt};
 #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "openbsd"))]
 use readdir::{DirEntry, ReadDir};
 #[cfg(not(any(target_os = "linux", target_os = "freebsd", target_os = "openbsd")))]
@@ -28,67 +30,152 @@ pub async fn read_dir(dir_path: impl AsRef<Path>) -> std::io::Result<ReadDir> {
     tokio::fs::read_dir(dir_path).await
 }
 
-pub struct ReadDirEntries<P: AsRef<Path>>(P, Option<ReadDir>);
+type NextEntry = (io::Result<Option<(String, FileType)>>, Option<ReadDir>);
+
+pub struct ReadDirEntries<P: AsRef<Path>> {
+    dir_path: P,
+    read_dir: Option<ReadDir>,
+    // Entry being read by the stream, which owns the `ReadDir` meanwhile.
+    pending: Option<BoxFuture<'static, NextEntry>>,
+}
 
 impl<P: AsRef<Path>> ReadDirEntries<P> {
     pub fn new(dir_path: P) -> ReadDirEntries<P> {
-        ReadDirEntries(dir_path, None)
+        ReadDirEntries {
+            dir_path,
+            read_dir: None,
+            pending: None,
+        }
     }
 
     pub async fn next_entry(&mut self) -> io::Result<Option<(String, FileType)>> {
-        let stream = if self.1.is_none() {
-            let stream = read_dir(self.0.as_ref()).await?;
-            self.1.insert(stream)
-        } else {
-            self.1.as_mut().unwrap()
-        };
+        if let Some(pending) = self.pending.take() {
+            let (entry, read_dir) = pending.await;
+            self.read_dir = read_dir;
+            return entry;
+        }
+        next_dir_entry(self.read_dir().await?).await
+    }
+
+    /// Like `next_entry`, but trusts the file type of the directory entry
+    /// when known, saving a stat, and also returns the inode number.
+    /// Not to be interleaved with polling the entries as a stream.
+    #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "openbsd"))]
+    pub async fn next_entry_fast(&mut self) -> io::Result<Option<(String, FileType, u64)>> {
+        let stream = self.read_dir().await?;
         while let Some(entry) = stream.next_entry().await? {
-            let mut typ = entry.file_type().await?;
-            let path: crate::utils::PathBuf = entry
-                .path()
-                .try_into()
-                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
-
-            if typ.is_symlink() {
-                // Traverse symlinks.
-                let meta = match crate::fs::metadata(&path).await {
-                    Ok(meta) => meta,
-                    Err(err) => {
-                        // It got deleted in the meantime, not found
-                        // or returns too many symlinks, ignore this
-                        // file/directory.
-                        if err_not_found(&err) && err_too_many_symlinks(&err) {
-                            continue;
-                        }
-                        return Err(err.into());
-                    }
-                };
-                // Ignore symlinked directories.
-                if meta.is_dir() {
-                    continue;
-                }
-                typ = meta.file_type();
+            let typ = match entry.known_file_type() {
+                Some(typ) => typ,
+                None => entry.file_type().await?,
+            };
+            if let Some((name, typ)) = resolve_dir_entry(&entry, typ).await? {
+                return Ok(Some((name, typ, entry.ino())));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn read_dir(&mut self) -> io::Result<&mut ReadDir> {
+        Ok(match self.read_dir {
+            Some(ref mut stream) => stream,
+            None => {
+                let stream = read_dir(self.dir_path.as_ref()).await?;
+                self.read_dir.insert(stream)
             }
+        })
+    }
+}
 
-            let name = path
-                .to_str()
-                .ok_or_else(|| {
-                    io::Error::new(io::ErrorKind::Other, "PathBuf contains invalid UTF-8")
-                })?
-                .to_owned();
-            let name = if typ.is_file() {
-                name
-            } else if typ.is_dir() {
-                name + crate::globals::SLASH_SEPARATOR
-            } else {
-                continue;
-            };
+impl<P: AsRef<Path> + Unpin> Stream for ReadDirEntries<P> {
+    type Item = io::Result<(String, FileType)>;
 
-            return Ok(Some((name, typ)));
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let pending = match this.pending {
+            Some(ref mut pending) => pending,
+            None => {
+                let dir_path = this.dir_path.as_ref().to_owned();
+                let read_dir = this.read_dir.take();
+                this.pending.insert(
+                    async move {
+                        let mut stream = match read_dir {
+                            Some(stream) => stream,
+                            None => match self::read_dir(dir_path).await {
+                                Ok(stream) => stream,
+                                Err(err) => return (Err(err), None),
+                            },
+                        };
+                        let entry = next_dir_entry(&mut stream).await;
+                        (entry, Some(stream))
+                    }
+                    .boxed(),
+                )
+            }
+        };
+        let (entry, read_dir) = ready!(pending.as_mut().poll(cx));
+        this.pending = None;
+        this.read_dir = read_dir;
+        Poll::Ready(entry.transpose())
+    }
+}
+
+// Returns the next file or directory, traversing symlinks to files.
+// Directory names end with a slash.
+async fn next_dir_entry(stream: &mut ReadDir) -> io::Result<Option<(String, FileType)>> {
+    while let Some(entry) = stream.next_entry().await? {
+        let typ = entry.file_type().await?;
+        if let Some(entry) = resolve_dir_entry(&entry, typ).await? {
+            return Ok(Some(entry));
         }
+    }
+    Ok(None)
+}
 
-        Ok(None)
+// Returns the name and type of an entry of type `typ`, or None if the entry
+// is to be skipped.
+async fn resolve_dir_entry(
+    entry: &DirEntry,
+    mut typ: FileType,
+) -> io::Result<Option<(String, FileType)>> {
+    let path: crate::utils::PathBuf = entry
+        .path()
+        .try_into()
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+
+    if typ.is_symlink() {
+        // Traverse symlinks.
+        let meta = match crate::fs::metadata(&path).await {
+            Ok(meta) => meta,
+            Err(err) => {
+                // It got deleted in the meantime, not found
+                // or returns too many symlinks, ignore this
+                // file/directory.
+                if err_not_found(&err) && err_too_many_symlinks(&err) {
+                    return Ok(None);
+                }
+                return Err(err.into());
+            }
+        };
+        // Ignore symlinked directories.
+        if meta.is_dir() {
+            return Ok(None);
+        }
+        typ = meta.file_type();
     }
+
+    let name = entry
+        .file_name()
+        .into_string()
+        .map_err(|_| io::Error::new(io::ErrorKind::Other, "file name contains invalid UTF-8"))?;
+    let name = if typ.is_file() {
+        name
+    } else if typ.is_dir() {
+        name + crate::globals::SLASH_SEPARATOR
+    } else {
+        return Ok(None);
+    };
+
+    Ok(Some((name, typ)))
 }
 
 pub async fn read_dir_entries(dir_path: impl AsRef<Path>) -> std::io::Result<Vec<String>> {
@@ -134,6 +221,7 @@ where
 
 #[cfg(test)]
 mod tests {
+    use futures_util::TryStreamExt;
     use tempfile::tempdir_in;
     use tokio::io::AsyncWriteExt;
 
@@ -211,4 +299,62 @@ mod tests {
             "expected true for empty dir, got false"
         );
     }
+
+    #[tokio::test]
+    async fn test_read_dir_entries_stream() {
+        let tmp_dir = tempdir_in(".").unwrap();
+        for name in ["a", "b", "c"] {
+            std::fs::write(tmp_dir.path().join(name), b"data").unwrap();
+        }
+        std::fs::create_dir(tmp_dir.path().join("dir")).unwrap();
+
+        let mut expected = read_dir_entries(tmp_dir.path()).await.unwrap();
+        let mut entries: Vec<_> = ReadDirEntries::new(tmp_dir.path())
+            .map_ok(|(name, _)| name)
+            .try_collect()
+            .await
+            .unwrap();
+        expected.sort();
+        entries.sort();
+        assert_eq!(entries, expected);
+        assert_eq!(entries, vec!["a", "b", "c", "dir/"]);
+
+        // Combinators.
+        let dirs: Vec<_> = ReadDirEntries::new(tmp_dir.path())
+            .try_filter(|(_, typ)| std::future::ready(typ.is_dir()))
+            .map_ok(|(name, _)| name)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(dirs, vec!["dir/"]);
+
+        let mut stream = ReadDirEntries::new(tmp_dir.path().join("missing"));
+        assert!(stream.try_next().await.is_err());
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "openbsd"))]
+    #[tokio::test]
+    async fn test_read_dir_entries_next_entry_fast() {
+        use std::os::unix::fs::MetadataExt;
+
+        let tmp_dir = tempdir_in(".").unwrap();
+        for i in 0..16 {
+            std::fs::write(tmp_dir.path().join(format!("file-{}", i)), b"data").unwrap();
+        }
+
+        let mut entries = Vec::new();
+        let mut stream = ReadDirEntries::new(tmp_dir.path());
+        while let Some(entry) = stream.next_entry().await.unwrap() {
+            entries.push(entry);
+        }
+        let mut fast_entries = Vec::new();
+        let mut stream = ReadDirEntries::new(tmp_dir.path());
+        while let Some((name, typ, ino)) = stream.next_entry_fast().await.unwrap() {
+            let meta = std::fs::metadata(tmp_dir.path().join(&name)).unwrap();
+            assert_eq!(ino, meta.ino());
+            fast_entries.push((name, typ));
+        }
+        assert_eq!(entries.len(), 16);
+        assert_eq!(fast_entries, entries);
+    }
 }
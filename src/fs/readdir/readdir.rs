@@ -97,6 +97,12 @@ impl DirEntry {
         asyncify(move || std.file_type().map(|f| FileType(f).to_std())).await
     }
 
+    /// Returns the file type reported by the directory entry, if it is known
+    /// and not a symlink.
+    pub fn known_file_type(&self) -> Option<std::fs::FileType> {
+        self.0.known_file_type().map(|f| FileType(f).to_std())
+    }
+
     #[cfg(unix)]
     pub(super) fn as_inner(&self) -> &readdir_impl::DirEntry {
         &self.0
@@ -282,6 +282,20 @@ impl DirEntry {
         }
     }
 
+    // File type from `d_type`, without a stat. None if it is unknown or a symlink.
+    #[cfg(not(any(
+        target_os = "solaris",
+        target_os = "illumos",
+        target_os = "haiku",
+        target_os = "vxworks"
+    )))]
+    pub fn known_file_type(&self) -> Option<FileType> {
+        match self.entry.d_type {
+            libc::DT_UNKNOWN | libc::DT_LNK => None,
+            _ => self.file_type().ok(),
+        }
+    }
+
     #[cfg(any(
         target_os = "macos",
         target_os = "ios",
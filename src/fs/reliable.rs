@@ -1,6 +1,8 @@
 use std::convert::TryFrom;
 use std::io::ErrorKind;
 
+use tokio::io::AsyncWriteExt;
+
 use super::*;
 use crate::errors::StorageError;
 use crate::utils::Path;
@@ -120,3 +122,230 @@ async fn reliable_remove_all_inner(path: impl AsRef<Path>) -> std::io::Result<()
         };
     }
 }
+
+// Buffer size of the fallback copy.
+const COPY_BUFFER_SIZE: usize = 1 << 20;
+
+/// Copies a regular file, preserving its mode, and fsyncs the destination.
+/// Reflinks or `copy_file_range` are used where supported, with a buffered
+/// copy as fallback.
+pub async fn reliable_copy_file(
+    src_path: impl AsRef<Path>,
+    dst_path: impl AsRef<Path>,
+) -> anyhow::Result<()> {
+    let src_path = src_path.as_ref();
+    let dst_path = dst_path.as_ref();
+    let _ = check_path_length(src_path.as_str())?;
+    let _ = check_path_length(dst_path.as_str())?;
+    if let Err(err) = reliable_copy_file_inner(src_path, dst_path).await {
+        return if err_not_found(&err) {
+            Err(StorageError::FileNotFound.into())
+        } else if err_not_dir(&err) {
+            Err(StorageError::FileAccessDenied.into())
+        } else if err_is_dir(&err) {
+            Err(StorageError::IsNotRegular.into())
+        } else {
+            Err(err.into())
+        };
+    }
+    Ok(())
+}
+
+async fn reliable_copy_file_inner(src_path: &Path, dst_path: &Path) -> std::io::Result<()> {
+    if let Some(dst_dir) = dst_path.parent() {
+        let _ = reliable_mkdir_all_inner(dst_dir, 0o777).await?;
+    }
+
+    let src = tokio::fs::File::open(src_path.as_std_path()).await?;
+    let meta = src.metadata().await?;
+    if meta.is_dir() {
+        return Err(std::io::Error::from_raw_os_error(libc::EISDIR));
+    }
+    // Truncating the destination must not truncate the source.
+    if let Ok(dst_path) = tokio::fs::canonicalize(dst_path.as_std_path()).await {
+        if dst_path == tokio::fs::canonicalize(src_path.as_std_path()).await? {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "source and destination are the same file",
+            ));
+        }
+    }
+    let mut dst = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(dst_path.as_std_path())
+        .await?;
+    if !copy_file_offload(&src, &dst, meta.len()).await? {
+        copy_file_buffered(src, &mut dst).await?;
+    }
+    dst.set_permissions(meta.permissions()).await?;
+    dst.sync_all().await
+}
+
+async fn copy_file_buffered(src: File, dst: &mut File) -> std::io::Result<()> {
+    let mut reader = tokio::io::BufReader::with_capacity(COPY_BUFFER_SIZE, src);
+    tokio::io::copy_buf(&mut reader, dst).await?;
+    dst.flush().await
+}
+
+// Copies the file in the kernel, returns false if it is not supported
+// between these files.
+#[cfg(target_os = "linux")]
+async fn copy_file_offload(src: &File, dst: &File, len: u64) -> std::io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    const FICLONE: libc::c_ulong = 0x40049409;
+
+    let src_fd = src.as_raw_fd();
+    let dst_fd = dst.as_raw_fd();
+    asyncify(move || {
+        // Shares the extents on filesystems supporting reflinks.
+        if unsafe { libc::ioctl(dst_fd, FICLONE, src_fd) } == 0 {
+            return Ok(true);
+        }
+        let mut copied = 0;
+        while copied < len {
+            let n = unsafe {
+                libc::copy_file_range(
+                    src_fd,
+                    std::ptr::null_mut(),
+                    dst_fd,
+                    std::ptr::null_mut(),
+                    (len - copied) as usize,
+                    0,
+                )
+            };
+            if n < 0 {
+                let err = std::io::Error::last_os_error();
+                if err.kind() == ErrorKind::Interrupted {
+                    continue;
+                }
+                if copied == 0
+                    && matches!(
+                        err.raw_os_error(),
+                        Some(libc::EXDEV | libc::ENOSYS | libc::EOPNOTSUPP | libc::EINVAL)
+                    )
+                {
+                    return Ok(false);
+                }
+                return Err(err);
+            }
+            if n == 0 {
+                break;
+            }
+            copied += n as u64;
+        }
+        Ok(true)
+    })
+    .await
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn copy_file_offload(_src: &File, _dst: &File, _len: u64) -> std::io::Result<bool> {
+    Ok(false)
+}
+
+/// Renames a file, falling back to copy and remove when the destination
+/// is on another device.
+pub async fn reliable_move_file(
+    src_path: impl AsRef<Path>,
+    dst_path: impl AsRef<Path>,
+) -> anyhow::Result<()> {
+    let src_path = src_path.as_ref();
+    let dst_path = dst_path.as_ref();
+    match reliable_rename(src_path, dst_path).await {
+        Err(err)
+            if matches!(
+                err.downcast_ref::<StorageError>(),
+                Some(StorageError::CrossDeviceLink(..))
+            ) =>
+        {
+            reliable_copy_file(src_path, dst_path).await?;
+            remove(src_path).await?;
+            Ok(())
+        }
+        res => res,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches::assert_matches;
+    #[cfg(unix)]
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reliable_copy_file() {
+        let tmp_dir = tempfile::tempdir_in(".").unwrap();
+        let dir = Path::from_path(tmp_dir.path()).unwrap();
+        let src_path = dir.join("src");
+        let dst_path = dir.join("dir/dst");
+        let data: Vec<u8> = (0..3 * COPY_BUFFER_SIZE + 7).map(|i| i as u8).collect();
+        std::fs::write(&src_path, &data).unwrap();
+        #[cfg(unix)]
+        std::fs::set_permissions(&src_path, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+        reliable_copy_file(&src_path, &dst_path).await.unwrap();
+        assert_eq!(std::fs::read(&dst_path).unwrap(), data);
+        #[cfg(unix)]
+        assert_eq!(
+            std::fs::metadata(&dst_path).unwrap().permissions().mode() & 0o777,
+            0o640
+        );
+
+        // Overwrites a larger destination.
+        std::fs::write(&src_path, b"small").unwrap();
+        reliable_copy_file(&src_path, &dst_path).await.unwrap();
+        assert_eq!(std::fs::read(&dst_path).unwrap(), b"small");
+
+        // Copying a file onto itself leaves it untouched.
+        for path in [src_path.clone(), dir.join("dir/../src")] {
+            assert!(reliable_copy_file(&src_path, &path).await.is_err());
+            assert_eq!(std::fs::read(&src_path).unwrap(), b"small");
+        }
+
+        let err = reliable_copy_file(dir.join("missing"), &dst_path)
+            .await
+            .unwrap_err();
+        assert_matches!(
+            err.downcast_ref::<StorageError>(),
+            Some(StorageError::FileNotFound)
+        );
+        let err = reliable_copy_file(dir, dir.join("dir/copy"))
+            .await
+            .unwrap_err();
+        assert_matches!(
+            err.downcast_ref::<StorageError>(),
+            Some(StorageError::IsNotRegular)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_copy_file_buffered() {
+        let tmp_dir = tempfile::tempdir_in(".").unwrap();
+        let dir = Path::from_path(tmp_dir.path()).unwrap();
+        let data: Vec<u8> = (0..COPY_BUFFER_SIZE + 1).map(|i| (i % 251) as u8).collect();
+        std::fs::write(dir.join("src"), &data).unwrap();
+
+        let src = File::open(dir.join("src")).await.unwrap();
+        let mut dst = File::create(dir.join("dst")).await.unwrap();
+        copy_file_buffered(src, &mut dst).await.unwrap();
+        assert_eq!(std::fs::read(dir.join("dst")).unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_reliable_move_file() {
+        let tmp_dir = tempfile::tempdir_in(".").unwrap();
+        let dir = Path::from_path(tmp_dir.path()).unwrap();
+        std::fs::write(dir.join("src"), b"data").unwrap();
+
+        reliable_move_file(dir.join("src"), dir.join("dir/dst"))
+            .await
+            .unwrap();
+        assert!(!dir.join("src").exists());
+        assert_eq!(std::fs::read(dir.join("dir/dst")).unwrap(), b"data");
+    }
+}
@@ -1,7 +1,9 @@
 mod constants;
 mod env;
+mod registry;
 mod vars;
 
 pub use constants::*;
 pub use env::*;
+pub use registry::*;
 pub use vars::*;
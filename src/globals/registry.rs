@@ -0,0 +1,63 @@
+use std::sync::Arc;
+
+use arc_swap::ArcSwapOption;
+
+// Holds a global instance, set once initialized, which readers reach with a
+// lock-free load.
+pub struct Registry<T>(ArcSwapOption<T>);
+
+impl<T> Default for Registry<T> {
+    fn default() -> Self {
+        Registry(ArcSwapOption::empty())
+    }
+}
+
+impl<T> Registry<T> {
+    pub fn get(&self) -> Option<Arc<T>> {
+        self.0.load_full()
+    }
+
+    pub fn set(&self, val: Arc<T>) {
+        self.0.store(Some(val));
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.0.load().is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Barrier;
+
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let registry = Registry::default();
+        assert!(registry.get().is_none());
+        assert!(!registry.is_set());
+
+        let val = Arc::new("layer".to_owned());
+        registry.set(val.clone());
+        assert!(Arc::ptr_eq(&registry.get().unwrap(), &val));
+
+        // Concurrent readers all reach the same instance.
+        let registry = Arc::new(registry);
+        let start = Arc::new(Barrier::new(8));
+        let readers: Vec<_> = (0..8)
+            .map(|_| {
+                let registry = registry.clone();
+                let start = start.clone();
+                std::thread::spawn(move || {
+                    start.wait();
+                    (0..10_000).all(|_| registry.get().map_or(false, |got| *got == "layer"))
+                })
+            })
+            .collect();
+        for reader in readers {
+            assert!(reader.join().unwrap());
+        }
+        assert!(Arc::ptr_eq(&registry.get().unwrap(), &val));
+    }
+}
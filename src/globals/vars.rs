@@ -1,6 +1,7 @@
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
+use arc_swap::ArcSwap;
 use lazy_static::lazy_static;
 
 use super::*;
@@ -58,7 +59,9 @@ pub struct Globals {
 
     pub api_config: Arc<Mutex<ApiConfig>>,
 
-    pub storage_class: Arc<Mutex<crate::config::storageclass::Config>>,
+    // Read on every disk I/O, so swapped as a whole on config change rather
+    // than locked.
+    pub storage_class: Arc<ArcSwap<crate::config::storageclass::Config>>,
 
     // IsSSL indicates if the server is configured with SSL.
     pub is_tls: Arc<AtomicBool>,
@@ -73,10 +76,15 @@ pub struct Globals {
 
     pub active_cred: Arc<RwLock<crate::auth::Credentials>>,
 
+    // Policies of the users, groups and buckets.
+    pub iam: Arc<crate::iam::Iam>,
+
     // Root domains for virtual host style requests.
     pub domain_names: Arc<RwLock<Vec<String>>>,
     // Root domain IP addresses.
     pub domain_ips: Arc<Mutex<StringSet>>,
+    // If not empty, only root domain IP addresses within these ranges are kept.
+    pub domain_cidrs: Arc<Mutex<Vec<ipnet::IpNet>>>,
 
     // Deployment ID, unique per deployment.
     pub deployment_id: Arc<RwLock<String>>,
@@ -114,6 +122,21 @@ impl<T: ?Sized> ReadWriteGuard<T> for Arc<RwLock<T>> {
     }
 }
 
+pub trait Snapshot<T> {
+    fn snapshot(&self) -> arc_swap::Guard<Arc<T>>;
+    fn update(&self, val: T);
+}
+
+impl<T> Snapshot<T> for Arc<ArcSwap<T>> {
+    fn snapshot(&self) -> arc_swap::Guard<Arc<T>> {
+        self.load()
+    }
+
+    fn update(&self, val: T) {
+        self.store(Arc::new(val));
+    }
+}
+
 pub trait Get<T: Copy> {
     fn get(&self) -> T;
 }
@@ -147,3 +170,49 @@ pub fn get_url_scheme() -> &'static str {
         "http"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Barrier;
+
+    use super::*;
+    use crate::config::storageclass::{self, DMA_READ_WRITE};
+
+    #[test]
+    fn test_snapshot() {
+        let storage_class: Arc<ArcSwap<storageclass::Config>> = Default::default();
+
+        // A reader holding a snapshot does not block the writer.
+        let snapshot = storage_class.snapshot();
+        storage_class.update(storageclass::Config {
+            dma: DMA_READ_WRITE.to_owned(),
+            ..Default::default()
+        });
+        assert_eq!(snapshot.dma, "");
+        assert_eq!(storage_class.snapshot().dma, DMA_READ_WRITE);
+
+        // Concurrent readers observe the swap.
+        storage_class.update(Default::default());
+        let start = Arc::new(Barrier::new(5));
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let storage_class = storage_class.clone();
+                let start = start.clone();
+                std::thread::spawn(move || {
+                    start.wait();
+                    while storage_class.snapshot().dma != DMA_READ_WRITE {
+                        std::hint::spin_loop();
+                    }
+                })
+            })
+            .collect();
+        start.wait();
+        storage_class.update(storageclass::Config {
+            dma: DMA_READ_WRITE.to_owned(),
+            ..Default::default()
+        });
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+}
@@ -175,6 +175,12 @@ impl<'a, R: ReadMaybeTagger<'a>> Reader<'a, R> {
         let md5 = hex::decode(md5_hex)?;
         let sha256 = hex::decode(sha256_hex)?;
 
+        // The content is only verified against the given MD5, if any.
+        let checksum = if !md5.is_empty() {
+            Some(ETag::new(md5))
+        } else {
+            None
+        };
         let s: ReaderInner<R>;
         if size >= 0 {
             if src.as_tagger().is_some() {
@@ -184,11 +190,11 @@ impl<'a, R: ReadMaybeTagger<'a>> Reader<'a, R> {
             } else {
                 s = ReaderInner::LimitedEtagReader(etag::Reader::new(
                     src.take(size as u64),
-                    Some(ETag::new(md5)),
+                    checksum,
                 ));
             }
         } else if src.as_tagger().is_none() {
-            s = ReaderInner::EtagReader(etag::Reader::new(src, Some(ETag::new(md5))));
+            s = ReaderInner::EtagReader(etag::Reader::new(src, checksum));
         } else {
             s = ReaderInner::Reader(src);
         }
@@ -1,8 +1,14 @@
+use std::collections::HashMap;
+
 use actix_web::http::{header, Method};
 use actix_web::HttpRequest;
+use anyhow::bail;
 
 use super::*;
+use crate::bucket::policy;
+use crate::errors::ApiError;
 use crate::http::{self, RequestExtensionsContext};
+use crate::iam::Iam;
 
 fn is_request_jwt(req: &HttpRequest) -> bool {
     req.headers()
@@ -130,3 +136,252 @@ pub fn is_supported_s3_auth_type(auth_type: AuthType) -> bool {
         Anonymous | Presigned | PresignedV2 | Signed | SignedV2 | PostPolicy | StreamingSigned
     )
 }
+
+// Value of the principal type condition key for anonymous requests.
+const ANONYMOUS_PRINCIPAL_TYPE: &str = "Anonymous";
+
+// Condition values of an anonymous request, for the bucket policy.
+fn anonymous_condition_values(req: &HttpRequest) -> HashMap<String, Vec<String>> {
+    use crate::bucket::policy::condition::*;
+
+    let mut values = HashMap::new();
+    values.insert(
+        AWS_PRINCIPAL_TYPE.name().to_owned(),
+        vec![ANONYMOUS_PRINCIPAL_TYPE.to_owned()],
+    );
+    if let Some(addr) = req.peer_addr() {
+        values.insert(AWS_SOURCE_IP.name().to_owned(), vec![addr.ip().to_string()]);
+    }
+    for (key, header) in [
+        (AWS_USER_AGENT, header::USER_AGENT),
+        (AWS_REFERER, header::REFERER),
+    ] {
+        if let Some(value) = req.headers().get(header).and_then(|v| v.to_str().ok()) {
+            values.insert(key.name().to_owned(), vec![value.to_owned()]);
+        }
+    }
+    values
+}
+
+/// Authorizes a request without credentials: the anonymous principal is only
+/// allowed what the bucket policy grants to everyone, e.g. on public-read
+/// buckets.
+pub fn check_anonymous_request(
+    iam: &Iam,
+    req: &HttpRequest,
+    action: policy::Action,
+    bucket: &str,
+    object: &str,
+) -> anyhow::Result<()> {
+    let args = policy::Args {
+        account_name: "".to_owned(),
+        groups: vec![],
+        action,
+        bucket_name: bucket.to_owned(),
+        condition_values: anonymous_condition_values(req),
+        is_owner: false,
+        object_name: object.to_owned(),
+    };
+    if !iam.is_allowed_anonymous(&args) {
+        bail!(ApiError::AccessDenied);
+    }
+    Ok(())
+}
+
+/// Returns the policy action of an S3 request on `object`, or on its bucket
+/// if `object` is empty. Unknown sub-resources map to no action.
+pub fn request_policy_action(req: &HttpRequest, object: &str) -> Option<policy::Action> {
+    use crate::bucket::policy::*;
+
+    let query = req.query();
+    let has = |key: &str| query.as_ref().map_or(false, |q| q.contains_key(key));
+    let method = req.method();
+    let action = if !object.is_empty() {
+        if method == Method::GET || method == Method::HEAD {
+            if has("tagging") {
+                GET_OBJECT_TAGGING_ACTION
+            } else if has("retention") {
+                GET_OBJECT_RETENTION_ACTION
+            } else if has("legal-hold") {
+                GET_OBJECT_LEGAL_HOLD_ACTION
+            } else if has("uploadId") {
+                LIST_MULTIPART_UPLOAD_PARTS_ACTION
+            } else {
+                GET_OBJECT_ACTION
+            }
+        } else if method == Method::PUT {
+            if has("tagging") {
+                PUT_OBJECT_TAGGING_ACTION
+            } else if has("retention") {
+                PUT_OBJECT_RETENTION_ACTION
+            } else if has("legal-hold") {
+                PUT_OBJECT_LEGAL_HOLD_ACTION
+            } else {
+                PUT_OBJECT_ACTION
+            }
+        } else if method == Method::POST {
+            if has("uploads") || has("uploadId") {
+                PUT_OBJECT_ACTION
+            } else if has("restore") {
+                RESTORE_OBJECT_ACTION
+            } else if has("select") {
+                GET_OBJECT_ACTION
+            } else {
+                return None;
+            }
+        } else if method == Method::DELETE {
+            if has("tagging") {
+                DELETE_OBJECT_TAGGING_ACTION
+            } else if has("uploadId") {
+                ABORT_MULTIPART_UPLOAD_ACTION
+            } else {
+                DELETE_OBJECT_ACTION
+            }
+        } else {
+            return None;
+        }
+    } else if method == Method::GET {
+        if has("location") {
+            GET_BUCKET_LOCATION_ACTION
+        } else if has("policy") {
+            GET_BUCKET_POLICY_ACTION
+        } else if has("uploads") {
+            LIST_BUCKET_MULTIPART_UPLOADS_ACTION
+        } else if has("versions") {
+            LIST_BUCKET_VERSIONS_ACTION
+        } else if has("versioning") {
+            GET_BUCKET_VERSIONING_ACTION
+        } else if has("lifecycle") {
+            GET_BUCKET_LIFECYCLE_ACTION
+        } else if has("tagging") {
+            GET_BUCKET_TAGGING_ACTION
+        } else if has("notification") {
+            GET_BUCKET_NOTIFICATION_ACTION
+        } else {
+            LIST_BUCKET_ACTION
+        }
+    } else if method == Method::HEAD {
+        LIST_BUCKET_ACTION
+    } else if method == Method::PUT {
+        if has("policy") {
+            PUT_BUCKET_POLICY_ACTION
+        } else if has("versioning") {
+            PUT_BUCKET_VERSIONING_ACTION
+        } else if has("lifecycle") {
+            PUT_BUCKET_LIFECYCLE_ACTION
+        } else if has("tagging") {
+            PUT_BUCKET_TAGGING_ACTION
+        } else if has("notification") {
+            PUT_BUCKET_NOTIFICATION_ACTION
+        } else {
+            CREATE_BUCKET_ACTION
+        }
+    } else if method == Method::DELETE {
+        if has("policy") {
+            DELETE_BUCKET_POLICY_ACTION
+        } else {
+            DELETE_BUCKET_ACTION
+        }
+    } else if method == Method::POST && has("delete") {
+        DELETE_OBJECT_ACTION
+    } else {
+        return None;
+    };
+    Some(action)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches::assert_matches;
+
+    use actix_web::test::TestRequest;
+    use actix_web::HttpMessage;
+
+    use super::*;
+    use crate::http::RequestExtensions;
+
+    const PUBLIC_READ_POLICY: &str = r#"{
+        "Version": "2012-10-17",
+        "Statement": [
+            {
+                "Effect": "Allow",
+                "Principal": {"AWS": ["*"]},
+                "Action": ["s3:GetObject"],
+                "Resource": ["arn:aws:s3:::public/*"]
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_check_anonymous_request() {
+        let iam = Iam::default();
+        iam.set_bucket_policy("public", serde_json::from_str(PUBLIC_READ_POLICY).unwrap());
+
+        let req = TestRequest::get().uri("/public/a.txt").to_http_request();
+        req.extensions_mut().insert(RequestExtensions::default());
+        assert_eq!(get_request_auth_type(&req), AuthType::Anonymous);
+        assert!(
+            check_anonymous_request(&iam, &req, policy::GET_OBJECT_ACTION, "public", "a.txt")
+                .is_ok()
+        );
+
+        // Only reads are public.
+        let err = check_anonymous_request(&iam, &req, policy::PUT_OBJECT_ACTION, "public", "a.txt")
+            .unwrap_err();
+        assert_matches!(err.downcast_ref::<ApiError>(), Some(ApiError::AccessDenied));
+
+        // Private bucket.
+        let err =
+            check_anonymous_request(&iam, &req, policy::GET_OBJECT_ACTION, "private", "a.txt")
+                .unwrap_err();
+        assert_matches!(err.downcast_ref::<ApiError>(), Some(ApiError::AccessDenied));
+
+        // Actions of the requests.
+        for (method, uri, object, action) in [
+            (
+                Method::GET,
+                "/public/a.txt",
+                "a.txt",
+                policy::GET_OBJECT_ACTION,
+            ),
+            (
+                Method::HEAD,
+                "/public/a.txt",
+                "a.txt",
+                policy::GET_OBJECT_ACTION,
+            ),
+            (
+                Method::GET,
+                "/public/a.txt?tagging",
+                "a.txt",
+                policy::GET_OBJECT_TAGGING_ACTION,
+            ),
+            (
+                Method::PUT,
+                "/public/a.txt",
+                "a.txt",
+                policy::PUT_OBJECT_ACTION,
+            ),
+            (Method::GET, "/public", "", policy::LIST_BUCKET_ACTION),
+            (
+                Method::PUT,
+                "/public?policy",
+                "",
+                policy::PUT_BUCKET_POLICY_ACTION,
+            ),
+        ] {
+            let req = TestRequest::default()
+                .method(method)
+                .uri(uri)
+                .to_http_request();
+            req.extensions_mut().insert(RequestExtensions::default());
+            assert_eq!(request_policy_action(&req, object), Some(action), "{}", uri);
+        }
+
+        iam.delete_bucket_policy("public");
+        assert!(
+            check_anonymous_request(&iam, &req, policy::GET_OBJECT_ACTION, "public", "a.txt")
+                .is_err()
+        );
+    }
+}
@@ -1,4 +1,44 @@
+use ring::hmac;
+
+use crate::utils;
+
 // AWS Signature Version '4' constants.
 pub(super) const SIGN_V4_ALGORITHM: &str = "AWS4-HMAC-SHA256";
 
 pub const ISO_8601_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+pub const YYYYMMDD: &str = "%Y%m%d";
+
+const SERVICE_S3: &str = "s3";
+const AWS4_REQUEST: &str = "aws4_request";
+
+pub(super) fn sum_hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, key);
+    hmac::sign(&key, data).as_ref().to_vec()
+}
+
+/// Returns the credential scope, e.g. `20130524/us-east-1/s3/aws4_request`.
+pub fn get_scope(t: utils::DateTime, region: &str) -> String {
+    format!(
+        "{}/{}/{}/{}",
+        t.format(YYYYMMDD),
+        region,
+        SERVICE_S3,
+        AWS4_REQUEST
+    )
+}
+
+/// Derives the signing key for the given secret key, date and region.
+pub fn get_signing_key(secret_key: &str, t: utils::DateTime, region: &str) -> Vec<u8> {
+    let date = sum_hmac(
+        format!("AWS4{}", secret_key).as_bytes(),
+        t.format(YYYYMMDD).to_string().as_bytes(),
+    );
+    let region = sum_hmac(&date, region.as_bytes());
+    let service = sum_hmac(&region, SERVICE_S3.as_bytes());
+    sum_hmac(&service, AWS4_REQUEST.as_bytes())
+}
+
+/// Returns the hex encoded signature of the string to sign.
+pub fn get_signature(signing_key: &[u8], string_to_sign: &str) -> String {
+    hex::encode(sum_hmac(signing_key, string_to_sign.as_bytes()))
+}
@@ -1,2 +1,238 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_util::ready;
+use thiserror::Error;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use super::*;
+use crate::auth::secure_compare;
+use crate::hash::sha256_hex;
+use crate::s3utils::{parse_chunk, ChunkedDecodeError};
+use crate::utils;
+
 // Streaming AWS Signature Version '4' constants.
 pub(super) const STREAMING_CONTENT_SHA256: &str = "STREAMING-AWS4-HMAC-SHA256-PAYLOAD";
+const SIGN_V4_CHUNKED_ALGORITHM: &str = "AWS4-HMAC-SHA256-PAYLOAD";
+const EMPTY_SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+const READ_BUF_SIZE: usize = 32 * 1024;
+
+#[derive(Error, Debug)]
+pub enum StreamingSignatureError {
+    #[error("malformed encoded chunk")]
+    MalformedEncoding,
+    #[error(transparent)]
+    Decode(#[from] ChunkedDecodeError),
+    #[error("chunk signature does not match")]
+    SignatureMismatch,
+    #[error("unexpected end of chunked stream")]
+    UnexpectedEof,
+}
+
+impl From<StreamingSignatureError> for std::io::Error {
+    fn from(err: StreamingSignatureError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// Decodes an `aws-chunked` encoded payload, verifying every chunk signature
+/// against the running signature chain seeded by the request signature.
+///
+/// Chunk data is only handed out after its signature has been verified.
+pub struct StreamingVerifier<R> {
+    reader: R,
+    signing_key: Vec<u8>,
+    date: String,
+    scope: String,
+    prev_signature: String,
+    // Raw bytes read from the reader, not yet decoded.
+    buf: Vec<u8>,
+    // Verified data of the current chunk.
+    chunk: Vec<u8>,
+    chunk_pos: usize,
+    done: bool,
+}
+
+impl<R: AsyncRead + Unpin> StreamingVerifier<R> {
+    pub fn new(
+        reader: R,
+        secret_key: &str,
+        region: &str,
+        t: utils::DateTime,
+        seed_signature: &str,
+    ) -> Self {
+        StreamingVerifier {
+            reader,
+            signing_key: get_signing_key(secret_key, t, region),
+            date: t.format(ISO_8601_FORMAT).to_string(),
+            scope: get_scope(t, region),
+            prev_signature: seed_signature.to_owned(),
+            buf: Vec::new(),
+            chunk: Vec::new(),
+            chunk_pos: 0,
+            done: false,
+        }
+    }
+
+    fn chunk_signature(&self, data: &[u8]) -> String {
+        let string_to_sign = [
+            SIGN_V4_CHUNKED_ALGORITHM,
+            &self.date,
+            &self.scope,
+            &self.prev_signature,
+            EMPTY_SHA256,
+            &sha256_hex(data),
+        ]
+        .join("\n");
+        get_signature(&self.signing_key, &string_to_sign)
+    }
+
+    // Decodes and verifies one chunk from the buffered bytes.
+    // Returns false if more bytes are needed.
+    fn decode_chunk(&mut self) -> Result<bool, StreamingSignatureError> {
+        let chunk = match parse_chunk(&self.buf)? {
+            Some(chunk) => chunk,
+            None => return Ok(false),
+        };
+        let signature = chunk
+            .signature
+            .ok_or(StreamingSignatureError::MalformedEncoding)?;
+        let expected = self.chunk_signature(chunk.data);
+        if !secure_compare(expected.as_bytes(), signature.as_bytes()) {
+            return Err(StreamingSignatureError::SignatureMismatch);
+        }
+
+        self.chunk.clear();
+        self.chunk.extend_from_slice(chunk.data);
+        self.chunk_pos = 0;
+        if chunk.data.is_empty() {
+            self.done = true;
+        }
+        let encoded_len = chunk.encoded_len;
+        self.buf.drain(..encoded_len);
+        self.prev_signature = expected;
+        Ok(true)
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for StreamingVerifier<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.chunk_pos < this.chunk.len() {
+                let n = buf.remaining().min(this.chunk.len() - this.chunk_pos);
+                buf.put_slice(&this.chunk[this.chunk_pos..this.chunk_pos + n]);
+                this.chunk_pos += n;
+                return Poll::Ready(Ok(()));
+            }
+            if this.done {
+                return Poll::Ready(Ok(()));
+            }
+            if this.decode_chunk()? {
+                continue;
+            }
+
+            let mut read_buf = [0u8; READ_BUF_SIZE];
+            let mut read_buf = ReadBuf::new(&mut read_buf);
+            ready!(Pin::new(&mut this.reader).poll_read(cx, &mut read_buf))?;
+            if read_buf.filled().is_empty() {
+                return Poll::Ready(Err(StreamingSignatureError::UnexpectedEof.into()));
+            }
+            this.buf.extend_from_slice(read_buf.filled());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+    use crate::s3utils::MAX_CHUNK_SIZE;
+
+    const SECRET_KEY: &str = "wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY";
+    const REGION: &str = "us-east-1";
+    const SEED_SIGNATURE: &str = "4f232c4386841ef735655705268965c44a0e4690baa4adea153f7db9fa80a0a9";
+
+    fn encode_chunk(data: &[u8], signature: &str) -> Vec<u8> {
+        let mut chunk = format!("{:x};chunk-signature={}\r\n", data.len(), signature).into_bytes();
+        chunk.extend_from_slice(data);
+        chunk.extend_from_slice(b"\r\n");
+        chunk
+    }
+
+    fn payload(first: &[u8]) -> Vec<u8> {
+        let mut payload = encode_chunk(
+            first,
+            "ad80c730a21e5b8d04586a2213dd63b9a0e99e0e2307b0ade35a65485a288648",
+        );
+        payload.extend(encode_chunk(
+            &[b'a'; 1024],
+            "0055627c9e194cb4542bae2aa5492e3c1575bbb81b612b7d234b86a503ef5497",
+        ));
+        payload.extend(encode_chunk(
+            b"",
+            "b6c6ea8a5354eaf15b3cb7646744f4275b71ea724fed81ceb9323e279d449df9",
+        ));
+        payload
+    }
+
+    fn verifier(payload: &[u8]) -> StreamingVerifier<&[u8]> {
+        let t = Utc.ymd(2013, 5, 24).and_hms(0, 0, 0);
+        StreamingVerifier::new(payload, SECRET_KEY, REGION, t, SEED_SIGNATURE)
+    }
+
+    #[tokio::test]
+    async fn test_streaming_verifier() {
+        let payload = payload(&[b'a'; 65536]);
+        let mut data = Vec::new();
+        verifier(&payload).read_to_end(&mut data).await.unwrap();
+        assert_eq!(data.len(), 65536 + 1024);
+        assert!(data.iter().all(|b| *b == b'a'));
+    }
+
+    #[tokio::test]
+    async fn test_streaming_verifier_tampered_chunk() {
+        let mut first = [b'a'; 65536];
+        first[100] = b'b';
+        let payload = payload(&first);
+        let mut data = Vec::new();
+        let err = verifier(&payload).read_to_end(&mut data).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(data.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_streaming_verifier_truncated() {
+        let payload = payload(&[b'a'; 65536]);
+        let mut data = Vec::new();
+        let err = verifier(&payload[..payload.len() - 10])
+            .read_to_end(&mut data)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn test_streaming_verifier_chunk_too_large() {
+        for size in [MAX_CHUNK_SIZE + 1, usize::MAX] {
+            let payload = format!(
+                "{:x};chunk-signature=ad80c730a21e5b8d04586a2213dd63b9a0e99e0e2307b0ade35a65485a288648\r\n",
+                size
+            );
+            let mut data = Vec::new();
+            let err = verifier(payload.as_bytes())
+                .read_to_end(&mut data)
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+            assert!(err.to_string().contains("chunk size exceeds"), "{}", err);
+        }
+    }
+}
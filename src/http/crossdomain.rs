@@ -1,6 +1,9 @@
 use actix_web::dev::AnyBody;
 use actix_web::http::StatusCode;
 use actix_web::{HttpRequest, HttpResponse};
+use lazy_static::lazy_static;
+
+use crate::config::ENV_CROSSDOMAIN_POLICY;
 
 // Standard cross domain policy information located at https://s3.amazonaws.com/crossdomain.xml
 const CROSS_DOMAIN_XML: &str = r#"<?xml version="1.0"?><!DOCTYPE cross-domain-policy SYSTEM "http://www.adobe.com/xml/dtds/cross-domain-policy.dtd"><cross-domain-policy><allow-access-from domain="*" secure="false" /></cross-domain-policy>"#;
@@ -8,17 +11,104 @@ const CROSS_DOMAIN_XML: &str = r#"<?xml version="1.0"?><!DOCTYPE cross-domain-po
 // Standard path where an app would find cross domain policy information.
 const CROSS_DOMAIN_XMLENTITY: &str = "/crossdomain.xml";
 
+lazy_static! {
+    static ref CROSS_DOMAIN_POLICY: CrossDomainPolicy = lookup_cross_domain_policy()
+        .unwrap_or_else(|err| panic!("Invalid {} env var: {}", ENV_CROSSDOMAIN_POLICY, err));
+}
+
+/// Reads the cross domain policy from the environment, an unreadable policy
+/// file is an error.
+pub fn lookup_cross_domain_policy() -> anyhow::Result<CrossDomainPolicy> {
+    CrossDomainPolicy::from_env_value(std::env::var(ENV_CROSSDOMAIN_POLICY).ok().as_deref())
+}
+
+// Cross domain policy served at /crossdomain.xml, selected by HULK_CROSSDOMAIN_POLICY:
+// `open` (default) allows all domains, `none` disables it, and any other
+// value is the path of a policy file to serve.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CrossDomainPolicy {
+    Open,
+    Disabled,
+    Custom(String),
+}
+
+impl CrossDomainPolicy {
+    pub fn from_env_value(value: Option<&str>) -> anyhow::Result<CrossDomainPolicy> {
+        match value.map(str::trim) {
+            None | Some("") | Some("open") => Ok(CrossDomainPolicy::Open),
+            Some("none") => Ok(CrossDomainPolicy::Disabled),
+            Some(path) => Ok(CrossDomainPolicy::Custom(std::fs::read_to_string(path)?)),
+        }
+    }
+
+    pub fn body(&self) -> Option<&str> {
+        match self {
+            CrossDomainPolicy::Open => Some(CROSS_DOMAIN_XML),
+            CrossDomainPolicy::Disabled => None,
+            CrossDomainPolicy::Custom(body) => Some(body),
+        }
+    }
+}
+
 // A cross-domain policy file is an XML document that grants a web client, such as Adobe Flash Player
 // or Adobe Acrobat (though not necessarily limited to these), permission to handle data across domains.
 // When clients request content hosted on a particular source domain and that content make requests
 // directed towards a domain other than its own, the remote domain needs to host a cross-domain
 // policy file that grants access to the source domain, allowing the client to continue the transaction.
 pub fn cross_domain_policy(req: &HttpRequest) -> Option<HttpResponse> {
+    serve_cross_domain_policy(req, &CROSS_DOMAIN_POLICY)
+}
+
+fn serve_cross_domain_policy(
+    req: &HttpRequest,
+    policy: &CrossDomainPolicy,
+) -> Option<HttpResponse> {
     if req.path() == CROSS_DOMAIN_XMLENTITY {
+        let body = policy.body()?;
         return Some(HttpResponse::with_body(
             StatusCode::OK,
-            AnyBody::from(CROSS_DOMAIN_XML),
+            AnyBody::from(body.to_owned()),
         ));
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    fn body_of(res: &HttpResponse) -> &[u8] {
+        match res.body() {
+            AnyBody::Bytes(bytes) => bytes,
+            _ => panic!("unexpected body"),
+        }
+    }
+
+    #[test]
+    fn test_cross_domain_policy() {
+        let req = TestRequest::with_uri(CROSS_DOMAIN_XMLENTITY).to_http_request();
+        let other = TestRequest::with_uri("/bucket/crossdomain.xml").to_http_request();
+
+        let open = CrossDomainPolicy::from_env_value(None).unwrap();
+        assert_eq!(open, CrossDomainPolicy::Open);
+        let res = serve_cross_domain_policy(&req, &open).unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(body_of(&res), CROSS_DOMAIN_XML.as_bytes());
+        assert!(serve_cross_domain_policy(&other, &open).is_none());
+
+        let disabled = CrossDomainPolicy::from_env_value(Some("none")).unwrap();
+        assert!(serve_cross_domain_policy(&req, &disabled).is_none());
+
+        let custom_xml = r#"<?xml version="1.0"?><cross-domain-policy><allow-access-from domain="*.example.com" secure="true" /></cross-domain-policy>"#;
+        let tmp_dir = tempfile::tempdir_in(".").unwrap();
+        let path = tmp_dir.path().join("crossdomain.xml");
+        std::fs::write(&path, custom_xml).unwrap();
+        let custom = CrossDomainPolicy::from_env_value(Some(path.to_str().unwrap())).unwrap();
+        let res = serve_cross_domain_policy(&req, &custom).unwrap();
+        assert_eq!(body_of(&res), custom_xml.as_bytes());
+
+        assert!(CrossDomainPolicy::from_env_value(Some("/nonexistent/crossdomain.xml")).is_err());
+    }
+}
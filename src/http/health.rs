@@ -0,0 +1,154 @@
+use std::sync::Arc;
+
+use actix_web::{web, HttpResponse};
+use async_trait::async_trait;
+use tokio::time::timeout;
+
+use crate::storage::{DiskInfo, StorageApi};
+use crate::utils;
+
+// Time a disk has to report its status to the readiness probe, so a hung
+// disk does not hang the probe.
+pub const DEFAULT_READINESS_DISK_TIMEOUT: utils::Duration = utils::Duration::from_secs(2);
+
+#[async_trait]
+pub trait HealthDisk {
+    async fn disk_info(&self) -> anyhow::Result<DiskInfo>;
+}
+
+#[async_trait]
+impl HealthDisk for StorageApi {
+    async fn disk_info(&self) -> anyhow::Result<DiskInfo> {
+        StorageApi::disk_info(self).await
+    }
+}
+
+// Readiness of the node: ready once at least `quorum` of the local disks
+// report healthy.
+pub struct ReadinessCheck {
+    disks: Vec<Arc<dyn HealthDisk + Send + Sync>>,
+    quorum: usize,
+    disk_timeout: utils::Duration,
+}
+
+impl ReadinessCheck {
+    pub fn new(disks: Vec<Arc<dyn HealthDisk + Send + Sync>>, quorum: usize) -> ReadinessCheck {
+        ReadinessCheck {
+            disks,
+            quorum,
+            disk_timeout: DEFAULT_READINESS_DISK_TIMEOUT,
+        }
+    }
+
+    pub fn set_disk_timeout(&mut self, disk_timeout: utils::Duration) {
+        self.disk_timeout = disk_timeout;
+    }
+
+    /// Returns the number of disks which reported healthy in time.
+    pub async fn healthy_disks(&self) -> usize {
+        let checks = self.disks.iter().map(|disk| async move {
+            match timeout(self.disk_timeout, disk.disk_info()).await {
+                Ok(Ok(info)) => info.error.is_none(),
+                _ => false,
+            }
+        });
+        futures_util::future::join_all(checks)
+            .await
+            .into_iter()
+            .filter(|&healthy| healthy)
+            .count()
+    }
+
+    pub async fn is_ready(&self) -> bool {
+        self.healthy_disks().await >= self.quorum
+    }
+}
+
+/// Liveness probe, the node is live as long as it serves requests.
+pub async fn liveness_handler() -> HttpResponse {
+    HttpResponse::Ok().finish()
+}
+
+/// Readiness probe, 503 until enough local disks are healthy.
+pub async fn readiness_handler(check: web::Data<ReadinessCheck>) -> HttpResponse {
+    if check.is_ready().await {
+        HttpResponse::Ok().finish()
+    } else {
+        HttpResponse::ServiceUnavailable().finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+
+    use super::*;
+
+    enum MockDisk {
+        Healthy,
+        Faulty,
+        Hung,
+    }
+
+    #[async_trait]
+    impl HealthDisk for MockDisk {
+        async fn disk_info(&self) -> anyhow::Result<DiskInfo> {
+            let mut info = DiskInfo {
+                total: 0,
+                free: 0,
+                used: 0,
+                used_inodes: 0,
+                free_inodes: 0,
+                fs_type: "".to_owned(),
+                root_disk: false,
+                healing: false,
+                endpoint: "".to_owned(),
+                mount_path: "".to_owned(),
+                id: "".to_owned(),
+                pool_index: 0,
+                metrics: None,
+                error: None,
+            };
+            match self {
+                MockDisk::Healthy => {}
+                MockDisk::Faulty => info.error = Some("faulty disk".to_owned()),
+                MockDisk::Hung => futures_util::future::pending::<()>().await,
+            }
+            Ok(info)
+        }
+    }
+
+    fn readiness_check(disks: Vec<MockDisk>, quorum: usize) -> web::Data<ReadinessCheck> {
+        let disks = disks
+            .into_iter()
+            .map(|d| Arc::new(d) as Arc<dyn HealthDisk + Send + Sync>)
+            .collect();
+        let mut check = ReadinessCheck::new(disks, quorum);
+        check.set_disk_timeout(utils::Duration::from_millis(100));
+        web::Data::new(check)
+    }
+
+    #[tokio::test]
+    async fn test_readiness_handler() {
+        let check = readiness_check(vec![MockDisk::Healthy, MockDisk::Healthy], 2);
+        assert_eq!(readiness_handler(check).await.status(), StatusCode::OK);
+
+        let check = readiness_check(vec![MockDisk::Healthy, MockDisk::Faulty], 2);
+        assert_eq!(
+            readiness_handler(check).await.status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        // A hung disk counts as unhealthy once the timeout expires.
+        let check = readiness_check(vec![MockDisk::Healthy, MockDisk::Hung], 2);
+        assert_eq!(check.healthy_disks().await, 1);
+        assert_eq!(
+            readiness_handler(check).await.status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        let check = readiness_check(vec![MockDisk::Healthy, MockDisk::Hung], 1);
+        assert_eq!(readiness_handler(check).await.status(), StatusCode::OK);
+
+        assert_eq!(liveness_handler().await.status(), StatusCode::OK);
+    }
+}
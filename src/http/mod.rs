@@ -6,6 +6,7 @@ mod crossdomain;
 mod dnscache;
 mod generic;
 mod headers;
+mod health;
 mod range;
 mod redirect;
 mod request_extensions;
@@ -20,6 +21,7 @@ pub use crossdomain::*;
 pub use dnscache::*;
 pub use generic::*;
 pub use headers::*;
+pub use health::*;
 pub use range::*;
 pub use redirect::*;
 pub use request_extensions::*;
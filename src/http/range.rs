@@ -4,6 +4,7 @@ use std::str::FromStr;
 use actix_web::http::header::{ByteRangeSpec, Range};
 
 use crate::errors::TypedError;
+use crate::object;
 
 #[derive(Default, Debug)]
 pub struct HttpRange(Option<Range>);
@@ -22,6 +23,53 @@ impl fmt::Display for HttpRange {
 }
 
 impl HttpRange {
+    /// Returns the range of `length` bytes at `offset`, `length` being
+    /// greater than zero.
+    pub fn new(offset: u64, length: u64) -> HttpRange {
+        HttpRange(Some(Range::Bytes(vec![ByteRangeSpec::FromTo(
+            offset,
+            offset + length - 1,
+        )])))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Parses a Range header, possibly holding multiple comma-separated
+    /// ranges, into the satisfiable ranges of a resource of `resource_size`
+    /// bytes. Unsatisfiable ranges are dropped; if none is satisfiable, an
+    /// `InvalidRange` error (416 Range Not Satisfiable) is returned.
+    pub fn parse(header: &str, resource_size: u64) -> anyhow::Result<Vec<HttpRange>> {
+        let specs = match Range::from_str(header).map_err(|_| TypedError::InvalidRange)? {
+            Range::Bytes(specs) if !specs.is_empty() => specs,
+            _ => return Err(TypedError::InvalidRange.into()),
+        };
+        let ranges: Vec<_> = specs
+            .iter()
+            .filter_map(|spec| spec.to_satisfiable_range(resource_size))
+            .map(|(start, end)| {
+                HttpRange(Some(Range::Bytes(vec![ByteRangeSpec::FromTo(start, end)])))
+            })
+            .collect();
+        if ranges.is_empty() {
+            let (offset_begin, offset_end) = match specs[0] {
+                ByteRangeSpec::FromTo(start, end) => (start, end),
+                ByteRangeSpec::From(start) => (start, resource_size),
+                ByteRangeSpec::Last(length) => {
+                    (resource_size.saturating_sub(length), resource_size)
+                }
+            };
+            return Err(object::ApiError::InvalidRange {
+                offset_begin: offset_begin as usize,
+                offset_end: offset_end as usize,
+                resource_size: resource_size as usize,
+            }
+            .into());
+        }
+        Ok(ranges)
+    }
+
     pub fn get_length(&self, size: u64) -> Option<u64> {
         let (_, length) = self.get_offset_length(size)?;
         Some(length)
@@ -107,10 +155,67 @@ mod tests {
             assert_matches!(HttpRange::from_str(spec), Err(_));
         }
 
+        let range = HttpRange::new(2, 4);
+        assert_eq!(range.get_offset_length(10), Some((2, 4)));
+        assert!(!range.is_empty());
+        assert!(HttpRange::default().is_empty());
+
         let cases = vec!["bytes=10-10", "bytes=10-", "bytes=100-", "bytes=-0"];
         for spec in cases {
             let range = HttpRange::from_str(spec).unwrap();
             assert_matches!(range.get_offset_length(10), None);
         }
     }
+
+    #[test]
+    fn test_http_range_parse() {
+        let offset_lengths = |spec: &str, size: u64| -> Vec<(u64, u64)> {
+            HttpRange::parse(spec, size)
+                .unwrap()
+                .iter()
+                .map(|r| r.get_offset_length(size).unwrap())
+                .collect()
+        };
+        // Bounded.
+        assert_eq!(offset_lengths("bytes=0-99", 1000), vec![(0, 100)]);
+        assert_eq!(offset_lengths("bytes=900-1999", 1000), vec![(900, 100)]);
+        // Suffix.
+        assert_eq!(offset_lengths("bytes=-500", 1000), vec![(500, 500)]);
+        assert_eq!(offset_lengths("bytes=-5000", 1000), vec![(0, 1000)]);
+        // Open-ended.
+        assert_eq!(offset_lengths("bytes=100-", 1000), vec![(100, 900)]);
+        // Multiple ranges, unsatisfiable ones are dropped.
+        assert_eq!(
+            offset_lengths("bytes=0-99, 200-, -50", 1000),
+            vec![(0, 100), (200, 800), (950, 50)]
+        );
+        assert_eq!(offset_lengths("bytes=2000-2100,0-9", 1000), vec![(0, 10)]);
+
+        // Out of bounds start.
+        let err = HttpRange::parse("bytes=1000-1099", 1000).unwrap_err();
+        assert_matches!(
+            err.downcast_ref::<object::ApiError>(),
+            Some(object::ApiError::InvalidRange {
+                offset_begin: 1000,
+                offset_end: 1099,
+                resource_size: 1000,
+            })
+        );
+        for spec in ["bytes=1000-", "bytes=-0", "bytes=1000-1099,2000-"] {
+            let err = HttpRange::parse(spec, 1000).unwrap_err();
+            assert_matches!(
+                err.downcast_ref::<object::ApiError>(),
+                Some(object::ApiError::InvalidRange { .. })
+            );
+        }
+
+        // Malformed.
+        for spec in ["bytes=", "bytes=a-b", "items=0-9", ""] {
+            let err = HttpRange::parse(spec, 1000).unwrap_err();
+            assert_matches!(
+                err.downcast_ref::<TypedError>(),
+                Some(TypedError::InvalidRange)
+            );
+        }
+    }
 }
@@ -5,6 +5,7 @@ use actix_web::http::HeaderMap;
 use actix_web::web::Query;
 use actix_web::HttpRequest;
 
+#[derive(Default)]
 pub struct RequestExtensions {
     // Handler function name.
     pub handler_fn_name: Option<&'static str>,
@@ -1 +1,4 @@
 pub mod policy;
+mod sys;
+
+pub use sys::*;
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::fmt;
+use std::net::IpAddr;
 
 use anyhow::bail;
 use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
@@ -10,6 +11,7 @@ use crate::bucket::policy as bpolicy;
 use crate::bucket::policy::condition;
 use crate::jwt::MapClaims;
 use crate::strset::StringSet;
+use crate::utils::{self, DateTimeFormatExt};
 
 // Default policy version as per AWS S3 specification.
 pub const DEFAULT_VERSION: &str = "2012-10-17";
@@ -31,6 +33,26 @@ impl<'a> Args<'a> {
     pub fn get_policies(&self, policy_claim_name: &str) -> Option<StringSet> {
         get_policies_from_claims(&self.claims, policy_claim_name)
     }
+
+    // Sets the condition values describing the request itself, i.e. the client
+    // address for `aws:SourceIp` and the request time for `aws:CurrentTime`
+    // and `aws:EpochTime`.
+    pub fn set_request_values(&mut self, source_ip: Option<IpAddr>, current_time: utils::DateTime) {
+        if let Some(source_ip) = source_ip {
+            self.condition_values.insert(
+                condition::AWS_SOURCE_IP.name().to_owned(),
+                vec![source_ip.to_string()],
+            );
+        }
+        self.condition_values.insert(
+            condition::AWS_CURRENT_TIME.name().to_owned(),
+            vec![current_time.rfc3339()],
+        );
+        self.condition_values.insert(
+            condition::AWS_EPOCH_TIME.name().to_owned(),
+            vec![current_time.timestamp().to_string()],
+        );
+    }
 }
 
 pub fn get_policies_from_claims(claims: &MapClaims, policy_claim_name: &str) -> Option<StringSet> {
@@ -1570,4 +1592,126 @@ mod tests {
 
         Ok(())
     }
+
+    fn request_args(
+        action: &'static str,
+        object_name: &str,
+        source_ip: &str,
+        current_time: &str,
+    ) -> Args<'static> {
+        let mut args = Args {
+            account_name: "testuser".to_string(),
+            groups: vec![],
+            action: Action::from(action),
+            bucket_name: "test".to_string(),
+            condition_values: HashMap::new(),
+            is_owner: false,
+            object_name: object_name.to_string(),
+            claims: MapClaims::default(),
+            deny_only: false,
+        };
+        args.set_request_values(
+            Some(source_ip.parse().unwrap()),
+            DateTime::from_rfc3339(current_time).unwrap(),
+        );
+        args
+    }
+
+    #[test]
+    fn test_policy_is_allowed_source_ip() {
+        let data = r#"{
+            "Version": "2012-10-17",
+            "Statement": [
+                {
+                    "Effect": "Allow",
+                    "Action": "s3:GetObject",
+                    "Resource": "arn:aws:s3:::test/*",
+                    "Condition": {
+                        "IpAddress": {
+                            "aws:SourceIp": "192.168.1.0/24"
+                        }
+                    }
+                },
+                {
+                    "Effect": "Allow",
+                    "Action": "s3:ListBucket",
+                    "Resource": "arn:aws:s3:::test",
+                    "Condition": {
+                        "StringEquals": {
+                            "s3:prefix": "public/"
+                        },
+                        "NotIpAddress": {
+                            "aws:SourceIp": "10.0.0.0/8"
+                        }
+                    }
+                }
+            ]
+        }"#;
+        let policy = assert_ok!(serde_json::from_str::<Policy>(data));
+        assert_ok!(policy.validate());
+
+        let now = "2021-06-15T12:00:00Z";
+        let args = request_args(GET_OBJECT_ACTION, "a.txt", "192.168.1.10", now);
+        assert!(policy.is_allowed(&args));
+        let args = request_args(GET_OBJECT_ACTION, "a.txt", "192.168.2.10", now);
+        assert!(!policy.is_allowed(&args));
+
+        let mut args = request_args(LIST_BUCKET_ACTION, "", "172.16.0.1", now);
+        args.condition_values
+            .insert("prefix".to_string(), vec!["public/".to_string()]);
+        assert!(policy.is_allowed(&args));
+        let mut args = request_args(LIST_BUCKET_ACTION, "", "10.1.2.3", now);
+        args.condition_values
+            .insert("prefix".to_string(), vec!["public/".to_string()]);
+        assert!(!policy.is_allowed(&args));
+        let mut args = request_args(LIST_BUCKET_ACTION, "", "172.16.0.1", now);
+        args.condition_values
+            .insert("prefix".to_string(), vec!["private/".to_string()]);
+        assert!(!policy.is_allowed(&args));
+
+        // Malformed addresses never match.
+        let mut args = request_args(GET_OBJECT_ACTION, "a.txt", "192.168.1.10", now);
+        args.condition_values
+            .insert("SourceIp".to_string(), vec!["not-an-ip".to_string()]);
+        assert!(!policy.is_allowed(&args));
+    }
+
+    #[test]
+    fn test_policy_is_allowed_time_window_deny() {
+        let data = r#"{
+            "Version": "2012-10-17",
+            "Statement": [
+                {
+                    "Effect": "Allow",
+                    "Action": "s3:*",
+                    "Resource": "arn:aws:s3:::test/*"
+                },
+                {
+                    "Effect": "Deny",
+                    "Action": "s3:PutObject",
+                    "Resource": "arn:aws:s3:::test/*",
+                    "Condition": {
+                        "DateGreaterThan": {
+                            "aws:CurrentTime": "2021-06-01T00:00:00Z"
+                        },
+                        "DateLessThan": {
+                            "aws:CurrentTime": "2021-07-01T00:00:00Z"
+                        }
+                    }
+                }
+            ]
+        }"#;
+        let policy = assert_ok!(serde_json::from_str::<Policy>(data));
+        assert_ok!(policy.validate());
+
+        let ip = "127.0.0.1";
+        let args = request_args(PUT_OBJECT_ACTION, "a.txt", ip, "2021-06-15T12:00:00Z");
+        assert!(!policy.is_allowed(&args));
+        let args = request_args(GET_OBJECT_ACTION, "a.txt", ip, "2021-06-15T12:00:00Z");
+        assert!(policy.is_allowed(&args));
+        let args = request_args(PUT_OBJECT_ACTION, "a.txt", ip, "2021-05-31T12:00:00Z");
+        assert!(policy.is_allowed(&args));
+        let args = request_args(PUT_OBJECT_ACTION, "a.txt", ip, "2021-07-01T12:00:00Z");
+        assert!(policy.is_allowed(&args));
+    }
 }
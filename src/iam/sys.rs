@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use anyhow::ensure;
+
+use super::policy::{Args, Policy, DEFAULT_VERSION};
+use crate::bucket::policy as bucket_policy;
+use crate::strset::StringSet;
+
+// In-memory IAM state: named policies, the policies attached to users
+// and groups, the members of each group, and the bucket policies.
+#[derive(Default)]
+pub struct Iam(RwLock<IamState>);
+
+#[derive(Default)]
+struct IamState {
+    policies: HashMap<String, Policy<'static, 'static>>,
+    user_policies: HashMap<String, StringSet>,
+    group_policies: HashMap<String, StringSet>,
+    group_members: HashMap<String, StringSet>,
+    bucket_policies: HashMap<String, bucket_policy::Policy<'static, 'static>>,
+}
+
+impl Iam {
+    pub fn set_policy(&self, name: &str, policy: Policy<'static, 'static>) {
+        self.0
+            .write()
+            .unwrap()
+            .policies
+            .insert(name.to_owned(), policy);
+    }
+
+    pub fn attach_user_policy(&self, user: &str, policy_name: &str) -> anyhow::Result<()> {
+        let mut state = self.0.write().unwrap();
+        ensure!(
+            state.policies.contains_key(policy_name),
+            "policy {} does not exist",
+            policy_name
+        );
+        state
+            .user_policies
+            .entry(user.to_owned())
+            .or_default()
+            .add(policy_name.to_owned());
+        Ok(())
+    }
+
+    pub fn attach_group_policy(&self, group: &str, policy_name: &str) -> anyhow::Result<()> {
+        let mut state = self.0.write().unwrap();
+        ensure!(
+            state.policies.contains_key(policy_name),
+            "policy {} does not exist",
+            policy_name
+        );
+        state
+            .group_policies
+            .entry(group.to_owned())
+            .or_default()
+            .add(policy_name.to_owned());
+        Ok(())
+    }
+
+    /// Adds the user to the group, creating the group if needed.
+    pub fn add_user_to_group(&self, user: &str, group: &str) {
+        self.0
+            .write()
+            .unwrap()
+            .group_members
+            .entry(group.to_owned())
+            .or_default()
+            .add(user.to_owned());
+    }
+
+    pub fn remove_user_from_group(&self, user: &str, group: &str) -> anyhow::Result<()> {
+        let mut state = self.0.write().unwrap();
+        let members = state.group_members.get_mut(group);
+        ensure!(
+            members.as_ref().map_or(false, |m| m.contains(user)),
+            "user {} is not a member of group {}",
+            user,
+            group
+        );
+        members.unwrap().remove(user);
+        Ok(())
+    }
+
+    /// Returns the groups the user is a member of.
+    pub fn user_groups(&self, user: &str) -> StringSet {
+        let state = self.0.read().unwrap();
+        state.user_groups(user)
+    }
+
+    /// Checks the request against the policies attached to the user and to
+    /// all its groups, both stored and carried by `args.groups`.
+    /// An explicit deny in any of the policies wins over any allow.
+    pub fn is_allowed(&self, args: &Args) -> bool {
+        let state = self.0.read().unwrap();
+        let mut groups = state.user_groups(&args.account_name);
+        for group in &args.groups {
+            groups.add(group.clone());
+        }
+
+        let mut policy_names = state
+            .user_policies
+            .get(&args.account_name)
+            .cloned()
+            .unwrap_or_default();
+        for group in groups.iter() {
+            if let Some(names) = state.group_policies.get(group) {
+                policy_names = policy_names.union(names);
+            }
+        }
+
+        let combined = Policy {
+            id: "".to_owned(),
+            version: DEFAULT_VERSION.to_owned(),
+            statements: policy_names
+                .iter()
+                .filter_map(|name| state.policies.get(name))
+                .flat_map(|policy| policy.statements.iter().cloned())
+                .collect(),
+        };
+        combined.is_allowed(args)
+    }
+
+    pub fn set_bucket_policy(&self, bucket: &str, policy: bucket_policy::Policy<'static, 'static>) {
+        self.0
+            .write()
+            .unwrap()
+            .bucket_policies
+            .insert(bucket.to_owned(), policy);
+    }
+
+    pub fn delete_bucket_policy(&self, bucket: &str) {
+        self.0.write().unwrap().bucket_policies.remove(bucket);
+    }
+
+    /// Checks a request of the anonymous principal, i.e. with an empty
+    /// `args.account_name`, against the policy of the bucket.
+    /// A bucket without policy allows nothing.
+    pub fn is_allowed_anonymous(&self, args: &bucket_policy::Args) -> bool {
+        self.0
+            .read()
+            .unwrap()
+            .bucket_policies
+            .get(&args.bucket_name)
+            .map_or(false, |policy| policy.is_allowed(args))
+    }
+}
+
+impl IamState {
+    fn user_groups(&self, user: &str) -> StringSet {
+        let groups: Vec<String> = self
+            .group_members
+            .iter()
+            .filter(|(_, members)| members.contains(user))
+            .map(|(group, _)| group.clone())
+            .collect();
+        StringSet::from_vec(groups)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iam::policy::{Action, GET_OBJECT_ACTION, PUT_OBJECT_ACTION};
+    use crate::jwt::MapClaims;
+
+    fn policy(effect: &str, action: &str) -> Policy<'static, 'static> {
+        let data = format!(
+            r#"{{
+                "Version": "2012-10-17",
+                "Statement": [
+                    {{
+                        "Effect": "{}",
+                        "Action": "{}",
+                        "Resource": "arn:aws:s3:::test/*"
+                    }}
+                ]
+            }}"#,
+            effect, action
+        );
+        serde_json::from_str(&data).unwrap()
+    }
+
+    fn args(user: &str, action: &'static str) -> Args<'static> {
+        Args {
+            account_name: user.to_owned(),
+            groups: vec![],
+            action: Action::from(action),
+            bucket_name: "test".to_owned(),
+            condition_values: HashMap::new(),
+            is_owner: false,
+            object_name: "a.txt".to_owned(),
+            claims: MapClaims::default(),
+            deny_only: false,
+        }
+    }
+
+    #[test]
+    fn test_iam_group_policies() {
+        let iam = Iam::default();
+        iam.set_policy("readonly", policy("Allow", GET_OBJECT_ACTION));
+        iam.set_policy("denyput", policy("Deny", PUT_OBJECT_ACTION));
+        iam.set_policy("readwrite", policy("Allow", "s3:*"));
+        iam.attach_user_policy("alice", "readonly").unwrap();
+        iam.attach_user_policy("bob", "denyput").unwrap();
+        iam.attach_group_policy("writers", "readwrite").unwrap();
+        assert!(iam.attach_group_policy("writers", "missing").is_err());
+
+        // Only implicitly denied by the user policy.
+        assert!(!iam.is_allowed(&args("alice", PUT_OBJECT_ACTION)));
+        iam.add_user_to_group("alice", "writers");
+        assert!(iam.is_allowed(&args("alice", PUT_OBJECT_ACTION)));
+        assert!(iam.user_groups("alice").contains("writers"));
+
+        // Explicitly denied by the user policy.
+        iam.add_user_to_group("bob", "writers");
+        assert!(!iam.is_allowed(&args("bob", PUT_OBJECT_ACTION)));
+        assert!(iam.is_allowed(&args("bob", GET_OBJECT_ACTION)));
+
+        iam.remove_user_from_group("alice", "writers").unwrap();
+        assert!(!iam.is_allowed(&args("alice", PUT_OBJECT_ACTION)));
+        assert!(iam.remove_user_from_group("alice", "writers").is_err());
+        assert!(iam.remove_user_from_group("alice", "missing").is_err());
+
+        // Groups carried by the request apply as well.
+        let mut args = args("carol", PUT_OBJECT_ACTION);
+        assert!(!iam.is_allowed(&args));
+        args.groups = vec!["writers".to_owned()];
+        assert!(iam.is_allowed(&args));
+    }
+}
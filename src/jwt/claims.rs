@@ -4,7 +4,7 @@ use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::utils;
+use crate::{auth, utils};
 
 #[derive(Error, Debug)]
 pub enum JwtError {
@@ -73,15 +73,22 @@ impl StandardClaims {
     }
 
     pub fn validate(&self) -> Result<(), JwtError> {
+        self.validate_with_leeway(utils::Duration::ZERO)
+    }
+
+    /// Validates the time based claims, tolerating `leeway` of clock skew
+    /// on `exp`, `iat` and `nbf`.
+    pub fn validate_with_leeway(&self, leeway: utils::Duration) -> Result<(), JwtError> {
         let mut verr = Vec::new();
         let now = utils::now().timestamp() as usize;
-        if !verify_exp(&self.expires_at, now, false) {
+        let leeway = leeway.as_secs() as usize;
+        if !verify_exp(&self.expires_at, now, leeway, false) {
             verr.push(JwtError::Expired);
         }
-        if !verify_iat_or_nbf(&self.issued_at, now, false) {
+        if !verify_iat_or_nbf(&self.issued_at, now, leeway, false) {
             verr.push(JwtError::IssuedAt);
         }
-        if !verify_iat_or_nbf(&self.not_before, now, false) {
+        if !verify_iat_or_nbf(&self.not_before, now, leeway, false) {
             verr.push(JwtError::NotValidYet);
         }
         if !verr.is_empty() {
@@ -116,21 +123,57 @@ impl MapClaims {
 
 fn verify_aud_or_iss(source: &Option<String>, target: &str, required: bool) -> bool {
     match source {
-        Some(s) => constant_time_eq::constant_time_eq(s.as_bytes(), target.as_bytes()),
+        Some(s) => auth::secure_compare(s.as_bytes(), target.as_bytes()),
         None => !required,
     }
 }
 
-fn verify_exp(source: &Option<usize>, now: usize, required: bool) -> bool {
+fn verify_exp(source: &Option<usize>, now: usize, leeway: usize, required: bool) -> bool {
     match source {
-        Some(e) => *e >= now,
+        Some(e) => e.saturating_add(leeway) >= now,
         None => !required,
     }
 }
 
-fn verify_iat_or_nbf(source: &Option<usize>, now: usize, required: bool) -> bool {
+fn verify_iat_or_nbf(source: &Option<usize>, now: usize, leeway: usize, required: bool) -> bool {
     match source {
-        Some(e) => *e <= now,
+        Some(e) => e.saturating_sub(leeway) <= now,
         None => !required,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_with_leeway() {
+        let mut claims = StandardClaims::new();
+        claims.set_access_key("access".into());
+        claims.set_expiry(utils::now() - utils::ChronoDuration::seconds(3));
+        assert!(claims.validate().is_err());
+        assert!(claims
+            .validate_with_leeway(utils::Duration::from_secs(0))
+            .is_err());
+        assert!(claims
+            .validate_with_leeway(utils::Duration::from_secs(5))
+            .is_ok());
+
+        let mut claims = StandardClaims::new();
+        claims.set_access_key("access".into());
+        claims.not_before = Some((utils::now().timestamp() + 3) as usize);
+        assert!(claims.validate().is_err());
+        assert!(claims
+            .validate_with_leeway(utils::Duration::from_secs(5))
+            .is_ok());
+
+        // Issued by a server whose clock is ahead.
+        let mut claims = StandardClaims::new();
+        claims.set_access_key("access".into());
+        claims.issued_at = Some((utils::now().timestamp() + 3) as usize);
+        assert!(claims.validate().is_err());
+        assert!(claims
+            .validate_with_leeway(utils::Duration::from_secs(5))
+            .is_ok());
+    }
+}
@@ -0,0 +1,257 @@
+use std::sync::{Mutex, RwLock};
+
+use anyhow::{anyhow, bail, ensure};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+
+use super::{MapClaims, JWT_LEEWAY};
+use crate::utils;
+
+const RSA_ALGORITHMS: &[Algorithm] = &[Algorithm::RS256, Algorithm::RS384, Algorithm::RS512];
+
+// Minimum interval between two refetches of the key set for unknown key ids,
+// tokens naming random kids must not make us flood the provider.
+const JWKS_MIN_REFETCH_INTERVAL: utils::Duration = utils::Duration::from_secs(60);
+
+// JSON web key, only RSA public keys are supported.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct Jwk {
+    pub kty: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alg: Option<String>,
+    #[serde(rename = "use", default, skip_serializing_if = "Option::is_none")]
+    pub key_use: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+}
+
+impl Jwk {
+    pub fn decoding_key(&self) -> anyhow::Result<DecodingKey<'_>> {
+        ensure!(self.kty == "RSA", "unsupported key type '{}'", self.kty);
+        let (n, e) = match (&self.n, &self.e) {
+            (Some(n), Some(e)) => (n, e),
+            _ => bail!("RSA key without modulus or exponent"),
+        };
+        // `from_rsa_components` panics on malformed components.
+        for c in [n, e] {
+            base64::decode_config(c, base64::URL_SAFE_NO_PAD)
+                .map_err(|err| anyhow!("invalid RSA key component: {}", err))?;
+        }
+        Ok(DecodingKey::from_rsa_components(n, e))
+    }
+}
+
+// JSON web key set, as published by OpenID providers at `jwks_uri`.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
+
+impl JwkSet {
+    /// Finds the key with the given id, or the only key of the set if the
+    /// token does not name one.
+    pub fn find(&self, kid: Option<&str>) -> Option<&Jwk> {
+        match kid {
+            Some(kid) => self.keys.iter().find(|k| k.kid.as_deref() == Some(kid)),
+            None if self.keys.len() == 1 => self.keys.first(),
+            None => None,
+        }
+    }
+}
+
+/// Verifies a RS256, RS384 or RS512 signed token against the key set and
+/// validates its standard claims.
+pub fn verify_rs256(token: &str, jwks: &JwkSet) -> anyhow::Result<MapClaims> {
+    let header = decode_header(token)?;
+    let jwk = jwks
+        .find(header.kid.as_deref())
+        .ok_or_else(|| anyhow!("no key found for kid {:?}", header.kid))?;
+    if let Some(alg) = &jwk.alg {
+        ensure!(
+            alg.parse::<Algorithm>().ok() == Some(header.alg),
+            "token algorithm {:?} does not match key algorithm {}",
+            header.alg,
+            alg
+        );
+    }
+    verify_rsa(token, &jwk.decoding_key()?, *JWT_LEEWAY)
+}
+
+/// Verifies a RSA signed token against a PEM encoded public key.
+pub fn verify_rsa_pem(token: &str, pem: &[u8]) -> anyhow::Result<MapClaims> {
+    verify_rsa(token, &DecodingKey::from_rsa_pem(pem)?, *JWT_LEEWAY)
+}
+
+fn verify_rsa(
+    token: &str,
+    key: &DecodingKey,
+    leeway: utils::Duration,
+) -> anyhow::Result<MapClaims> {
+    let validation = Validation {
+        algorithms: RSA_ALGORITHMS.into(),
+        leeway: leeway.as_secs(),
+        validate_exp: true,
+        validate_nbf: true,
+        ..Default::default()
+    };
+    Ok(decode::<MapClaims>(token, key, &validation)?.claims)
+}
+
+// Caches the key set fetched from an OpenID provider. The set is refetched
+// when it expires or when a token names a key not in the cached set,
+// which happens after the provider rotates its keys, at most once every
+// `JWKS_MIN_REFETCH_INTERVAL`.
+pub struct JwksCache {
+    url: String,
+    ttl: utils::Duration,
+    client: reqwest::Client,
+    cached: RwLock<Option<(JwkSet, utils::Instant)>>,
+    // Last refetch for an unknown key id, successful or not.
+    last_refetch: Mutex<Option<utils::Instant>>,
+}
+
+impl JwksCache {
+    pub fn new(url: &str, ttl: utils::Duration) -> JwksCache {
+        JwksCache {
+            url: url.to_owned(),
+            ttl,
+            client: reqwest::Client::new(),
+            cached: RwLock::new(None),
+            last_refetch: Mutex::new(None),
+        }
+    }
+
+    pub fn with_keys(jwks: JwkSet, ttl: utils::Duration) -> JwksCache {
+        let cache = JwksCache::new("", ttl);
+        *cache.cached.write().unwrap() = Some((jwks, utils::Instant::now()));
+        cache
+    }
+
+    /// Returns the key set, fetching it if the cached one is stale or
+    /// does not contain `kid`.
+    pub async fn get(&self, kid: Option<&str>) -> anyhow::Result<JwkSet> {
+        if let Some((jwks, fetched_at)) = &*self.cached.read().unwrap() {
+            if fetched_at.elapsed() < self.ttl {
+                if jwks.find(kid).is_some() {
+                    return Ok(jwks.clone());
+                }
+                let mut last_refetch = self.last_refetch.lock().unwrap();
+                if let Some(last_refetch) = *last_refetch {
+                    if last_refetch.elapsed() < JWKS_MIN_REFETCH_INTERVAL {
+                        return Ok(jwks.clone());
+                    }
+                }
+                *last_refetch = Some(utils::Instant::now());
+            }
+        }
+        self.refresh().await
+    }
+
+    pub async fn refresh(&self) -> anyhow::Result<JwkSet> {
+        ensure!(!self.url.is_empty(), "no JWKS url configured");
+        let jwks: JwkSet = self
+            .client
+            .get(&self.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        *self.cached.write().unwrap() = Some((jwks.clone(), utils::Instant::now()));
+        Ok(jwks)
+    }
+
+    pub async fn verify(&self, token: &str) -> anyhow::Result<MapClaims> {
+        let header = decode_header(token)?;
+        let jwks = self.get(header.kid.as_deref()).await?;
+        verify_rs256(token, &jwks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    use super::*;
+
+    const RSA_KEY: &[u8] = include_bytes!("testdata/rsa.key");
+    const RSA_PUB: &[u8] = include_bytes!("testdata/rsa.pub");
+    const RSA_OTHER_PUB: &[u8] = include_bytes!("testdata/rsa-other.pub");
+    const JWKS: &str = include_str!("testdata/jwks.json");
+
+    fn sign(alg: Algorithm, kid: Option<&str>) -> String {
+        let mut header = Header::new(alg);
+        header.kid = kid.map(str::to_owned);
+        let claims = serde_json::json!({
+            "sub": "alice",
+            "iss": "https://idp.example.com",
+            "exp": utils::now().timestamp() + 3600,
+        });
+        encode(
+            &header,
+            &claims,
+            &EncodingKey::from_rsa_pem(RSA_KEY).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_verify_rs256() {
+        let jwks: JwkSet = serde_json::from_str(JWKS).unwrap();
+
+        let claims = verify_rs256(&sign(Algorithm::RS256, Some("test")), &jwks).unwrap();
+        assert_eq!(claims.lookup("sub"), "alice");
+
+        // Signed with the key of another kid.
+        assert!(verify_rs256(&sign(Algorithm::RS256, Some("other")), &jwks).is_err());
+        // Unknown kid.
+        assert!(verify_rs256(&sign(Algorithm::RS256, Some("missing")), &jwks).is_err());
+        // Algorithm not matching the key.
+        assert!(verify_rs256(&sign(Algorithm::RS512, Some("test")), &jwks).is_err());
+        // HMAC tokens are rejected.
+        let hs256 = encode(
+            &Header::new(Algorithm::HS256),
+            &serde_json::json!({"sub": "alice", "exp": utils::now().timestamp() + 3600}),
+            &EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap();
+        assert!(verify_rs256(&hs256, &jwks).is_err());
+    }
+
+    #[test]
+    fn test_verify_rsa_pem() {
+        for alg in RSA_ALGORITHMS {
+            let token = sign(*alg, None);
+            let claims = verify_rsa_pem(&token, RSA_PUB).unwrap();
+            assert_eq!(claims.lookup("sub"), "alice");
+            assert!(verify_rsa_pem(&token, RSA_OTHER_PUB).is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_jwks_cache() {
+        let jwks: JwkSet = serde_json::from_str(JWKS).unwrap();
+        let cache = JwksCache::with_keys(jwks, utils::Duration::from_secs(60));
+        let claims = cache
+            .verify(&sign(Algorithm::RS256, Some("test")))
+            .await
+            .unwrap();
+        assert_eq!(claims.lookup("sub"), "alice");
+        // An unknown kid triggers a refetch, which fails without an url.
+        let err = cache
+            .verify(&sign(Algorithm::RS256, Some("missing")))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no JWKS url"), "{}", err);
+        // But not again right after.
+        let err = cache
+            .verify(&sign(Algorithm::RS256, Some("missing")))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no key found"), "{}", err);
+    }
+}
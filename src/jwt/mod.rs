@@ -1,7 +1,9 @@
 mod claims;
+mod jwks;
 mod parser;
 mod signer;
 
 pub use claims::*;
+pub use jwks::*;
 pub use parser::*;
 pub use signer::*;
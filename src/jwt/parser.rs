@@ -1,29 +1,62 @@
 use jsonwebtoken::{decode, decode_with_key_fn, Algorithm, DecodingKey, Validation};
+use lazy_static::lazy_static;
 
 use super::{JwtError, MapClaims, StandardClaims};
+use crate::config::ENV_JWT_LEEWAY;
+use crate::utils;
 
 const ALGORITHMS: &[Algorithm] = &[Algorithm::HS256, Algorithm::HS384, Algorithm::HS512];
 
-pub fn parse_with_standard_claims(token: &str, key: &[u8]) -> anyhow::Result<StandardClaims> {
+lazy_static! {
+    /// Clock skew tolerated when validating `exp` and `nbf`, defaults to zero.
+    pub static ref JWT_LEEWAY: utils::Duration = lookup_jwt_leeway()
+        .unwrap_or_else(|err| panic!("Invalid {} env var: {}", ENV_JWT_LEEWAY, err));
+}
+
+/// Reads the JWT leeway from the environment, an invalid duration is an
+/// error.
+pub fn lookup_jwt_leeway() -> anyhow::Result<utils::Duration> {
+    match std::env::var(ENV_JWT_LEEWAY) {
+        Ok(leeway) => parse_jwt_leeway(&leeway),
+        Err(_) => Ok(utils::Duration::ZERO),
+    }
+}
+
+fn parse_jwt_leeway(leeway: &str) -> anyhow::Result<utils::Duration> {
+    humantime::parse_duration(leeway)
+        .map_err(|err| anyhow::anyhow!("invalid JWT leeway '{}': {}", leeway, err))
+}
+
+pub fn parse_with_standard_claims(
+    token: &str,
+    key: &[u8],
+    leeway: utils::Duration,
+) -> anyhow::Result<StandardClaims> {
     let validation = Validation {
         algorithms: ALGORITHMS.into(),
+        leeway: leeway.as_secs(),
         ..Default::default()
     };
 
     let claims = decode::<StandardClaims>(token, &DecodingKey::from_secret(key), &validation)?;
     let claims = claims.claims;
 
-    claims.validate()?;
+    claims.validate_with_leeway(leeway)?;
 
     Ok(claims)
 }
 
-pub fn parse_with_claims<F>(token: &str, key_fn: F) -> anyhow::Result<MapClaims>
+pub fn parse_with_claims<F>(
+    token: &str,
+    key_fn: F,
+    leeway: utils::Duration,
+) -> anyhow::Result<MapClaims>
 where
     F: FnOnce(&MapClaims) -> DecodingKey,
 {
     let validation = Validation {
         algorithms: ALGORITHMS.into(),
+        leeway: leeway.as_secs(),
         validate_exp: true,
         validate_iat: true,
         validate_nbf: true,
@@ -39,3 +72,23 @@ where
 
     Ok(claims)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_jwt_leeway() {
+        assert_eq!(
+            parse_jwt_leeway("5s").unwrap(),
+            utils::Duration::from_secs(5)
+        );
+        assert_eq!(
+            parse_jwt_leeway("1m 30s").unwrap(),
+            utils::Duration::from_secs(90)
+        );
+        for leeway in ["", "5", "five seconds", "-5s"] {
+            assert!(parse_jwt_leeway(leeway).is_err(), "{}", leeway);
+        }
+    }
+}
@@ -22,13 +22,15 @@ pub trait RWLocker {
 #[derive(Default)]
 struct NamespaceLock {
     refs: i32,
-    lock: Arc<Mutex<TimedRWLock>>,
+    lock: Arc<TimedRWLock>,
 }
 
 struct NamespaceLockMap {
     // Indicates if namespace is part of a distributed setup.
     is_dist_erasure: bool,
     lock_map: Option<Mutex<HashMap<String, NamespaceLock>>>,
+    // Options of the local locks.
+    lock_opts: LockOpts,
 }
 
 impl NamespaceLockMap {
@@ -40,9 +42,14 @@ impl NamespaceLockMap {
             } else {
                 Some(Mutex::default())
             },
+            lock_opts: Default::default(),
         }
     }
 
+    pub fn set_lock_opts(&mut self, opts: LockOpts) {
+        self.lock_opts = opts;
+    }
+
     pub async fn new_namespace_lock<
         'a,
         L: NetLocker + Send + Sync + 'static,
@@ -94,9 +101,9 @@ impl NamespaceLockMap {
         }
 
         let locked = if read_lock {
-            lock.lock().await.rlock(timeout).await
+            lock.rlock(timeout, self.lock_opts).await
         } else {
-            lock.lock().await.lock(timeout).await
+            lock.lock(timeout, self.lock_opts).await
         };
 
         if !locked {
@@ -127,9 +134,9 @@ impl NamespaceLockMap {
             Some(ns_lock) => ns_lock,
         };
         if read_lock {
-            ns_lock.lock.lock().await.runlock();
+            ns_lock.lock.runlock();
         } else {
-            ns_lock.lock.lock().await.unlock();
+            ns_lock.lock.unlock();
         }
         ns_lock.refs -= 1;
         if ns_lock.refs < 0 {
@@ -165,7 +172,7 @@ impl<L: NetLocker + Send + Sync + 'static, D: Dsync<L> + Send + Sync + 'static>
         let start = Instant::now();
         if !self
             .lock
-            .try_lock(
+            .get_lock(
                 || {},
                 &self.ops_id,
                 &lock_source,
@@ -191,7 +198,7 @@ impl<L: NetLocker + Send + Sync + 'static, D: Dsync<L> + Send + Sync + 'static>
         let start = Instant::now();
         if !self
             .lock
-            .try_rlock(
+            .get_rlock(
                 || {},
                 &self.ops_id,
                 &lock_source,
@@ -1,42 +1,75 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
 use tokio::time::{timeout_at, Duration, Instant};
 
 use crate::utils::{rng_seed_now, sleep_until};
 
 const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
 
+#[derive(Clone, Copy, Default, Debug)]
+pub struct LockOpts {
+    // Grant the lock to the waiters in their arrival order.
+    pub fair: bool,
+}
+
 #[derive(Default)]
 pub struct TimedRWLock {
+    state: Mutex<LockState>,
+}
+
+#[derive(Default)]
+struct LockState {
     is_write_lock: bool,
     refs: u32,
+    // Tickets of the fair waiters, in arrival order.
+    waiters: VecDeque<u64>,
+    next_ticket: u64,
 }
 
 impl TimedRWLock {
-    pub async fn lock(&mut self, timeout: Duration) -> bool {
-        self.lock_loop(timeout, true).await
+    pub async fn lock(&self, timeout: Duration, opts: LockOpts) -> bool {
+        self.lock_loop(timeout, true, opts).await
     }
 
-    pub async fn rlock(&mut self, timeout: Duration) -> bool {
-        self.lock_loop(timeout, false).await
+    pub async fn rlock(&self, timeout: Duration, opts: LockOpts) -> bool {
+        self.lock_loop(timeout, false, opts).await
     }
 
-    pub fn unlock(&mut self) {
-        if !self.unlock_internal(true) {
+    pub fn unlock(&self) {
+        if !self.state.lock().unwrap().unlock_internal(true) {
             panic!("trying to unlock while no lock is active");
         }
     }
 
-    pub fn runlock(&mut self) {
-        if !self.unlock_internal(false) {
+    pub fn runlock(&self) {
+        if !self.state.lock().unwrap().unlock_internal(false) {
             panic!("trying to runlock while no rlock is active");
         }
     }
 
-    async fn lock_loop(&mut self, timeout: Duration, is_write_lock: bool) -> bool {
+    async fn lock_loop(&self, timeout: Duration, is_write_lock: bool, opts: LockOpts) -> bool {
+        let ticket = if opts.fair {
+            Some(self.state.lock().unwrap().enqueue())
+        } else {
+            None
+        };
+        // Gives up the place in the queue on timeout, and when the future is
+        // dropped before it got the lock.
+        let _guard = TicketGuard {
+            state: &self.state,
+            ticket,
+        };
         let rng = &mut rng_seed_now();
         let deadline = Instant::now() + timeout;
         loop {
             let r = timeout_at(deadline, async {
-                if self.lock_internal(is_write_lock) {
+                if self
+                    .state
+                    .lock()
+                    .unwrap()
+                    .lock_internal(is_write_lock, ticket)
+                {
                     return true;
                 }
                 sleep_until(deadline, LOCK_RETRY_INTERVAL, Some(rng)).await;
@@ -49,14 +82,41 @@ impl TimedRWLock {
                         return true;
                     }
                 }
-                Err(_) => {
-                    return false; // timeout
-                }
+                Err(_) => return false, // timeout
             }
         }
     }
+}
 
-    fn lock_internal(&mut self, is_write_lock: bool) -> bool {
+struct TicketGuard<'a> {
+    state: &'a Mutex<LockState>,
+    ticket: Option<u64>,
+}
+
+impl Drop for TicketGuard<'_> {
+    fn drop(&mut self) {
+        // The ticket is already dequeued if the lock was granted.
+        if let Some(ticket) = self.ticket {
+            self.state.lock().unwrap().waiters.retain(|&t| t != ticket);
+        }
+    }
+}
+
+impl LockState {
+    fn enqueue(&mut self) -> u64 {
+        let ticket = self.next_ticket;
+        self.next_ticket += 1;
+        self.waiters.push_back(ticket);
+        ticket
+    }
+
+    fn lock_internal(&mut self, is_write_lock: bool, ticket: Option<u64>) -> bool {
+        // A fair waiter has to wait for the waiters which arrived before it.
+        if let Some(ticket) = ticket {
+            if self.waiters.front() != Some(&ticket) {
+                return false;
+            }
+        }
         let mut locked = false;
         if is_write_lock {
             if self.refs == 0 && !self.is_write_lock {
@@ -70,6 +130,9 @@ impl TimedRWLock {
                 locked = true;
             }
         }
+        if locked && ticket.is_some() {
+            self.waiters.pop_front();
+        }
         locked
     }
 
@@ -82,7 +145,7 @@ impl TimedRWLock {
                 unlocked = true;
             }
         } else {
-            if !self.is_write_lock && self.refs > 1 {
+            if !self.is_write_lock && self.refs > 0 {
                 self.refs -= 1;
                 unlocked = true;
             }
@@ -90,3 +153,84 @@ impl TimedRWLock {
         unlocked
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_timed_rwlock() {
+        let lock = TimedRWLock::default();
+        let opts = LockOpts::default();
+        assert!(lock.rlock(Duration::from_secs(1), opts).await);
+        assert!(lock.rlock(Duration::from_secs(1), opts).await);
+        assert!(!lock.lock(Duration::from_millis(100), opts).await);
+        lock.runlock();
+        lock.runlock();
+        assert!(lock.lock(Duration::from_secs(1), opts).await);
+        assert!(!lock.rlock(Duration::from_millis(100), opts).await);
+        lock.unlock();
+    }
+
+    #[tokio::test]
+    async fn test_timed_rwlock_fair() {
+        let lock = Arc::new(TimedRWLock::default());
+        let opts = LockOpts { fair: true };
+        assert!(lock.lock(Duration::from_secs(1), opts).await);
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut handles = Vec::new();
+        for i in 0..3 {
+            let lock = lock.clone();
+            let order = order.clone();
+            handles.push(tokio::spawn(async move {
+                assert!(lock.lock(Duration::from_secs(10), opts).await);
+                order.lock().unwrap().push(i);
+                lock.unlock();
+            }));
+            // Let the writer queue up before the next one.
+            while lock.state.lock().unwrap().waiters.len() < i + 1 {
+                tokio::task::yield_now().await;
+            }
+        }
+
+        lock.unlock();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_timed_rwlock_fair_timeout() {
+        let lock = TimedRWLock::default();
+        let opts = LockOpts { fair: true };
+        assert!(lock.lock(Duration::from_secs(1), opts).await);
+        assert!(!lock.lock(Duration::from_millis(100), opts).await);
+        // The timed out waiter does not block the next ones.
+        assert!(lock.state.lock().unwrap().waiters.is_empty());
+        lock.unlock();
+        assert!(lock.rlock(Duration::from_secs(1), opts).await);
+        lock.runlock();
+    }
+
+    #[tokio::test]
+    async fn test_timed_rwlock_fair_cancel() {
+        let lock = TimedRWLock::default();
+        let opts = LockOpts { fair: true };
+        assert!(lock.lock(Duration::from_secs(1), opts).await);
+        // The waiter is dropped before its own timeout.
+        let r = tokio::time::timeout(
+            Duration::from_millis(100),
+            lock.lock(Duration::from_secs(10), opts),
+        )
+        .await;
+        assert!(r.is_err());
+        assert!(lock.state.lock().unwrap().waiters.is_empty());
+        lock.unlock();
+        assert!(lock.lock(Duration::from_millis(100), opts).await);
+        lock.unlock();
+    }
+}
@@ -56,11 +56,11 @@ impl<'a> slog::Serializer for ConsoleSerializer<'a> {
 
         self.decorator.start_whitespace()?;
 
-        if *super::JSON_FLAG {
+        if super::get_log_format() == super::LogFormat::Json {
             write!(
                 self.decorator,
                 "{}",
-                serde_json::to_string(entry).map_err(|e| {
+                format_json(entry).map_err(|e| {
                     std::io::Error::new(
                         std::io::ErrorKind::Other,
                         format!("serde serialization error: {}", e),
@@ -135,6 +135,44 @@ impl<'a> slog::Serializer for ConsoleSerializer<'a> {
     }
 }
 
+// Formats the entry as a single line JSON object.
+fn format_json(entry: &super::log::Entry) -> serde_json::Result<String> {
+    let mut fields = serde_json::Map::new();
+    let mut add_field = |key: &str, val: &str| {
+        if !val.is_empty() {
+            fields.insert(key.to_owned(), val.into());
+        }
+    };
+    add_field("deploymentid", &entry.deployment_id);
+    add_field("api", &entry.api.name);
+    if let Some(args) = &entry.api.args {
+        add_field("bucket", &args.bucket);
+        add_field("object", &args.object);
+    }
+    add_field("remotehost", &entry.remote_host);
+    add_field("host", &entry.host);
+    add_field("requestid", &entry.request_id);
+    add_field("useragent", &entry.user_agent);
+    for (key, val) in &entry.trace.variables {
+        add_field(key, val);
+    }
+
+    let mut line = serde_json::Map::new();
+    line.insert("level".to_owned(), entry.level.as_str().into());
+    line.insert("time".to_owned(), entry.time.as_str().into());
+    let message = if entry.trace.message.is_empty() {
+        &entry.message
+    } else {
+        &entry.trace.message
+    };
+    line.insert("message".to_owned(), message.as_str().into());
+    line.insert("fields".to_owned(), fields.into());
+    if !entry.trace.source.is_empty() {
+        line.insert("backtrace".to_owned(), entry.trace.source.clone().into());
+    }
+    serde_json::to_string(&line)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -175,4 +213,52 @@ mod tests {
         };
         slog::info!(log, ""; entry);
     }
+
+    #[test]
+    fn test_logger_format_json() {
+        use super::super::log;
+        let entry = log::Entry {
+            deployment_id: "deployment_id".to_string(),
+            level: "Error".to_string(),
+            kind: super::super::ErrKind::System,
+            time: "time".to_string(),
+            api: log::Api {
+                name: "PutObject".to_string(),
+                args: Some(log::Args {
+                    bucket: "bucket".to_string(),
+                    object: "object".to_string(),
+                    metadata: Default::default(),
+                }),
+            },
+            remote_host: "".to_string(),
+            host: "host".to_string(),
+            request_id: "request_id".to_string(),
+            user_agent: "".to_string(),
+            message: "".to_string(),
+            trace: log::Trace {
+                message: "disk not found".to_string(),
+                source: vec!["source one".to_string()],
+                variables: maplit::hashmap! {
+                    "k1".to_owned() => "v1".to_owned(),
+                },
+            },
+        };
+
+        let line = format_json(&entry).unwrap();
+        assert!(!line.contains('\n'));
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["level"], "Error");
+        assert_eq!(value["time"], "time");
+        assert_eq!(value["message"], "disk not found");
+        assert_eq!(value["fields"]["api"], "PutObject");
+        assert_eq!(value["fields"]["bucket"], "bucket");
+        assert_eq!(value["fields"]["k1"], "v1");
+        assert!(value["fields"].get("remotehost").is_none());
+        assert_eq!(value["backtrace"], serde_json::json!(["source one"]));
+
+        let mut entry = entry;
+        entry.trace.source.clear();
+        let value: serde_json::Value = serde_json::from_str(&format_json(&entry).unwrap()).unwrap();
+        assert!(value.get("backtrace").is_none());
+    }
 }
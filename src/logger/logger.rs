@@ -19,7 +19,6 @@ const MAGIC_HIGHWAY_HASH_256_KEY: [u8; 32] =
 lazy_static! {
     pub(super) static ref QUIET_FLAG: bool = GLOBALS.cli_context.guard().quiet;
     pub(super) static ref ANONYMOUS_FLAG: bool = GLOBALS.cli_context.guard().anonymous;
-    pub(super) static ref JSON_FLAG: bool = GLOBALS.cli_context.guard().json;
     static ref LOGGER_HIGHWAY_KEY: highway::Key = {
         let mut key = [0; 4];
         let mut rdr = std::io::Cursor::new(MAGIC_HIGHWAY_HASH_256_KEY);
@@ -91,17 +90,24 @@ fn log_inner<Err: std::error::Error>(ctx: Context, err: Err, err_kind: Option<Er
     };
 
     if *ANONYMOUS_FLAG {
-        let args = entry.api.args.as_mut().unwrap();
-        args.bucket = hash_string(&args.bucket);
-        args.object = hash_string(&args.object);
-        entry.remote_host = hash_string(&entry.remote_host);
-        entry.trace.message = std::any::type_name_of_val(&err).to_owned();
-        entry.trace.variables = Default::default();
+        anonymize_entry(&mut entry, std::any::type_name_of_val(&err));
     }
 
     slog::error!(super::LOG_LOGGER, ""; entry);
 }
 
+// Redacts the request details and error message of the entry, which is done
+// before the entry is formatted so that it applies to all log formats.
+fn anonymize_entry(entry: &mut Entry, err_type_name: &str) {
+    if let Some(args) = entry.api.args.as_mut() {
+        args.bucket = hash_string(&args.bucket);
+        args.object = hash_string(&args.object);
+    }
+    entry.remote_host = hash_string(&entry.remote_host);
+    entry.trace.message = err_type_name.to_owned();
+    entry.trace.variables = Default::default();
+}
+
 fn hash_string(input: &str) -> String {
     let mut hasher = highway::HighwayHasher::new(*LOGGER_HIGHWAY_KEY);
     hasher.append(input.as_bytes());
@@ -196,3 +202,47 @@ macro_rules! fatal(
         std::process::exit(1)
     };
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anonymize_entry() {
+        let mut entry = Entry {
+            deployment_id: "".to_string(),
+            level: Level::Error.to_string(),
+            kind: ErrKind::System,
+            time: "".to_string(),
+            api: Api {
+                name: "PutObject".to_string(),
+                args: Some(Args {
+                    bucket: "secret-bucket".to_string(),
+                    object: "secret-object".to_string(),
+                    metadata: Default::default(),
+                }),
+            },
+            remote_host: "10.0.0.1".to_string(),
+            host: "".to_string(),
+            request_id: "".to_string(),
+            user_agent: "".to_string(),
+            message: "".to_string(),
+            trace: Trace {
+                message: "access denied for secret-object".to_string(),
+                source: vec![],
+                variables: maplit::hashmap! {
+                    "accessKey".to_owned() => "secret".to_owned(),
+                },
+            },
+        };
+        anonymize_entry(&mut entry, "hulk::Error");
+
+        let args = entry.api.args.as_ref().unwrap();
+        assert_eq!(args.bucket, hash_string("secret-bucket"));
+        assert_eq!(args.object, hash_string("secret-object"));
+        assert_eq!(entry.remote_host, hash_string("10.0.0.1"));
+        assert_eq!(entry.trace.message, "hulk::Error");
+        assert!(entry.trace.variables.is_empty());
+        assert!(!serde_json::to_string(&entry).unwrap().contains("secret"));
+    }
+}
@@ -8,6 +8,7 @@ mod drain;
 mod entry;
 mod logger;
 mod reqinfo;
+mod throttle;
 mod webhook;
 
 pub use audit::*;
@@ -18,11 +19,32 @@ pub use entry::*;
 pub use logger::*;
 pub use reqinfo::*;
 pub use slog::Level;
+pub use throttle::*;
 pub use webhook::*;
 
 pub use self::backtrace::*;
 
 static LOG_LEVEL: AtomicUsize = AtomicUsize::new(usize::MAX);
+static LOG_FORMAT: AtomicUsize = AtomicUsize::new(LogFormat::Text as usize);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+/// Selects the output format of the server logs.
+pub fn init(format: LogFormat) {
+    LOG_FORMAT.store(format as usize, Ordering::SeqCst);
+}
+
+pub fn get_log_format() -> LogFormat {
+    if LOG_FORMAT.load(Ordering::Relaxed) == LogFormat::Json as usize {
+        LogFormat::Json
+    } else {
+        LogFormat::Text
+    }
+}
 
 pub fn get_log_level() -> Option<Level> {
     Level::from_usize(LOG_LEVEL.load(Ordering::Relaxed))
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+
+use crate::utils::{Duration, Instant};
+
+lazy_static! {
+    static ref THROTTLER: Throttler = Default::default();
+}
+
+// A log line collapsed from repeated messages within a window.
+#[derive(Debug, PartialEq)]
+pub struct ThrottledLine {
+    pub key: String,
+    pub message: String,
+    pub count: u64,
+}
+
+struct Window {
+    start: Instant,
+    length: Duration,
+    message: String,
+    count: u64,
+}
+
+// Collapses repeated messages of the same key within a window.
+#[derive(Default)]
+pub struct Throttler {
+    windows: Mutex<HashMap<String, Window>>,
+}
+
+impl Throttler {
+    /// Records one occurrence of `key`, returning whether it opened a new window
+    /// and the line of the previous window if that one has already expired.
+    pub fn record(
+        &self,
+        key: &str,
+        message: &str,
+        length: Duration,
+        now: Instant,
+    ) -> (bool, Option<ThrottledLine>) {
+        let mut windows = self.windows.lock().unwrap();
+        if let Some(window) = windows.get_mut(key) {
+            if now.saturating_duration_since(window.start) < window.length {
+                window.count += 1;
+                return (false, None);
+            }
+        }
+        let expired = windows.insert(
+            key.to_owned(),
+            Window {
+                start: now,
+                length,
+                message: message.to_owned(),
+                count: 1,
+            },
+        );
+        (
+            true,
+            expired.map(|window| ThrottledLine {
+                key: key.to_owned(),
+                message: window.message,
+                count: window.count,
+            }),
+        )
+    }
+
+    /// Removes and returns the lines of all the windows expired at `now`.
+    pub fn take_expired(&self, now: Instant) -> Vec<ThrottledLine> {
+        let mut windows = self.windows.lock().unwrap();
+        let expired: Vec<_> = windows
+            .iter()
+            .filter(|(_, window)| now.saturating_duration_since(window.start) >= window.length)
+            .map(|(key, _)| key.clone())
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|key| {
+                windows.remove(&key).map(|window| ThrottledLine {
+                    key,
+                    message: window.message,
+                    count: window.count,
+                })
+            })
+            .collect()
+    }
+}
+
+fn emit(line: ThrottledLine) {
+    crate::error!("{}", line.message; "count" => line.count, "key" => line.key);
+}
+
+/// Logs `msg` at most once per `window` for the same `key`, the emitted line
+/// carries the number of occurrences collapsed into it.
+/// The key should identify the message and its source location,
+/// e.g. `concat!(file!(), ":", line!())` plus the error kind.
+pub fn log_throttled(key: &str, msg: &str, window: Duration) {
+    let (opened, expired) = THROTTLER.record(key, msg, window, Instant::now());
+    if let Some(line) = expired {
+        emit(line);
+    }
+    if opened {
+        // Flush the window when it expires, even if no more messages arrive.
+        // Without a runtime it is flushed by the next message after it.
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            handle.spawn(async move {
+                tokio::time::sleep(window).await;
+                THROTTLER
+                    .take_expired(Instant::now())
+                    .into_iter()
+                    .for_each(emit);
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttler_collapse() {
+        let throttler = Throttler::default();
+        let window = Duration::from_secs(10);
+        let now = Instant::now();
+
+        let mut opened_count = 0;
+        for i in 0..100 {
+            let (opened, expired) = throttler.record(
+                "xl_storage:faulty",
+                "drive is faulty",
+                window,
+                now + Duration::from_millis(i),
+            );
+            if opened {
+                opened_count += 1;
+            }
+            assert!(expired.is_none());
+        }
+        assert_eq!(opened_count, 1);
+        assert!(throttler
+            .take_expired(now + Duration::from_secs(1))
+            .is_empty());
+
+        let lines = throttler.take_expired(now + window);
+        assert_eq!(
+            lines,
+            vec![ThrottledLine {
+                key: "xl_storage:faulty".to_owned(),
+                message: "drive is faulty".to_owned(),
+                count: 100,
+            }]
+        );
+        assert!(throttler.take_expired(now + window).is_empty());
+    }
+
+    #[test]
+    fn test_throttler_next_window() {
+        let throttler = Throttler::default();
+        let window = Duration::from_secs(1);
+        let now = Instant::now();
+
+        assert_eq!(throttler.record("a", "first", window, now), (true, None));
+        assert_eq!(throttler.record("b", "other", window, now), (true, None));
+        assert_eq!(throttler.record("a", "first", window, now), (false, None));
+
+        // A message after the window closes emits the previous window.
+        let (opened, expired) = throttler.record("a", "second", window, now + window);
+        assert!(opened);
+        assert_eq!(
+            expired,
+            Some(ThrottledLine {
+                key: "a".to_owned(),
+                message: "first".to_owned(),
+                count: 2,
+            })
+        );
+    }
+}
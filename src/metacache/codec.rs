@@ -0,0 +1,157 @@
+use std::sync::Arc;
+
+use anyhow::ensure;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use super::*;
+use crate::utils;
+
+// Frames are read from remote nodes, their lengths are bounded before
+// anything is allocated.
+const MAX_NAME_LEN: usize = 64 * utils::KIB;
+const MAX_METADATA_LEN: usize = 16 * utils::MIB;
+
+/// Encodes `MetaCacheEntry`s for transfer between nodes.
+///
+/// Each entry is written as a length-prefixed frame:
+/// `name length (u32 BE) | name | metadata length (u32 BE) | metadata`.
+/// Directory markers carry empty metadata.
+pub struct MetaCacheEncoder<W: AsyncWrite + Unpin> {
+    writer: W,
+}
+
+impl<W: AsyncWrite + Unpin> MetaCacheEncoder<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub async fn encode(&mut self, entry: &MetaCacheEntry) -> anyhow::Result<()> {
+        ensure!(!entry.name.is_empty(), "metacache entry without name");
+        ensure!(
+            entry.name.len() <= MAX_NAME_LEN,
+            "metacache entry name too long: {} bytes",
+            entry.name.len()
+        );
+        ensure!(
+            entry.metadata.len() <= MAX_METADATA_LEN,
+            "metacache entry '{}' metadata too large: {} bytes",
+            entry.name,
+            entry.metadata.len()
+        );
+        self.writer.write_u32(entry.name.len() as u32).await?;
+        self.writer.write_all(entry.name.as_bytes()).await?;
+        self.writer.write_u32(entry.metadata.len() as u32).await?;
+        self.writer.write_all(&entry.metadata).await?;
+        Ok(())
+    }
+
+    pub async fn finish(mut self) -> anyhow::Result<W> {
+        self.writer.flush().await?;
+        Ok(self.writer)
+    }
+}
+
+/// Decodes the stream written by `MetaCacheEncoder`.
+pub struct MetaCacheDecoder<R: AsyncRead + Unpin> {
+    reader: R,
+}
+
+impl<R: AsyncRead + Unpin> MetaCacheDecoder<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+
+    /// Returns the next entry, or `None` if the stream ended at a frame boundary.
+    pub async fn decode(&mut self) -> anyhow::Result<Option<MetaCacheEntry>> {
+        // Read the first byte separately to tell a clean end of stream
+        // from a truncated frame.
+        let mut first = [0u8; 1];
+        if self.reader.read(&mut first).await? == 0 {
+            return Ok(None);
+        }
+        let mut rest = [0u8; 3];
+        self.reader.read_exact(&mut rest).await?;
+        let name_len = u32::from_be_bytes([first[0], rest[0], rest[1], rest[2]]) as usize;
+        ensure!(
+            name_len > 0 && name_len <= MAX_NAME_LEN,
+            "invalid metacache entry name length {}",
+            name_len
+        );
+
+        let mut name = vec![0u8; name_len];
+        self.reader.read_exact(&mut name).await?;
+        let name = String::from_utf8(name)?;
+
+        let metadata_len = self.reader.read_u32().await? as usize;
+        ensure!(
+            metadata_len <= MAX_METADATA_LEN,
+            "metacache entry '{}' metadata too large: {} bytes",
+            name,
+            metadata_len
+        );
+        let mut metadata = vec![0u8; metadata_len];
+        self.reader.read_exact(&mut metadata).await?;
+
+        Ok(Some(MetaCacheEntry::new(name, Arc::new(metadata))))
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_metacache_codec_roundtrip() {
+        let entries = vec![
+            MetaCacheEntry::new("dir/".to_owned(), Arc::new(Vec::new())),
+            MetaCacheEntry::new("dir/object".to_owned(), Arc::new(vec![1, 2, 3])),
+            MetaCacheEntry::new("other/".to_owned(), Arc::new(Vec::new())),
+        ];
+
+        let mut encoder = MetaCacheEncoder::new(Vec::new());
+        for entry in &entries {
+            encoder.encode(entry).await.unwrap();
+        }
+        let buf = encoder.finish().await.unwrap();
+
+        // Feed the decoder one byte at a time to exercise partial reads.
+        let mut reader = tokio_test::io::Builder::new();
+        for b in buf.chunks(1) {
+            reader.read(b);
+        }
+        let mut decoder = MetaCacheDecoder::new(reader.build());
+        for entry in &entries {
+            let decoded = decoder.decode().await.unwrap().unwrap();
+            assert_eq!(decoded.name, entry.name);
+            assert_eq!(decoded.metadata, entry.metadata);
+        }
+        assert!(decoder.decode().await.unwrap().is_none());
+
+        let mut decoder = MetaCacheDecoder::new(&buf[..buf.len() - 1]);
+        decoder.decode().await.unwrap();
+        decoder.decode().await.unwrap();
+        assert!(decoder.decode().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_metacache_codec_limits() {
+        let mut encoder = MetaCacheEncoder::new(Vec::new());
+        let entry = MetaCacheEntry::new(String::new(), Arc::new(Vec::new()));
+        assert!(encoder.encode(&entry).await.is_err());
+        let entry = MetaCacheEntry::new("a".repeat(MAX_NAME_LEN + 1), Arc::new(Vec::new()));
+        assert!(encoder.encode(&entry).await.is_err());
+        assert!(encoder.finish().await.unwrap().is_empty());
+
+        // Oversized lengths are rejected without allocating them.
+        let buf = u32::MAX.to_be_bytes();
+        assert!(MetaCacheDecoder::new(&buf[..]).decode().await.is_err());
+        let mut buf = 1u32.to_be_bytes().to_vec();
+        buf.push(b'a');
+        buf.extend_from_slice(&u32::MAX.to_be_bytes());
+        assert!(MetaCacheDecoder::new(&buf[..]).decode().await.is_err());
+    }
+}
@@ -0,0 +1,102 @@
+use anyhow::bail;
+
+use super::MetaCacheEntry;
+use crate::errors::ApiError;
+
+/// Encodes the last returned key of a listing into an opaque continuation token.
+pub fn encode_continuation_token(key: &str) -> String {
+    base64::encode_config(key, base64::URL_SAFE_NO_PAD)
+}
+
+/// Decodes the key a listing is to be resumed past from a continuation token.
+pub fn decode_continuation_token(token: &str) -> anyhow::Result<String> {
+    let key = match base64::decode_config(token, base64::URL_SAFE_NO_PAD) {
+        Ok(key) => key,
+        Err(_) => bail!(ApiError::IncorrectContinuationToken),
+    };
+    match String::from_utf8(key) {
+        Ok(key) if !key.is_empty() => Ok(key),
+        _ => bail!(ApiError::IncorrectContinuationToken),
+    }
+}
+
+/// A page of a listing.
+pub struct MetaCachePage {
+    pub entries: Vec<MetaCacheEntry>,
+    /// Token to resume the listing with, if truncated.
+    pub next_continuation_token: Option<String>,
+}
+
+/// Collects up to `max_keys` entries of a sorted walk which come after the key
+/// `after`, i.e. the key decoded from the continuation token of the request.
+/// The walk is expected to be forwarded to `after`, which it may or may not
+/// return depending on whether the key still exists.
+pub fn paginate<I: IntoIterator<Item = MetaCacheEntry>>(
+    entries: I,
+    after: &str,
+    max_keys: usize,
+) -> MetaCachePage {
+    let mut entries = entries
+        .into_iter()
+        .filter(|entry| after.is_empty() || entry.name.as_str() > after)
+        .peekable();
+    let page: Vec<_> = entries.by_ref().take(max_keys).collect();
+    let next_continuation_token = match (page.last(), entries.peek()) {
+        (Some(last), Some(_)) => Some(encode_continuation_token(&last.name)),
+        _ => None,
+    };
+    MetaCachePage {
+        entries: page,
+        next_continuation_token,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches::assert_matches;
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn test_continuation_token() {
+        for key in ["a", "dir/object", "dir/ünïcode object+=/"] {
+            let token = encode_continuation_token(key);
+            assert_eq!(decode_continuation_token(&token).unwrap(), key);
+        }
+        for token in ["", "not base64!", "__8"] {
+            let err = decode_continuation_token(token).unwrap_err();
+            assert_matches!(
+                err.downcast_ref::<ApiError>(),
+                Some(ApiError::IncorrectContinuationToken),
+                "{}",
+                token
+            );
+        }
+    }
+
+    #[test]
+    fn test_paginate() {
+        let entries = || {
+            ["a", "b", "c", "d", "e"]
+                .iter()
+                .map(|name| MetaCacheEntry::new(name.to_string(), Arc::new(vec![0])))
+        };
+        let names = |page: &MetaCachePage| -> Vec<String> {
+            page.entries.iter().map(|e| e.name.clone()).collect()
+        };
+
+        let page = paginate(entries(), "", 2);
+        assert_eq!(names(&page), vec!["a", "b"]);
+        let after = decode_continuation_token(&page.next_continuation_token.unwrap()).unwrap();
+        assert_eq!(after, "b");
+
+        let page = paginate(entries(), &after, 3);
+        assert_eq!(names(&page), vec!["c", "d", "e"]);
+        assert!(page.next_continuation_token.is_none());
+
+        // Resumes at the next greater key if the token key no longer exists.
+        let page = paginate(entries().filter(|e| e.name != "c"), "c", 10);
+        assert_eq!(names(&page), vec!["d", "e"]);
+    }
+}
@@ -3,6 +3,7 @@ use std::sync::Arc;
 
 use crate::object::ObjectInfo;
 use crate::storage::{FileInfo, FileInfoVersions};
+use crate::utils;
 use crate::xl_storage::{VersionType, XlMetaV2};
 
 #[derive(Debug)]
@@ -91,6 +92,17 @@ impl MetaCacheEntry {
         }
     }
 
+    /// Returns the modification time of the latest version, `None` for a
+    /// directory or unreadable metadata.
+    pub fn latest_mod_time(&self) -> Option<utils::DateTime> {
+        if self.is_dir() {
+            return None;
+        }
+        crate::xl_storage::get_file_info(&self.metadata, "", &self.name, "", false)
+            .ok()
+            .map(|fi| fi.mod_time)
+    }
+
     fn file_info(&self, bucket: &str) -> anyhow::Result<Ref<FileInfo>> {
         let mut fi_ref = self.cached.borrow_mut();
         if fi_ref.is_none() {
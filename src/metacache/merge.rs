@@ -0,0 +1,149 @@
+use futures_core::Stream;
+use futures_util::StreamExt;
+
+use super::MetaCacheEntry;
+
+struct MergeHead<S> {
+    stream: S,
+    head: Option<MetaCacheEntry>,
+    exhausted: bool,
+}
+
+struct Merger<S> {
+    heads: Vec<MergeHead<S>>,
+    quorum: usize,
+}
+
+impl<S: Stream<Item = MetaCacheEntry> + Unpin> Merger<S> {
+    async fn next(&mut self) -> Option<MetaCacheEntry> {
+        loop {
+            for head in self.heads.iter_mut() {
+                if head.head.is_none() && !head.exhausted {
+                    head.head = head.stream.next().await;
+                    head.exhausted = head.head.is_none();
+                }
+            }
+            let name = self
+                .heads
+                .iter()
+                .filter_map(|head| head.head.as_ref())
+                .map(|entry| &entry.name)
+                .min()?
+                .clone();
+            let found: Vec<_> = self
+                .heads
+                .iter_mut()
+                .filter(|head| head.head.as_ref().map_or(false, |entry| entry.name == name))
+                .filter_map(|head| head.head.take())
+                .collect();
+            if found.len() >= self.quorum {
+                return pick_latest(found);
+            }
+        }
+    }
+}
+
+// Picks the entry with the latest version, the first one on ties.
+fn pick_latest(entries: Vec<MetaCacheEntry>) -> Option<MetaCacheEntry> {
+    entries
+        .into_iter()
+        .map(|entry| (entry.latest_mod_time(), entry))
+        .reduce(|latest, other| if other.0 > latest.0 { other } else { latest })
+        .map(|(_, entry)| entry)
+}
+
+/// Merges the sorted entry streams of multiple disks into a single sorted
+/// stream. An entry is only returned if it was found on at least `quorum`
+/// disks, with the metadata of the disk having the latest version.
+pub fn merge_entries<S>(streams: Vec<S>, quorum: usize) -> impl Stream<Item = MetaCacheEntry>
+where
+    S: Stream<Item = MetaCacheEntry> + Unpin,
+{
+    let merger = Merger {
+        heads: streams
+            .into_iter()
+            .map(|stream| MergeHead {
+                stream,
+                head: None,
+                exhausted: false,
+            })
+            .collect(),
+        quorum,
+    };
+    futures_util::stream::unfold(merger, |mut merger| async move {
+        let entry = merger.next().await?;
+        Some((entry, merger))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::bitrot::BitrotAlgorithm;
+    use crate::storage::FileInfo;
+    use crate::utils::{self, ChronoDuration};
+    use crate::xl_storage::{ChecksumInfo, ErasureAlgo, ErasureInfo, XlMetaV2};
+
+    fn new_entry(name: &str, size: u64, age_secs: i64) -> MetaCacheEntry {
+        let fi = FileInfo {
+            data_dir: uuid::Uuid::new_v4().to_string(),
+            mod_time: utils::now() - ChronoDuration::seconds(age_secs),
+            size,
+            erasure: Some(ErasureInfo {
+                algorithm: ErasureAlgo::ReedSolomon.to_string(),
+                data_blocks: 2,
+                parity_blocks: 1,
+                block_size: 10000,
+                index: 1,
+                distribution: vec![1, 2, 3],
+                checksums: vec![ChecksumInfo {
+                    part_number: 1,
+                    algorithm: BitrotAlgorithm::HighwayHash256,
+                    hash: Vec::new(),
+                }],
+            }),
+            ..Default::default()
+        };
+        let mut xl_meta = XlMetaV2::default();
+        xl_meta.add_version(&fi).unwrap();
+        MetaCacheEntry::new(name.to_owned(), Arc::new(xl_meta.dump().unwrap()))
+    }
+
+    #[tokio::test]
+    async fn test_merge_entries() {
+        let disks = vec![
+            vec![
+                new_entry("a", 1, 100),
+                new_entry("b", 1, 100),
+                new_entry("d", 1, 100),
+            ],
+            vec![new_entry("b", 2, 10), new_entry("c", 1, 100)],
+            vec![
+                new_entry("a", 1, 100),
+                new_entry("c", 1, 100),
+                new_entry("d", 3, 50),
+                new_entry("e", 1, 100),
+            ],
+        ];
+        let streams = disks.into_iter().map(futures_util::stream::iter).collect();
+        let merged: Vec<_> = merge_entries(streams, 2).collect().await;
+
+        let names: Vec<_> = merged.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "b", "c", "d"]);
+        // The latest version wins.
+        let size = |entry: &MetaCacheEntry| {
+            crate::xl_storage::get_file_info(&entry.metadata, "", &entry.name, "", false)
+                .unwrap()
+                .size
+        };
+        assert_eq!(size(&merged[1]), 2);
+        assert_eq!(size(&merged[3]), 3);
+
+        // Every entry is found on a single disk only.
+        let disks = vec![vec![new_entry("a", 1, 0)], vec![new_entry("b", 1, 0)]];
+        let streams = disks.into_iter().map(futures_util::stream::iter).collect();
+        assert_eq!(merge_entries(streams, 2).count().await, 0);
+    }
+}
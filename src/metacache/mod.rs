@@ -1,12 +1,18 @@
 mod bucket;
+mod codec;
+mod continuation;
 mod entry;
+mod merge;
 mod metacache;
 mod set;
 mod stream;
 mod walk;
 
 pub use bucket::*;
+pub use codec::*;
+pub use continuation::*;
 pub use entry::*;
+pub use merge::*;
 pub use metacache::*;
 pub use set::*;
 pub use stream::*;
@@ -1,3 +1,7 @@
+use crate::storage::FileInfo;
+use crate::utils;
+
+#[derive(Default)]
 pub struct WalkDirOptions {
     /// Bucket.
     pub bucket: String,
@@ -12,4 +16,38 @@ pub struct WalkDirOptions {
     pub filter_prefix: String,
     /// Forward to the given object path.
     pub forward_to: String,
+    /// Only return objects whose latest version is at least this size.
+    pub min_size: Option<u64>,
+    /// Only return objects whose latest version was modified before this time.
+    pub modified_before: Option<utils::DateTime>,
+}
+
+impl WalkDirOptions {
+    /// Forwards the walk to the key of a continuation token, returns the key
+    /// the listing is to be resumed past.
+    pub fn resume_from(&mut self, continuation_token: &str) -> anyhow::Result<String> {
+        let key = super::decode_continuation_token(continuation_token)?;
+        self.forward_to = key.clone();
+        Ok(key)
+    }
+
+    /// Reports whether entries need to be filtered by their metadata.
+    pub fn has_object_filters(&self) -> bool {
+        self.min_size.is_some() || self.modified_before.is_some()
+    }
+
+    /// Reports whether the object passes the size and modification time filters.
+    pub fn matches_object(&self, fi: &FileInfo) -> bool {
+        if let Some(min_size) = self.min_size {
+            if fi.size < min_size {
+                return false;
+            }
+        }
+        if let Some(modified_before) = self.modified_before {
+            if fi.mod_time >= modified_before {
+                return false;
+            }
+        }
+        true
+    }
 }
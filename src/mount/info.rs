@@ -1,37 +1,86 @@
+use std::collections::HashMap;
 use std::str::FromStr;
 
 use anyhow::ensure;
+use thiserror::Error;
 
 use crate::utils::{Path, PathBuf};
 
-#[cfg(target_os = "linux")]
-pub fn check_cross_device<P: AsRef<Path>>(abs_paths: &[P]) -> anyhow::Result<()> {
-    use procfs::process::Process;
+#[derive(Error, Debug)]
+#[error("cross-device mounts detected on path '{path}' at following locations {mounts:?}. Export path should not have any sub-mounts, refusing to start")]
+pub struct CrossDeviceError {
+    pub path: PathBuf,
+    pub mounts: Vec<std::path::PathBuf>,
+}
 
-    let process = Process::myself()?;
-    let mounts = process.mountinfo()?;
+// Checks export paths for sub-mounts. Mount info is read once and each path
+// is checked once, so that a single checker can be used for all the
+// endpoints of a setup. Any sub-mount is rejected, even one of the same
+// device: a bind mount still makes renames across it fail with EXDEV.
+pub struct CrossDeviceChecker {
+    mount_points: Vec<std::path::PathBuf>,
+    checked: HashMap<PathBuf, Vec<std::path::PathBuf>>,
+}
 
-    for path in abs_paths {
-        let path = path.as_ref();
-        ensure!(
-            path.is_absolute(),
-            "invalid argument, path '{}' is expected to be absolute",
-            path.to_str().unwrap()
-        );
-        let mut cross_mounts = Vec::new();
-        for mount in &mounts {
-            if mount.mount_point.starts_with(path) && mount.mount_point != path {
-                cross_mounts.push(&mount.mount_point);
+impl CrossDeviceChecker {
+    #[cfg(target_os = "linux")]
+    pub fn new() -> anyhow::Result<Self> {
+        use procfs::process::Process;
+
+        let mounts = Process::myself()?.mountinfo()?;
+        let mount_points = mounts.into_iter().map(|m| m.mount_point).collect();
+        Ok(Self::with_mount_points(mount_points))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn new() -> anyhow::Result<Self> {
+        Ok(Self::with_mount_points(Vec::new()))
+    }
+
+    pub fn with_mount_points(mount_points: Vec<std::path::PathBuf>) -> Self {
+        CrossDeviceChecker {
+            mount_points,
+            checked: HashMap::new(),
+        }
+    }
+
+    fn sub_mounts(&mut self, path: &Path) -> &[std::path::PathBuf] {
+        let mount_points = &self.mount_points;
+        self.checked.entry(path.to_owned()).or_insert_with(|| {
+            mount_points
+                .iter()
+                .filter(|mount_point| {
+                    mount_point.starts_with(path.as_std_path())
+                        && mount_point.as_path() != path.as_std_path()
+                })
+                .cloned()
+                .collect()
+        })
+    }
+
+    pub fn check<P: AsRef<Path>>(&mut self, abs_paths: &[P]) -> anyhow::Result<()> {
+        for path in abs_paths {
+            let path = path.as_ref();
+            ensure!(
+                path.is_absolute(),
+                "invalid argument, path '{}' is expected to be absolute",
+                path
+            );
+            let cross_mounts = self.sub_mounts(path);
+            if !cross_mounts.is_empty() {
+                return Err(CrossDeviceError {
+                    path: path.to_owned(),
+                    mounts: cross_mounts.to_vec(),
+                }
+                .into());
             }
         }
-        ensure!(cross_mounts.is_empty(), "cross-device mounts detected on path '{}' at following locations {:?}. Export path should not have any sub-mounts, refusing to start", path.to_str().unwrap(), cross_mounts);
+        Ok(())
     }
-    Ok(())
 }
 
-#[cfg(not(target_os = "linux"))]
 pub fn check_cross_device<P: AsRef<Path>>(abs_paths: &[P]) -> anyhow::Result<()> {
-    Ok(())
+    CrossDeviceChecker::new()?.check(abs_paths)
 }
 
 #[cfg(target_os = "linux")]
@@ -104,3 +153,37 @@ pub fn is_likely_mount_point<P: AsRef<Path>>(path: P) -> bool {
 pub fn is_likely_mount_point<P: AsRef<Path>>(path: P) -> bool {
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches::assert_matches;
+
+    use super::*;
+
+    #[test]
+    fn test_cross_device_checker() {
+        let mount_points: Vec<std::path::PathBuf> = vec![
+            "/".into(),
+            "/data".into(),
+            "/data/disk1".into(),
+            "/data/disk2/sub".into(),
+        ];
+        let mut checker = CrossDeviceChecker::with_mount_points(mount_points);
+        // No sub-mounts, a path being a mount point itself is fine.
+        checker.check(&["/data/disk1", "/data/disk3"]).unwrap();
+        checker.check(&["/data/disk1", "/data/disk3"]).unwrap();
+        // Each path is checked once.
+        assert_eq!(checker.checked.len(), 2);
+
+        // Any sub-mount is rejected, whatever its device.
+        let err = checker.check(&["/data/disk1", "/data/disk2"]).unwrap_err();
+        assert_matches!(
+            err.downcast_ref::<CrossDeviceError>(),
+            Some(CrossDeviceError { path, mounts })
+                if path == "/data/disk2" && mounts == &[std::path::PathBuf::from("/data/disk2/sub")]
+        );
+        assert!(err.to_string().contains("'/data/disk2'"));
+
+        assert!(checker.check(&["data/disk1"]).is_err());
+    }
+}
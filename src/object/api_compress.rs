@@ -0,0 +1,595 @@
+use std::collections::HashMap;
+use std::io::{BufRead, Read, Write};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use anyhow::{anyhow, bail};
+use const_format::concatcp;
+use futures_util::ready;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use crate::globals::RESERVED_METADATA_PREFIX;
+
+// Metadata of compressed objects: the compression algorithm, the size of the
+// object before compression, and its size once compressed, as stored.
+pub const META_COMPRESSION: &str = concatcp!(RESERVED_METADATA_PREFIX, "compression");
+pub const META_ACTUAL_SIZE: &str = concatcp!(RESERVED_METADATA_PREFIX, "actual-size");
+pub const META_COMPRESSED_SIZE: &str = concatcp!(RESERVED_METADATA_PREFIX, "compressed-size");
+
+pub const COMPRESSION_ALGORITHM_S2: &str = "klauspost/compress/s2";
+// Legacy snappy streams.
+pub const COMPRESSION_ALGORITHM_SNAPPY: &str = "golang/snappy/LZ77";
+pub const COMPRESSION_ALGORITHM_GZIP: &str = "gzip";
+pub const COMPRESSION_ALGORITHM_ZSTD: &str = "zstd";
+
+const DECOMPRESS_BUF_SIZE: usize = 32 * 1024;
+const COMPRESS_BUF_SIZE: usize = 32 * 1024;
+
+fn get_meta<'a>(user_defined: &'a HashMap<String, String>, key: &str) -> Option<&'a str> {
+    user_defined
+        .get(key)
+        .or_else(|| {
+            user_defined
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v)
+        })
+        .map(|v| v.as_str())
+}
+
+/// Returns the compression algorithm of the object, if compressed.
+pub fn compression_algorithm(user_defined: &HashMap<String, String>) -> Option<&str> {
+    get_meta(user_defined, META_COMPRESSION).filter(|algorithm| !algorithm.is_empty())
+}
+
+/// Returns the size of a compressed object before compression.
+pub fn actual_size(user_defined: &HashMap<String, String>) -> anyhow::Result<i64> {
+    let size = get_meta(user_defined, META_ACTUAL_SIZE)
+        .ok_or_else(|| anyhow!("actual size of compressed object is missing"))?;
+    size.parse()
+        .map_err(|_| anyhow!("invalid actual size '{}' of compressed object", size))
+}
+
+/// Wraps the reader of a stored object with a decompressor if the object is
+/// compressed, returning the size of the object before compression then.
+pub fn new_decompressed_reader(
+    reader: Box<dyn AsyncRead + Unpin>,
+    user_defined: &HashMap<String, String>,
+) -> anyhow::Result<(Box<dyn AsyncRead + Unpin>, Option<i64>)> {
+    match compression_algorithm(user_defined) {
+        Some(algorithm) => {
+            let size = actual_size(user_defined)?;
+            let reader = DecompressReader::new(reader, algorithm)?;
+            Ok((Box::new(reader), Some(size)))
+        }
+        None => Ok((reader, None)),
+    }
+}
+
+// Compressed input of the decoders, filled from the object stream as they
+// need more. Reading it fails with `WouldBlock` once drained, until more
+// input is received or the end of the stream is reached.
+#[derive(Default)]
+struct DecoderInput {
+    buf: Vec<u8>,
+    pos: usize,
+    eof: bool,
+}
+
+impl DecoderInput {
+    fn extend(&mut self, data: &[u8]) {
+        self.buf.drain(..self.pos);
+        self.pos = 0;
+        self.buf.extend_from_slice(data);
+    }
+}
+
+impl Read for DecoderInput {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.fill_buf()?.read(out)?;
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for DecoderInput {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        if self.pos < self.buf.len() || self.eof {
+            Ok(&self.buf[self.pos..])
+        } else {
+            Err(std::io::ErrorKind::WouldBlock.into())
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.buf.len());
+    }
+}
+
+// Pull based decoders, only decompressing as much as is read from them, so
+// highly compressed objects are never decompressed in memory ahead of reads.
+enum Decoder {
+    Gzip(flate2::bufread::GzDecoder<DecoderInput>),
+    Zstd(zstd::stream::read::Decoder<'static, DecoderInput>),
+    Snappy(SnappyFrameDecoder),
+}
+
+impl Decoder {
+    fn new(algorithm: &str) -> anyhow::Result<Decoder> {
+        let input = DecoderInput::default();
+        Ok(match algorithm {
+            COMPRESSION_ALGORITHM_GZIP => Decoder::Gzip(flate2::bufread::GzDecoder::new(input)),
+            COMPRESSION_ALGORITHM_ZSTD => {
+                Decoder::Zstd(zstd::stream::read::Decoder::with_buffer(input)?)
+            }
+            // Only S2 streams written in snappy compatible mode are supported.
+            COMPRESSION_ALGORITHM_S2 | COMPRESSION_ALGORITHM_SNAPPY => {
+                Decoder::Snappy(SnappyFrameDecoder::new(input))
+            }
+            _ => bail!("unsupported compression algorithm '{}'", algorithm),
+        })
+    }
+
+    fn input(&mut self) -> &mut DecoderInput {
+        match self {
+            Decoder::Gzip(d) => d.get_mut(),
+            Decoder::Zstd(d) => d.get_mut(),
+            Decoder::Snappy(d) => &mut d.input,
+        }
+    }
+}
+
+impl Read for Decoder {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Decoder::Gzip(d) => d.read(out),
+            Decoder::Zstd(d) => d.read(out),
+            Decoder::Snappy(d) => d.read(out),
+        }
+    }
+}
+
+const SNAPPY_STREAM_IDENTIFIER: &[u8] = b"sNaPpY";
+const SNAPPY_CHUNK_HEADER_LEN: usize = 4;
+const SNAPPY_CHECKSUM_LEN: usize = 4;
+
+// Decodes the snappy framing format, one chunk at a time. Chunk checksums
+// are not verified, the stored data being protected by bitrot checksums
+// already.
+struct SnappyFrameDecoder {
+    decoder: snap::raw::Decoder,
+    input: DecoderInput,
+    // Decoded data of the current chunk, read from `pos`.
+    output: Vec<u8>,
+    pos: usize,
+    identified: bool,
+}
+
+impl SnappyFrameDecoder {
+    fn new(input: DecoderInput) -> Self {
+        SnappyFrameDecoder {
+            decoder: snap::raw::Decoder::new(),
+            input,
+            output: Vec::new(),
+            pos: 0,
+            identified: false,
+        }
+    }
+
+    // Decodes the next chunk into the output, returning false at the end
+    // of the stream.
+    fn decode_chunk(&mut self) -> std::io::Result<bool> {
+        // Error for a chunk not fully received yet.
+        let need_more = if self.input.eof {
+            std::io::ErrorKind::UnexpectedEof
+        } else {
+            std::io::ErrorKind::WouldBlock
+        };
+        let input = self.input.fill_buf()?;
+        if input.is_empty() {
+            return Ok(false);
+        }
+        if input.len() < SNAPPY_CHUNK_HEADER_LEN {
+            return Err(need_more.into());
+        }
+        let chunk_type = input[0];
+        let len = u32::from_le_bytes([input[1], input[2], input[3], 0]) as usize;
+        let chunk_len = SNAPPY_CHUNK_HEADER_LEN + len;
+        if input.len() < chunk_len {
+            return Err(need_more.into());
+        }
+        let chunk = &input[SNAPPY_CHUNK_HEADER_LEN..chunk_len];
+        if !self.identified && chunk_type != 0xff {
+            return Err(invalid_data("missing snappy stream identifier"));
+        }
+        self.output.clear();
+        self.pos = 0;
+        match chunk_type {
+            0xff => {
+                if chunk != SNAPPY_STREAM_IDENTIFIER {
+                    return Err(invalid_data("invalid snappy stream identifier"));
+                }
+                self.identified = true;
+            }
+            0x00 | 0x01 => {
+                if chunk.len() < SNAPPY_CHECKSUM_LEN {
+                    return Err(invalid_data("snappy chunk too short"));
+                }
+                let data = &chunk[SNAPPY_CHECKSUM_LEN..];
+                if chunk_type == 0x00 {
+                    self.output = self
+                        .decoder
+                        .decompress_vec(data)
+                        .map_err(|err| invalid_data(err.to_string()))?;
+                } else {
+                    self.output.extend_from_slice(data);
+                }
+            }
+            // Reserved unskippable chunks.
+            0x02..=0x7f => return Err(invalid_data("unsupported snappy chunk")),
+            // Padding and skippable chunks.
+            _ => {}
+        }
+        self.input.consume(chunk_len);
+        Ok(true)
+    }
+}
+
+impl Read for SnappyFrameDecoder {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        while self.pos == self.output.len() {
+            if !self.decode_chunk()? {
+                return Ok(0);
+            }
+        }
+        let n = out.len().min(self.output.len() - self.pos);
+        out[..n].copy_from_slice(&self.output[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+fn invalid_data<E>(err: E) -> std::io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+}
+
+/// Decompresses the stream of a compressed object.
+pub struct DecompressReader<R> {
+    reader: R,
+    decoder: Decoder,
+    buf: Box<[u8]>,
+}
+
+impl<R: AsyncRead + Unpin> DecompressReader<R> {
+    pub fn new(reader: R, algorithm: &str) -> anyhow::Result<Self> {
+        Ok(DecompressReader {
+            reader,
+            decoder: Decoder::new(algorithm)?,
+            buf: vec![0; DECOMPRESS_BUF_SIZE].into_boxed_slice(),
+        })
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for DecompressReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            match this.decoder.read(buf.initialize_unfilled()) {
+                Ok(n) => {
+                    buf.advance(n);
+                    return Poll::Ready(Ok(()));
+                }
+                // The decoder needs more input.
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {}
+                Err(err) => return Poll::Ready(Err(err)),
+            }
+
+            let mut input = ReadBuf::new(&mut this.buf);
+            ready!(Pin::new(&mut this.reader).poll_read(cx, &mut input))?;
+            let decoder_input = this.decoder.input();
+            if input.filled().is_empty() {
+                decoder_input.eof = true;
+            } else {
+                decoder_input.extend(input.filled());
+            }
+        }
+    }
+}
+
+// Output of the streaming encoders, drained as the compressed stream is read.
+#[derive(Clone, Default)]
+struct EncoderOutput(Arc<Mutex<Vec<u8>>>);
+
+impl Write for EncoderOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+enum Encoder {
+    Gzip(flate2::write::GzEncoder<EncoderOutput>),
+    Zstd(zstd::stream::write::Encoder<'static, EncoderOutput>),
+    Snappy(snap::write::FrameEncoder<EncoderOutput>),
+}
+
+impl Encoder {
+    fn new(algorithm: &str, output: EncoderOutput) -> anyhow::Result<Encoder> {
+        Ok(match algorithm {
+            COMPRESSION_ALGORITHM_GZIP => Encoder::Gzip(flate2::write::GzEncoder::new(
+                output,
+                flate2::Compression::default(),
+            )),
+            COMPRESSION_ALGORITHM_ZSTD => {
+                Encoder::Zstd(zstd::stream::write::Encoder::new(output, 0)?)
+            }
+            // S2 streams are written in snappy compatible mode.
+            COMPRESSION_ALGORITHM_S2 | COMPRESSION_ALGORITHM_SNAPPY => {
+                Encoder::Snappy(snap::write::FrameEncoder::new(output))
+            }
+            _ => bail!("unsupported compression algorithm '{}'", algorithm),
+        })
+    }
+
+    fn write(&mut self, input: &[u8]) -> std::io::Result<()> {
+        match self {
+            Encoder::Gzip(e) => e.write_all(input),
+            Encoder::Zstd(e) => e.write_all(input),
+            Encoder::Snappy(e) => e.write_all(input),
+        }
+    }
+
+    fn finish(self) -> std::io::Result<()> {
+        match self {
+            Encoder::Gzip(e) => e.finish().map(|_| ()),
+            Encoder::Zstd(e) => e.finish().map(|_| ()),
+            Encoder::Snappy(mut e) => e.flush(),
+        }
+    }
+}
+
+/// Compresses the stream of an object to be stored, keeping track of its size
+/// before and after compression.
+pub struct CompressReader<R> {
+    reader: R,
+    encoder: Option<Encoder>,
+    output: EncoderOutput,
+    // Position in the encoder output of the data not read yet.
+    pos: usize,
+    buf: Box<[u8]>,
+    actual_size: i64,
+    size: i64,
+}
+
+impl<R: AsyncRead + Unpin> CompressReader<R> {
+    pub fn new(reader: R, algorithm: &str) -> anyhow::Result<Self> {
+        let output = EncoderOutput::default();
+        Ok(CompressReader {
+            reader,
+            encoder: Some(Encoder::new(algorithm, output.clone())?),
+            output,
+            pos: 0,
+            buf: vec![0; COMPRESS_BUF_SIZE].into_boxed_slice(),
+            actual_size: 0,
+            size: 0,
+        })
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns the number of bytes read so far, before compression.
+    pub fn actual_size(&self) -> i64 {
+        self.actual_size
+    }
+
+    /// Returns the number of compressed bytes read so far.
+    pub fn size(&self) -> i64 {
+        self.size
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for CompressReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            {
+                let mut output = this.output.0.lock().unwrap();
+                if this.pos < output.len() {
+                    let n = buf.remaining().min(output.len() - this.pos);
+                    buf.put_slice(&output[this.pos..this.pos + n]);
+                    this.pos += n;
+                    this.size += n as i64;
+                    if this.pos == output.len() {
+                        output.clear();
+                        this.pos = 0;
+                    }
+                    return Poll::Ready(Ok(()));
+                }
+            }
+            let encoder = match this.encoder.as_mut() {
+                Some(encoder) => encoder,
+                None => return Poll::Ready(Ok(())),
+            };
+
+            let mut input = ReadBuf::new(&mut this.buf);
+            ready!(Pin::new(&mut this.reader).poll_read(cx, &mut input))?;
+            if input.filled().is_empty() {
+                this.encoder.take().unwrap().finish()?;
+            } else {
+                this.actual_size += input.filled().len() as i64;
+                encoder.write(input.filled())?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    fn compress(algorithm: &str, data: &[u8]) -> Vec<u8> {
+        match algorithm {
+            COMPRESSION_ALGORITHM_GZIP => {
+                let mut w =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                w.write_all(data).unwrap();
+                w.finish().unwrap()
+            }
+            COMPRESSION_ALGORITHM_ZSTD => zstd::encode_all(data, 0).unwrap(),
+            _ => {
+                let mut w = snap::write::FrameEncoder::new(Vec::new());
+                w.write_all(data).unwrap();
+                w.into_inner().unwrap()
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decompressed_reader() {
+        let data: Vec<u8> = (0..200_000u32)
+            .map(|i| (i % 251) as u8 ^ (i / 1000) as u8)
+            .collect();
+        for algorithm in [
+            COMPRESSION_ALGORITHM_GZIP,
+            COMPRESSION_ALGORITHM_ZSTD,
+            COMPRESSION_ALGORITHM_S2,
+            COMPRESSION_ALGORITHM_SNAPPY,
+        ] {
+            let compressed = compress(algorithm, &data);
+            assert!(compressed.len() < data.len(), "{}", algorithm);
+            let user_defined = maplit::hashmap! {
+                META_COMPRESSION.to_owned() => algorithm.to_owned(),
+                META_ACTUAL_SIZE.to_lowercase() => data.len().to_string(),
+            };
+
+            let (mut reader, size) =
+                new_decompressed_reader(Box::new(std::io::Cursor::new(compressed)), &user_defined)
+                    .unwrap();
+            assert_eq!(size, Some(data.len() as i64), "{}", algorithm);
+            let mut buf = Vec::new();
+            reader.read_to_end(&mut buf).await.unwrap();
+            assert!(buf == data, "{}", algorithm);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decompressed_reader_errors() {
+        // Not compressed.
+        let (mut reader, size) =
+            new_decompressed_reader(Box::new(&b"hello"[..]), &HashMap::new()).unwrap();
+        assert_eq!(size, None);
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"hello");
+
+        let mut user_defined = maplit::hashmap! {
+            META_COMPRESSION.to_owned() => "lz4".to_owned(),
+            META_ACTUAL_SIZE.to_owned() => "5".to_owned(),
+        };
+        assert!(new_decompressed_reader(Box::new(&b""[..]), &user_defined).is_err());
+        user_defined.insert(
+            META_COMPRESSION.to_owned(),
+            COMPRESSION_ALGORITHM_GZIP.to_owned(),
+        );
+        user_defined.remove(META_ACTUAL_SIZE);
+        assert!(new_decompressed_reader(Box::new(&b""[..]), &user_defined).is_err());
+
+        // Truncated stream.
+        user_defined.insert(
+            META_COMPRESSION.to_owned(),
+            COMPRESSION_ALGORITHM_SNAPPY.to_owned(),
+        );
+        user_defined.insert(META_ACTUAL_SIZE.to_owned(), "5".to_owned());
+        let mut compressed = compress(COMPRESSION_ALGORITHM_SNAPPY, b"hello");
+        compressed.pop();
+        let (mut reader, _) =
+            new_decompressed_reader(Box::new(std::io::Cursor::new(compressed)), &user_defined)
+                .unwrap();
+        assert!(reader.read_to_end(&mut Vec::new()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_compress_reader() {
+        let data = vec![b'a'; 300_000];
+        for algorithm in [
+            COMPRESSION_ALGORITHM_GZIP,
+            COMPRESSION_ALGORITHM_ZSTD,
+            COMPRESSION_ALGORITHM_S2,
+        ] {
+            let mut reader = CompressReader::new(&data[..], algorithm).unwrap();
+            let mut compressed = Vec::new();
+            reader.read_to_end(&mut compressed).await.unwrap();
+            assert_eq!(reader.actual_size(), data.len() as i64, "{}", algorithm);
+            assert_eq!(reader.size(), compressed.len() as i64, "{}", algorithm);
+            assert!(compressed.len() < data.len() / 10, "{}", algorithm);
+
+            let mut reader = DecompressReader::new(&compressed[..], algorithm).unwrap();
+            let mut buf = Vec::new();
+            reader.read_to_end(&mut buf).await.unwrap();
+            assert!(buf == data, "{}", algorithm);
+        }
+        assert!(CompressReader::new(&data[..], "lz4").is_err());
+    }
+
+    // Yields its data one byte per read, so decoders run out of input often.
+    struct OneByteReader<'a>(&'a [u8]);
+
+    impl AsyncRead for OneByteReader<'_> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            if let Some((&b, rest)) = self.0.split_first() {
+                buf.put_slice(&[b]);
+                self.0 = rest;
+            }
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decompressed_reader_bounded() {
+        let data = vec![0u8; 16 << 20];
+        for algorithm in [
+            COMPRESSION_ALGORITHM_GZIP,
+            COMPRESSION_ALGORITHM_ZSTD,
+            COMPRESSION_ALGORITHM_SNAPPY,
+        ] {
+            let compressed = compress(algorithm, &data);
+            let mut reader = DecompressReader::new(OneByteReader(&compressed), algorithm).unwrap();
+            let mut buf = [0u8; 4096];
+            reader.read_exact(&mut buf).await.unwrap();
+            // Nothing is decompressed ahead of what was read, beyond the
+            // current snappy chunk.
+            if let Decoder::Snappy(d) = &reader.decoder {
+                assert!(d.output.len() <= 64 * 1024);
+            }
+
+            let mut rest = Vec::new();
+            reader.read_to_end(&mut rest).await.unwrap();
+            assert_eq!(buf.len() + rest.len(), data.len(), "{}", algorithm);
+            assert!(rest.iter().all(|&b| b == 0), "{}", algorithm);
+        }
+    }
+}
@@ -171,6 +171,10 @@ pub enum ApiError {
         part_number: usize,
         part_etag: String,
     },
+    #[error("Part number {part_number} must be between 1 and 10000")]
+    InvalidPartNumber { part_number: usize },
+    #[error("Part number {part_number} is out of order, expected part number {expected}")]
+    InvalidPartOrder { part_number: usize, expected: usize },
     #[error("Part size bigger than the allowed limit")]
     PartTooBig,
     #[error("ETag of the object has changed")]
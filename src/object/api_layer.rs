@@ -1,10 +1,12 @@
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex, MutexGuard};
+use std::str::FromStr;
+use std::sync::Arc;
 
+use anyhow::bail;
 use lazy_static::lazy_static;
 
 use super::*;
-use crate::utils;
+use crate::{errors, globals, http, utils};
 
 type CheckPreconditionFn = Box<dyn Fn(ObjectInfo) -> bool>;
 
@@ -36,6 +38,50 @@ pub struct ObjectOptions {
 
     // Use the maximum parity (N/2), used when saving server configuration files
     pub max_parity: bool,
+
+    pub metadata_directive: MetadataDirective, // only set in CopyObject operations
+}
+
+impl ObjectOptions {
+    // Version id of the object version written with these options: a new one
+    // in a versioned bucket unless given, the null version otherwise.
+    pub fn put_version_id(&self) -> String {
+        if !self.versioned {
+            return String::new();
+        }
+        if self.version_id.is_empty() || self.version_id == "null" {
+            return uuid::Uuid::new_v4().to_string();
+        }
+        self.version_id.clone()
+    }
+}
+
+// Directive of CopyObject for the destination user metadata, from the
+// x-amz-metadata-directive header.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MetadataDirective {
+    // Keep the user metadata of the source object.
+    Copy,
+    // Replace it with the user metadata of the request.
+    Replace,
+}
+
+impl Default for MetadataDirective {
+    fn default() -> Self {
+        MetadataDirective::Copy
+    }
+}
+
+impl FromStr for MetadataDirective {
+    type Err = errors::ApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "COPY" => Ok(MetadataDirective::Copy),
+            "REPLACE" => Ok(MetadataDirective::Replace),
+            _ => Err(errors::ApiError::InvalidMetadataDirective),
+        }
+    }
 }
 
 #[derive(Default)]
@@ -49,6 +95,7 @@ pub struct TransitionOptions {
 }
 
 // Represents required locking for ObjectLayer operations.
+#[derive(Clone, Copy)]
 pub enum LockType {
     None,
     Read,
@@ -143,7 +190,12 @@ impl ObjectLayer {
         data: &mut PutObjectReader,
         opts: Option<ObjectOptions>,
     ) -> anyhow::Result<ObjectInfo> {
-        todo!()
+        let mut opts = opts.unwrap_or_default();
+        crate::bucket::load_versioning(self, bucket)
+            .await?
+            .apply(&mut opts);
+        // No backend writes objects yet, fail the request rather than the server.
+        bail!(errors::ApiError::NotImplemented)
     }
 
     pub async fn copy_object(
@@ -156,7 +208,40 @@ impl ObjectLayer {
         src_opts: Option<ObjectOptions>,
         dst_opts: Option<ObjectOptions>,
     ) -> anyhow::Result<ObjectInfo> {
-        todo!()
+        let src_opts = src_opts.unwrap_or_default();
+        let dst_opts = dst_opts.unwrap_or_default();
+        check_copy_object_dest(
+            src_bucket,
+            src_object,
+            dst_bucket,
+            dst_object,
+            &src_info.storage_class,
+            &src_opts,
+            &dst_opts,
+        )?;
+        let user_defined = copy_object_metadata(&src_info.user_defined, &dst_opts);
+        let src = self
+            .get_object_and_info(
+                src_bucket,
+                src_object,
+                http::HttpRange::default(),
+                &actix_web::http::HeaderMap::new(),
+                LockType::Read,
+                Some(src_opts),
+            )
+            .await?;
+        // The ETag of the copy is computed over the copied data.
+        let mut data = PutObjectReader::new(crate::etag::Reader::new(src.reader, None));
+        self.put_object(
+            dst_bucket,
+            dst_object,
+            &mut data,
+            Some(ObjectOptions {
+                user_defined,
+                ..dst_opts
+            }),
+        )
+        .await
     }
 
     pub async fn delete_object(
@@ -192,14 +277,193 @@ impl ObjectLayer {
     // ObjectTagging operations.
 }
 
+fn is_system_metadata(key: &str) -> bool {
+    key.to_lowercase()
+        .starts_with(globals::RESERVED_METADATA_PREFIX_LOWER)
+}
+
+fn get_storage_class(user_defined: &HashMap<String, String>) -> Option<&str> {
+    user_defined
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(http::AMZ_STORAGE_CLASS))
+        .map(|(_, v)| v.as_str())
+}
+
+/// Returns the user metadata of the destination object of CopyObject, either
+/// the source object one or the request one (in `dst_opts.user_defined`)
+/// depending on the metadata directive.
+/// System metadata is never carried over, it is recomputed by the write.
+pub fn copy_object_metadata(
+    src_user_defined: &HashMap<String, String>,
+    dst_opts: &ObjectOptions,
+) -> HashMap<String, String> {
+    let mut user_defined: HashMap<String, String> = match dst_opts.metadata_directive {
+        MetadataDirective::Copy => src_user_defined
+            .iter()
+            .filter(|(k, _)| !k.eq_ignore_ascii_case(http::AMZ_STORAGE_CLASS))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect(),
+        MetadataDirective::Replace => dst_opts.user_defined.clone(),
+    };
+    user_defined.retain(|k, _| !is_system_metadata(k));
+    // The storage class may be changed by either directive.
+    if dst_opts.metadata_directive == MetadataDirective::Copy {
+        if let Some(storage_class) =
+            get_storage_class(&dst_opts.user_defined).or(get_storage_class(src_user_defined))
+        {
+            user_defined.insert(http::AMZ_STORAGE_CLASS.to_owned(), storage_class.to_owned());
+        }
+    }
+    user_defined
+}
+
+/// Rejects copying an object onto itself without changing anything,
+/// which S3 refuses as an illegal copy request.
+pub fn check_copy_object_dest(
+    src_bucket: &str,
+    src_object: &str,
+    dst_bucket: &str,
+    dst_object: &str,
+    src_storage_class: &str,
+    src_opts: &ObjectOptions,
+    dst_opts: &ObjectOptions,
+) -> anyhow::Result<()> {
+    if src_bucket != dst_bucket || src_object != dst_object {
+        return Ok(());
+    }
+    let storage_class_changed = match get_storage_class(&dst_opts.user_defined) {
+        Some(storage_class) => storage_class != src_storage_class,
+        None => false,
+    };
+    if dst_opts.metadata_directive == MetadataDirective::Copy
+        && src_opts.version_id.is_empty()
+        && dst_opts.server_side_encryption.is_none()
+        && !storage_class_changed
+    {
+        bail!(errors::ApiError::InvalidCopyDest);
+    }
+    Ok(())
+}
+
 lazy_static! {
-    static ref GLOBAL_OBJECT_API: Arc<Mutex<Option<ObjectLayer>>> = Arc::new(Mutex::new(None));
+    static ref GLOBAL_OBJECT_API: globals::Registry<ObjectLayer> = globals::Registry::default();
+}
+
+pub fn get_object_layer() -> Option<Arc<ObjectLayer>> {
+    GLOBAL_OBJECT_API.get()
 }
 
-pub fn get_object_layer() -> MutexGuard<'static, Option<ObjectLayer>> {
-    GLOBAL_OBJECT_API.lock().unwrap()
+pub fn set_object_layer(api: Arc<ObjectLayer>) {
+    GLOBAL_OBJECT_API.set(api);
 }
 
-pub fn set_object_layer(api: ObjectLayer) {
-    *GLOBAL_OBJECT_API.lock().unwrap() = Some(api);
+#[cfg(test)]
+mod tests {
+    use std::assert_matches::assert_matches;
+
+    use maplit::hashmap;
+
+    use super::*;
+
+    #[test]
+    fn test_metadata_directive() {
+        assert_eq!(
+            MetadataDirective::from_str("COPY").unwrap(),
+            MetadataDirective::Copy
+        );
+        assert_eq!(
+            MetadataDirective::from_str("REPLACE").unwrap(),
+            MetadataDirective::Replace
+        );
+        assert_matches!(
+            MetadataDirective::from_str("replace"),
+            Err(errors::ApiError::InvalidMetadataDirective)
+        );
+        assert_eq!(MetadataDirective::default(), MetadataDirective::Copy);
+    }
+
+    #[test]
+    fn test_copy_object_metadata() {
+        let src_user_defined = hashmap! {
+            "content-type".to_owned() => "text/plain".to_owned(),
+            "x-amz-meta-color".to_owned() => "red".to_owned(),
+            "X-Hulk-Internal-compression".to_owned() => "klauspost/compress/s2".to_owned(),
+        };
+        let mut dst_opts = ObjectOptions {
+            user_defined: hashmap! {
+                "x-amz-meta-shape".to_owned() => "round".to_owned(),
+                "x-hulk-internal-actual-size".to_owned() => "10".to_owned(),
+            },
+            ..Default::default()
+        };
+
+        let user_defined = copy_object_metadata(&src_user_defined, &dst_opts);
+        assert_eq!(
+            user_defined,
+            hashmap! {
+                "content-type".to_owned() => "text/plain".to_owned(),
+                "x-amz-meta-color".to_owned() => "red".to_owned(),
+            }
+        );
+
+        dst_opts.metadata_directive = MetadataDirective::Replace;
+        let user_defined = copy_object_metadata(&src_user_defined, &dst_opts);
+        assert_eq!(
+            user_defined,
+            hashmap! {
+                "x-amz-meta-shape".to_owned() => "round".to_owned(),
+            }
+        );
+
+        // The storage class may change with the COPY directive.
+        dst_opts.metadata_directive = MetadataDirective::Copy;
+        dst_opts.user_defined.insert(
+            "X-Amz-Storage-Class".to_owned(),
+            "REDUCED_REDUNDANCY".to_owned(),
+        );
+        let user_defined = copy_object_metadata(&src_user_defined, &dst_opts);
+        assert_eq!(user_defined.get("x-amz-meta-color").unwrap(), "red");
+        assert_eq!(
+            user_defined.get(http::AMZ_STORAGE_CLASS).unwrap(),
+            "REDUCED_REDUNDANCY"
+        );
+        assert!(!user_defined.contains_key("x-amz-meta-shape"));
+    }
+
+    #[test]
+    fn test_check_copy_object_dest() {
+        let check = |dst_object: &str, src_opts: &ObjectOptions, dst_opts: &ObjectOptions| {
+            check_copy_object_dest(
+                "bucket", "object", "bucket", dst_object, "STANDARD", src_opts, dst_opts,
+            )
+        };
+        let copy = ObjectOptions::default();
+        let replace = ObjectOptions {
+            metadata_directive: MetadataDirective::Replace,
+            ..Default::default()
+        };
+
+        // Copy onto itself without any change.
+        let err = check("object", &copy, &copy).unwrap_err();
+        assert_matches!(
+            err.downcast_ref::<errors::ApiError>(),
+            Some(errors::ApiError::InvalidCopyDest)
+        );
+
+        assert!(check("other", &copy, &copy).is_ok());
+        assert!(check("object", &copy, &replace).is_ok());
+        let src_version = ObjectOptions {
+            version_id: "version".to_owned(),
+            ..Default::default()
+        };
+        assert!(check("object", &src_version, &copy).is_ok());
+        let storage_class = |storage_class: &str| ObjectOptions {
+            user_defined: hashmap! {
+                http::AMZ_STORAGE_CLASS.to_owned() => storage_class.to_owned(),
+            },
+            ..Default::default()
+        };
+        assert!(check("object", &copy, &storage_class("REDUCED_REDUNDANCY")).is_ok());
+        assert!(check("object", &copy, &storage_class("STANDARD")).is_err());
+    }
 }
@@ -0,0 +1,99 @@
+use super::*;
+use crate::utils;
+use crate::xl_storage::ObjectPartInfo;
+
+// Minimum size of all the parts of a multipart upload but the last one.
+pub const MIN_PART_SIZE: i64 = 5 * utils::MIB as i64;
+// Maximum part number of a multipart upload.
+pub const MAX_PART_ID: usize = 10000;
+
+/// Validates the parts of a multipart upload being completed, in the order
+/// they are listed: part numbers must be contiguous from 1 up to at most
+/// `MAX_PART_ID`, and all parts but the last must be at least `MIN_PART_SIZE`.
+pub fn check_complete_multipart_parts(parts: &[ObjectPartInfo]) -> anyhow::Result<()> {
+    for (i, part) in parts.iter().enumerate() {
+        if part.number < 1 || part.number > MAX_PART_ID {
+            return Err(ApiError::InvalidPartNumber {
+                part_number: part.number,
+            }
+            .into());
+        }
+        if part.number != i + 1 {
+            return Err(ApiError::InvalidPartOrder {
+                part_number: part.number,
+                expected: i + 1,
+            }
+            .into());
+        }
+        let is_last = i == parts.len() - 1;
+        if !is_last && part.actual_size < MIN_PART_SIZE {
+            return Err(ApiError::PartTooSmall {
+                part_size: part.actual_size as usize,
+                part_number: part.number,
+                part_etag: part.etag.clone(),
+            }
+            .into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches::assert_matches;
+
+    use super::*;
+
+    fn parts(parts: &[(usize, i64)]) -> Vec<ObjectPartInfo> {
+        parts
+            .iter()
+            .map(|&(number, size)| ObjectPartInfo {
+                etag: format!("etag-{}", number),
+                number,
+                size: size as u64,
+                actual_size: size,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_check_complete_multipart_parts() {
+        // Valid, the last part may be small.
+        let valid = parts(&[(1, MIN_PART_SIZE), (2, 2 * MIN_PART_SIZE), (3, 1)]);
+        check_complete_multipart_parts(&valid).unwrap();
+        check_complete_multipart_parts(&parts(&[(1, 1)])).unwrap();
+
+        // Undersized middle part.
+        let err = check_complete_multipart_parts(&parts(&[
+            (1, MIN_PART_SIZE),
+            (2, MIN_PART_SIZE - 1),
+            (3, MIN_PART_SIZE),
+        ]))
+        .unwrap_err();
+        assert_matches!(
+            err.downcast_ref::<ApiError>(),
+            Some(ApiError::PartTooSmall { part_number: 2, .. })
+        );
+        assert_eq!(err.to_string(), "Part size for 2 should be at least 5MB");
+
+        // Gap in the part numbers.
+        let err = check_complete_multipart_parts(&parts(&[(1, MIN_PART_SIZE), (3, MIN_PART_SIZE)]))
+            .unwrap_err();
+        assert_matches!(
+            err.downcast_ref::<ApiError>(),
+            Some(ApiError::InvalidPartOrder {
+                part_number: 3,
+                expected: 2,
+            })
+        );
+
+        // Out of range.
+        for number in [0, MAX_PART_ID + 1] {
+            let err = check_complete_multipart_parts(&parts(&[(number, 1)])).unwrap_err();
+            assert_matches!(
+                err.downcast_ref::<ApiError>(),
+                Some(ApiError::InvalidPartNumber { .. })
+            );
+        }
+    }
+}
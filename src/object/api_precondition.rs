@@ -0,0 +1,187 @@
+use actix_web::http::HeaderMap;
+
+use super::*;
+use crate::{http, utils};
+
+// Outcome of the conditional request headers.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PreconditionResult {
+    // All the conditions hold.
+    Proceed,
+    // 304 Not Modified, for reads.
+    NotModified,
+    // 412 Precondition Failed.
+    PreconditionFailed,
+}
+
+impl PreconditionResult {
+    /// Returns the outcome for a write, which fails instead of being
+    /// reported as not modified.
+    pub fn for_write(self) -> PreconditionResult {
+        match self {
+            PreconditionResult::NotModified => PreconditionResult::PreconditionFailed,
+            res => res,
+        }
+    }
+}
+
+/// Evaluates If-Match, If-None-Match, If-Modified-Since and If-Unmodified-Since
+/// against the object, with the semantics of a read.
+/// Use `PreconditionResult::for_write` for writes.
+pub fn precondition_check(info: &ObjectInfo, headers: &HeaderMap) -> PreconditionResult {
+    check_preconditions(&info.etag, info.mod_time, headers)
+}
+
+// As in RFC 7232, If-Unmodified-Since is only evaluated without If-Match,
+// and If-Modified-Since only without If-None-Match.
+fn check_preconditions(
+    etag: &str,
+    mod_time: utils::DateTime,
+    headers: &HeaderMap,
+) -> PreconditionResult {
+    if let Some(if_match) = get_header(headers, http::IF_MATCH) {
+        if !etag_matches(etag, if_match) {
+            return PreconditionResult::PreconditionFailed;
+        }
+    } else if let Some(since) = get_header_time(headers, http::IF_UNMODIFIED_SINCE) {
+        if is_modified_since(mod_time, since) {
+            return PreconditionResult::PreconditionFailed;
+        }
+    }
+
+    if let Some(if_none_match) = get_header(headers, http::IF_NONE_MATCH) {
+        if etag_matches(etag, if_none_match) {
+            return PreconditionResult::NotModified;
+        }
+    } else if let Some(since) = get_header_time(headers, http::IF_MODIFIED_SINCE) {
+        if !is_modified_since(mod_time, since) {
+            return PreconditionResult::NotModified;
+        }
+    }
+
+    PreconditionResult::Proceed
+}
+
+fn get_header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+// Invalid dates are ignored.
+fn get_header_time(headers: &HeaderMap, name: &str) -> Option<utils::DateTime> {
+    let value = get_header(headers, name)?;
+    chrono::DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|t| t.with_timezone(&chrono::Utc))
+}
+
+// HTTP dates have a precision of seconds.
+fn is_modified_since(mod_time: utils::DateTime, since: utils::DateTime) -> bool {
+    mod_time.timestamp() > since.timestamp()
+}
+
+fn canonicalize_etag(etag: &str) -> &str {
+    let etag = etag.trim();
+    let etag = etag.strip_prefix("W/").unwrap_or(etag);
+    etag.trim_matches('"')
+}
+
+// Matches a comma-separated list of ETags, or `*`.
+fn etag_matches(etag: &str, header: &str) -> bool {
+    let etag = canonicalize_etag(etag);
+    header.split(',').any(|candidate| {
+        let candidate = candidate.trim();
+        candidate == "*" || canonicalize_etag(candidate) == etag
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::{HeaderName, HeaderValue};
+    use chrono::TimeZone;
+
+    use super::*;
+
+    const ETAG: &str = "d41d8cd98f00b204e9800998ecf8427e";
+
+    fn headers(pairs: &[(&str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                HeaderValue::from_str(value).unwrap(),
+            );
+        }
+        headers
+    }
+
+    #[test]
+    fn test_check_preconditions() {
+        use PreconditionResult::*;
+
+        // Wed, 21 Oct 2015 07:28:00 GMT
+        let mod_time = chrono::Utc.ymd(2015, 10, 21).and_hms_milli(7, 28, 0, 500);
+        let before = "Tue, 20 Oct 2015 07:28:00 GMT";
+        let at = "Wed, 21 Oct 2015 07:28:00 GMT";
+        let after = "Thu, 22 Oct 2015 07:28:00 GMT";
+        let quoted_etag = format!("\"{}\"", ETAG);
+        let quoted_etag = quoted_etag.as_str();
+
+        let cases: Vec<(Vec<(&str, &str)>, PreconditionResult)> = vec![
+            (vec![], Proceed),
+            // If-Match.
+            (vec![(http::IF_MATCH, quoted_etag)], Proceed),
+            (vec![(http::IF_MATCH, ETAG)], Proceed),
+            (vec![(http::IF_MATCH, "\"other\", *")], Proceed),
+            (vec![(http::IF_MATCH, "\"other\"")], PreconditionFailed),
+            // If-None-Match.
+            (vec![(http::IF_NONE_MATCH, quoted_etag)], NotModified),
+            (vec![(http::IF_NONE_MATCH, "*")], NotModified),
+            (vec![(http::IF_NONE_MATCH, "\"other\"")], Proceed),
+            // If-Modified-Since.
+            (vec![(http::IF_MODIFIED_SINCE, before)], Proceed),
+            (vec![(http::IF_MODIFIED_SINCE, at)], NotModified),
+            (vec![(http::IF_MODIFIED_SINCE, after)], NotModified),
+            (vec![(http::IF_MODIFIED_SINCE, "invalid")], Proceed),
+            // If-Unmodified-Since.
+            (
+                vec![(http::IF_UNMODIFIED_SINCE, before)],
+                PreconditionFailed,
+            ),
+            (vec![(http::IF_UNMODIFIED_SINCE, at)], Proceed),
+            (vec![(http::IF_UNMODIFIED_SINCE, after)], Proceed),
+            // If-Match takes precedence over If-Unmodified-Since.
+            (
+                vec![
+                    (http::IF_MATCH, quoted_etag),
+                    (http::IF_UNMODIFIED_SINCE, before),
+                ],
+                Proceed,
+            ),
+            // If-None-Match takes precedence over If-Modified-Since.
+            (
+                vec![
+                    (http::IF_NONE_MATCH, "\"other\""),
+                    (http::IF_MODIFIED_SINCE, after),
+                ],
+                Proceed,
+            ),
+            (
+                vec![(http::IF_MATCH, "\"other\""), (http::IF_NONE_MATCH, "*")],
+                PreconditionFailed,
+            ),
+        ];
+        for (i, (pairs, expected)) in cases.into_iter().enumerate() {
+            let got = check_preconditions(ETAG, mod_time, &headers(&pairs));
+            assert_eq!(got, expected, "case {}: {:?}", i, pairs);
+        }
+    }
+
+    #[test]
+    fn test_precondition_result_for_write() {
+        use PreconditionResult::*;
+
+        assert_eq!(NotModified.for_write(), PreconditionFailed);
+        assert_eq!(PreconditionFailed.for_write(), PreconditionFailed);
+        assert_eq!(Proceed.for_write(), Proceed);
+    }
+}
@@ -1,8 +1,10 @@
 use const_format::concatcp;
+use futures_util::ready;
 use relative_path::{RelativePath, RelativePathBuf};
-use tokio::io::AsyncRead;
+use tokio::io::{AsyncRead, ReadBuf};
 
 use super::*;
+use crate::etag::{ETag, MaybeTagger};
 use crate::globals;
 use crate::prelude::*;
 use crate::utils::Path;
@@ -83,8 +85,13 @@ pub fn encode_dir_object(object: &str) -> Cow<str> {
     }
 }
 
+/// Inverse of `encode_dir_object`, also accepts the encoded name with a trailing slash,
+/// as returned when listing directories.
 pub fn decode_dir_object(object: &str) -> Cow<str> {
-    if let Some(object) = object.strip_suffix(globals::GLOBAL_DIR_SUFFIX) {
+    if let Some(object) = object
+        .strip_suffix(globals::GLOBAL_DIR_SUFFIX_WITH_SLASH)
+        .or_else(|| object.strip_suffix(globals::GLOBAL_DIR_SUFFIX))
+    {
         Cow::Owned(object.to_owned() + SLASH_SEPARATOR)
     } else {
         Cow::Borrowed(object)
@@ -98,7 +105,137 @@ pub struct GetObjectReader {
     opts: ObjectOptions,
 }
 
-pub struct PutObjectReader {}
+impl GetObjectReader {
+    /// Wraps the reader of the stored object, transparently decompressing
+    /// compressed objects, whose `obj_info.size` is then their size before
+    /// compression.
+    pub fn new(
+        reader: Box<dyn AsyncRead + Unpin>,
+        mut obj_info: ObjectInfo,
+        opts: ObjectOptions,
+    ) -> anyhow::Result<GetObjectReader> {
+        let (reader, actual_size) = new_decompressed_reader(reader, &obj_info.user_defined)?;
+        if let Some(size) = actual_size {
+            obj_info.size = size;
+        }
+        Ok(GetObjectReader {
+            reader,
+            obj_info,
+            cleanup_fns: vec![],
+            opts,
+        })
+    }
+
+    /// Wraps a reader of the object data as returned to clients, which is
+    /// already decompressed.
+    pub fn from_decompressed(
+        reader: Box<dyn AsyncRead + Unpin>,
+        obj_info: ObjectInfo,
+        opts: ObjectOptions,
+    ) -> GetObjectReader {
+        GetObjectReader {
+            reader,
+            obj_info,
+            cleanup_fns: vec![],
+            opts,
+        }
+    }
+}
+
+// Source of a `PutObjectReader`, usually the `hash::Reader` of the request body.
+trait PutObjectSource: AsyncRead + MaybeTagger + Unpin {}
+
+impl<R: AsyncRead + MaybeTagger + Unpin> PutObjectSource for R {}
+
+enum PutObjectReaderInner<'a> {
+    Plain(Box<dyn PutObjectSource + 'a>),
+    Compressed(CompressReader<Box<dyn PutObjectSource + 'a>>),
+}
+
+/// Reader of the data of an object to be stored, compressed or not.
+///
+/// The ETag is always computed over the data as sent by the client, before
+/// compression, as S3 clients expect it to be the MD5 of the content.
+pub struct PutObjectReader<'a> {
+    inner: PutObjectReaderInner<'a>,
+    algorithm: Option<String>,
+    size: i64,
+}
+
+impl<'a> PutObjectReader<'a> {
+    pub fn new<R: AsyncRead + MaybeTagger + Unpin + 'a>(reader: R) -> PutObjectReader<'a> {
+        PutObjectReader {
+            inner: PutObjectReaderInner::Plain(Box::new(reader)),
+            algorithm: None,
+            size: 0,
+        }
+    }
+
+    /// Returns a reader compressing the data with the given algorithm.
+    pub fn with_compression<R: AsyncRead + MaybeTagger + Unpin + 'a>(
+        reader: R,
+        algorithm: &str,
+    ) -> anyhow::Result<PutObjectReader<'a>> {
+        let reader: Box<dyn PutObjectSource + 'a> = Box::new(reader);
+        Ok(PutObjectReader {
+            inner: PutObjectReaderInner::Compressed(CompressReader::new(reader, algorithm)?),
+            algorithm: Some(algorithm.to_owned()),
+            size: 0,
+        })
+    }
+
+    fn source(&self) -> &dyn PutObjectSource {
+        match &self.inner {
+            PutObjectReaderInner::Plain(r) => r.as_ref(),
+            PutObjectReaderInner::Compressed(r) => r.get_ref().as_ref(),
+        }
+    }
+
+    /// Returns the ETag of the data read so far, before compression.
+    pub fn etag(&self) -> Option<ETag> {
+        self.source().as_tagger().and_then(|t| t.etag())
+    }
+
+    /// Returns the number of bytes read so far, as stored.
+    pub fn size(&self) -> i64 {
+        self.size
+    }
+
+    /// Returns the number of bytes read so far, before compression.
+    pub fn actual_size(&self) -> i64 {
+        match &self.inner {
+            PutObjectReaderInner::Plain(_) => self.size,
+            PutObjectReaderInner::Compressed(r) => r.actual_size(),
+        }
+    }
+
+    /// Records the compression algorithm and both sizes of a compressed object
+    /// in its metadata, once fully read.
+    pub fn set_compression_metadata(&self, user_defined: &mut HashMap<String, String>) {
+        if let Some(algorithm) = &self.algorithm {
+            user_defined.insert(META_COMPRESSION.to_owned(), algorithm.clone());
+            user_defined.insert(META_ACTUAL_SIZE.to_owned(), self.actual_size().to_string());
+            user_defined.insert(META_COMPRESSED_SIZE.to_owned(), self.size.to_string());
+        }
+    }
+}
+
+impl AsyncRead for PutObjectReader<'_> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let len_prev = buf.filled().len();
+        ready!(match &mut this.inner {
+            PutObjectReaderInner::Plain(r) => Pin::new(r).poll_read(cx, buf),
+            PutObjectReaderInner::Compressed(r) => Pin::new(r).poll_read(cx, buf),
+        })?;
+        this.size += (buf.filled().len() - len_prev) as i64;
+        Poll::Ready(Ok(()))
+    }
+}
 
 pub fn compress_self_test() {}
 
@@ -126,4 +263,89 @@ mod tests {
             assert_eq!(path_join(&elements), path);
         }
     }
+
+    #[test]
+    fn test_encode_decode_dir_object() {
+        let names = vec![
+            "",
+            "a",
+            "a/b",
+            "dir/obj.txt",
+            "/",
+            "a//",
+            "日本語",
+            "a.b-c_d",
+        ];
+        for name in names {
+            // Plain objects are left untouched.
+            if !name.ends_with(SLASH_SEPARATOR) {
+                assert_eq!(encode_dir_object(name), name);
+                assert_eq!(decode_dir_object(name), name);
+            }
+
+            let dir = name.to_owned() + SLASH_SEPARATOR;
+            let encoded = encode_dir_object(&dir);
+            assert!(encoded.ends_with(globals::GLOBAL_DIR_SUFFIX));
+            assert!(!encoded.ends_with(SLASH_SEPARATOR));
+            assert_eq!(decode_dir_object(&encoded), dir);
+
+            // Listed directory entries carry a trailing slash.
+            let listed = encoded.into_owned() + SLASH_SEPARATOR;
+            assert_eq!(decode_dir_object(&listed), dir);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_put_object_reader_compressed() {
+        use tokio::io::AsyncReadExt;
+
+        let data = vec![b'x'; 1 << 20];
+        let md5 = crate::hash::md5_hex(&data);
+        let hash_reader =
+            crate::hash::Reader::new(&data[..], data.len() as isize, "", "", data.len()).unwrap();
+        let mut reader =
+            PutObjectReader::with_compression(hash_reader, COMPRESSION_ALGORITHM_S2).unwrap();
+        let mut compressed = Vec::new();
+        reader.read_to_end(&mut compressed).await.unwrap();
+
+        assert_eq!(reader.etag().unwrap().to_string(), md5);
+        assert_eq!(reader.actual_size(), data.len() as i64);
+        assert_eq!(reader.size(), compressed.len() as i64);
+        assert!(compressed.len() < data.len() / 10);
+
+        let mut user_defined = HashMap::new();
+        reader.set_compression_metadata(&mut user_defined);
+        assert_eq!(
+            user_defined[META_COMPRESSED_SIZE],
+            compressed.len().to_string()
+        );
+        let (mut reader, size) =
+            new_decompressed_reader(Box::new(std::io::Cursor::new(compressed)), &user_defined)
+                .unwrap();
+        assert_eq!(size, Some(data.len() as i64));
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).await.unwrap();
+        assert!(buf == data);
+    }
+
+    #[tokio::test]
+    async fn test_put_object_reader() {
+        use tokio::io::AsyncReadExt;
+
+        let data = b"hello world";
+        let md5 = crate::hash::md5_hex(data);
+        let hash_reader =
+            crate::hash::Reader::new(&data[..], data.len() as isize, &md5, "", data.len()).unwrap();
+        let mut reader = PutObjectReader::new(hash_reader);
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).await.unwrap();
+
+        assert_eq!(buf, data);
+        assert_eq!(reader.etag().unwrap().to_string(), md5);
+        assert_eq!(reader.size(), data.len() as i64);
+        assert_eq!(reader.actual_size(), data.len() as i64);
+        let mut user_defined = HashMap::new();
+        reader.set_compression_metadata(&mut user_defined);
+        assert!(user_defined.is_empty());
+    }
 }
@@ -1,11 +1,17 @@
+mod api_compress;
 mod api_datatypes;
 mod api_errors;
 mod api_layer;
+mod api_multipart;
+mod api_precondition;
 mod api_response;
 mod api_utils;
 
+pub use api_compress::*;
 pub use api_datatypes::*;
 pub use api_errors::*;
 pub use api_layer::*;
+pub use api_multipart::*;
+pub use api_precondition::*;
 pub use api_response::*;
 pub use api_utils::*;
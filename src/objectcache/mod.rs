@@ -1,19 +1,25 @@
-use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::Arc;
 
 use const_format::concatcp;
 use lazy_static::lazy_static;
 use strum::Display;
+use tokio::io::AsyncReadExt;
 
+use crate::errors::TypedError;
 use crate::utils::{minutes, Duration};
 use crate::{globals, object};
 
+mod range;
 mod stats;
 mod utils;
 
+pub use range::*;
 pub use stats::*;
 pub use utils::*;
 
 const CACHE_BLK_SIZE: usize = 1 << 20;
+// Cached blocks are held in memory, whatever the quota of the cache drives.
+const RANGE_CACHE_MAX_MEMORY: u64 = 1 << 30;
 const CACHE_GC_INTERVAL: Duration = minutes(30);
 const WRITE_BACK_STATUS_HEADER: &str =
     concatcp!(globals::RESERVED_METADATA_PREFIX_LOWER, "write-back-status");
@@ -39,9 +45,21 @@ pub struct CacheStorageInfo {
 }
 
 // Implements primitives for cache object API layer.
-pub enum CacheObjectLayer {}
+pub struct CacheObjectLayer {
+    range_cache: RangeCache,
+}
 
 impl CacheObjectLayer {
+    /// Creates the cache layer over cache drives of `total` bytes, of which
+    /// at most `quota` percent, and no more than `RANGE_CACHE_MAX_MEMORY`,
+    /// are used.
+    pub fn new(config: &crate::config::cache::Config, total: u64) -> CacheObjectLayer {
+        let capacity = (total / 100 * config.quota as u64).min(RANGE_CACHE_MAX_MEMORY);
+        CacheObjectLayer {
+            range_cache: RangeCache::new(config.range, capacity),
+        }
+    }
+
     pub async fn get_object_and_info(
         &self,
         bucket: &str,
@@ -51,7 +69,82 @@ impl CacheObjectLayer {
         lock_type: object::LockType,
         opts: Option<object::ObjectOptions>,
     ) -> anyhow::Result<object::GetObjectReader> {
-        todo!()
+        let object_api = object::get_object_layer().ok_or(TypedError::ServerNotInitialized)?;
+        let mut info = object_api.get_object_info(bucket, object, opts).await?;
+        // Reads of the cached version.
+        let version_id = info.version_id.clone();
+        let version_opts = || object::ObjectOptions {
+            version_id: version_id.clone(),
+            ..Default::default()
+        };
+        let size = match object::compression_algorithm(&info.user_defined) {
+            Some(_) => object::actual_size(&info.user_defined)? as u64,
+            None => info.size as u64,
+        };
+        let (offset, length) = match range.get_offset_length(size) {
+            Some(offset_length) => offset_length,
+            None if range.is_empty() => (0, size),
+            // Unsatisfiable, let the backend report it.
+            None => {
+                return object_api
+                    .get_object_and_info(
+                        bucket,
+                        object,
+                        range,
+                        header,
+                        lock_type,
+                        Some(version_opts()),
+                    )
+                    .await;
+            }
+        };
+
+        let fetch = |offset: u64, length: u64| {
+            let object_api = object_api.clone();
+            let opts = version_opts();
+            async move {
+                let mut reader = object_api
+                    .get_object_and_info(
+                        bucket,
+                        object,
+                        crate::http::HttpRange::new(offset, length),
+                        header,
+                        lock_type,
+                        Some(opts),
+                    )
+                    .await?;
+                let mut buf = Vec::with_capacity(length as usize);
+                reader.reader.read_to_end(&mut buf).await?;
+                Ok::<_, anyhow::Error>(buf)
+            }
+        };
+        let reader = self
+            .range_cache
+            .read(bucket, object, &info.etag, size, offset, length, fetch)
+            .await?;
+        match reader {
+            Some(reader) => {
+                info.size = size as i64;
+                Ok(object::GetObjectReader::from_decompressed(
+                    Box::new(reader),
+                    info,
+                    version_opts(),
+                ))
+            }
+            // Not cached, stream it from the backend.
+            None => {
+                object_api
+                    .get_object_and_info(
+                        bucket,
+                        object,
+                        range,
+                        header,
+                        lock_type,
+                        Some(version_opts()),
+                    )
+                    .await
+            }
+        }
     }
 
     pub async fn get_object_info(
@@ -114,13 +207,13 @@ impl CacheObjectLayer {
 }
 
 lazy_static! {
-    static ref GLOBAL_CACHE_API: Arc<Mutex<Option<CacheObjectLayer>>> = Arc::new(Mutex::new(None));
+    static ref GLOBAL_CACHE_API: globals::Registry<CacheObjectLayer> = globals::Registry::default();
 }
 
-pub fn get_cache_layer() -> MutexGuard<'static, Option<CacheObjectLayer>> {
-    GLOBAL_CACHE_API.lock().unwrap()
+pub fn get_cache_layer() -> Option<Arc<CacheObjectLayer>> {
+    GLOBAL_CACHE_API.get()
 }
 
-pub fn set_cache_layer(api: CacheObjectLayer) {
-    *GLOBAL_CACHE_API.lock().unwrap() = Some(api);
+pub fn set_cache_layer(api: Arc<CacheObjectLayer>) {
+    GLOBAL_CACHE_API.set(api);
 }
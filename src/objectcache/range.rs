@@ -0,0 +1,419 @@
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+
+use anyhow::ensure;
+use bytes::Bytes;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use super::CACHE_BLK_SIZE;
+
+const BLK_SIZE: u64 = CACHE_BLK_SIZE as u64;
+
+// Cached blocks of an object version.
+struct CachedBlocks {
+    etag: String,
+    size: u64,
+    blocks: HashMap<u64, Bytes>,
+    last_used: u64,
+}
+
+impl CachedBlocks {
+    fn new(etag: &str, size: u64) -> CachedBlocks {
+        CachedBlocks {
+            etag: etag.to_owned(),
+            size,
+            blocks: HashMap::new(),
+            last_used: 0,
+        }
+    }
+
+    fn bytes(&self) -> u64 {
+        self.blocks.values().map(|data| data.len() as u64).sum()
+    }
+
+    fn block_count(&self) -> u64 {
+        (self.size + BLK_SIZE - 1) / BLK_SIZE
+    }
+
+    fn is_complete(&self) -> bool {
+        self.blocks.len() as u64 == self.block_count()
+    }
+
+    // Slices the range out of the cached blocks, if they are all cached.
+    fn read_at(&self, offset: u64, length: u64) -> Option<RangeReader> {
+        let mut blocks = VecDeque::new();
+        for blk in offset / BLK_SIZE..=(offset + length - 1) / BLK_SIZE {
+            let data = self.blocks.get(&blk)?;
+            let blk_offset = blk * BLK_SIZE;
+            let start = offset.max(blk_offset) - blk_offset;
+            let end = (offset + length).min(blk_offset + data.len() as u64) - blk_offset;
+            blocks.push_back(data.slice(start as usize..end as usize));
+        }
+        Some(RangeReader { blocks })
+    }
+}
+
+#[derive(Default)]
+struct CachedObjects {
+    objects: HashMap<String, CachedBlocks>,
+    // Total size of the cached blocks.
+    used: u64,
+    // Incremented on each access, to order objects by last use.
+    clock: u64,
+}
+
+impl CachedObjects {
+    fn remove(&mut self, key: &str) {
+        if let Some(cached) = self.objects.remove(key) {
+            self.used -= cached.bytes();
+        }
+    }
+
+    // Evicts the least recently used objects until the cache fits in
+    // `capacity`.
+    fn evict(&mut self, capacity: u64) {
+        while self.used > capacity {
+            let key = match self.objects.iter().min_by_key(|(_, c)| c.last_used) {
+                Some((key, _)) => key.clone(),
+                None => break,
+            };
+            self.remove(&key);
+        }
+    }
+}
+
+/// Reader of a range served from the cached blocks, which it shares with
+/// the cache.
+pub struct RangeReader {
+    blocks: VecDeque<Bytes>,
+}
+
+impl AsyncRead for RangeReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        while let Some(block) = this.blocks.front_mut() {
+            if block.is_empty() {
+                this.blocks.pop_front();
+                continue;
+            }
+            let n = block.len().min(buf.remaining());
+            buf.put_slice(&block.split_to(n));
+            break;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+// Caches objects in blocks of `CACHE_BLK_SIZE`, up to `capacity` bytes held
+// in memory, the least recently used objects being evicted first.
+// With range caching, a partial read only caches the blocks covering the
+// requested range, which later overlapping reads are served from. Without it,
+// only full object reads are cached.
+pub struct RangeCache {
+    range: bool,
+    capacity: u64,
+    objects: Mutex<CachedObjects>,
+}
+
+impl RangeCache {
+    pub fn new(range: bool, capacity: u64) -> RangeCache {
+        RangeCache {
+            range,
+            capacity,
+            objects: Mutex::new(CachedObjects::default()),
+        }
+    }
+
+    /// Reads `length` bytes at `offset` of the object of `size` bytes with the
+    /// given `etag`, from the cache where possible. The missing blocks are read
+    /// with `fetch(offset, length)`. Returns [`None`] if the range is not to be
+    /// cached, it is then to be streamed from the backend.
+    pub async fn read<F, Fut>(
+        &self,
+        bucket: &str,
+        object: &str,
+        etag: &str,
+        size: u64,
+        offset: u64,
+        length: u64,
+        mut fetch: F,
+    ) -> anyhow::Result<Option<RangeReader>>
+    where
+        F: FnMut(u64, u64) -> Fut,
+        Fut: Future<Output = anyhow::Result<Vec<u8>>>,
+    {
+        ensure!(
+            offset.checked_add(length).map_or(false, |end| end <= size),
+            "range out of object bounds"
+        );
+        if length == 0 {
+            return Ok(Some(RangeReader {
+                blocks: VecDeque::new(),
+            }));
+        }
+        let key = crate::object::path_join(&[bucket, object]);
+        let full = offset == 0 && length == size;
+
+        // Blocks covering the range, missing from the cache.
+        let (first, last) = (offset / BLK_SIZE, (offset + length - 1) / BLK_SIZE);
+        let missing: Vec<u64> = {
+            let mut objects = self.objects.lock().unwrap();
+            if objects
+                .objects
+                .get(&key)
+                .map_or(false, |cached| cached.etag != etag || cached.size != size)
+            {
+                // Stale version.
+                objects.remove(&key);
+            }
+            match objects.objects.get(&key) {
+                Some(cached) => (first..=last)
+                    .filter(|blk| !cached.blocks.contains_key(blk))
+                    .collect(),
+                None => (first..=last).collect(),
+            }
+        };
+
+        if !missing.is_empty() && ((!self.range && !full) || length > self.capacity) {
+            return Ok(None);
+        }
+
+        // Fetch the contiguous runs of missing blocks.
+        let mut fetched = Vec::with_capacity(missing.len());
+        let mut runs = missing.iter().peekable();
+        while let Some(&run_first) = runs.next() {
+            let mut run_last = run_first;
+            while runs.peek() == Some(&&(run_last + 1)) {
+                run_last += 1;
+                runs.next();
+            }
+            let start = run_first * BLK_SIZE;
+            let end = ((run_last + 1) * BLK_SIZE).min(size);
+            let data = fetch(start, end - start).await?;
+            ensure!(
+                data.len() as u64 == end - start,
+                "short read of {} bytes at offset {}",
+                data.len(),
+                start
+            );
+            fetched.extend(
+                (run_first..=run_last).zip(data.chunks(CACHE_BLK_SIZE).map(Bytes::copy_from_slice)),
+            );
+        }
+
+        let reader = {
+            let mut objects = self.objects.lock().unwrap();
+            if objects
+                .objects
+                .get(&key)
+                .map_or(false, |cached| cached.etag != etag || cached.size != size)
+            {
+                // Replaced by a concurrent read of another version.
+                objects.remove(&key);
+            }
+            objects.clock += 1;
+            let clock = objects.clock;
+            let cached = objects
+                .objects
+                .entry(key)
+                .or_insert_with(|| CachedBlocks::new(etag, size));
+            cached.last_used = clock;
+            let before = cached.bytes();
+            cached.blocks.extend(fetched);
+            let added = cached.bytes() - before;
+            let reader = cached.read_at(offset, length);
+            objects.used += added;
+            objects.evict(self.capacity);
+            reader
+        };
+        Ok(reader)
+    }
+
+    /// Reports whether the whole object is cached.
+    pub fn is_cached(&self, bucket: &str, object: &str) -> bool {
+        let key = crate::object::path_join(&[bucket, object]);
+        self.objects
+            .lock()
+            .unwrap()
+            .objects
+            .get(&key)
+            .map_or(false, |cached| cached.is_complete())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    const SIZE: u64 = 4 * BLK_SIZE + 100;
+
+    // Backend object, recording the ranges read from it.
+    struct Backend {
+        data: Vec<u8>,
+        reads: Mutex<Vec<(u64, u64)>>,
+    }
+
+    impl Backend {
+        fn new() -> Arc<Backend> {
+            Arc::new(Backend {
+                data: (0..SIZE).map(|i| i as u8).collect(),
+                reads: Mutex::new(Vec::new()),
+            })
+        }
+
+        fn take_reads(&self) -> Vec<(u64, u64)> {
+            std::mem::take(&mut self.reads.lock().unwrap())
+        }
+    }
+
+    async fn read(
+        cache: &RangeCache,
+        backend: &Arc<Backend>,
+        etag: &str,
+        offset: u64,
+        length: u64,
+    ) -> Vec<u8> {
+        read_object(cache, backend, "object", etag, offset, length).await
+    }
+
+    async fn read_object(
+        cache: &RangeCache,
+        backend: &Arc<Backend>,
+        object: &str,
+        etag: &str,
+        offset: u64,
+        length: u64,
+    ) -> Vec<u8> {
+        let fetch = |offset: u64, length: u64| {
+            let backend = backend.clone();
+            async move {
+                backend.reads.lock().unwrap().push((offset, length));
+                Ok(backend.data[offset as usize..(offset + length) as usize].to_vec())
+            }
+        };
+        let reader = cache
+            .read("bucket", object, etag, SIZE, offset, length, fetch)
+            .await
+            .unwrap();
+        // Uncached ranges are streamed from the backend by the caller.
+        let buf = match reader {
+            Some(mut reader) => {
+                let mut buf = Vec::new();
+                reader.read_to_end(&mut buf).await.unwrap();
+                buf
+            }
+            None => fetch(offset, length).await.unwrap(),
+        };
+        assert_eq!(
+            buf,
+            &backend.data[offset as usize..(offset + length) as usize]
+        );
+        buf
+    }
+
+    #[tokio::test]
+    async fn test_range_cache() {
+        let backend = Backend::new();
+        let cache = RangeCache::new(true, 2 * SIZE);
+
+        // Only the blocks covering the range are fetched.
+        read(&cache, &backend, "etag", BLK_SIZE / 2, 2 * BLK_SIZE).await;
+        assert_eq!(backend.take_reads(), vec![(0, 3 * BLK_SIZE)]);
+
+        // Overlapping read, only the missing block is fetched.
+        read(&cache, &backend, "etag", 2 * BLK_SIZE, 2 * BLK_SIZE).await;
+        assert_eq!(backend.take_reads(), vec![(3 * BLK_SIZE, BLK_SIZE)]);
+
+        // Fully cached range.
+        read(&cache, &backend, "etag", 10, 3 * BLK_SIZE).await;
+        assert!(backend.take_reads().is_empty());
+        assert!(!cache.is_cached("bucket", "object"));
+
+        // Last partial block.
+        read(&cache, &backend, "etag", SIZE - 10, 10).await;
+        assert_eq!(backend.take_reads(), vec![(4 * BLK_SIZE, 100)]);
+        assert!(cache.is_cached("bucket", "object"));
+
+        // A new version invalidates the cached blocks.
+        read(&cache, &backend, "etag2", 0, 10).await;
+        assert_eq!(backend.take_reads(), vec![(0, BLK_SIZE)]);
+    }
+
+    #[tokio::test]
+    async fn test_range_cache_disabled() {
+        let backend = Backend::new();
+        let cache = RangeCache::new(false, 2 * SIZE);
+
+        // Partial reads are not cached.
+        for _ in 0..2 {
+            read(&cache, &backend, "etag", BLK_SIZE / 2, BLK_SIZE).await;
+            assert_eq!(backend.take_reads(), vec![(BLK_SIZE / 2, BLK_SIZE)]);
+        }
+        assert!(!cache.is_cached("bucket", "object"));
+
+        // Full objects are, and serve later partial reads.
+        read(&cache, &backend, "etag", 0, SIZE).await;
+        assert_eq!(backend.take_reads(), vec![(0, SIZE)]);
+        assert!(cache.is_cached("bucket", "object"));
+        read(&cache, &backend, "etag", BLK_SIZE / 2, BLK_SIZE).await;
+        assert!(backend.take_reads().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_range_cache_capacity() {
+        let backend = Backend::new();
+        let cache = RangeCache::new(true, 2 * SIZE);
+
+        read_object(&cache, &backend, "a", "etag", 0, SIZE).await;
+        read_object(&cache, &backend, "b", "etag", 0, SIZE).await;
+        assert!(cache.is_cached("bucket", "a"));
+        assert!(cache.is_cached("bucket", "b"));
+        backend.take_reads();
+
+        // The least recently used object is evicted.
+        read_object(&cache, &backend, "a", "etag", 0, 10).await;
+        read_object(&cache, &backend, "c", "etag", 0, SIZE).await;
+        assert!(cache.is_cached("bucket", "a"));
+        assert!(!cache.is_cached("bucket", "b"));
+        assert!(cache.is_cached("bucket", "c"));
+        assert_eq!(backend.take_reads(), vec![(0, SIZE)]);
+
+        // Reads larger than the cache are not cached.
+        let cache = RangeCache::new(true, SIZE - 1);
+        for _ in 0..2 {
+            read(&cache, &backend, "etag", 0, SIZE).await;
+            assert_eq!(backend.take_reads(), vec![(0, SIZE)]);
+        }
+        assert!(!cache.is_cached("bucket", "object"));
+    }
+
+    #[tokio::test]
+    async fn test_range_cache_out_of_bounds() {
+        let cache = RangeCache::new(true, 2 * SIZE);
+        for (offset, length) in [(SIZE, 1), (1, SIZE), (u64::MAX, 2), (2, u64::MAX)] {
+            let res = cache
+                .read(
+                    "bucket",
+                    "object",
+                    "etag",
+                    SIZE,
+                    offset,
+                    length,
+                    |_, _| async { Ok(Vec::new()) },
+                )
+                .await;
+            assert!(res.is_err(), "{} {}", offset, length);
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::marker::PhantomData;
+
+use bytes::{Buf, BufMut, BytesMut};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::utils;
+
+const FRAME_HEADER_SIZE: usize = 4;
+
+// Default upper bound of a frame body, larger frames are rejected
+// before any buffer is allocated for them.
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 16 * utils::MIB;
+
+#[derive(Error, Debug)]
+pub enum ProtoCodecError {
+    #[error("frame size {size} exceeds the maximum frame size {max}")]
+    FrameTooLarge { size: usize, max: usize },
+    #[error("encode message: {0}")]
+    Encode(#[from] rmp_serde::encode::Error),
+    #[error("decode message: {0}")]
+    Decode(#[from] rmp_serde::decode::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+// Frames peer messages as a 4-byte big-endian body length followed by
+// the msgpack encoded message.
+pub struct ProtoCodec<T> {
+    max_frame_size: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> ProtoCodec<T> {
+    pub fn new() -> Self {
+        Self::with_max_frame_size(DEFAULT_MAX_FRAME_SIZE)
+    }
+
+    pub fn with_max_frame_size(max_frame_size: usize) -> Self {
+        ProtoCodec {
+            max_frame_size,
+            _marker: PhantomData,
+        }
+    }
+
+    fn check_frame_size(&self, size: usize) -> Result<(), ProtoCodecError> {
+        if size > self.max_frame_size || size > u32::MAX as usize {
+            return Err(ProtoCodecError::FrameTooLarge {
+                size,
+                max: self.max_frame_size,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<T> Default for ProtoCodec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Serialize> Encoder<T> for ProtoCodec<T> {
+    type Error = ProtoCodecError;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let body = rmp_serde::to_vec(&item)?;
+        self.check_frame_size(body.len())?;
+        dst.reserve(FRAME_HEADER_SIZE + body.len());
+        dst.put_u32(body.len() as u32);
+        dst.extend_from_slice(&body);
+        Ok(())
+    }
+}
+
+impl<T: DeserializeOwned> Decoder for ProtoCodec<T> {
+    type Item = T;
+    type Error = ProtoCodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if src.len() < FRAME_HEADER_SIZE {
+            return Ok(None);
+        }
+        let mut header = [0u8; FRAME_HEADER_SIZE];
+        header.copy_from_slice(&src[..FRAME_HEADER_SIZE]);
+        let size = u32::from_be_bytes(header) as usize;
+        // Check before reserving, the size comes from the peer.
+        self.check_frame_size(size)?;
+
+        if src.len() < FRAME_HEADER_SIZE + size {
+            src.reserve(FRAME_HEADER_SIZE + size - src.len());
+            return Ok(None);
+        }
+        src.advance(FRAME_HEADER_SIZE);
+        let body = src.split_to(size);
+        Ok(Some(rmp_serde::from_read_ref(&body)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+    use serde::Deserialize;
+    use tokio::io::AsyncWriteExt;
+    use tokio_util::codec::FramedRead;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Message {
+        id: u64,
+        method: String,
+        payload: Vec<u8>,
+    }
+
+    fn message(id: u64, payload_size: usize) -> Message {
+        Message {
+            id,
+            method: format!("method-{}", id),
+            payload: vec![id as u8; payload_size],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_proto_codec_round_trip() {
+        let (mut client, server) = tokio::io::duplex(64);
+        let mut buf = BytesMut::new();
+        let mut codec = ProtoCodec::new();
+        for i in 0..5 {
+            codec
+                .encode(message(i, i as usize * 100), &mut buf)
+                .unwrap();
+        }
+        let writer = tokio::spawn(async move {
+            // Written in small chunks through the small duplex buffer,
+            // so frames arrive split.
+            client.write_all(&buf).await.unwrap();
+        });
+
+        let mut reader = FramedRead::new(server, ProtoCodec::<Message>::new());
+        for i in 0..5 {
+            assert_eq!(
+                reader.next().await.unwrap().unwrap(),
+                message(i, i as usize * 100)
+            );
+        }
+        writer.await.unwrap();
+        assert!(reader.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_proto_codec_oversize_frame() {
+        let mut codec = ProtoCodec::with_max_frame_size(128);
+        let mut buf = BytesMut::new();
+        assert!(matches!(
+            codec.encode(message(1, 1024), &mut buf),
+            Err(ProtoCodecError::FrameTooLarge { max: 128, .. })
+        ));
+        assert!(buf.is_empty());
+
+        // A peer announcing a huge frame is rejected without waiting for,
+        // or allocating, its body.
+        let (mut client, server) = tokio::io::duplex(64);
+        client.write_all(&u32::MAX.to_be_bytes()).await.unwrap();
+        let mut reader = FramedRead::new(server, ProtoCodec::<Message>::new());
+        let err = reader.next().await.unwrap().unwrap_err();
+        assert!(matches!(err, ProtoCodecError::FrameTooLarge { .. }));
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "frame size {} exceeds the maximum frame size {}",
+                u32::MAX,
+                DEFAULT_MAX_FRAME_SIZE
+            )
+        );
+    }
+}
@@ -1,7 +1,9 @@
+mod codec;
 mod common;
 mod peer;
 mod storage;
 
+pub use codec::*;
 pub use common::*;
 pub use peer::peer_service_client::*;
 pub use peer::peer_service_server::*;
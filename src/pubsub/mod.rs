@@ -1,12 +1,85 @@
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
 
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, Notify};
+
+type Topic<T> = Box<dyn Fn(&T) -> bool + Send + Sync>;
 
 #[derive(Clone)]
 pub struct PubSub<T: Clone> {
     tx: broadcast::Sender<T>,
     subscribers: Arc<AtomicUsize>,
+    bounded: Arc<BoundedQueues<T>>,
+}
+
+// Queues of the bounded subscribers, which are closed once the last
+// publisher is dropped.
+struct BoundedQueues<T>(Mutex<Vec<Weak<BoundedQueue<T>>>>);
+
+impl<T> Drop for BoundedQueues<T> {
+    fn drop(&mut self) {
+        for queue in self.0.get_mut().unwrap().drain(..) {
+            if let Some(queue) = queue.upgrade() {
+                queue.closed.store(true, Ordering::SeqCst);
+                queue.notify.notify_one();
+            }
+        }
+    }
+}
+
+// Queue of a bounded subscriber, the oldest value is dropped when it is full
+// so that publishing never waits for a slow subscriber.
+struct BoundedQueue<T> {
+    topic: Topic<T>,
+    values: Mutex<VecDeque<T>>,
+    capacity: usize,
+    dropped: AtomicU64,
+    closed: AtomicBool,
+    notify: Notify,
+}
+
+impl<T> BoundedQueue<T> {
+    fn push(&self, value: T) {
+        {
+            let mut values = self.values.lock().unwrap();
+            if values.len() >= self.capacity {
+                values.pop_front();
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            values.push_back(value);
+        }
+        self.notify.notify_one();
+    }
+}
+
+pub struct BoundedReceiver<T: Clone>(Arc<BoundedQueue<T>>, Arc<AtomicUsize>);
+
+impl<T: Clone> BoundedReceiver<T> {
+    /// Returns the next value, or `None` once the queued values are consumed
+    /// and the publisher is dropped.
+    pub async fn recv(&mut self) -> Option<T> {
+        loop {
+            if let Some(value) = self.0.values.lock().unwrap().pop_front() {
+                return Some(value);
+            }
+            if self.0.closed.load(Ordering::SeqCst) {
+                return None;
+            }
+            self.0.notify.notified().await;
+        }
+    }
+
+    // Number of values dropped because the subscriber fell behind.
+    pub fn dropped_count(&self) -> u64 {
+        self.0.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl<T: Clone> Drop for BoundedReceiver<T> {
+    fn drop(&mut self) {
+        self.1.fetch_sub(1, Ordering::Relaxed);
+    }
 }
 
 pub struct Receiver<T: Clone>(broadcast::Receiver<T>, Arc<AtomicUsize>);
@@ -49,10 +122,23 @@ impl<T: Clone> PubSub<T> {
         PubSub {
             tx,
             subscribers: Arc::new(AtomicUsize::new(0)),
+            bounded: Arc::new(BoundedQueues(Mutex::new(Vec::new()))),
         }
     }
 
     pub fn publish(&self, value: T) {
+        {
+            let mut bounded = self.bounded.0.lock().unwrap();
+            bounded.retain(|queue| match queue.upgrade() {
+                Some(queue) => {
+                    if (queue.topic)(&value) {
+                        queue.push(value.clone());
+                    }
+                    true
+                }
+                None => false,
+            });
+        }
         let _ = self.tx.send(value); // ignore error
     }
 
@@ -61,7 +147,79 @@ impl<T: Clone> PubSub<T> {
         Receiver(self.tx.subscribe(), self.subscribers.clone())
     }
 
+    /// Subscribes to the values matching `topic`, keeping at most `capacity`
+    /// of them and dropping the oldest ones when the subscriber falls behind.
+    pub fn subscribe_bounded<F>(&self, topic: F, capacity: usize) -> BoundedReceiver<T>
+    where
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        let queue = Arc::new(BoundedQueue {
+            topic: Box::new(topic),
+            values: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity: capacity.max(1),
+            dropped: AtomicU64::new(0),
+            closed: AtomicBool::new(false),
+            notify: Notify::new(),
+        });
+        self.bounded.0.lock().unwrap().push(Arc::downgrade(&queue));
+        self.subscribers.fetch_add(1, Ordering::Relaxed);
+        BoundedReceiver(queue, self.subscribers.clone())
+    }
+
     pub fn subscribers_num(&self) -> usize {
         self.subscribers.load(Ordering::Relaxed)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_subscribe_bounded_drop_oldest() {
+        let ps = PubSub::new(16);
+        let mut rx = ps.subscribe_bounded(|_: &i32| true, 2);
+        let mut even_rx = ps.subscribe_bounded(|v: &i32| v % 2 == 0, 8);
+        assert_eq!(ps.subscribers_num(), 2);
+
+        for i in 1..=5 {
+            ps.publish(i);
+        }
+
+        assert_eq!(rx.recv().await, Some(4));
+        assert_eq!(rx.recv().await, Some(5));
+        assert_eq!(rx.dropped_count(), 3);
+
+        assert_eq!(even_rx.recv().await, Some(2));
+        assert_eq!(even_rx.recv().await, Some(4));
+        assert_eq!(even_rx.dropped_count(), 0);
+
+        drop(rx);
+        drop(even_rx);
+        assert_eq!(ps.subscribers_num(), 0);
+        ps.publish(6);
+        assert!(ps.bounded.0.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_bounded_closed() {
+        let ps = PubSub::new(16);
+        let mut rx = ps.subscribe_bounded(|_: &i32| true, 8);
+        ps.publish(1);
+        let ps_clone = ps.clone();
+        drop(ps);
+        ps_clone.publish(2);
+
+        let waiter = tokio::spawn(async move {
+            let mut values = Vec::new();
+            while let Some(value) = rx.recv().await {
+                values.push(value);
+            }
+            values
+        });
+        tokio::task::yield_now().await;
+        // The queued values are received before the end of the channel.
+        drop(ps_clone);
+        assert_eq!(waiter.await.unwrap(), vec![1, 2]);
+    }
+}
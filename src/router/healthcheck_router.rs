@@ -1,6 +1,7 @@
+use actix_web::web;
 use const_format::concatcp;
 
-use crate::globals;
+use crate::{globals, http};
 
 pub const HEALTH_CHECK_PATH: &str = "/health";
 pub const HEALTH_CHECK_LIVENESS_PATH: &str = "/live";
@@ -9,3 +10,58 @@ pub const HEALTH_CHECK_CLUSTER_PATH: &str = "/cluster";
 pub const HEALTH_CHECK_CLUSTER_READ_PATH: &str = "/cluster/read";
 pub const HEALTH_CHECK_PATH_PREFIX: &str =
     concatcp!(globals::SYSTEM_RESERVED_BUCKET_PATH, HEALTH_CHECK_PATH);
+
+// Registers the liveness and readiness probes, the readiness check is
+// expected as `web::Data<http::ReadinessCheck>` app data.
+pub fn register_health_check_router(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope(HEALTH_CHECK_PATH_PREFIX)
+            .route(
+                HEALTH_CHECK_LIVENESS_PATH,
+                web::get().to(http::liveness_handler),
+            )
+            .route(
+                HEALTH_CHECK_LIVENESS_PATH,
+                web::head().to(http::liveness_handler),
+            )
+            .route(
+                HEALTH_CHECK_READINESS_PATH,
+                web::get().to(http::readiness_handler),
+            )
+            .route(
+                HEALTH_CHECK_READINESS_PATH,
+                web::head().to(http::readiness_handler),
+            ),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::App;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_register_health_check_router() {
+        let check = http::ReadinessCheck::new(vec![], 0);
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(check))
+                .configure(register_health_check_router),
+        )
+        .await;
+
+        for path in [HEALTH_CHECK_LIVENESS_PATH, HEALTH_CHECK_READINESS_PATH] {
+            let uri = format!("{}{}", HEALTH_CHECK_PATH_PREFIX, path);
+            let req = TestRequest::get().uri(&uri).to_request();
+            assert_eq!(call_service(&app, req).await.status(), StatusCode::OK);
+            let req = TestRequest::default()
+                .method(actix_web::http::Method::HEAD)
+                .uri(&uri)
+                .to_request();
+            assert_eq!(call_service(&app, req).await.status(), StatusCode::OK);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use regex::Regex;
+
+use crate::globals;
+
+const HOST_CACHE_CAPACITY: usize = 1024;
+
+// Resolves the bucket of virtual-host-style requests, i.e. <bucket>.<domain>.
+// Domain regexes are compiled once, and results are cached per host so
+// repeated requests from the same host skip the regex matching.
+pub struct HostBucketResolver {
+    domains: Vec<(Regex, String)>,
+    reserve_system_bucket: bool,
+    cache: Mutex<HostCache>,
+}
+
+impl HostBucketResolver {
+    /// Creates a resolver for the domains. If `reserve_system_bucket` is set,
+    /// `<system reserved bucket>.<domain>` is never resolved to a bucket.
+    pub fn new(domains: &[impl AsRef<str>], reserve_system_bucket: bool) -> anyhow::Result<Self> {
+        let domains = domains
+            .iter()
+            .map(|domain| {
+                let domain = domain.as_ref();
+                let host_re = Regex::new(&format!(r#"^(.+)\.{}$"#, regex::escape(domain)))?;
+                let reserved_host = format!("{}.{}", globals::SYSTEM_RESERVED_BUCKET, domain);
+                Ok((host_re, reserved_host))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(HostBucketResolver {
+            domains,
+            reserve_system_bucket,
+            cache: Mutex::new(HostCache::new(HOST_CACHE_CAPACITY)),
+        })
+    }
+
+    /// Returns the bucket addressed by the host, if any.
+    pub fn resolve(&self, host: &str) -> Option<String> {
+        if let Some(bucket) = self.cache.lock().unwrap().get(host) {
+            return bucket;
+        }
+        let bucket = self.resolve_uncached(host);
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(host.to_owned(), bucket.clone());
+        bucket
+    }
+
+    fn resolve_uncached(&self, host: &str) -> Option<String> {
+        for (host_re, reserved_host) in &self.domains {
+            // Reserve hulk.<namespace>.svc.<cluster_domain> if in Kubernetes.
+            if self.reserve_system_bucket && host == reserved_host {
+                return None;
+            }
+            // Allow <bucket>.<namespace>.svc.<cluster_domain> and extract bucket.
+            if let Some(caps) = host_re.captures(host) {
+                return Some(caps.get(1).unwrap().as_str().to_owned());
+            }
+        }
+        None
+    }
+}
+
+// Least recently used cache of resolved hosts.
+struct HostCache {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<String, (Option<String>, u64)>,
+    // Last use tick to host, oldest first.
+    order: BTreeMap<u64, String>,
+}
+
+impl HostCache {
+    fn new(capacity: usize) -> HostCache {
+        HostCache {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    fn get(&mut self, host: &str) -> Option<Option<String>> {
+        self.tick += 1;
+        let tick = self.tick;
+        let (bucket, last_used) = self.entries.get_mut(host)?;
+        let host = self.order.remove(last_used).unwrap();
+        *last_used = tick;
+        self.order.insert(tick, host);
+        Some(bucket.clone())
+    }
+
+    fn insert(&mut self, host: String, bucket: Option<String>) {
+        self.tick += 1;
+        if let Some((_, last_used)) = self.entries.remove(&host) {
+            self.order.remove(&last_used);
+        }
+        if self.entries.len() >= self.capacity {
+            if let Some((&oldest, _)) = self.order.iter().next() {
+                let oldest_host = self.order.remove(&oldest).unwrap();
+                self.entries.remove(&oldest_host);
+            }
+        }
+        self.order.insert(self.tick, host.clone());
+        self.entries.insert(host, (bucket, self.tick));
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_bucket_resolver() {
+        let resolver = HostBucketResolver::new(&["domain.com", "example.org"], true).unwrap();
+        for _ in 0..2 {
+            assert_eq!(
+                resolver.resolve("mybucket.domain.com").as_deref(),
+                Some("mybucket")
+            );
+            assert_eq!(
+                resolver.resolve("other.example.org").as_deref(),
+                Some("other")
+            );
+            assert_eq!(resolver.resolve("hulk.domain.com"), None);
+            assert_eq!(resolver.resolve("domain.com"), None);
+            assert_eq!(resolver.resolve("mybucket.domain.net"), None);
+        }
+        assert_eq!(resolver.cache.lock().unwrap().len(), 5);
+
+        let resolver = HostBucketResolver::new(&["domain.com"], false).unwrap();
+        assert_eq!(resolver.resolve("hulk.domain.com").as_deref(), Some("hulk"));
+    }
+
+    #[test]
+    fn test_host_cache_eviction() {
+        let mut cache = HostCache::new(2);
+        cache.insert("a".to_owned(), Some("a".to_owned()));
+        cache.insert("b".to_owned(), None);
+        // Touch "a" so "b" is the least recently used.
+        assert_eq!(cache.get("a"), Some(Some("a".to_owned())));
+        cache.insert("c".to_owned(), Some("c".to_owned()));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("a"), Some(Some("a".to_owned())));
+        assert_eq!(cache.get("c"), Some(Some("c".to_owned())));
+    }
+}
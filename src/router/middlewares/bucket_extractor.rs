@@ -0,0 +1,158 @@
+use std::future::{ready, Ready};
+use std::sync::Arc;
+
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::error::Error;
+use actix_web::guard::get_host_uri;
+use futures_util::future::Either;
+
+use crate::errors::ApiError;
+use crate::http::ApiResponse;
+use crate::router::{
+    is_internal_request, is_reserved_bucket, path_to_bucket_object, HostBucketResolver,
+};
+
+// Bucket addressed by a request, stored in the request extensions.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RequestBucket(pub String);
+
+// Resolves the bucket of a request from the Host header for virtual-host-style
+// requests, or from the first path segment for path-style requests, and
+// rejects requests to the reserved buckets.
+pub struct BucketExtractor {
+    resolver: Option<Arc<HostBucketResolver>>,
+}
+
+impl BucketExtractor {
+    pub fn new(resolver: Option<Arc<HostBucketResolver>>) -> Self {
+        BucketExtractor { resolver }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for BucketExtractor
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = BucketExtractorMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(BucketExtractorMiddleware {
+            service,
+            resolver: self.resolver.clone(),
+        }))
+    }
+}
+
+pub struct BucketExtractorMiddleware<S> {
+    service: S,
+    resolver: Option<Arc<HostBucketResolver>>,
+}
+
+impl<S> BucketExtractorMiddleware<S> {
+    // Returns the bucket and whether it was addressed virtual-host-style.
+    fn extract_bucket(&self, req: &ServiceRequest) -> (String, bool) {
+        let host_bucket = self.resolver.as_ref().and_then(|resolver| {
+            get_host_uri(req.head())
+                .and_then(|uri| uri.host().and_then(|host| resolver.resolve(host)))
+        });
+        match host_bucket {
+            Some(bucket) => (bucket, true),
+            None => (path_to_bucket_object(req.path()).0.to_owned(), false),
+        }
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for BucketExtractorMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let (bucket, virtual_host) = self.extract_bucket(&req);
+        // Internal requests are always path-style.
+        if is_reserved_bucket(&bucket) && (virtual_host || !is_internal_request(req.request())) {
+            let res = ApiResponse::error_xml(ApiError::AllAccessDisabled.to(), req.request());
+            return Either::Right(ready(Err(res.into())));
+        }
+        if !bucket.is_empty() {
+            req.extensions_mut().insert(RequestBucket(bucket));
+        }
+        Either::Left(self.service.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::{header, StatusCode};
+    use actix_web::test::{init_service, read_body, TestRequest};
+    use actix_web::{web, App, HttpRequest, HttpResponse};
+
+    use super::*;
+
+    async fn bucket_handler(req: HttpRequest) -> HttpResponse {
+        match req.extensions().get::<RequestBucket>() {
+            Some(bucket) => HttpResponse::Ok().body(bucket.0.clone()),
+            None => HttpResponse::NotFound().finish(),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_bucket_extractor() {
+        let resolver = HostBucketResolver::new(&["domain.com"], false).unwrap();
+        let app = init_service(
+            App::new()
+                .wrap(BucketExtractor::new(Some(Arc::new(resolver))))
+                .default_service(web::to(bucket_handler)),
+        )
+        .await;
+
+        // Virtual-host-style.
+        let req = TestRequest::with_uri("/object")
+            .insert_header((header::HOST, "mybucket.domain.com"))
+            .to_srv_request();
+        let res = app.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, "mybucket");
+
+        // Path-style.
+        let req = TestRequest::with_uri("/mybucket/dir/object")
+            .insert_header((header::HOST, "domain.com"))
+            .to_srv_request();
+        let res = app.call(req).await.unwrap();
+        assert_eq!(read_body(res).await, "mybucket");
+
+        // No bucket.
+        let req = TestRequest::with_uri("/").to_srv_request();
+        let res = app.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        // Reserved bucket, in both styles.
+        for (uri, host) in [
+            ("/object", "hulk.domain.com"),
+            ("/hulk/object", "domain.com"),
+        ] {
+            let req = TestRequest::with_uri(uri)
+                .insert_header((header::HOST, host))
+                .to_srv_request();
+            let err = app.call(req).await.err().unwrap();
+            assert_eq!(
+                err.as_response_error().status_code(),
+                StatusCode::FORBIDDEN,
+                "{}{}",
+                host,
+                uri
+            );
+        }
+    }
+}
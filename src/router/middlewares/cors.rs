@@ -5,8 +5,24 @@ use actix_web::http::{header, Method};
 
 use crate::globals::{Guard, GLOBALS};
 
-// CORS (Cross Origin Resource Sharing) middleware.
+// CORS (Cross Origin Resource Sharing) middleware, allowing the origins
+// configured by `api.cors_allow_origin`.
 pub fn cors() -> Cors {
+    new_cors(|origin| is_allowed_origin(&GLOBALS.api_config.guard().cors_allow_origins, origin))
+}
+
+// Whether the request origin matches any of the allowed origins, which
+// may contain `*` wildcards.
+fn is_allowed_origin(allowed_origins: &[String], origin: &str) -> bool {
+    allowed_origins
+        .iter()
+        .any(|allowed_origin| crate::wildcard::match_wildcard_simple(allowed_origin, origin))
+}
+
+fn new_cors<F>(allow_origin: F) -> Cors
+where
+    F: Fn(&str) -> bool + 'static,
+{
     let common_s3_headers = {
         use header::*;
         vec![
@@ -32,16 +48,7 @@ pub fn cors() -> Cors {
         ]
     };
     Cors::default()
-        .allowed_origin_fn(|origin, head| {
-            for allowed_origin in &GLOBALS.api_config.guard().cors_allow_origins {
-                if let Ok(origin) = origin.to_str() {
-                    if crate::wildcard::match_wildcard_simple(allowed_origin, origin) {
-                        return true;
-                    }
-                }
-            }
-            false
-        })
+        .allowed_origin_fn(move |origin, _| origin.to_str().map_or(false, &allow_origin))
         .allowed_methods(vec![
             Method::GET,
             Method::PUT,
@@ -55,3 +62,87 @@ pub fn cors() -> Cors {
         .expose_headers(common_s3_headers)
         .supports_credentials()
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+
+    use super::*;
+
+    #[test]
+    fn test_is_allowed_origin() {
+        let allowed_origins = vec![
+            "https://example.com".to_owned(),
+            "https://*.example.org".to_owned(),
+        ];
+        assert!(is_allowed_origin(&allowed_origins, "https://example.com"));
+        assert!(is_allowed_origin(
+            &allowed_origins,
+            "https://app.example.org"
+        ));
+        assert!(!is_allowed_origin(&allowed_origins, "https://example.net"));
+        assert!(!is_allowed_origin(&allowed_origins, "http://example.com"));
+        assert!(!is_allowed_origin(&[], "https://example.com"));
+        assert!(is_allowed_origin(&["*".to_owned()], "https://example.net"));
+    }
+
+    #[actix_rt::test]
+    async fn test_cors() {
+        for (allowed_origins, origin, allowed) in [
+            (vec!["https://example.com"], "https://example.com", true),
+            (vec!["https://example.com"], "https://example.net", false),
+            (vec!["*"], "https://example.net", true),
+        ] {
+            let allowed_origins: Vec<String> =
+                allowed_origins.into_iter().map(|o| o.to_owned()).collect();
+            let app = init_service(
+                App::new()
+                    .wrap(new_cors(move |origin| {
+                        is_allowed_origin(&allowed_origins, origin)
+                    }))
+                    .default_service(web::to(|| async { HttpResponse::Ok().finish() })),
+            )
+            .await;
+
+            // Preflight request.
+            let req = TestRequest::default()
+                .method(Method::OPTIONS)
+                .uri("/bucket/object")
+                .insert_header((header::ORIGIN, origin))
+                .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "PUT"))
+                .to_request();
+            let res = call_service(&app, req).await;
+            let allow_origin = res.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN);
+            if allowed {
+                assert_eq!(res.status(), StatusCode::OK, "{}", origin);
+                assert_eq!(allow_origin.unwrap(), origin);
+                assert!(res
+                    .headers()
+                    .get(header::ACCESS_CONTROL_ALLOW_METHODS)
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .contains("PUT"));
+            } else {
+                assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{}", origin);
+                assert!(allow_origin.is_none());
+            }
+
+            // Actual request.
+            let req = TestRequest::get()
+                .uri("/bucket/object")
+                .insert_header((header::ORIGIN, origin))
+                .to_request();
+            let res = call_service(&app, req).await;
+            let allow_origin = res.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN);
+            if allowed {
+                assert_eq!(res.status(), StatusCode::OK, "{}", origin);
+                assert_eq!(allow_origin.unwrap(), origin);
+            } else {
+                assert!(allow_origin.is_none(), "{}", origin);
+            }
+        }
+    }
+}
@@ -1,10 +1,14 @@
 use std::convert::TryInto;
 use std::future::{ready, Future, Ready};
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{Context, Poll};
 
+use actix_http::body::{BodySize, MessageBody};
 use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
 use actix_web::error::Error;
 use actix_web::http::{header, HeaderMap, Method, StatusCode};
+use actix_web::web::Bytes;
 use actix_web::HttpRequest;
 use futures_util::future::{Either, LocalBoxFuture};
 use futures_util::FutureExt;
@@ -15,12 +19,11 @@ use crate::crypto::{self, SseType};
 use crate::errors::ApiError;
 use crate::globals::{self, Get, GLOBALS};
 use crate::http::{
-    get_request_auth_type, guess_is_admin_req, guess_is_browser_req, guess_is_health_check_req,
-    guess_is_metrics_req, guess_is_rpc_req, ApiResponse, RequestExtensionsContext,
+    get_request_auth_type, guess_is_browser_req, ApiResponse, RequestExtensionsContext,
 };
-use crate::router::request_to_bucket_object;
+use crate::router::{is_internal_request, is_reserved_bucket, request_to_bucket_object};
 use crate::utils::{AtomicExt, DateTimeExt, DateTimeFormatExt};
-use crate::{errors, http, utils};
+use crate::{errors, http, signals, utils};
 
 pub struct GenericHandlers {}
 
@@ -28,8 +31,9 @@ impl<S, B> Transform<S, ServiceRequest> for GenericHandlers
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
     S::Future: 'static,
+    B: MessageBody,
 {
-    type Response = ServiceResponse<B>;
+    type Response = ServiceResponse<InFlightBody<B>>;
     type Error = Error;
     type Transform = GenericHandlersMiddleware<S>;
     type InitError = ();
@@ -48,8 +52,9 @@ impl<S, B> Service<ServiceRequest> for GenericHandlersMiddleware<S>
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
     S::Future: 'static,
+    B: MessageBody,
 {
-    type Response = ServiceResponse<B>;
+    type Response = ServiceResponse<InFlightBody<B>>;
     type Error = Error;
     type Future = Either<
         LocalBoxFuture<'static, Result<Self::Response, Self::Error>>,
@@ -141,17 +146,23 @@ where
                 .insert(header::CACHE_CONTROL, cache_control.try_into().unwrap());
         }
 
-        let (bucket, _) = request_to_bucket_object(request);
-        if bucket.as_ref() == globals::SYSTEM_RESERVED_BUCKET
-            || bucket.as_ref() == crate::object::SYSTEM_META_BUCKET
-        {
-            if !guess_is_rpc_req(request)
-                && !guess_is_browser_req(request)
-                && !guess_is_health_check_req(request)
-                && !guess_is_metrics_req(request)
-                && !guess_is_admin_req(request)
-            {
-                let res = ApiResponse::error_xml(ApiError::AllAccessDisabled.to(), request);
+        let (bucket, object) = request_to_bucket_object(request);
+        if is_reserved_bucket(bucket.as_ref()) && !is_internal_request(request) {
+            let res = ApiResponse::error_xml(ApiError::AllAccessDisabled.to(), request);
+            return Either::Right(ready(Err(res.into())));
+        }
+
+        // Requests without credentials get what the bucket policy grants to everyone.
+        // Requests not addressed to a bucket, such as STS form posts to the root,
+        // are left to their handler.
+        if auth_type == AuthType::Anonymous && !bucket.is_empty() && !is_internal_request(request) {
+            let allowed = http::request_policy_action(request, &object).map_or(false, |action| {
+                http::check_anonymous_request(&GLOBALS.iam, request, action, &bucket, &object)
+                    .is_ok()
+            });
+            if !allowed {
+                GLOBALS.http_stats.total_s3_rejected_auth.inc();
+                let res = ApiResponse::error_xml(ApiError::AccessDenied.to(), request);
                 return Either::Right(ready(Err(res.into())));
             }
         }
@@ -187,22 +198,48 @@ where
             amz_request_id.clone(),
         );
 
+        let in_flight = signals::IN_FLIGHT_REQUESTS.enter();
         let res = self.service.call(req);
         Either::Left(
             async move {
-                let mut res = res.await;
-                if let Ok(res) = res.as_mut() {
-                    let _ = res
-                        .headers_mut()
-                        .insert(http::AMZ_REQUEST_ID.try_into().unwrap(), amz_request_id);
-                }
-                res
+                let mut res = res.await?;
+                let _ = res
+                    .headers_mut()
+                    .insert(http::AMZ_REQUEST_ID.try_into().unwrap(), amz_request_id);
+                // The request is in flight until its body is sent.
+                Ok(res.map_body(move |_, body| InFlightBody {
+                    body,
+                    _in_flight: in_flight,
+                }))
             }
             .boxed_local(),
         )
     }
 }
 
+// Response body holding the in-flight guard of its request.
+#[pin_project::pin_project]
+pub struct InFlightBody<B> {
+    #[pin]
+    body: B,
+    _in_flight: signals::InFlightGuard<'static>,
+}
+
+impl<B: MessageBody> MessageBody for InFlightBody<B> {
+    type Error = B::Error;
+
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        self.project().body.poll_next(cx)
+    }
+}
+
 fn contains_reserved_metadata(headers: &HeaderMap) -> bool {
     for k in headers.keys() {
         if k.as_str()
@@ -290,3 +327,131 @@ fn is_header_size_too_large(headers: &HeaderMap) -> bool {
     }
     user_size > MAX_USER_DATA_SIZE || size > MAX_HEADER_SIZE
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::{header, StatusCode};
+    use actix_web::test::{init_service, read_body, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+
+    use super::*;
+    use crate::http::RequestExtensions;
+
+    const PUBLIC_READ_POLICY: &str = r#"{
+        "Version": "2012-10-17",
+        "Statement": [
+            {
+                "Effect": "Allow",
+                "Principal": {"AWS": ["*"]},
+                "Action": ["s3:GetObject"],
+                "Resource": ["arn:aws:s3:::anonymous-public/*"]
+            }
+        ]
+    }"#;
+
+    fn status<B>(res: Result<ServiceResponse<B>, Error>) -> StatusCode {
+        match res {
+            Ok(res) => res.status(),
+            Err(err) => err.as_response_error().status_code(),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_generic_handlers_in_flight() {
+        GLOBALS.iam.set_bucket_policy(
+            "in-flight-public",
+            serde_json::from_str(
+                &PUBLIC_READ_POLICY.replace("anonymous-public", "in-flight-public"),
+            )
+            .unwrap(),
+        );
+        let app = init_service(
+            App::new()
+                .wrap(GenericHandlers {})
+                .default_service(web::to(|| HttpResponse::Ok().body("data"))),
+        )
+        .await;
+        let req = TestRequest::get()
+            .uri("http://localhost/in-flight-public/a.txt")
+            .insert_header((header::HOST, "localhost"))
+            .to_srv_request();
+        req.extensions_mut().insert(RequestExtensions::default());
+        let res = app.call(req).await.unwrap();
+        // The request is still in flight while its body is not sent.
+        assert!(signals::IN_FLIGHT_REQUESTS.count() >= 1);
+        assert_eq!(read_body(res).await, "data");
+    }
+
+    #[actix_rt::test]
+    async fn test_generic_handlers_anonymous() {
+        GLOBALS.iam.set_bucket_policy(
+            "anonymous-public",
+            serde_json::from_str(PUBLIC_READ_POLICY).unwrap(),
+        );
+        let app = init_service(
+            App::new()
+                .wrap(GenericHandlers {})
+                .default_service(web::to(|| HttpResponse::Ok())),
+        )
+        .await;
+
+        let request = |method: Method, uri: &str| {
+            let req = TestRequest::default()
+                .method(method)
+                .uri(uri)
+                .insert_header((header::HOST, "localhost"))
+                .to_srv_request();
+            req.extensions_mut().insert(RequestExtensions::default());
+            req
+        };
+
+        let res = app
+            .call(request(
+                Method::GET,
+                "http://localhost/anonymous-public/a.txt",
+            ))
+            .await;
+        assert_eq!(status(res), StatusCode::OK);
+
+        // Only reads are granted.
+        let res = app
+            .call(request(
+                Method::PUT,
+                "http://localhost/anonymous-public/a.txt",
+            ))
+            .await;
+        assert_eq!(status(res), StatusCode::FORBIDDEN);
+        let res = app
+            .call(request(Method::GET, "http://localhost/anonymous-public"))
+            .await;
+        assert_eq!(status(res), StatusCode::FORBIDDEN);
+
+        // Bucket without policy.
+        let res = app
+            .call(request(
+                Method::GET,
+                "http://localhost/anonymous-private/a.txt",
+            ))
+            .await;
+        assert_eq!(status(res), StatusCode::FORBIDDEN);
+
+        // STS form posts carry their credentials in the body.
+        let req = TestRequest::post()
+            .uri("http://localhost/")
+            .insert_header((header::HOST, "localhost"))
+            .insert_header((header::CONTENT_TYPE, "application/x-www-form-urlencoded"))
+            .set_payload("Action=AssumeRoleWithWebIdentity&Version=2011-06-15")
+            .to_srv_request();
+        req.extensions_mut().insert(RequestExtensions::default());
+        assert_eq!(status(app.call(req).await), StatusCode::OK);
+
+        GLOBALS.iam.delete_bucket_policy("anonymous-public");
+        let res = app
+            .call(request(
+                Method::GET,
+                "http://localhost/anonymous-public/a.txt",
+            ))
+            .await;
+        assert_eq!(status(res), StatusCode::FORBIDDEN);
+    }
+}
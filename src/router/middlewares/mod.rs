@@ -1,3 +1,4 @@
+mod bucket_extractor;
 mod cors;
 mod custom_headers;
 mod generic_handlers;
@@ -5,6 +6,7 @@ mod max_clients;
 mod reqinfo;
 mod trace;
 
+pub use bucket_extractor::*;
 pub use cors::*;
 pub use custom_headers::*;
 pub use generic_handlers::*;
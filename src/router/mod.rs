@@ -1,6 +1,7 @@
 pub use admin_router::*;
 pub use api_config::*;
 pub use healthcheck_router::*;
+pub use host::*;
 pub use metrics_router::*;
 pub use router::*;
 pub use utils::*;
@@ -8,6 +9,7 @@ pub use utils::*;
 mod admin_router;
 mod api_config;
 mod healthcheck_router;
+mod host;
 mod metrics_router;
 pub mod middlewares;
 mod router;
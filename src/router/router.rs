@@ -1,4 +1,4 @@
-use std::sync::MutexGuard;
+use std::sync::Arc;
 
 use actix_http::body::MessageBody;
 use actix_web::dev::ServiceRequest;
@@ -6,50 +6,60 @@ use actix_web::guard::get_host_uri;
 use actix_web::{guard, web, App};
 
 use super::*;
-use crate::globals::{self, Guard, GLOBALS};
+use crate::globals::{self, ReadWriteGuard, GLOBALS};
 use crate::utils::Duration;
-use crate::{object, objectcache};
+use crate::{http, object, objectcache};
 
 struct Api {}
 
 impl Api {
-    fn object_api() -> MutexGuard<'static, Option<object::ObjectLayer>> {
+    fn object_api() -> Option<Arc<object::ObjectLayer>> {
         object::get_object_layer()
     }
 
-    fn cache_object_api() -> MutexGuard<'static, Option<objectcache::CacheObjectLayer>> {
+    fn cache_object_api() -> Option<Arc<objectcache::CacheObjectLayer>> {
         objectcache::get_cache_layer()
     }
 }
 
+// Guard matching virtual-host-style requests, the bucket is stored in the
+// request extensions.
+fn host_bucket_guard(resolver: Arc<HostBucketResolver>) -> impl guard::Guard {
+    guard::fn_guard(move |req| {
+        let bucket =
+            get_host_uri(req).and_then(|uri| uri.host().and_then(|host| resolver.resolve(host)));
+        match bucket {
+            Some(bucket) => {
+                req.extensions_mut().insert(bucket); // TODO
+                true
+            }
+            None => false,
+        }
+    })
+}
+
 // Configure server http handler.
 pub fn configure_server_handler(
+    readiness: web::Data<http::ReadinessCheck>,
 ) -> anyhow::Result<App<impl actix_service::ServiceFactory<ServiceRequest>, impl MessageBody>> {
-    let mut app = App::new();
+    // Health checks are registered before the bucket scopes, which would
+    // match their paths otherwise.
+    let mut app = App::new()
+        .app_data(readiness)
+        .configure(register_health_check_router);
 
     let mut scopes = Vec::new();
-    for domain_name in GLOBALS.domain_ips.guard().iter() {
-        let host_re = regex::Regex::new(&format!(r#"^(.+)\.{}$"#, regex::escape(domain_name)))?;
-        let reserved_host = format!("{}.{}", globals::SYSTEM_RESERVED_BUCKET, domain_name);
-        let scope = web::scope("/").guard(guard::fn_guard(move |req| {
-            if let Some(uri) = get_host_uri(req) {
-                if let Some(uri_host) = uri.host() {
-                    // Reserve hulk.<namespace>.svc.<cluster_domain> if in Kubernetes.
-                    if *globals::IS_KUBERNETES && uri_host == reserved_host {
-                        return false;
-                    }
-                    // Allow <bucket>.<namespace>.svc.<cluster_domain> and extract bucket.
-                    if let Some(caps) = host_re.captures(uri_host) {
-                        let bucket = caps.get(1).unwrap().as_str();
-                        req.extensions_mut().insert(bucket.to_owned()); // TODO
-                        return true;
-                    }
-                }
-            }
-            false
-        }));
-        scopes.push(scope);
-    }
+    let domain_names = GLOBALS.domain_names.guard().clone();
+    let resolver = if !domain_names.is_empty() {
+        let resolver = Arc::new(HostBucketResolver::new(
+            &domain_names,
+            *globals::IS_KUBERNETES,
+        )?);
+        scopes.push(web::scope("/").guard(host_bucket_guard(resolver.clone())));
+        Some(resolver)
+    } else {
+        None
+    };
     scopes.push(web::scope("/{bucket}"));
 
     for scope in scopes {
@@ -57,6 +67,7 @@ pub fn configure_server_handler(
     }
 
     let app = app
+        .wrap(middlewares::BucketExtractor::new(resolver))
         .wrap(middlewares::GenericHandlers {})
         .wrap(middlewares::cors())
         .wrap(middlewares::Trace::new())
@@ -65,3 +76,33 @@ pub fn configure_server_handler(
 
     Ok(app)
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::guard::Guard as _;
+    use actix_web::http::header;
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    #[test]
+    fn test_host_bucket_guard() {
+        let resolver = HostBucketResolver::new(&["domain.com"], true).unwrap();
+        let guard = host_bucket_guard(Arc::new(resolver));
+
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "mybucket.domain.com"))
+            .to_http_request();
+        assert!(guard.check(req.head()));
+        assert_eq!(
+            req.extensions().get::<String>().map(String::as_str),
+            Some("mybucket")
+        );
+
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "hulk.domain.com"))
+            .to_http_request();
+        assert!(!guard.check(req.head()));
+        assert!(req.extensions().get::<String>().is_none());
+    }
+}
@@ -3,6 +3,7 @@ use std::borrow::Cow;
 use actix_web::HttpRequest;
 
 use crate::globals::{self, Guard, ReadWriteGuard, GLOBALS};
+use crate::http;
 
 // Returns "/<bucket>/<object>" for path-style or virtual-host-style requests.
 pub fn get_resource<'a>(
@@ -23,7 +24,7 @@ pub fn get_resource<'a>(
         if host == &format!("{}.{}", globals::SYSTEM_RESERVED_BUCKET, domain) {
             continue;
         }
-        if let Some(bucket) = host.strip_suffix(&format!("{}.{}", host, domain)) {
+        if let Some(bucket) = host.strip_suffix(&format!(".{}", domain)) {
             return Ok(Cow::Owned(format!(
                 "{}{}",
                 globals::SLASH_SEPARATOR,
@@ -67,3 +68,18 @@ pub fn path_to_bucket_object_with_base_path<'a>(
     let path = splits.next().unwrap();
     (path, splits.next().unwrap_or(""))
 }
+
+// Returns whether the bucket is reserved for internal use.
+pub fn is_reserved_bucket(bucket: &str) -> bool {
+    bucket == globals::SYSTEM_RESERVED_BUCKET || bucket == crate::object::SYSTEM_META_BUCKET
+}
+
+// Returns whether the request is an internal one, which is allowed to
+// access the reserved buckets.
+pub fn is_internal_request(req: &HttpRequest) -> bool {
+    http::guess_is_rpc_req(req)
+        || http::guess_is_browser_req(req)
+        || http::guess_is_health_check_req(req)
+        || http::guess_is_metrics_req(req)
+        || http::guess_is_admin_req(req)
+}
@@ -169,7 +169,12 @@ impl WrappedChannel {
                 }
 
                 tokio::select! {
-                    _ = utils::sleep(inner.health_check_interval, Some(&mut rng)) => {},
+                    _ = utils::sleep_jitter(
+                        inner.health_check_interval,
+                        0.5,
+                        1.5,
+                        Some(&mut rng),
+                    ) => {},
                     _ = inner.rx.notified() => {
                         break;
                     },
@@ -9,6 +9,7 @@ pub use storage_client::*;
 pub use storage_server::*;
 use tonic::{Request, Status};
 
+use crate::auth::secure_compare;
 use crate::globals::{Guard, ReadWriteGuard, GLOBALS};
 use crate::utils::{self, DateTimeExt, DateTimeFormatExt};
 
@@ -36,11 +37,17 @@ pub fn validate_request(req: Request<()>) -> Result<Request<()>, Status> {
 
     let token = get_str("authorization")?;
     let active_cred = GLOBALS.active_cred.guard();
-    let claims = crate::jwt::parse_with_standard_claims(token, active_cred.secret_key.as_bytes())
-        .map_err(|_| Status::unauthenticated(NO_AUTH_TOKEN))?;
+    let claims = crate::jwt::parse_with_standard_claims(
+        token,
+        active_cred.secret_key.as_bytes(),
+        *crate::jwt::JWT_LEEWAY,
+    )
+    .map_err(|_| Status::unauthenticated(NO_AUTH_TOKEN))?;
 
-    let owner =
-        claims.access_key == active_cred.access_key || claims.subject == active_cred.access_key;
+    let owner = secure_compare(
+        claims.access_key.as_bytes(),
+        active_cred.access_key.as_bytes(),
+    ) || secure_compare(claims.subject.as_bytes(), active_cred.access_key.as_bytes());
     if !owner {
         return Err(Status::unauthenticated(NO_AUTH_TOKEN));
     }
@@ -0,0 +1,127 @@
+use std::io::Write;
+
+use super::CSVOutput;
+
+const DEFAULT_FIELD_DELIMITER: &str = ",";
+const DEFAULT_QUOTE_CHARACTER: &str = "\"";
+const DEFAULT_RECORD_DELIMITER: &str = "\r\n";
+const QUOTE_FIELDS_ALWAYS: &str = "ALWAYS";
+
+impl CSVOutput {
+    pub fn field_delimiter(&self) -> &str {
+        non_empty_or(&self.field_delimiter, DEFAULT_FIELD_DELIMITER)
+    }
+
+    pub fn quote_character(&self) -> &str {
+        non_empty_or(&self.quote_character, DEFAULT_QUOTE_CHARACTER)
+    }
+
+    /// Defaults to the quote character, i.e. quotes are escaped by doubling them.
+    pub fn quote_escape_character(&self) -> &str {
+        non_empty_or(&self.quote_escape_character, self.quote_character())
+    }
+
+    pub fn record_delimiter(&self) -> &str {
+        non_empty_or(&self.record_delimiter, DEFAULT_RECORD_DELIMITER)
+    }
+
+    fn quote_always(&self) -> bool {
+        self.quote_fields
+            .as_deref()
+            .map_or(false, |q| q.eq_ignore_ascii_case(QUOTE_FIELDS_ALWAYS))
+    }
+
+    /// Writes a single result row. Fields are quoted when `QuoteFields` is
+    /// `ALWAYS`, or when they contain a delimiter, quote or line break.
+    pub fn write_record<W: Write>(&self, w: &mut W, fields: &[&str]) -> std::io::Result<()> {
+        let field_delimiter = self.field_delimiter();
+        let quote = self.quote_character();
+        let record_delimiter = self.record_delimiter();
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                w.write_all(field_delimiter.as_bytes())?;
+            }
+            let need_quote = self.quote_always()
+                || field.contains(field_delimiter)
+                || field.contains(quote)
+                || field.contains(record_delimiter)
+                || field.contains(|c| c == '\r' || c == '\n');
+            if !need_quote {
+                w.write_all(field.as_bytes())?;
+                continue;
+            }
+            let escaped_quote = self.quote_escape_character().to_owned() + quote;
+            w.write_all(quote.as_bytes())?;
+            w.write_all(field.replace(quote, &escaped_quote).as_bytes())?;
+            w.write_all(quote.as_bytes())?;
+        }
+        w.write_all(record_delimiter.as_bytes())
+    }
+}
+
+fn non_empty_or<'a>(value: &'a Option<String>, default: &'a str) -> &'a str {
+    match value {
+        Some(value) if !value.is_empty() => value,
+        _ => default,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn csv_output(field_delimiter: &str, quote_fields: Option<&str>) -> CSVOutput {
+        CSVOutput {
+            field_delimiter: Some(field_delimiter.to_owned()),
+            quote_character: None,
+            quote_escape_character: None,
+            quote_fields: quote_fields.map(|q| q.to_owned()),
+            record_delimiter: Some("\n".to_owned()),
+        }
+    }
+
+    fn write_records(output: &CSVOutput, records: &[&[&str]]) -> String {
+        let mut buf = Vec::new();
+        for record in records {
+            output.write_record(&mut buf, record).unwrap();
+        }
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_csv_output_defaults() {
+        let output = CSVOutput {
+            field_delimiter: None,
+            quote_character: None,
+            quote_escape_character: None,
+            quote_fields: None,
+            record_delimiter: None,
+        };
+        assert_eq!(
+            write_records(&output, &[&["a", "b,c", "say \"hi\""]]),
+            "a,\"b,c\",\"say \"\"hi\"\"\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_output_pipe_delimited() {
+        let output = csv_output("|", None);
+        assert_eq!(
+            write_records(&output, &[&["1", "alice", "a,b"], &["2", "bob|smith", ""]]),
+            "1|alice|a,b\n2|\"bob|smith\"|\n"
+        );
+
+        let output = csv_output("|", Some("ALWAYS"));
+        assert_eq!(write_records(&output, &[&["1", "x"]]), "\"1\"|\"x\"\n");
+
+        let output = CSVOutput {
+            quote_character: Some("'".to_owned()),
+            quote_escape_character: Some("\\".to_owned()),
+            ..csv_output("|", None)
+        };
+        assert_eq!(
+            write_records(&output, &[&["it's", "line\nbreak"]]),
+            "'it\\'s'|'line\nbreak'\n"
+        );
+    }
+}
@@ -0,0 +1,340 @@
+use std::io::{BufRead, BufReader, Read};
+
+use anyhow::bail;
+use serde_json::Value;
+
+use super::JSONInput;
+
+const JSON_TYPE_DOCUMENT: &str = "DOCUMENT";
+const JSON_TYPE_LINES: &str = "LINES";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum JSONType {
+    /// The object is a single JSON document, records are the elements of
+    /// its root array, or the root itself.
+    Document,
+    /// The object holds one JSON record per line.
+    Lines,
+}
+
+impl JSONInput {
+    /// Defaults to `Document` when no type is given.
+    pub fn json_type(&self) -> anyhow::Result<JSONType> {
+        match self.type_.as_deref() {
+            None | Some("") => Ok(JSONType::Document),
+            Some(t) if t.eq_ignore_ascii_case(JSON_TYPE_DOCUMENT) => Ok(JSONType::Document),
+            Some(t) if t.eq_ignore_ascii_case(JSON_TYPE_LINES) => Ok(JSONType::Lines),
+            Some(t) => bail!("invalid JSON type '{}'", t),
+        }
+    }
+}
+
+/// A JSON record, whose fields are looked up by path.
+#[derive(PartialEq, Debug)]
+pub struct JSONRecord(Value);
+
+impl JSONRecord {
+    pub fn value(&self) -> &Value {
+        &self.0
+    }
+
+    pub fn into_value(self) -> Value {
+        self.0
+    }
+
+    /// Looks up a field by a dotted path, where array elements are selected
+    /// with an index, e.g. `address.city` or `phones[0].number`.
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        let mut value = &self.0;
+        for segment in path.split('.') {
+            let (name, mut indexes) = match segment.find('[') {
+                Some(i) => segment.split_at(i),
+                None => (segment, ""),
+            };
+            if !name.is_empty() {
+                value = value.get(name)?;
+            }
+            while !indexes.is_empty() {
+                let end = indexes.find(']')?;
+                let index: usize = indexes[1..end].trim().parse().ok()?;
+                value = value.get(index)?;
+                indexes = &indexes[end + 1..];
+                if !indexes.is_empty() && !indexes.starts_with('[') {
+                    return None;
+                }
+            }
+        }
+        Some(value)
+    }
+}
+
+impl From<Value> for JSONRecord {
+    fn from(value: Value) -> JSONRecord {
+        JSONRecord(value)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DocumentState {
+    Start,
+    // Within the root array.
+    Array,
+    Done,
+}
+
+// Reads the records of a JSON document. The elements of a root array are
+// read one at a time, so that only one record is held in memory.
+struct DocumentReader<R: BufRead> {
+    reader: R,
+    state: DocumentState,
+}
+
+impl<R: BufRead> DocumentReader<R> {
+    fn peek(&mut self) -> std::io::Result<Option<u8>> {
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    // Returns the first byte which is not a whitespace, without consuming it.
+    fn skip_whitespace(&mut self) -> std::io::Result<Option<u8>> {
+        while let Some(b) = self.peek()? {
+            if !b.is_ascii_whitespace() {
+                return Ok(Some(b));
+            }
+            self.reader.consume(1);
+        }
+        Ok(None)
+    }
+
+    fn next_record(&mut self) -> anyhow::Result<Option<Value>> {
+        match self.state {
+            DocumentState::Start => {
+                if self.skip_whitespace()? != Some(b'[') {
+                    // The root itself is the only record.
+                    self.state = DocumentState::Done;
+                    return Ok(Some(serde_json::from_reader(&mut self.reader)?));
+                }
+                self.reader.consume(1);
+                self.state = DocumentState::Array;
+                if self.skip_whitespace()? == Some(b']') {
+                    self.reader.consume(1);
+                    self.end()?;
+                    return Ok(None);
+                }
+                self.read_element()
+            }
+            DocumentState::Array => self.read_element(),
+            DocumentState::Done => Ok(None),
+        }
+    }
+
+    // Reads the next element of the root array, up to the following comma
+    // or closing bracket.
+    fn read_element(&mut self) -> anyhow::Result<Option<Value>> {
+        let mut buf = Vec::new();
+        let mut depth = 0usize;
+        let (mut in_string, mut escaped) = (false, false);
+        let last = loop {
+            let b = match self.peek()? {
+                Some(b) => b,
+                None => bail!("unexpected end of JSON document"),
+            };
+            self.reader.consume(1);
+            if in_string {
+                if escaped {
+                    escaped = false;
+                } else if b == b'\\' {
+                    escaped = true;
+                } else if b == b'"' {
+                    in_string = false;
+                }
+            } else {
+                match b {
+                    b'"' => in_string = true,
+                    b'[' | b'{' => depth += 1,
+                    b',' if depth == 0 => break false,
+                    b']' if depth == 0 => break true,
+                    b']' | b'}' if depth > 0 => depth -= 1,
+                    _ => {}
+                }
+            }
+            buf.push(b);
+        };
+        let value = serde_json::from_slice(&buf)?;
+        if last {
+            self.end()?;
+        }
+        Ok(Some(value))
+    }
+
+    // Checks that nothing follows the root array.
+    fn end(&mut self) -> anyhow::Result<()> {
+        self.state = DocumentState::Done;
+        if self.skip_whitespace()?.is_some() {
+            bail!("trailing characters after the JSON document");
+        }
+        Ok(())
+    }
+}
+
+enum JSONRecordReaderInner<R: Read> {
+    Document(DocumentReader<BufReader<R>>),
+    Lines(serde_json::StreamDeserializer<'static, serde_json::de::IoRead<BufReader<R>>, Value>),
+}
+
+/// Reads JSON records from an object, per its `JSONType`.
+pub struct JSONRecordReader<R: Read> {
+    inner: JSONRecordReaderInner<R>,
+}
+
+impl<R: Read> JSONRecordReader<R> {
+    pub fn new(reader: R, json_type: JSONType) -> JSONRecordReader<R> {
+        let reader = BufReader::new(reader);
+        let inner = match json_type {
+            JSONType::Document => JSONRecordReaderInner::Document(DocumentReader {
+                reader,
+                state: DocumentState::Start,
+            }),
+            JSONType::Lines => JSONRecordReaderInner::Lines(
+                serde_json::Deserializer::from_reader(reader).into_iter(),
+            ),
+        };
+        JSONRecordReader { inner }
+    }
+}
+
+impl<R: Read> Iterator for JSONRecordReader<R> {
+    type Item = anyhow::Result<JSONRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            JSONRecordReaderInner::Document(reader) => match reader.next_record() {
+                Ok(value) => value.map(|v| Ok(JSONRecord(v))),
+                Err(err) => {
+                    // The document is malformed, stop there.
+                    reader.state = DocumentState::Done;
+                    Some(Err(err))
+                }
+            },
+            JSONRecordReaderInner::Lines(values) => values
+                .next()
+                .map(|v| v.map(JSONRecord).map_err(|err| err.into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn json_input(type_: Option<&str>) -> JSONInput {
+        JSONInput {
+            type_: type_.map(|t| t.to_owned()),
+        }
+    }
+
+    #[test]
+    fn test_json_input_type() {
+        assert_eq!(json_input(None).json_type().unwrap(), JSONType::Document);
+        assert_eq!(
+            json_input(Some("DOCUMENT")).json_type().unwrap(),
+            JSONType::Document
+        );
+        assert_eq!(
+            json_input(Some("lines")).json_type().unwrap(),
+            JSONType::Lines
+        );
+        assert!(json_input(Some("csv")).json_type().is_err());
+    }
+
+    #[test]
+    fn test_json_record_reader_lines() {
+        let data = r#"{"id":1,"name":"alice","address":{"city":"paris"}}
+{"id":2,"name":"bob","address":{"city":"berlin"},"phones":[{"number":"123"}]}
+
+{"id":3,"name":"carol","address":{"city":"paris"}}
+"#;
+        let reader = JSONRecordReader::new(data.as_bytes(), JSONType::Lines);
+        let records: Vec<_> = reader.map(|r| r.unwrap()).collect();
+        assert_eq!(records.len(), 3);
+
+        // SELECT s.name FROM S3Object s
+        let names: Vec<_> = records.iter().map(|r| r.get("name").unwrap()).collect();
+        assert_eq!(names, vec!["alice", "bob", "carol"]);
+
+        // SELECT s.id FROM S3Object s WHERE s.address.city = 'paris'
+        let ids: Vec<_> = records
+            .iter()
+            .filter(|r| r.get("address.city") == Some(&json!("paris")))
+            .map(|r| r.get("id").unwrap())
+            .collect();
+        assert_eq!(ids, vec![1, 3]);
+
+        assert_eq!(records[1].get("phones[0].number"), Some(&json!("123")));
+        assert_eq!(records[1].get("phones[1].number"), None);
+        assert_eq!(records[0].get("address.zip"), None);
+
+        let data = "{\"id\":1}\n{\"id\":";
+        let mut reader = JSONRecordReader::new(data.as_bytes(), JSONType::Lines);
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_json_record_reader_document() {
+        let data = r#"[{"id":1,"tags":["a","b"]},{"id":2,"tags":[]}]"#;
+        let reader = JSONRecordReader::new(data.as_bytes(), JSONType::Document);
+        let records: Vec<_> = reader.map(|r| r.unwrap()).collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].get("tags[1]"), Some(&json!("b")));
+
+        let data = r#"{"id":1,"owner":{"name":"alice"}}"#;
+        let reader = JSONRecordReader::new(data.as_bytes(), JSONType::Document);
+        let records: Vec<_> = reader.map(|r| r.unwrap()).collect();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].get("owner.name"), Some(&json!("alice")));
+
+        let mut reader = JSONRecordReader::new(&b"{\"id\":"[..], JSONType::Document);
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+
+        let data = r#" [ {"s":"a],\"b","n":[1,{"m":2}]}, 3 ,"x" ] "#;
+        let reader = JSONRecordReader::new(data.as_bytes(), JSONType::Document);
+        let values: Vec<_> = reader.map(|r| r.unwrap().into_value()).collect();
+        assert_eq!(
+            values,
+            vec![
+                json!({"s": "a],\"b", "n": [1, {"m": 2}]}),
+                json!(3),
+                json!("x")
+            ]
+        );
+
+        let reader = JSONRecordReader::new(&b" [ ] "[..], JSONType::Document);
+        assert_eq!(reader.count(), 0);
+
+        for data in ["[1,]", "[1,,2]", "[1] 2", "[1", "[}]"] {
+            let reader = JSONRecordReader::new(data.as_bytes(), JSONType::Document);
+            assert!(reader.last().unwrap().is_err(), "{}", data);
+        }
+    }
+
+    #[test]
+    fn test_json_record_reader_document_streaming() {
+        // Records are read as the document is, a truncated document yields
+        // the records before the truncation.
+        let data = r#"[{"id":1},{"id":2},{"id":"#;
+        let mut reader = JSONRecordReader::new(data.as_bytes(), JSONType::Document);
+        assert_eq!(
+            reader.next().unwrap().unwrap().into_value(),
+            json!({"id": 1})
+        );
+        assert_eq!(
+            reader.next().unwrap().unwrap().into_value(),
+            json!({"id": 2})
+        );
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+}
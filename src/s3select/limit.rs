@@ -0,0 +1,186 @@
+use std::convert::TryFrom;
+
+use anyhow::bail;
+
+const LIMIT_KEYWORD: &str = "LIMIT";
+
+/// Splits a trailing `LIMIT N` clause off a select expression, returning
+/// the remaining expression and the limit, if any.
+pub fn split_limit(expression: &str) -> anyhow::Result<(&str, Option<u64>)> {
+    let expression = expression.trim_end().trim_end_matches(';').trim_end();
+    let tokens = tokenize(expression);
+    let n = tokens.len();
+    if n >= 1 && is_limit_keyword(tokens[n - 1].1) {
+        bail!("missing LIMIT value");
+    }
+    if n >= 2 && is_limit_keyword(tokens[n - 2].1) {
+        let limit = parse_limit(tokens[n - 1].1)?;
+        return Ok((expression[..tokens[n - 2].0].trim_end(), Some(limit)));
+    }
+    // A LIMIT clause anywhere else than at the end is rejected rather than
+    // being taken as part of the expression.
+    if tokens
+        .windows(2)
+        .any(|w| is_limit_keyword(w[0].1) && w[1].1.starts_with(|c: char| c.is_ascii_digit()))
+    {
+        bail!("LIMIT must be the last clause of the expression");
+    }
+    Ok((expression, None))
+}
+
+fn is_limit_keyword(token: &str) -> bool {
+    token.eq_ignore_ascii_case(LIMIT_KEYWORD)
+}
+
+fn parse_limit(value: &str) -> anyhow::Result<u64> {
+    // `u64::from_str` also accepts a leading '+'.
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        bail!("invalid LIMIT value '{}'", value);
+    }
+    value
+        .parse::<u64>()
+        .map_err(|_| anyhow::anyhow!("LIMIT value '{}' is out of range", value))
+}
+
+// Splits an expression on whitespace outside of quoted literals, returning
+// each token with its byte offset.
+fn tokenize(expression: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let mut quote = None;
+    for (i, c) in expression.char_indices() {
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+            }
+            None if c.is_whitespace() => {
+                if let Some(s) = start.take() {
+                    tokens.push((s, &expression[s..i]));
+                }
+            }
+            None => {
+                start.get_or_insert(i);
+                if c == '\'' || c == '"' {
+                    quote = Some(c);
+                }
+            }
+        }
+    }
+    if let Some(s) = start {
+        tokens.push((s, &expression[s..]));
+    }
+    tokens
+}
+
+/// Applies an optional limit to matching records. Once the limit is reached
+/// the underlying records are no longer pulled, so the object read stops early.
+pub fn limit_records<I: Iterator>(records: I, limit: Option<u64>) -> impl Iterator<Item = I::Item> {
+    let limit = limit.map_or(usize::MAX, |n| usize::try_from(n).unwrap_or(usize::MAX));
+    records.take(limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::io::BufRead;
+
+    use super::*;
+
+    #[test]
+    fn test_split_limit() {
+        let cases = vec![
+            (
+                "SELECT * FROM S3Object LIMIT 3",
+                "SELECT * FROM S3Object",
+                Some(3),
+            ),
+            (
+                "select * from S3Object s where s._1 > 2 limit 10;",
+                "select * from S3Object s where s._1 > 2",
+                Some(10),
+            ),
+            ("SELECT * FROM S3Object", "SELECT * FROM S3Object", None),
+            (
+                "SELECT limit FROM S3Object",
+                "SELECT limit FROM S3Object",
+                None,
+            ),
+        ];
+        for (i, (expression, expected, expected_limit)) in cases.into_iter().enumerate() {
+            let (rest, limit) = split_limit(expression).unwrap();
+            assert_eq!(rest, expected, "test {}", i + 1);
+            assert_eq!(limit, expected_limit, "test {}", i + 1);
+        }
+
+        assert!(split_limit("SELECT * FROM S3Object LIMIT -1").is_err());
+        assert!(split_limit("SELECT * FROM S3Object LIMIT x").is_err());
+    }
+
+    #[test]
+    fn test_split_limit_quoted() {
+        let expression = "SELECT * FROM S3Object s WHERE s._2 = ' LIMIT 3'";
+        assert_eq!(split_limit(expression).unwrap(), (expression, None));
+
+        let expression = "SELECT * FROM S3Object s WHERE s._2 = 'it''s LIMIT' LIMIT 2";
+        assert_eq!(
+            split_limit(expression).unwrap(),
+            (
+                "SELECT * FROM S3Object s WHERE s._2 = 'it''s LIMIT'",
+                Some(2)
+            )
+        );
+    }
+
+    #[test]
+    fn test_split_limit_malformed() {
+        let cases = [
+            "SELECT * FROM S3Object LIMIT",
+            "SELECT * FROM S3Object LIMIT;",
+            "SELECT * FROM S3Object LIMIT +3",
+            "SELECT * FROM S3Object LIMIT 3.5",
+            "SELECT * FROM S3Object LIMIT 1e3",
+            "SELECT * FROM S3Object LIMIT 0x10",
+            "SELECT * FROM S3Object LIMIT 3abc",
+            "SELECT * FROM S3Object LIMIT 99999999999999999999999",
+            "SELECT * FROM S3Object LIMIT 3 4",
+            "SELECT * FROM S3Object LIMIT 3 WHERE _1 > 2",
+            "SELECT * FROM S3Object LIMIT '3'",
+        ];
+        for (i, expression) in cases.iter().enumerate() {
+            assert!(split_limit(expression).is_err(), "test {}", i + 1);
+        }
+    }
+
+    #[test]
+    fn test_limit_records_stops_reading() {
+        let csv = (1..=10)
+            .map(|i| format!("{},name{}\n", i, i))
+            .collect::<String>();
+        let (_, limit) = split_limit("SELECT * FROM S3Object LIMIT 3").unwrap();
+
+        let read = Cell::new(0);
+        let records = csv.as_bytes().lines().map(|line| {
+            read.set(read.get() + 1);
+            line.unwrap()
+        });
+        let rows: Vec<String> = limit_records(records, limit).collect();
+        assert_eq!(rows, vec!["1,name1", "2,name2", "3,name3"]);
+        assert_eq!(read.get(), 3);
+
+        // Composes with a WHERE filter.
+        let read = Cell::new(0);
+        let records = csv
+            .as_bytes()
+            .lines()
+            .map(|line| {
+                read.set(read.get() + 1);
+                line.unwrap()
+            })
+            .filter(|line| line.split(',').next().unwrap().parse::<u32>().unwrap() % 2 == 0);
+        let rows: Vec<String> = limit_records(records, Some(2)).collect();
+        assert_eq!(rows, vec!["2,name2", "4,name4"]);
+        assert_eq!(read.get(), 4);
+    }
+}
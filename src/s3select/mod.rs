@@ -1,3 +1,10 @@
+mod csv_output;
+mod json_reader;
+mod limit;
+mod parquet_reader;
 mod select;
 
+pub use json_reader::*;
+pub use limit::*;
+pub use parquet_reader::*;
 pub use select::*;
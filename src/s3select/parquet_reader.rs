@@ -0,0 +1,338 @@
+use anyhow::{bail, ensure};
+use parquet::column::reader::{ColumnReader, ColumnReaderImpl};
+use parquet::data_type::DataType;
+use parquet::file::reader::{ChunkReader, FileReader, RowGroupReader, SerializedFileReader};
+use serde_json::{Map, Value};
+
+use super::JSONRecord;
+
+/// Reads the rows of a Parquet object as records, so they are queried the
+/// same way as JSON ones. Row groups are read one at a time, and only the
+/// column chunks of the projected columns are loaded.
+///
+/// Only flat schemas are supported, with boolean, integer, floating point
+/// and string columns, which may be optional.
+pub struct ParquetRecordReader {
+    reader: Box<dyn FileReader>,
+    // Leaf index and name of the projected columns.
+    columns: Vec<(usize, String)>,
+    row_group: usize,
+    rows: std::vec::IntoIter<Map<String, Value>>,
+}
+
+impl ParquetRecordReader {
+    /// Reads only the given top-level `columns`, or all of them if empty.
+    pub fn new<R>(reader: R, columns: &[&str]) -> anyhow::Result<ParquetRecordReader>
+    where
+        R: ChunkReader + 'static,
+    {
+        let reader = SerializedFileReader::new(reader)?;
+        let schema = reader.metadata().file_metadata().schema_descr();
+        let mut projection = Vec::new();
+        for (i, column) in schema.columns().iter().enumerate() {
+            let parts = column.path().parts();
+            if !columns.is_empty() && !columns.contains(&parts[0].as_str()) {
+                continue;
+            }
+            ensure!(
+                parts.len() == 1 && column.max_rep_level() == 0,
+                "nested parquet column '{}' is not supported",
+                column.path()
+            );
+            projection.push((i, column.name().to_owned()));
+        }
+        if projection.is_empty() {
+            bail!(
+                "none of the columns {:?} exist in the parquet schema",
+                columns
+            );
+        }
+        Ok(ParquetRecordReader {
+            reader: Box::new(reader),
+            columns: projection,
+            row_group: 0,
+            rows: Vec::new().into_iter(),
+        })
+    }
+
+    fn read_row_group(&self, i: usize) -> anyhow::Result<Vec<Map<String, Value>>> {
+        let row_group = self.reader.get_row_group(i)?;
+        let num_rows = row_group.metadata().num_rows() as usize;
+        let mut columns = Vec::with_capacity(self.columns.len());
+        for (column, _) in &self.columns {
+            columns.push(read_column(&*row_group, *column, num_rows)?.into_iter());
+        }
+        let rows = (0..num_rows)
+            .map(|_| {
+                self.columns
+                    .iter()
+                    .zip(columns.iter_mut())
+                    .map(|((_, name), values)| (name.clone(), values.next().unwrap()))
+                    .collect()
+            })
+            .collect();
+        Ok(rows)
+    }
+}
+
+impl Iterator for ParquetRecordReader {
+    type Item = anyhow::Result<JSONRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(row) = self.rows.next() {
+                return Some(Ok(Value::Object(row).into()));
+            }
+            let num_row_groups = self.reader.num_row_groups();
+            if self.row_group >= num_row_groups {
+                return None;
+            }
+            match self.read_row_group(self.row_group) {
+                Ok(rows) => {
+                    self.rows = rows.into_iter();
+                    self.row_group += 1;
+                }
+                Err(err) => {
+                    // The object is corrupted, stop there.
+                    self.row_group = num_row_groups;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+// Reads the `num_rows` values of a column chunk, null for missing values
+// of optional columns.
+fn read_column(
+    row_group: &dyn RowGroupReader,
+    column: usize,
+    num_rows: usize,
+) -> anyhow::Result<Vec<Value>> {
+    let descr = row_group.metadata().column(column).column_descr_ptr();
+    let max_def_level = descr.max_def_level();
+    let values = match row_group.get_column_reader(column)? {
+        ColumnReader::BoolColumnReader(r) => {
+            read_values(r, num_rows, max_def_level, |v| Value::from(*v))
+        }
+        ColumnReader::Int32ColumnReader(r) => {
+            read_values(r, num_rows, max_def_level, |v| Value::from(*v))
+        }
+        ColumnReader::Int64ColumnReader(r) => {
+            read_values(r, num_rows, max_def_level, |v| Value::from(*v))
+        }
+        // NaN and infinities are mapped to null.
+        ColumnReader::FloatColumnReader(r) => {
+            read_values(r, num_rows, max_def_level, |v| Value::from(*v))
+        }
+        ColumnReader::DoubleColumnReader(r) => {
+            read_values(r, num_rows, max_def_level, |v| Value::from(*v))
+        }
+        ColumnReader::ByteArrayColumnReader(r) => read_values(r, num_rows, max_def_level, |v| {
+            Value::from(String::from_utf8_lossy(v.data()))
+        }),
+        _ => bail!(
+            "parquet column '{}' of type {} is not supported",
+            descr.name(),
+            descr.physical_type()
+        ),
+    };
+    values.map_err(|err| anyhow::anyhow!("reading parquet column '{}': {}", descr.name(), err))
+}
+
+fn read_values<T, F>(
+    mut reader: ColumnReaderImpl<T>,
+    num_rows: usize,
+    max_def_level: i16,
+    to_value: F,
+) -> anyhow::Result<Vec<Value>>
+where
+    T: DataType,
+    F: Fn(&T::T) -> Value,
+{
+    let mut values = vec![T::T::default(); num_rows];
+    let mut def_levels = vec![0; num_rows];
+    let mut out = Vec::with_capacity(num_rows);
+    while out.len() < num_rows {
+        let batch_size = num_rows - out.len();
+        let has_def_levels = max_def_level > 0;
+        let levels = if has_def_levels {
+            Some(&mut def_levels[..batch_size])
+        } else {
+            None
+        };
+        let (num_values, num_levels) =
+            reader.read_batch(batch_size, levels, None, &mut values[..batch_size])?;
+        let read = if has_def_levels {
+            num_levels
+        } else {
+            num_values
+        };
+        ensure!(
+            read > 0,
+            "column chunk ended after {} of {} rows",
+            out.len(),
+            num_rows
+        );
+        if has_def_levels {
+            let mut values = values[..num_values].iter();
+            for &level in &def_levels[..num_levels] {
+                match values.next() {
+                    Some(v) if level == max_def_level => out.push(to_value(v)),
+                    _ => out.push(Value::Null),
+                }
+            }
+        } else {
+            out.extend(values[..num_values].iter().map(&to_value));
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::sync::Arc;
+
+    use parquet::column::writer::ColumnWriter;
+    use parquet::data_type::ByteArray;
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::{FileWriter, RowGroupWriter, SerializedFileWriter};
+    use parquet::schema::parser::parse_message_type;
+    use serde_json::json;
+
+    use super::*;
+
+    const SCHEMA: &str = "
+        message schema {
+            REQUIRED INT64 id;
+            REQUIRED BYTE_ARRAY name (UTF8);
+            REQUIRED DOUBLE score;
+            REQUIRED BOOLEAN active;
+        }
+    ";
+
+    fn write_parquet(file: File) {
+        let schema = Arc::new(parse_message_type(SCHEMA).unwrap());
+        let props = Arc::new(WriterProperties::builder().build());
+        let mut writer = SerializedFileWriter::new(file, schema, props).unwrap();
+        let mut row_group_writer = writer.next_row_group().unwrap();
+        while let Some(mut column_writer) = row_group_writer.next_column().unwrap() {
+            match &mut column_writer {
+                ColumnWriter::Int64ColumnWriter(w) => {
+                    w.write_batch(&[1, 2, 3, 4], None, None).unwrap();
+                }
+                ColumnWriter::ByteArrayColumnWriter(w) => {
+                    let names: Vec<ByteArray> = ["alice", "bob", "carol", "dave"]
+                        .iter()
+                        .map(|&n| n.into())
+                        .collect();
+                    w.write_batch(&names, None, None).unwrap();
+                }
+                ColumnWriter::DoubleColumnWriter(w) => {
+                    w.write_batch(&[9.5, 7.0, 8.25, 6.5], None, None).unwrap();
+                }
+                ColumnWriter::BoolColumnWriter(w) => {
+                    w.write_batch(&[true, false, true, true], None, None)
+                        .unwrap();
+                }
+                _ => unreachable!(),
+            }
+            row_group_writer.close_column(column_writer).unwrap();
+        }
+        writer.close_row_group(row_group_writer).unwrap();
+        writer.close().unwrap();
+    }
+
+    #[test]
+    fn test_parquet_record_reader() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let path = dir.path().join("data.parquet");
+        write_parquet(File::create(&path).unwrap());
+
+        let reader = ParquetRecordReader::new(File::open(&path).unwrap(), &[]).unwrap();
+        let records: Vec<_> = reader.map(|r| r.unwrap()).collect();
+        assert_eq!(records.len(), 4);
+        assert_eq!(
+            records[0].value(),
+            &json!({"id": 1, "name": "alice", "score": 9.5, "active": true})
+        );
+
+        // SELECT s.name FROM S3Object s WHERE s.active AND s.score > 7
+        let reader =
+            ParquetRecordReader::new(File::open(&path).unwrap(), &["name", "score", "active"])
+                .unwrap();
+        let names: Vec<_> = reader
+            .map(|r| r.unwrap())
+            .filter(|r| {
+                r.get("active") == Some(&json!(true))
+                    && r.get("score").and_then(|s| s.as_f64()).unwrap() > 7.0
+            })
+            .map(|r| r.get("name").unwrap().clone())
+            .collect();
+        assert_eq!(names, vec!["alice", "carol"]);
+
+        // Columns which are not referenced are not read.
+        let reader = ParquetRecordReader::new(File::open(&path).unwrap(), &["name"]).unwrap();
+        for record in reader {
+            let record = record.unwrap();
+            assert_eq!(record.get("id"), None);
+            assert!(record.get("name").unwrap().is_string());
+        }
+
+        assert!(ParquetRecordReader::new(File::open(&path).unwrap(), &["missing"]).is_err());
+    }
+
+    #[test]
+    fn test_parquet_record_reader_optional() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let path = dir.path().join("data.parquet");
+        let schema = Arc::new(parse_message_type("message schema { OPTIONAL INT32 n; }").unwrap());
+        let props = Arc::new(WriterProperties::builder().build());
+        let file = File::create(&path).unwrap();
+        let mut writer = SerializedFileWriter::new(file, schema, props).unwrap();
+        let mut row_group_writer = writer.next_row_group().unwrap();
+        let mut column_writer = row_group_writer.next_column().unwrap().unwrap();
+        match &mut column_writer {
+            ColumnWriter::Int32ColumnWriter(w) => {
+                w.write_batch(&[1, 3], Some(&[1, 0, 1]), None).unwrap();
+            }
+            _ => unreachable!(),
+        }
+        row_group_writer.close_column(column_writer).unwrap();
+        writer.close_row_group(row_group_writer).unwrap();
+        writer.close().unwrap();
+
+        let reader = ParquetRecordReader::new(File::open(&path).unwrap(), &[]).unwrap();
+        let values: Vec<_> = reader.map(|r| r.unwrap().into_value()).collect();
+        assert_eq!(
+            values,
+            vec![json!({"n": 1}), json!({"n": null}), json!({"n": 3})]
+        );
+    }
+
+    #[test]
+    fn test_parquet_record_reader_corrupted() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let path = dir.path().join("data.parquet");
+        write_parquet(File::create(&path).unwrap());
+        let data = std::fs::read(&path).unwrap();
+
+        // A truncated object has no footer.
+        let truncated = dir.path().join("truncated.parquet");
+        std::fs::write(&truncated, &data[..data.len() / 2]).unwrap();
+        assert!(ParquetRecordReader::new(File::open(&truncated).unwrap(), &[]).is_err());
+
+        // The footer is intact but the first page header is zeroed, reading
+        // the rows fails instead of panicking.
+        let mut corrupted = data.clone();
+        for b in &mut corrupted[4..20] {
+            *b = 0;
+        }
+        let path = dir.path().join("corrupted.parquet");
+        std::fs::write(&path, &corrupted).unwrap();
+        let mut reader = ParquetRecordReader::new(File::open(&path).unwrap(), &[]).unwrap();
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+}
@@ -80,8 +80,10 @@ pub struct JSONOutput {
 #[serde(rename_all = "PascalCase")]
 pub struct OutputSerialization {
     /// <p>Describes the serialization of CSV-encoded Select results.</p>
+    #[serde(rename = "CSV")]
     pub csv: Option<CSVOutput>,
     /// <p>Specifies JSON as request's output serialization format.</p>
+    #[serde(rename = "JSON")]
     pub json: Option<JSONOutput>,
 }
 
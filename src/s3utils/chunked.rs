@@ -0,0 +1,269 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_util::ready;
+use tokio::io::{AsyncRead, ReadBuf};
+
+const CHUNK_SIGNATURE_EXT: &str = "chunk-signature=";
+// Longest chunk header accepted: 16 hex digits cover any u64, plus a
+// signature and some room for other extensions.
+const MAX_CHUNK_HEADER_LEN: usize = 4 * 1024;
+const READ_BUF_SIZE: usize = 32 * 1024;
+// Chunks are buffered whole before being handed out, so their size is bounded.
+pub const MAX_CHUNK_SIZE: usize = 16 << 20;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ChunkedDecodeError {
+    #[error("malformed chunk header")]
+    MalformedHeader,
+    #[error("malformed encoded chunk")]
+    MalformedEncoding,
+    #[error("chunk size exceeds {} bytes", MAX_CHUNK_SIZE)]
+    ChunkTooLarge,
+    #[error("unexpected end of chunked stream")]
+    UnexpectedEof,
+}
+
+impl From<ChunkedDecodeError> for std::io::Error {
+    fn from(err: ChunkedDecodeError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// Decodes an `aws-chunked` encoded payload, only yielding the chunk data.
+///
+/// Decoding stops at the zero-length chunk, so a trailer following it is
+/// never read as data. Chunk signatures are collected, not verified.
+pub struct ChunkedDecoder<R> {
+    reader: R,
+    // Raw bytes read from the reader, not yet decoded.
+    buf: Vec<u8>,
+    // Data of the current chunk, yielded from `chunk_pos`.
+    chunk: Vec<u8>,
+    chunk_pos: usize,
+    signatures: Vec<String>,
+    done: bool,
+}
+
+impl<R: AsyncRead + Unpin> ChunkedDecoder<R> {
+    pub fn new(reader: R) -> Self {
+        ChunkedDecoder {
+            reader,
+            buf: Vec::new(),
+            chunk: Vec::new(),
+            chunk_pos: 0,
+            signatures: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// Signatures of the chunks decoded so far, the zero chunk included.
+    pub fn chunk_signatures(&self) -> &[String] {
+        &self.signatures
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    // Decodes one chunk from the buffered bytes.
+    // Returns false if more bytes are needed.
+    fn decode_chunk(&mut self) -> Result<bool, ChunkedDecodeError> {
+        let chunk = match parse_chunk(&self.buf)? {
+            Some(chunk) => chunk,
+            None => return Ok(false),
+        };
+        self.signatures.extend(chunk.signature.map(str::to_owned));
+        if chunk.data.is_empty() {
+            // What follows is the trailer, if any.
+            self.buf.clear();
+            self.done = true;
+            return Ok(true);
+        }
+        self.chunk.clear();
+        self.chunk.extend_from_slice(chunk.data);
+        self.chunk_pos = 0;
+        let encoded_len = chunk.encoded_len;
+        self.buf.drain(..encoded_len);
+        Ok(true)
+    }
+}
+
+/// A chunk parsed from the front of an `aws-chunked` encoded buffer.
+pub struct Chunk<'a> {
+    pub data: &'a [u8],
+    pub signature: Option<&'a str>,
+    /// Number of bytes the chunk takes in the buffer.
+    pub encoded_len: usize,
+}
+
+/// Parses the chunk at the front of `buf`, or returns `None` if more bytes
+/// are needed. The zero-length chunk ends right after its header, so a
+/// trailer following it is left alone.
+pub fn parse_chunk(buf: &[u8]) -> Result<Option<Chunk<'_>>, ChunkedDecodeError> {
+    let header_end = match buf.windows(2).position(|w| w == b"\r\n") {
+        Some(pos) => pos,
+        None if buf.len() > MAX_CHUNK_HEADER_LEN => {
+            return Err(ChunkedDecodeError::MalformedHeader);
+        }
+        None => return Ok(None),
+    };
+    let (size, signature) = parse_chunk_header(&buf[..header_end])?;
+    if size > MAX_CHUNK_SIZE {
+        return Err(ChunkedDecodeError::ChunkTooLarge);
+    }
+
+    let data_start = header_end + 2;
+    if size == 0 {
+        return Ok(Some(Chunk {
+            data: &[],
+            signature,
+            encoded_len: data_start,
+        }));
+    }
+    let data_end = data_start
+        .checked_add(size)
+        .ok_or(ChunkedDecodeError::MalformedEncoding)?;
+    let encoded_len = data_end
+        .checked_add(2)
+        .ok_or(ChunkedDecodeError::MalformedEncoding)?;
+    if buf.len() < encoded_len {
+        return Ok(None);
+    }
+    if &buf[data_end..encoded_len] != b"\r\n" {
+        return Err(ChunkedDecodeError::MalformedEncoding);
+    }
+    Ok(Some(Chunk {
+        data: &buf[data_start..data_end],
+        signature,
+        encoded_len,
+    }))
+}
+
+// Parses a `<hex-size>[;chunk-signature=<signature>]` chunk header.
+fn parse_chunk_header(header: &[u8]) -> Result<(usize, Option<&str>), ChunkedDecodeError> {
+    let header = std::str::from_utf8(header).map_err(|_| ChunkedDecodeError::MalformedHeader)?;
+    let mut parts = header.split(';');
+    let size = parts
+        .next()
+        .filter(|size| !size.is_empty())
+        .and_then(|size| usize::from_str_radix(size, 16).ok())
+        .ok_or(ChunkedDecodeError::MalformedHeader)?;
+    let mut signature = None;
+    for ext in parts {
+        if let Some(sig) = ext.strip_prefix(CHUNK_SIGNATURE_EXT) {
+            if sig.is_empty() || !sig.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(ChunkedDecodeError::MalformedHeader);
+            }
+            signature = Some(sig);
+        }
+    }
+    Ok((size, signature))
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for ChunkedDecoder<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.chunk_pos < this.chunk.len() {
+                let n = buf.remaining().min(this.chunk.len() - this.chunk_pos);
+                buf.put_slice(&this.chunk[this.chunk_pos..this.chunk_pos + n]);
+                this.chunk_pos += n;
+                return Poll::Ready(Ok(()));
+            }
+            if this.done {
+                return Poll::Ready(Ok(()));
+            }
+            if this.decode_chunk()? {
+                continue;
+            }
+
+            let mut read_buf = [0u8; READ_BUF_SIZE];
+            let mut read_buf = ReadBuf::new(&mut read_buf);
+            ready!(Pin::new(&mut this.reader).poll_read(cx, &mut read_buf))?;
+            if read_buf.filled().is_empty() {
+                return Poll::Ready(Err(ChunkedDecodeError::UnexpectedEof.into()));
+            }
+            this.buf.extend_from_slice(read_buf.filled());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    const SIG1: &str = "ad80c730a21e5b8d04586a2213dd63b9a0e99e0e2307b0ade35a65485a288648";
+    const SIG2: &str = "0055627c9e194cb4542bae2aa5492e3c1575bbb81b612b7d234b86a503ef5497";
+    const SIG3: &str = "b6c6ea8a5354eaf15b3cb7646744f4275b71ea724fed81ceb9323e279d449df9";
+
+    fn encode_chunk(data: &[u8], signature: &str) -> Vec<u8> {
+        let mut chunk = format!("{:x};chunk-signature={}\r\n", data.len(), signature).into_bytes();
+        chunk.extend_from_slice(data);
+        chunk.extend_from_slice(b"\r\n");
+        chunk
+    }
+
+    async fn decode(payload: &[u8]) -> (std::io::Result<Vec<u8>>, Vec<String>) {
+        let mut decoder = ChunkedDecoder::new(payload);
+        let mut data = Vec::new();
+        let res = decoder.read_to_end(&mut data).await.map(|_| data);
+        (res, decoder.chunk_signatures().to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_chunked_decoder() {
+        let mut payload = encode_chunk(&[b'a'; 65536], SIG1);
+        payload.extend(encode_chunk(b"hello", SIG2));
+        payload.extend(format!("0;chunk-signature={}\r\n", SIG3).as_bytes());
+        payload.extend_from_slice(b"x-amz-checksum-crc32:sOO8/Q==\r\n\r\n");
+
+        let (data, signatures) = decode(&payload).await;
+        let data = data.unwrap();
+        assert_eq!(data.len(), 65536 + 5);
+        assert!(data.ends_with(b"ahello"));
+        assert_eq!(signatures, vec![SIG1, SIG2, SIG3]);
+    }
+
+    #[tokio::test]
+    async fn test_chunked_decoder_malformed() {
+        for payload in [
+            // Size is not hex.
+            format!("zz;chunk-signature={}\r\nhello\r\n", SIG1),
+            // Signature is not hex.
+            "5;chunk-signature=not-hex\r\nhello\r\n".to_owned(),
+            // Data longer than the size.
+            format!("3;chunk-signature={}\r\nhello\r\n", SIG1),
+        ] {
+            let (data, _) = decode(payload.as_bytes()).await;
+            let err = data.unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{}", payload);
+        }
+
+        // No zero chunk.
+        let payload = encode_chunk(b"hello", SIG1);
+        let (data, signatures) = decode(&payload).await;
+        assert_eq!(data.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(signatures, vec![SIG1]);
+    }
+
+    #[test]
+    fn test_parse_chunk_too_large() {
+        for size in [MAX_CHUNK_SIZE + 1, usize::MAX] {
+            let header = format!("{:x};chunk-signature={}\r\n", size, SIG1);
+            assert!(matches!(
+                parse_chunk(header.as_bytes()),
+                Err(ChunkedDecodeError::ChunkTooLarge)
+            ));
+        }
+        // A chunk at the limit is buffered until complete.
+        let header = format!("{:x};chunk-signature={}\r\n", MAX_CHUNK_SIZE, SIG1);
+        assert!(parse_chunk(header.as_bytes()).unwrap().is_none());
+    }
+}
@@ -1,7 +1,17 @@
+mod chunked;
+
 use anyhow::bail;
 use lazy_static::lazy_static;
 use regex::Regex;
 
+use crate::errors::ApiError;
+use crate::utils::{self, DateTimeFormatExt};
+
+pub use chunked::*;
+
+// Maximum expiry of presigned requests, 7 days.
+pub const PRESIGN_MAX_EXPIRES: u64 = 7 * 24 * 60 * 60;
+
 lazy_static! {
     static ref VALID_BUCKET_NAME: Regex =
         Regex::new(r#"^[A-Za-z0-9][A-Za-z0-9\.\-_:]{1,61}[A-Za-z0-9]$"#).unwrap();
@@ -46,3 +56,168 @@ fn check_bucket_name_common(bucket_name: &str, strict: bool) -> anyhow::Result<(
     }
     Ok(())
 }
+
+// Checks that a presigned request dated `x_amz_date` (ISO8601 long format)
+// and valid for `expires_secs` seconds can be served at `now`.
+pub fn validate_presign_expiry(
+    x_amz_date: &str,
+    expires_secs: u64,
+    now: utils::DateTime,
+) -> anyhow::Result<()> {
+    let date = utils::DateTime::parse(x_amz_date, crate::http::ISO_8601_FORMAT)
+        .map_err(|_| ApiError::MalformedPresignedDate)?;
+    if expires_secs > PRESIGN_MAX_EXPIRES {
+        bail!(ApiError::MaximumExpires);
+    }
+    if now < date {
+        bail!(ApiError::RequestNotReadyYet);
+    }
+    if now > date + utils::ChronoDuration::seconds(expires_secs as i64) {
+        bail!(ApiError::ExpiredPresignRequest);
+    }
+    Ok(())
+}
+
+// URI encodes `s` as required by AWS signature V4: every byte but the
+// unreserved characters 'A'-'Z', 'a'-'z', '0'-'9', '-', '.', '_' and '~' is
+// percent encoded with upper case hex digits, '/' only if `encode_slash`.
+pub fn uri_encode(s: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(b as char)
+            }
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+// Returns the canonical query string of the raw `query` for signature V4:
+// the decoded parameter names and values are URI encoded, including '/',
+// sorted by name then value, and joined as `name=value` pairs with '&'.
+// A parameter without value gets an empty one.
+pub fn canonical_query_string(query: &str) -> String {
+    let mut params: Vec<(String, String)> = url::form_urlencoded::parse(query.as_bytes())
+        .map(|(name, value)| (uri_encode(&name, true), uri_encode(&value, true)))
+        .collect();
+    params.sort();
+    params
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches::assert_matches;
+
+    use super::*;
+    use crate::errors::AsError;
+    use crate::utils::assert::{assert_err, assert_ok};
+
+    #[test]
+    fn test_validate_presign_expiry() {
+        let date = "20130524T000000Z";
+        let signed_at = utils::DateTime::parse(date, crate::http::ISO_8601_FORMAT).unwrap();
+        let after = |secs: i64| signed_at + utils::ChronoDuration::seconds(secs);
+
+        assert_ok!(validate_presign_expiry(date, 86400, after(0)));
+        assert_ok!(validate_presign_expiry(date, 86400, after(86400)));
+        assert_ok!(validate_presign_expiry(
+            date,
+            PRESIGN_MAX_EXPIRES,
+            after(PRESIGN_MAX_EXPIRES as i64)
+        ));
+
+        // Expired.
+        let err = assert_err!(validate_presign_expiry(date, 86400, after(86401)));
+        assert_matches!(
+            err.as_error::<ApiError>(),
+            Some(ApiError::ExpiredPresignRequest)
+        );
+
+        // Future dated.
+        let err = assert_err!(validate_presign_expiry(date, 86400, after(-1)));
+        assert_matches!(
+            err.as_error::<ApiError>(),
+            Some(ApiError::RequestNotReadyYet)
+        );
+
+        // Over the limit.
+        let err = assert_err!(validate_presign_expiry(
+            date,
+            PRESIGN_MAX_EXPIRES + 1,
+            after(0)
+        ));
+        assert_matches!(err.as_error::<ApiError>(), Some(ApiError::MaximumExpires));
+
+        // Malformed date.
+        let err = assert_err!(validate_presign_expiry(
+            "Fri, 24 May 2013 00:00:00 GMT",
+            86400,
+            after(0)
+        ));
+        assert_matches!(
+            err.as_error::<ApiError>(),
+            Some(ApiError::MalformedPresignedDate)
+        );
+    }
+
+    #[test]
+    fn test_uri_encode() {
+        let cases = [
+            ("", false, ""),
+            ("AZaz09-._~", true, "AZaz09-._~"),
+            ("photos/my file.jpg", false, "photos/my%20file.jpg"),
+            ("photos/my file.jpg", true, "photos%2Fmy%20file.jpg"),
+            ("a+b=c&d", false, "a%2Bb%3Dc%26d"),
+            ("caf\u{e9}/\u{1f600}", false, "caf%C3%A9/%F0%9F%98%80"),
+            (
+                "!*'();:@$,?#[]%",
+                false,
+                "%21%2A%27%28%29%3B%3A%40%24%2C%3F%23%5B%5D%25",
+            ),
+        ];
+        for (s, encode_slash, want) in cases {
+            assert_eq!(uri_encode(s, encode_slash), want, "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn test_canonical_query_string() {
+        let cases = [
+            ("", ""),
+            // From the AWS signature V4 documentation.
+            (
+                "Action=ListUsers&Version=2010-05-08",
+                "Action=ListUsers&Version=2010-05-08",
+            ),
+            (
+                "prefix=somePrefix&marker=someMarker&max-keys=20",
+                "marker=someMarker&max-keys=20&prefix=somePrefix",
+            ),
+            ("acl", "acl="),
+            (
+                "uploads&prefix=photos/2006",
+                "prefix=photos%2F2006&uploads=",
+            ),
+            // Sorted by name, then value.
+            ("b=2&a=3&b=1", "a=3&b=1&b=2"),
+            // '+' is a space, an encoded '+' is kept.
+            ("prefix=my+file%2Bv2", "prefix=my%20file%2Bv2"),
+            ("key%20name=caf%C3%A9", "key%20name=caf%C3%A9"),
+            (
+                "prefix=%E2%82%AC&delimiter=%2F",
+                "delimiter=%2F&prefix=%E2%82%AC",
+            ),
+            ("x-id=a%3Db%26c", "x-id=a%3Db%26c"),
+        ];
+        for (query, want) in cases {
+            assert_eq!(canonical_query_string(query), want, "{:?}", query);
+        }
+    }
+}
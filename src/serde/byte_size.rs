@@ -0,0 +1,67 @@
+//! (De)serializes a size in bytes as a human readable string like `"4MiB"`,
+//! to be used as `#[serde(with = "crate::serde::byte_size")]`.
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serializer};
+
+const UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+pub fn serialize<S: Serializer>(size: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    // Uses the largest unit the size is a whole multiple of, so it parses back exactly.
+    let mut size = *size;
+    let mut unit = 0;
+    while size != 0 && size % 1024 == 0 && unit < UNITS.len() - 1 {
+        size /= 1024;
+        unit += 1;
+    }
+    serializer.collect_str(&format_args!("{}{}", size, UNITS[unit]))
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    let size = byte_unit::Byte::from_str(&s).map_err(Error::custom)?;
+    Ok(size.get_bytes() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Config {
+        #[serde(with = "crate::serde::byte_size")]
+        size: u64,
+    }
+
+    #[test]
+    fn test_byte_size() {
+        let cases = [
+            (r#"{"size":"0B"}"#, 0),
+            (r#"{"size":"1000B"}"#, 1000),
+            (r#"{"size":"4MiB"}"#, 4194304),
+            (r#"{"size":"1536KiB"}"#, 1536 * 1024),
+            (r#"{"size":"2GiB"}"#, 2 << 30),
+        ];
+        for (json, size) in cases {
+            let cfg: Config = serde_json::from_str(json).unwrap();
+            assert_eq!(cfg, Config { size });
+            assert_eq!(serde_json::to_string(&cfg).unwrap(), json);
+        }
+
+        // Other notations are accepted as well.
+        let cfg: Config = serde_json::from_str(r#"{"size":"4 MiB"}"#).unwrap();
+        assert_eq!(cfg.size, 4194304);
+
+        for json in [
+            r#"{"size":"garbage"}"#,
+            r#"{"size":"4XiB"}"#,
+            r#"{"size":"-4MiB"}"#,
+            r#"{"size":""}"#,
+            r#"{"size":4194304}"#,
+        ] {
+            assert!(serde_json::from_str::<Config>(json).is_err(), "{}", json);
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! (De)serializes a `Duration` as a human readable string like `"15s"` or `"2h"`,
+//! to be used as `#[serde(with = "crate::serde::humantime_duration")]`.
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serializer};
+
+use crate::utils::Duration;
+
+pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&humantime::format_duration(*duration))
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    humantime::parse_duration(&s).map_err(Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Config {
+        #[serde(with = "crate::serde::humantime_duration")]
+        timeout: Duration,
+    }
+
+    #[test]
+    fn test_humantime_duration() {
+        let cases = [
+            (r#"{"timeout":"2h"}"#, Duration::from_secs(2 * 60 * 60)),
+            (r#"{"timeout":"500ms"}"#, Duration::from_millis(500)),
+        ];
+        for (json, timeout) in cases {
+            let cfg: Config = serde_json::from_str(json).unwrap();
+            assert_eq!(cfg, Config { timeout });
+            assert_eq!(serde_json::to_string(&cfg).unwrap(), json);
+        }
+
+        for json in [r#"{"timeout":"2x"}"#, r#"{"timeout":7200}"#] {
+            assert!(serde_json::from_str::<Config>(json).is_err(), "{}", json);
+        }
+    }
+}
@@ -1,2 +1,5 @@
+pub mod byte_size;
+pub mod humantime_duration;
+pub mod one_or_many;
 mod tests;
 pub mod xml;
@@ -0,0 +1,86 @@
+//! (De)serializes a collection of strings, accepting either a single string like `"*"`
+//! or an array like `["a", "b"]` when deserializing, always serialized as an array.
+//! To be used as `#[serde(with = "crate::serde::one_or_many")]`.
+
+use std::fmt;
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::Serializer;
+
+pub fn serialize<S, T>(values: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    for<'a> &'a T: IntoIterator<Item = &'a String>,
+{
+    serializer.collect_seq(values)
+}
+
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromIterator<String>,
+{
+    struct OneOrManyVisitor<T>(PhantomData<T>);
+
+    impl<'de, T: FromIterator<String>> Visitor<'de> for OneOrManyVisitor<T> {
+        type Value = T;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a string array or a string")
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(std::iter::once(v.to_owned()).collect())
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(v) = seq.next_element()? {
+                values.push(v);
+            }
+            Ok(values.into_iter().collect())
+        }
+    }
+
+    deserializer.deserialize_any(OneOrManyVisitor(PhantomData))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Config {
+        #[serde(with = "crate::serde::one_or_many")]
+        values: Vec<String>,
+    }
+
+    #[test]
+    fn test_one_or_many() {
+        let cases = [
+            (r#"{"values":"*"}"#, vec!["*"]),
+            (r#"{"values":["a","b"]}"#, vec!["a", "b"]),
+            (r#"{"values":[]}"#, vec![]),
+        ];
+        for (json, values) in cases {
+            let cfg: Config = serde_json::from_str(json).unwrap();
+            assert_eq!(cfg.values, values);
+        }
+        let cfg = Config {
+            values: vec!["*".to_owned()],
+        };
+        assert_eq!(serde_json::to_string(&cfg).unwrap(), r#"{"values":["*"]}"#);
+
+        for json in [r#"{"values":1}"#, r#"{"values":[1]}"#] {
+            assert!(serde_json::from_str::<Config>(json).is_err(), "{}", json);
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use lazy_static::lazy_static;
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+use super::{OneshotSignals, Signal, Signaller};
+use crate::config::ENV_SHUTDOWN_GRACE_PERIOD;
+use crate::utils;
+
+pub const DEFAULT_SHUTDOWN_GRACE_PERIOD: utils::Duration = utils::Duration::from_secs(30);
+
+lazy_static! {
+    /// Time in-flight requests have to complete on shutdown.
+    pub static ref SHUTDOWN_GRACE_PERIOD: utils::Duration = lookup_shutdown_grace_period()
+        .unwrap_or_else(|err| panic!("Invalid {} env var: {}", ENV_SHUTDOWN_GRACE_PERIOD, err));
+
+    // Requests being served, drained on graceful shutdown.
+    pub static ref IN_FLIGHT_REQUESTS: InFlight = InFlight::default();
+}
+
+/// Reads the shutdown grace period from the environment, an invalid duration
+/// is an error.
+pub fn lookup_shutdown_grace_period() -> anyhow::Result<utils::Duration> {
+    match std::env::var(ENV_SHUTDOWN_GRACE_PERIOD) {
+        Ok(grace) => parse_shutdown_grace_period(&grace),
+        Err(_) => Ok(DEFAULT_SHUTDOWN_GRACE_PERIOD),
+    }
+}
+
+fn parse_shutdown_grace_period(grace: &str) -> anyhow::Result<utils::Duration> {
+    humantime::parse_duration(grace)
+        .map_err(|err| anyhow::anyhow!("invalid shutdown grace period '{}': {}", grace, err))
+}
+
+// Counter of in-flight operations which can be waited on until it drops to zero.
+#[derive(Default)]
+pub struct InFlight {
+    count: AtomicUsize,
+    idle: Notify,
+}
+
+pub struct InFlightGuard<'a>(&'a InFlight);
+
+impl InFlight {
+    pub fn enter(&self) -> InFlightGuard<'_> {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard(self)
+    }
+
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    /// Waits until there is no operation in flight.
+    pub async fn wait_idle(&self) {
+        loop {
+            let idle = self.idle.notified();
+            if self.count() == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        if self.0.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
+
+impl Signaller for CancellationToken {
+    fn signal(&self, sig: Signal) {
+        if sig != Signal::Hup {
+            self.cancel();
+        }
+    }
+}
+
+/// Waits for the shutdown token to be cancelled, then for the in-flight
+/// operations to complete for at most `grace`.
+/// Returns whether all operations completed in time.
+pub async fn graceful_shutdown(
+    shutdown_token: CancellationToken,
+    in_flight: &InFlight,
+    grace: utils::Duration,
+) -> bool {
+    shutdown_token.cancelled().await;
+    let in_flight_count = in_flight.count();
+    if in_flight_count > 0 {
+        crate::info!(
+            "Waiting up to {:?} for {} in-flight requests",
+            grace,
+            in_flight_count
+        );
+    }
+    tokio::time::timeout(grace, in_flight.wait_idle())
+        .await
+        .is_ok()
+}
+
+/// Cancels the shutdown token on SIGINT, SIGTERM or SIGQUIT, so servers stop
+/// accepting new connections. The returned future resolves once the
+/// in-flight requests are drained or the grace period has elapsed, after
+/// which the remaining requests are to be aborted.
+pub fn install_graceful(
+    shutdown_token: CancellationToken,
+    grace: utils::Duration,
+) -> impl Future<Output = bool> {
+    OneshotSignals::start(shutdown_token.clone());
+    graceful_shutdown(shutdown_token, &IN_FLIGHT_REQUESTS, grace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_shutdown_grace_period() {
+        assert_eq!(
+            parse_shutdown_grace_period("10s").unwrap(),
+            utils::Duration::from_secs(10)
+        );
+        assert_eq!(
+            parse_shutdown_grace_period("1m").unwrap(),
+            utils::Duration::from_secs(60)
+        );
+        for grace in ["", "30", "thirty seconds", "-1s"] {
+            assert!(parse_shutdown_grace_period(grace).is_err(), "{}", grace);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_graceful_shutdown() {
+        let grace = utils::Duration::from_millis(200);
+        let in_flight = InFlight::default();
+        let token = CancellationToken::new();
+        token.cancel();
+
+        // Nothing in flight.
+        let start = utils::Instant::now();
+        assert!(graceful_shutdown(token.clone(), &in_flight, grace).await);
+        assert!(start.elapsed() < grace);
+
+        // A request outliving the grace period.
+        let guard = in_flight.enter();
+        let start = utils::Instant::now();
+        assert!(!graceful_shutdown(token.clone(), &in_flight, grace).await);
+        assert!(start.elapsed() >= grace);
+        drop(guard);
+        assert_eq!(in_flight.count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_graceful_shutdown_drained() {
+        lazy_static! {
+            static ref IN_FLIGHT: InFlight = InFlight::default();
+        }
+        let token = CancellationToken::new();
+        let guard = IN_FLIGHT.enter();
+        let mut shutdown = tokio::spawn(graceful_shutdown(
+            token.clone(),
+            &IN_FLIGHT,
+            utils::Duration::from_secs(10),
+        ));
+        token.cancel();
+        let pending = tokio::time::timeout(utils::Duration::from_millis(50), &mut shutdown).await;
+        assert!(pending.is_err());
+        // The shutdown completes as soon as the request does.
+        drop(guard);
+        assert!(shutdown.await.unwrap());
+    }
+}
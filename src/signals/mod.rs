@@ -1,3 +1,5 @@
+mod graceful;
 mod oneshot;
 
+pub use graceful::*;
 pub use oneshot::*;
@@ -20,6 +20,15 @@ pub struct DiskInfo {
     pub id: String,
     pub metrics: Option<DiskMetrics>,
     pub error: Option<String>,
+    // Index of the pool of the disk, negative if unknown.
+    // Kept last, DiskInfo is encoded positionally over RPC, so encodings
+    // of peers without it still decode.
+    #[serde(default = "unknown_pool_index")]
+    pub pool_index: isize,
+}
+
+fn unknown_pool_index() -> isize {
+    -1
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -116,3 +125,48 @@ pub enum VersionPurgeStatus {
     #[strum(serialize = "FAILED")]
     Failed,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disk_info_decode_without_pool_index() {
+        // DiskInfo as encoded before it had a pool index.
+        #[derive(Serialize)]
+        struct OldDiskInfo {
+            total: u64,
+            free: u64,
+            used: u64,
+            used_inodes: u64,
+            free_inodes: u64,
+            fs_type: String,
+            root_disk: bool,
+            healing: bool,
+            endpoint: String,
+            mount_path: String,
+            id: String,
+            metrics: Option<DiskMetrics>,
+            error: Option<String>,
+        }
+        let old = OldDiskInfo {
+            total: 100,
+            free: 60,
+            used: 40,
+            used_inodes: 1,
+            free_inodes: 2,
+            fs_type: "XFS".to_owned(),
+            root_disk: false,
+            healing: false,
+            endpoint: "/data1".to_owned(),
+            mount_path: "/data1".to_owned(),
+            id: "id".to_owned(),
+            metrics: None,
+            error: Some("faulty disk".to_owned()),
+        };
+        let info: DiskInfo = rmp_serde::from_read_ref(&rmp_serde::to_vec(&old).unwrap()).unwrap();
+        assert_eq!(info.total, 100);
+        assert_eq!(info.error.as_deref(), Some("faulty disk"));
+        assert_eq!(info.pool_index, -1);
+    }
+}
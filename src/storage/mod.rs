@@ -5,11 +5,11 @@ pub use datatypes::*;
 pub use heal::*;
 use tokio::io::{AsyncRead, AsyncWrite};
 
-use crate::xl_storage::XlStorage;
+use crate::xl_storage::XlStorageWithCheck;
 use crate::{bitrot, utils};
 
 pub enum StorageApi {
-    XlStorage(XlStorage),
+    XlStorage(XlStorageWithCheck),
 }
 
 impl StorageApi {
@@ -55,6 +55,12 @@ impl StorageApi {
         }
     }
 
+    pub async fn check_disk_id(&self, expected: &str) -> anyhow::Result<()> {
+        match self {
+            StorageApi::XlStorage(inner) => inner.check_disk_id(expected).await,
+        }
+    }
+
     pub fn set_disk_id(&mut self, id: String) {
         match self {
             StorageApi::XlStorage(inner) => inner.set_disk_id(id),
@@ -73,9 +79,12 @@ impl StorageApi {
         }
     }
 
-    pub async fn namespace_scanner(&self) -> anyhow::Result<()> {
+    pub async fn namespace_scanner(
+        &self,
+        progress: &crate::admin::ScanState,
+    ) -> anyhow::Result<()> {
         match self {
-            StorageApi::XlStorage(inner) => inner.namespace_scanner().await,
+            StorageApi::XlStorage(inner) => inner.namespace_scanner(progress).await,
         }
     }
 
@@ -198,6 +207,19 @@ impl StorageApi {
             StorageApi::XlStorage(inner) => inner.list_dir(volume, dir_path, count).await,
         }
     }
+    pub async fn list_path(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        delimiter: &str,
+        max_keys: usize,
+    ) -> anyhow::Result<(Vec<FileInfo>, Vec<String>)> {
+        match self {
+            StorageApi::XlStorage(inner) => {
+                inner.list_path(bucket, prefix, delimiter, max_keys).await
+            }
+        }
+    }
     pub async fn read_file(
         &self,
         volume: &str,
@@ -217,6 +239,15 @@ impl StorageApi {
             StorageApi::XlStorage(inner) => inner.append_file(volume, path, buf).await,
         }
     }
+    pub async fn create_append_writer(
+        &self,
+        volume: &str,
+        path: &str,
+    ) -> anyhow::Result<Box<dyn AsyncWrite + Unpin + Send>> {
+        match self {
+            StorageApi::XlStorage(inner) => inner.create_append_writer(volume, path).await,
+        }
+    }
     pub async fn create_file_writer(
         &self,
         volume: &str,
@@ -233,10 +264,13 @@ impl StorageApi {
         path: &str,
         offset: u64,
         size: u64,
+        deadline: Option<tokio::time::Instant>,
     ) -> anyhow::Result<Box<dyn AsyncRead + Unpin + Send>> {
         match self {
             StorageApi::XlStorage(inner) => {
-                inner.read_file_reader(volume, path, offset, size).await
+                inner
+                    .read_file_reader(volume, path, offset, size, deadline)
+                    .await
             }
         }
     }
@@ -280,9 +314,14 @@ impl StorageApi {
             StorageApi::XlStorage(inner) => inner.write_all(volume, path, data).await,
         }
     }
-    pub async fn read_all(&self, volume: &str, path: &str) -> anyhow::Result<Vec<u8>> {
+    pub async fn read_all(
+        &self,
+        volume: &str,
+        path: &str,
+        deadline: Option<tokio::time::Instant>,
+    ) -> anyhow::Result<Vec<u8>> {
         match self {
-            StorageApi::XlStorage(inner) => inner.read_all(volume, path).await,
+            StorageApi::XlStorage(inner) => inner.read_all(volume, path, deadline).await,
         }
     }
     pub async fn get_disk_location(&self) -> (isize, isize, isize) {
@@ -1,7 +1,7 @@
 use std::collections::HashSet;
 use std::fmt;
 
-use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::de::{Deserialize, Deserializer};
 use serde::ser::{Serialize, SerializeSeq, Serializer};
 
 #[derive(PartialEq, Eq, Clone, Debug)]
@@ -131,35 +131,7 @@ impl<'de> Deserialize<'de> for StringSet {
     where
         D: Deserializer<'de>,
     {
-        struct StringSetVisitor;
-        impl<'de> Visitor<'de> for StringSetVisitor {
-            type Value = StringSet;
-
-            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("a string array or a string")
-            }
-
-            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
-            where
-                E: de::Error,
-            {
-                Ok(crate::string_set!(v.to_owned()))
-            }
-
-            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
-            where
-                A: SeqAccess<'de>,
-            {
-                use serde::de::Error;
-                let mut set = StringSet::default();
-                while let Some(v) = seq.next_element()? {
-                    set.add(v);
-                }
-                Ok(set)
-            }
-        }
-
-        deserializer.deserialize_any(StringSetVisitor)
+        crate::serde::one_or_many::deserialize(deserializer)
     }
 }
 
@@ -1,6 +1,26 @@
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
-#[derive(Serialize, Deserialize)]
+// Maximum number of tags per object.
+const MAX_OBJECT_TAG_COUNT: usize = 10;
+// Maximum length of a tag key, in unicode characters.
+const MAX_TAG_KEY_LENGTH: usize = 128;
+// Maximum length of a tag value, in unicode characters.
+const MAX_TAG_VALUE_LENGTH: usize = 256;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum TagsError {
+    #[error("Tags cannot be more than {}", MAX_OBJECT_TAG_COUNT)]
+    TooManyTags,
+    #[error("The TagKey you have provided is invalid")]
+    InvalidTagKey,
+    #[error("The TagValue you have provided is invalid")]
+    InvalidTagValue,
+    #[error("Cannot provide multiple Tags with the same key")]
+    DuplicateTagKey,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct Tag {
     /// <p>Name of the object key.</p>
@@ -15,3 +35,151 @@ pub struct Tagging {
     /// <p>A collection for a set of tags</p>
     pub tag_set: Vec<Tag>,
 }
+
+/// Validated object tags, kept in the order they were given.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Tags(Vec<Tag>);
+
+impl Tags {
+    pub fn new(tags: Vec<Tag>) -> anyhow::Result<Tags> {
+        if tags.len() > MAX_OBJECT_TAG_COUNT {
+            return Err(TagsError::TooManyTags.into());
+        }
+        for (i, tag) in tags.iter().enumerate() {
+            check_tag_key(&tag.key)?;
+            check_tag_value(&tag.value)?;
+            if tags[..i].iter().any(|t| t.key == tag.key) {
+                return Err(TagsError::DuplicateTagKey.into());
+            }
+        }
+        Ok(Tags(tags))
+    }
+
+    /// Parses tags from the `x-amz-tagging` header, e.g. `k1=v1&k2=v2`.
+    pub fn parse_query(s: &str) -> anyhow::Result<Tags> {
+        let tags = url::form_urlencoded::parse(s.as_bytes())
+            .map(|(key, value)| Tag {
+                key: key.into_owned(),
+                value: value.into_owned(),
+            })
+            .collect();
+        Tags::new(tags)
+    }
+
+    /// Serializes tags in the `x-amz-tagging` header format.
+    pub fn to_query(&self) -> String {
+        url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(self.0.iter().map(|t| (&t.key, &t.value)))
+            .finish()
+    }
+
+    pub fn from_tagging(tagging: Tagging) -> anyhow::Result<Tags> {
+        Tags::new(tagging.tag_set)
+    }
+
+    pub fn to_tagging(&self) -> Tagging {
+        Tagging {
+            tag_set: self.0.clone(),
+        }
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Tag> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+// Tag keys and values may contain letters, numbers, spaces and `+ - = . _ : / @`.
+fn is_valid_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == ' ' || "+-=._:/@".contains(c)
+}
+
+fn check_tag_key(key: &str) -> anyhow::Result<()> {
+    let len = key.chars().count();
+    if len == 0 || len > MAX_TAG_KEY_LENGTH || !key.chars().all(is_valid_tag_char) {
+        return Err(TagsError::InvalidTagKey.into());
+    }
+    Ok(())
+}
+
+fn check_tag_value(value: &str) -> anyhow::Result<()> {
+    if value.chars().count() > MAX_TAG_VALUE_LENGTH || !value.chars().all(is_valid_tag_char) {
+        return Err(TagsError::InvalidTagValue.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches::assert_matches;
+
+    use super::*;
+    use crate::errors::AsError;
+    use crate::utils::assert::{assert_err, assert_ok};
+
+    #[test]
+    fn test_tags_parse_query() {
+        let tags = assert_ok!(Tags::parse_query("k2=v2&k1=v1&empty="));
+        let keys: Vec<&str> = tags.iter().map(|t| t.key.as_str()).collect();
+        assert_eq!(keys, vec!["k2", "k1", "empty"]);
+        assert_eq!(tags.to_query(), "k2=v2&k1=v1&empty=");
+        assert_eq!(Tags::parse_query(&tags.to_query()).unwrap(), tags);
+
+        // URL-encoded values.
+        let tags = assert_ok!(Tags::parse_query("project=a%20b%2Fc&path=x%3Dy%40z"));
+        assert_eq!(tags.iter().next().unwrap().value, "a b/c");
+        assert_eq!(tags.iter().nth(1).unwrap().value, "x=y@z");
+        assert_eq!(Tags::parse_query(&tags.to_query()).unwrap(), tags);
+
+        let tags = assert_ok!(Tags::parse_query(""));
+        assert!(tags.is_empty());
+    }
+
+    #[test]
+    fn test_tags_limits() {
+        let query = (0..10)
+            .map(|i| format!("k{}=v{}", i, i))
+            .collect::<Vec<_>>()
+            .join("&");
+        assert_eq!(assert_ok!(Tags::parse_query(&query)).len(), 10);
+
+        let query = query + "&k10=v10";
+        let err = assert_err!(Tags::parse_query(&query));
+        assert_matches!(err.as_error::<TagsError>(), Some(TagsError::TooManyTags));
+
+        let key = "k".repeat(MAX_TAG_KEY_LENGTH);
+        assert_ok!(Tags::parse_query(&format!("{}=v", key)));
+        let err = assert_err!(Tags::parse_query(&format!("{}k=v", key)));
+        assert_matches!(err.as_error::<TagsError>(), Some(TagsError::InvalidTagKey));
+
+        let value = "v".repeat(MAX_TAG_VALUE_LENGTH);
+        assert_ok!(Tags::parse_query(&format!("k={}", value)));
+        let err = assert_err!(Tags::parse_query(&format!("k={}v", value)));
+        assert_matches!(
+            err.as_error::<TagsError>(),
+            Some(TagsError::InvalidTagValue)
+        );
+
+        let err = assert_err!(Tags::parse_query("=v"));
+        assert_matches!(err.as_error::<TagsError>(), Some(TagsError::InvalidTagKey));
+        let err = assert_err!(Tags::parse_query("k%23=v"));
+        assert_matches!(err.as_error::<TagsError>(), Some(TagsError::InvalidTagKey));
+        let err = assert_err!(Tags::parse_query("k=v%3F"));
+        assert_matches!(
+            err.as_error::<TagsError>(),
+            Some(TagsError::InvalidTagValue)
+        );
+        let err = assert_err!(Tags::parse_query("k=v1&k=v2"));
+        assert_matches!(
+            err.as_error::<TagsError>(),
+            Some(TagsError::DuplicateTagKey)
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use rand::rngs::StdRng;
+
+use super::{rng_seed_now, Duration, Rng};
+
+/// Exponential backoff, yielding the durations to wait between retries.
+///
+/// Durations start at `initial` and are multiplied by `factor` at each step,
+/// capped at `max`. The sequence never ends, bound it with `take` if needed.
+pub struct Backoff {
+    next: Duration,
+    max: Duration,
+    factor: f64,
+    jitter: Option<(f64, f64, StdRng)>,
+}
+
+impl Backoff {
+    /// Panics if `factor` is not a finite number of at least 1.
+    pub fn new(initial: Duration, max: Duration, factor: f64) -> Backoff {
+        assert!(
+            factor >= 1.0 && factor.is_finite(),
+            "invalid backoff factor {}",
+            factor
+        );
+        Backoff {
+            next: initial.min(max),
+            max,
+            factor,
+            jitter: None,
+        }
+    }
+
+    /// Multiplies each duration by a random ratio in `[min_ratio, max_ratio]`,
+    /// still capped at `max`.
+    ///
+    /// Panics unless `0 <= min_ratio <= max_ratio`.
+    pub fn with_jitter(mut self, min_ratio: f64, max_ratio: f64) -> Backoff {
+        assert!(
+            0.0 <= min_ratio && min_ratio <= max_ratio,
+            "invalid backoff jitter ratios [{}, {}]",
+            min_ratio,
+            max_ratio
+        );
+        self.jitter = Some((min_ratio, max_ratio, rng_seed_now()));
+        self
+    }
+}
+
+impl Iterator for Backoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        let current = self.next;
+        self.next = mul_capped(current, self.factor, self.max);
+
+        Some(match &mut self.jitter {
+            Some((min_ratio, max_ratio, rng)) => {
+                let ratio = *min_ratio + (*max_ratio - *min_ratio) * rng.gen::<f64>();
+                mul_capped(current, ratio, self.max)
+            }
+            None => current,
+        })
+    }
+}
+
+// Multiplies `d` by `ratio`, capped at `max`. Compared as floats, the
+// multiplication may overflow a `Duration`.
+fn mul_capped(d: Duration, ratio: f64, max: Duration) -> Duration {
+    let secs = d.as_secs_f64() * ratio;
+    if secs < max.as_secs_f64() {
+        Duration::from_secs_f64(secs)
+    } else {
+        max
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let backoff = Backoff::new(Duration::from_millis(10), Duration::from_secs(1), 2.0);
+        let durations: Vec<_> = backoff.take(10).collect();
+        assert_eq!(durations[0], Duration::from_millis(10));
+        assert_eq!(durations[1], Duration::from_millis(20));
+        assert_eq!(durations[6], Duration::from_millis(640));
+        assert_eq!(durations[7], Duration::from_secs(1));
+        assert!(durations.iter().all(|d| *d <= Duration::from_secs(1)));
+
+        let mut backoff = Backoff::new(Duration::from_secs(5), Duration::from_secs(1), 2.0);
+        assert_eq!(backoff.next(), Some(Duration::from_secs(1)));
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::MAX, 1e30);
+        assert_eq!(backoff.nth(2), Some(Duration::MAX));
+    }
+
+    #[test]
+    fn test_backoff_jitter() {
+        let max = Duration::from_secs(10);
+        let backoff = Backoff::new(Duration::from_millis(100), max, 1.5).with_jitter(0.5, 1.5);
+        let mut base = Duration::from_millis(100);
+        for d in backoff.take(1000) {
+            assert!(d >= base.mul_f64(0.5), "{:?} {:?}", d, base);
+            assert!(d <= base.mul_f64(1.5).min(max), "{:?} {:?}", d, base);
+            base = Duration::from_secs_f64(base.as_secs_f64() * 1.5).min(max);
+        }
+
+        // Jitter above 1 must not overflow once the cap is reached.
+        let backoff =
+            Backoff::new(Duration::from_secs(1), Duration::MAX, 1e30).with_jitter(1.0, 2.0);
+        assert_eq!(backoff.skip(2).take(10).last(), Some(Duration::MAX));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid backoff factor")]
+    fn test_backoff_negative_factor() {
+        Backoff::new(Duration::from_secs(1), Duration::from_secs(10), -2.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid backoff jitter ratios")]
+    fn test_backoff_negative_jitter() {
+        Backoff::new(Duration::from_secs(1), Duration::from_secs(10), 2.0).with_jitter(-0.5, 1.5);
+    }
+}
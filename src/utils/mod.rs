@@ -5,6 +5,7 @@ pub use tokio::time::Instant;
 
 pub mod assert;
 mod atomic;
+mod backoff;
 mod datetime;
 mod guard;
 mod hash;
@@ -15,6 +16,7 @@ mod timed_value;
 mod units;
 
 pub use atomic::*;
+pub use backoff::*;
 pub use datetime::*;
 pub use guard::*;
 pub use hash::*;
@@ -40,6 +42,25 @@ pub async fn sleep(timeout: Duration, rng: Option<&mut StdRng>) {
     tokio::time::sleep(timeout.mul_f64(rand)).await;
 }
 
+// Returns `base` multiplied by a random ratio in `[min_ratio, max_ratio]`.
+fn jitter(base: Duration, min_ratio: f64, max_ratio: f64, rng: Option<&mut StdRng>) -> Duration {
+    let rand = rng.map_or_else(|| rng_seed_now().gen::<f64>(), |rng| rng.gen::<f64>());
+    base.mul_f64(min_ratio + (max_ratio - min_ratio) * rand)
+}
+
+/// Sleeps for `base` multiplied by a random ratio in `[min_ratio, max_ratio]`.
+///
+/// Unlike `sleep`, the sleep duration is bounded below, which keeps retry loops
+/// from spinning.
+pub async fn sleep_jitter(
+    base: Duration,
+    min_ratio: f64,
+    max_ratio: f64,
+    rng: Option<&mut StdRng>,
+) {
+    tokio::time::sleep(jitter(base, min_ratio, max_ratio, rng)).await;
+}
+
 pub async fn sleep_until(deadline: Instant, timeout: Duration, rng: Option<&mut StdRng>) {
     let rand = rng.map_or_else(|| rng_seed_now().gen::<f64>(), |rng| rng.gen::<f64>());
     let sleep_deadline = Instant::now() + timeout.mul_f64(rand);
@@ -75,3 +96,30 @@ pub fn parse_bool_ext(s: &str) -> anyhow::Result<bool> {
         _ => parse_bool(s),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jitter() {
+        let base = Duration::from_millis(100);
+        let mut rng = rng_seed_now();
+        for _ in 0..10000 {
+            let d = jitter(base, 0.5, 1.5, Some(&mut rng));
+            assert!(d >= Duration::from_millis(50) && d <= Duration::from_millis(150));
+        }
+        assert_eq!(jitter(base, 1.0, 1.0, None), base);
+    }
+
+    #[tokio::test]
+    async fn test_sleep_jitter() {
+        let base = Duration::from_millis(20);
+        let mut rng = rng_seed_now();
+        for _ in 0..5 {
+            let start = Instant::now();
+            sleep_jitter(base, 0.5, 1.5, Some(&mut rng)).await;
+            assert!(start.elapsed() >= Duration::from_millis(10));
+        }
+    }
+}
@@ -35,3 +35,44 @@ impl<T: AsRef<str>> StrExt for T {
         slice.iter().any(|s| s.eq_ignore_ascii_case(self.as_ref()))
     }
 }
+
+/// Returns the Levenshtein distance between `a` and `b`, i.e. the minimum
+/// number of single char insertions, deletions or substitutions turning
+/// one into the other.
+pub fn levenshtein_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // Distances between the processed prefix of `a` and every prefix of `b`.
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diag = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let subst = diag + if ca == cb { 0 } else { 1 };
+            diag = row[j + 1];
+            row[j + 1] = subst.min(row[j] + 1).min(diag + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levenshtein_distance() {
+        let cases = [
+            ("", "", 0),
+            ("", "abc", 3),
+            ("abc", "", 3),
+            ("abc", "abc", 0),
+            ("requets_max", "requests_max", 1),
+            ("kitten", "sitting", 3),
+            ("flaw", "lawn", 2),
+        ];
+        for (a, b, expected) in cases {
+            assert_eq!(levenshtein_distance(a, b), expected, "{} {}", a, b);
+            assert_eq!(levenshtein_distance(b, a), expected, "{} {}", b, a);
+        }
+    }
+}
@@ -58,4 +58,53 @@ impl<T: Clone> TimedValue<T> {
         inner.last_update = Instant::now();
         Ok(inner.value.as_ref().unwrap().clone())
     }
+
+    /// Like `get`, but returns the last good value if the update fails, along
+    /// with `true` to flag it as stale. Only errors if there is no such value.
+    ///
+    /// The next call retries the update.
+    pub async fn get_or_stale<Fut, F>(&self, update: Option<F>) -> anyhow::Result<(T, bool)>
+    where
+        Fut: Future<Output = anyhow::Result<T>>,
+        F: FnOnce() -> Fut,
+    {
+        match self.get(update).await {
+            Ok(value) => Ok((value, false)),
+            Err(err) => match &self.inner.read().await.value {
+                Some(value) => Ok((value.clone(), true)),
+                None => Err(err),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use super::*;
+    use crate::utils::assert::*;
+
+    #[tokio::test]
+    async fn test_get_or_stale() {
+        let value = TimedValue::<u32>::new(Some(Duration::from_millis(0)), None);
+
+        assert_err!(
+            value
+                .get_or_stale(Some(|| async { Err(anyhow!("failed")) }))
+                .await
+        );
+        let (v, stale) = assert_ok!(value.get_or_stale(Some(|| async { Ok(1) })).await);
+        assert_eq!((v, stale), (1, false));
+        for _ in 0..2 {
+            let (v, stale) = assert_ok!(
+                value
+                    .get_or_stale(Some(|| async { Err(anyhow!("failed")) }))
+                    .await
+            );
+            assert_eq!((v, stale), (1, true));
+        }
+        let (v, stale) = assert_ok!(value.get_or_stale(Some(|| async { Ok(2) })).await);
+        assert_eq!((v, stale), (2, false));
+    }
 }
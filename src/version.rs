@@ -1,24 +1,111 @@
+use std::fmt;
+
+use actix_web::HttpResponse;
+use serde::Serialize;
+
+const FALLBACK: &str = "Unknown (env var does not exist when building)";
+
+/// Build information of Hulk.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub edition: &'static str,
+    pub commit: &'static str,
+    pub branch: &'static str,
+    pub build_time: String,
+    pub rustc: &'static str,
+    pub features: &'static str,
+    pub profile: &'static str,
+    // Version of the MinIO compatible admin API.
+    pub go_compatible_api_version: &'static str,
+}
+
+impl BuildInfo {
+    pub fn new(build_time: Option<&str>) -> BuildInfo {
+        BuildInfo {
+            version: env!("CARGO_PKG_VERSION"),
+            edition: option_env!("HULK_EDITION").unwrap_or("Community"),
+            commit: option_env!("HULK_BUILD_GIT_HASH").unwrap_or(FALLBACK),
+            branch: option_env!("HULK_BUILD_GIT_BRANCH").unwrap_or(FALLBACK),
+            build_time: build_time.unwrap_or(FALLBACK).to_owned(),
+            rustc: option_env!("HULK_BUILD_RUSTC_VERSION").unwrap_or(FALLBACK),
+            features: option_env!("HULK_ENABLE_FEATURES")
+                .unwrap_or(FALLBACK)
+                .trim(),
+            profile: option_env!("HULK_PROFILE").unwrap_or(FALLBACK),
+            go_compatible_api_version: crate::router::ADMIN_API_VERSION,
+        }
+    }
+}
+
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "\nRelease Version:   {}\
+             \nEdition:           {}\
+             \nGit Commit Hash:   {}\
+             \nGit Commit Branch: {}\
+             \nUTC Build Time:    {}\
+             \nRust Version:      {}\
+             \nEnable Features:   {}\
+             \nProfile:           {}",
+            self.version,
+            self.edition,
+            self.commit,
+            self.branch,
+            self.build_time,
+            self.rustc,
+            self.features,
+            self.profile,
+        )
+    }
+}
+
+/// Returns the build information, with the build time recorded by the build script.
+pub fn build_info() -> BuildInfo {
+    BuildInfo::new(option_env!("HULK_BUILD_TIME"))
+}
+
 /// Returs the Hulk version information.
 pub fn hulk_version_info(build_time: Option<&str>) -> String {
-    let fallback = "Unknown (env var does not exist when building)";
-    format!(
-        "\nRelease Version:   {}\
-         \nEdition:           {}\
-         \nGit Commit Hash:   {}\
-         \nGit Commit Branch: {}\
-         \nUTC Build Time:    {}\
-         \nRust Version:      {}\
-         \nEnable Features:   {}\
-         \nProfile:           {}",
-        env!("CARGO_PKG_VERSION"),
-        option_env!("HULK_EDITION").unwrap_or("Community"),
-        option_env!("HULK_BUILD_GIT_HASH").unwrap_or(fallback),
-        option_env!("HULK_BUILD_GIT_BRANCH").unwrap_or(fallback),
-        build_time.unwrap_or(fallback),
-        option_env!("HULK_BUILD_RUSTC_VERSION").unwrap_or(fallback),
-        option_env!("HULK_ENABLE_FEATURES")
-            .unwrap_or(fallback)
-            .trim(),
-        option_env!("HULK_PROFILE").unwrap_or(fallback),
-    )
+    BuildInfo::new(build_time).to_string()
+}
+
+/// Serves the build information as JSON.
+pub async fn build_info_handler() -> HttpResponse {
+    HttpResponse::Ok().json(build_info())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+    use actix_web::{web, App};
+
+    use super::*;
+
+    #[test]
+    fn test_build_info() {
+        let info = build_info();
+        assert!(!info.version.is_empty());
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+
+        let version_info = hulk_version_info(Some("2021-08-01 00:00:00"));
+        let info = BuildInfo::new(Some("2021-08-01 00:00:00"));
+        assert_eq!(version_info, info.to_string());
+        assert!(version_info.contains(&format!("Release Version:   {}", info.version)));
+        assert!(version_info.contains("UTC Build Time:    2021-08-01 00:00:00"));
+    }
+
+    #[actix_rt::test]
+    async fn test_build_info_handler() {
+        let app = init_service(App::new().default_service(web::to(build_info_handler))).await;
+        let req = TestRequest::with_uri("/").to_request();
+        let json: serde_json::Value = read_body_json(call_service(&app, req).await).await;
+        assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(
+            json["go_compatible_api_version"],
+            crate::router::ADMIN_API_VERSION
+        );
+    }
 }
@@ -429,7 +429,8 @@ impl XlMetaV2 {
             match version.type_ {
                 VersionType::Object => {
                     if version.object_v2.as_ref().unwrap().version_id == uv {
-                        *version = version_entry.clone();
+                        *version = version_entry;
+                        return Ok(());
                     }
                 }
                 VersionType::Delete => {
@@ -437,7 +438,8 @@ impl XlMetaV2 {
                     // object data type as well, this is not S3 complaint
                     // behavior but kept here for future flexibility.
                     if version.delete_marker.as_ref().unwrap().version_id == uv {
-                        *version = version_entry.clone();
+                        *version = version_entry;
+                        return Ok(());
                     }
                 }
             }
@@ -1,5 +1,6 @@
 mod format_utils;
 mod format_v2;
+mod thresholds;
 mod types;
 mod with_check;
 use std::fs::Metadata;
@@ -10,8 +11,10 @@ pub use format_v2::*;
 use futures_util::{ready, FutureExt, StreamExt};
 use lazy_static::lazy_static;
 use path_absolutize::Absolutize;
+pub use thresholds::*;
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
 use tokio::sync::RwLock;
+use tokio::time::Instant;
 pub use types::*;
 pub use with_check::*;
 
@@ -24,7 +27,7 @@ use crate::fs::{
     err_not_found, err_permission, err_too_many_files, err_too_many_symlinks, AlignedWriter, File,
     OpenOptionsDirectIo, OpenOptionsNoAtime, OpenOptionsSync, SameFile,
 };
-use crate::globals::Guard;
+use crate::globals::Snapshot;
 use crate::io::{AsyncReadAt, AsyncReadFull};
 use crate::metacache::MetaCacheEntry;
 use crate::object::{self, path_ensure_dir, path_is_dir, path_join};
@@ -105,6 +108,9 @@ pub struct XlStorage {
     meta_cache: RwLock<Option<XlStorageMeta>>,
 
     disk_info_cache: utils::TimedValue<crate::storage::DiskInfo>,
+
+    // Whether the disk supports O_DIRECT, probed once in `new`.
+    supports_direct_io: bool,
 }
 
 struct XlStorageMeta {
@@ -125,6 +131,14 @@ impl XlStorage {
         utils::DateTime::zero()
     }
 
+    // Reads use O_DIRECT when enabled by the DMA storage class config, and
+    // supported by the disk.
+    fn read_direct_io(&self) -> bool {
+        self.supports_direct_io
+            && &globals::GLOBALS.storage_class.snapshot().dma
+                == crate::config::storageclass::DMA_READ_WRITE
+    }
+
     pub fn is_local(&self) -> bool {
         true
     }
@@ -205,7 +219,17 @@ impl XlStorage {
         }
 
         let content = fs::read_file(&format_file).await?;
-        let format: crate::format::FormatErasureV3 = serde_json::from_slice(&content)?;
+        let version = crate::format::format_erasure_version(&content)
+            .map_err(|_| StorageError::CorruptedFormat)?;
+        let format =
+            crate::format::migrate_to_v3(&content).map_err(|_| StorageError::CorruptedFormat)?;
+        let meta = if version != crate::format::FORMAT_ERASURE_VERSION_V3 {
+            // Persist the migrated format, so that it is migrated only once.
+            self.write_format(&format).await?;
+            fs::metadata(&format_file).await?
+        } else {
+            meta
+        };
         let disk_id = format.erasure.this;
 
         // Cache it anyhow.
@@ -218,6 +242,34 @@ impl XlStorage {
         Ok(disk_id)
     }
 
+    // Checks that the disk id is still `expected`, so that a disk swapped
+    // underneath the object layer is detected.
+    pub async fn check_disk_id(&self, expected: &str) -> anyhow::Result<()> {
+        let disk_id = self.get_disk_id().await?;
+        if disk_id != expected {
+            return Err(StorageError::DriveIdMismatch(expected.to_owned(), disk_id).into());
+        }
+        Ok(())
+    }
+
+    // Atomically replaces the format file with `format`.
+    async fn write_format(&self, format: &crate::format::FormatErasureV3) -> anyhow::Result<()> {
+        let tmp_path = uuid::Uuid::new_v4().to_string();
+        self.write_all(
+            object::SYSTEM_META_TMP_BUCKET,
+            &tmp_path,
+            &serde_json::to_vec(format)?,
+        )
+        .await?;
+        self.rename_file(
+            object::SYSTEM_META_TMP_BUCKET,
+            &tmp_path,
+            object::SYSTEM_META_BUCKET,
+            crate::format::FORMAT_CONFIG_FILE,
+        )
+        .await
+    }
+
     pub fn set_disk_id(&mut self, _id: String) {
         // Nothing to do.
     }
@@ -277,12 +329,22 @@ impl XlStorage {
                 endpoint: self.endpoint.to_string(),
                 mount_path: self.disk_path.to_owned(),
                 id: disk_id.unwrap_or_default(),
+                pool_index: self.pool_index,
                 metrics: None,
                 error: None,
             })
         };
 
-        self.disk_info_cache.get(Some(get_disk_info)).await
+        // A stale value is better than no value if the disk is briefly unavailable,
+        // but callers are told so through the error.
+        let (mut info, stale) = self
+            .disk_info_cache
+            .get_or_stale(Some(get_disk_info))
+            .await?;
+        if stale {
+            info.error = Some(format!("stale disk info of '{}'", self.endpoint));
+        }
+        Ok(info)
     }
 
     pub(super) async fn new(endpoint: Endpoint) -> anyhow::Result<Self> {
@@ -309,6 +371,37 @@ impl XlStorage {
             root_disk
         };
 
+        // Check if backend is writable and supports O_DIRECT, some filesystems
+        // (e.g. tmpfs, overlayfs) don't and are read and written without it.
+        use utils::Rng;
+        let rnd = utils::rng_seed_now().gen::<[u8; 8]>();
+        let tmp_file = format!(".writable-check-{}.tmp", hex::encode(rnd));
+        let tmp_file = path_join(&[path, globals::SYSTEM_RESERVED_BUCKET, &tmp_file]);
+        let (mut file, supports_direct_io) = match fs::OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            .open_direct_io(&tmp_file)
+            .await
+        {
+            Ok(file) => (file, true),
+            Err(err) if err_invalid_arg(&err) => {
+                // The file may have been created before O_DIRECT was refused.
+                let _ = fs::remove(&tmp_file).await;
+                let file = fs::OpenOptions::new()
+                    .create_new(true)
+                    .write(true)
+                    .open(&tmp_file)
+                    .await?;
+                (file, false)
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let mut aligned_buf = fs::AlignedBlock::new(4096);
+        utils::rng_seed_now().fill(&mut *aligned_buf);
+        let _ = file.write_all(aligned_buf.as_ref()).await?;
+        drop(file);
+        let _ = fs::remove(&tmp_file).await;
+
         let xl = XlStorage {
             disk_path: path.to_owned(),
             endpoint,
@@ -322,24 +415,9 @@ impl XlStorage {
             disk_index: -1,
             meta_cache: RwLock::new(None),
             disk_info_cache: utils::TimedValue::new(None, None),
+            supports_direct_io,
         };
 
-        // Check if backend is writable and supports O_DIRECT
-        use utils::Rng;
-        let rnd = utils::rng_seed_now().gen::<[u8; 8]>();
-        let tmp_file = format!(".writable-check-{}.tmp", hex::encode(rnd));
-        let tmp_file = path_join(&[&xl.disk_path, globals::SYSTEM_RESERVED_BUCKET, &tmp_file]);
-        let mut file = fs::OpenOptions::new()
-            .create_new(true)
-            .write(true)
-            .open_direct_io(&tmp_file)
-            .await?;
-        let mut aligned_buf = fs::AlignedBlock::new(4096);
-        utils::rng_seed_now().fill(&mut *aligned_buf);
-        let _ = file.write_all(aligned_buf.as_ref()).await?;
-        drop(file);
-        let _ = fs::remove(&tmp_file).await;
-
         Ok(xl)
     }
 
@@ -486,9 +564,9 @@ impl XlStorage {
 
         let dir_path = path_join(&[&volume_dir, dir_path]);
         match if count > 0 {
-            fs::read_dir_entries(dir_path).await
-        } else {
             fs::read_dir_entries_n(dir_path, count).await
+        } else {
+            fs::read_dir_entries(dir_path).await
         } {
             Err(err) => {
                 if err_not_found(&err) {
@@ -506,6 +584,82 @@ impl XlStorage {
         }
     }
 
+    /// Lists a single directory level under `prefix`, returning the objects
+    /// and the common prefixes grouped by `delimiter`, at most `max_keys` in total.
+    pub async fn list_path(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        delimiter: &str,
+        max_keys: usize,
+    ) -> anyhow::Result<(Vec<FileInfo>, Vec<String>)> {
+        if delimiter != globals::SLASH_SEPARATOR {
+            return Err(TypedError::InvalidArgument.into());
+        }
+        let volume_dir = self.get_volume_dir(bucket)?;
+
+        let (dir, filter_prefix) = match prefix.rfind(globals::SLASH_SEPARATOR) {
+            Some(idx) => prefix.split_at(idx + 1),
+            None => ("", prefix),
+        };
+        let mut entries = match self.list_dir(bucket, dir, 0).await {
+            Ok(entries) => entries,
+            Err(err) => {
+                return match err.as_error::<std::io::Error>() {
+                    Some(io_err) if err_not_found(io_err) => Ok((Vec::new(), Vec::new())),
+                    _ => Err(err),
+                };
+            }
+        };
+        entries.retain(|entry| entry.starts_with(filter_prefix));
+        entries.sort_unstable();
+
+        let mut objects = Vec::new();
+        let mut prefixes = Vec::new();
+        for entry in &entries {
+            if objects.len() + prefixes.len() >= max_keys {
+                break;
+            }
+            // Objects are always stored in directories, skip plain files.
+            let entry = match entry.strip_suffix(globals::SLASH_SEPARATOR) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            let path = path_join(&[dir, entry]);
+            match XlMetaV2::load_from_file(&path_join(&[
+                &volume_dir,
+                &path,
+                XL_STORAGE_FORMAT_FILE,
+            ]))
+            .await
+            {
+                Ok(xl_meta) => {
+                    let name = crate::object::decode_dir_object(&path);
+                    match xl_meta.to_file_info(bucket, &name, "") {
+                        Ok(fi) => objects.push(fi),
+                        // A bad object must not fail the listing of the others.
+                        Err(err) => crate::error!("skipping object '{}/{}': {}", bucket, name, err),
+                    }
+                }
+                Err(err) => {
+                    let not_found = err
+                        .as_error::<std::io::Error>()
+                        .map_or(false, |err| err_not_found(err));
+                    if !not_found {
+                        crate::error!("skipping object '{}/{}': {}", bucket, path, err);
+                    } else if !entry.ends_with(globals::GLOBAL_DIR_SUFFIX) {
+                        let path = path + globals::SLASH_SEPARATOR;
+                        if !fs::is_dir_empty(&path_join(&[&volume_dir, &path])).await {
+                            prefixes.push(path);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok((objects, prefixes))
+    }
+
     pub async fn read_version(
         &self,
         volume: &str,
@@ -515,7 +669,7 @@ impl XlStorage {
     ) -> anyhow::Result<FileInfo> {
         let volume_dir = self.get_volume_dir(volume)?;
         let buf = self
-            .read_all(volume, &path_join(&[path, XL_STORAGE_FORMAT_FILE]))
+            .read_all(volume, &path_join(&[path, XL_STORAGE_FORMAT_FILE]), None)
             .await?;
 
         if buf.is_empty() {
@@ -531,6 +685,7 @@ impl XlStorage {
         if read_data {
             if !fi.data.is_empty() || fi.size == 0 {
                 if !fi.data.is_empty() {
+                    check_inline_data(&fi)?;
                     let key = globals::RESERVED_METADATA_PREFIX_LOWER.to_owned() + "inline-data";
                     let _ = fi.metadata.entry(key).or_insert("true".to_owned());
                 }
@@ -543,11 +698,10 @@ impl XlStorage {
             // - object has maximum of 1 parts
             if fi.transition_status.is_empty()
                 && fi.data_dir.is_empty()
-                && fi.size <= SMALL_FILE_THRESHOLD as u64
+                && fi.size <= STORAGE_THRESHOLDS.small_file
                 && fi.parts.len() == 1
             {
-                let require_direct_io = &globals::GLOBALS.storage_class.guard().dma
-                    == crate::config::storageclass::DMA_READ_WRITE;
+                let require_direct_io = self.read_direct_io();
                 let part_path = format!("part.{}", fi.parts[0].number);
                 fi.data = read_all_data(
                     &volume_dir,
@@ -561,13 +715,22 @@ impl XlStorage {
         Ok(fi)
     }
 
-    pub async fn read_all(&self, volume: &str, path: &str) -> anyhow::Result<Vec<u8>> {
+    // Fails with `FaultyDisk` if the file is not read by `deadline`.
+    pub async fn read_all(
+        &self,
+        volume: &str,
+        path: &str,
+        deadline: Option<Instant>,
+    ) -> anyhow::Result<Vec<u8>> {
         let volume_dir = self.get_volume_dir(volume)?;
         let file_path = path_join(&[&volume_dir, path]);
         check_path_length(&file_path)?;
-        let require_direct_io = &globals::GLOBALS.storage_class.guard().dma
-            == crate::config::storageclass::DMA_READ_WRITE;
-        read_all_data(&volume_dir, &file_path, require_direct_io).await
+        let require_direct_io = self.read_direct_io();
+        with_deadline(
+            read_all_data(&volume_dir, &file_path, require_direct_io),
+            deadline,
+        )
+        .await
     }
 
     pub async fn delete_versions(
@@ -596,7 +759,7 @@ impl XlStorage {
             return self.delete(volume, path, false).await;
         }
         let mut buf = match self
-            .read_all(volume, &path_join(&[path, XL_STORAGE_FORMAT_FILE]))
+            .read_all(volume, &path_join(&[path, XL_STORAGE_FORMAT_FILE]), None)
             .await
         {
             Err(err) => {
@@ -695,6 +858,18 @@ impl XlStorage {
     }
 
     pub async fn append_file(&self, volume: &str, path: &str, buf: &[u8]) -> anyhow::Result<()> {
+        let mut writer = self.create_append_writer(volume, path).await?;
+        writer.write_all(buf).await?;
+        Ok(())
+    }
+
+    // Opens the file once for many appends, every write being synced before
+    // the next one is accepted, and the last one on flush.
+    pub async fn create_append_writer(
+        &self,
+        volume: &str,
+        path: &str,
+    ) -> anyhow::Result<Box<dyn AsyncWrite + Unpin + Send>> {
         let volume_dir = self.get_volume_dir(volume)?;
         if let Err(err) = fs::access(&volume_dir).await {
             return if err_not_found(&err) {
@@ -713,16 +888,18 @@ impl XlStorage {
 
         fs::reliable_mkdir_all(&volume_dir, 0o777).await?;
 
-        let mut file = fs::OpenOptions::new()
+        let file = fs::OpenOptions::new()
             .create(true)
             .append(true)
             .write(true)
             .sync()
             .open(file_path)
             .await?;
-        file.write_all(buf).await?;
 
-        Ok(())
+        Ok(Box::new(AppendWriter {
+            file,
+            flush_pending: false,
+        }))
     }
 
     pub async fn check_parts(&self, volume: &str, path: &str, fi: &FileInfo) -> anyhow::Result<()> {
@@ -893,7 +1070,7 @@ impl XlStorage {
         fi: &FileInfo,
     ) -> anyhow::Result<()> {
         let path = path_join(&[path, XL_STORAGE_FORMAT_FILE]);
-        let mut buf = match self.read_all(volume, &path).await {
+        let mut buf = match self.read_all(volume, &path, None).await {
             Ok(buf) => buf,
             Err(err) => {
                 return if err.is_error(&StorageError::FileNotFound) && !fi.version_id.is_empty() {
@@ -922,7 +1099,7 @@ impl XlStorage {
         fi: &FileInfo,
     ) -> anyhow::Result<()> {
         let path = path_join(&[path, XL_STORAGE_FORMAT_FILE]);
-        let mut buf = self.read_all(volume, &path).await?;
+        let mut buf = self.read_all(volume, &path, None).await?;
 
         let mut xl_meta = if !is_xl2_v1_format(&buf) {
             XlMetaV2::default()
@@ -1097,11 +1274,36 @@ impl XlStorage {
             }
         }
 
+        self.sync_parent_dir(&dest_file_path).await?;
+
         let _ = fs::remove(Path::new(&src_file_path).parent().unwrap()).await;
 
         Ok(())
     }
 
+    // Persists the directory entry of a renamed file if O_SYNC is requested.
+    async fn sync_parent_dir(&self, file_path: &str) -> anyhow::Result<()> {
+        if !self.global_sync {
+            return Ok(());
+        }
+        let parent_dir = match Path::new(file_path).parent() {
+            Some(parent_dir) => parent_dir,
+            None => return Ok(()),
+        };
+        if let Err(err) = fs::fsync_dir(parent_dir).await {
+            // Some filesystems do not support fsync on directories.
+            if err_invalid_arg(&err) {
+                return Ok(());
+            }
+            return if err_io(&err) {
+                Err(StorageError::FaultyDisk.into())
+            } else {
+                Err(err.into())
+            };
+        }
+        Ok(())
+    }
+
     pub async fn rename_file(
         &self,
         src_volume: &str,
@@ -1168,6 +1370,7 @@ impl XlStorage {
         }
 
         fs::reliable_rename(&src_file_path, &dest_file_path).await?;
+        self.sync_parent_dir(&dest_file_path).await?;
 
         // Remove parent dir of the src file if empty.
         if let Some(src_parent_dir) = Path::new(&src_file_path).parent() {
@@ -1237,7 +1440,8 @@ impl XlStorage {
 
         fs::reliable_mkdir_all(&volume_dir, 0o777).await?;
 
-        let writer = if file_size.is_some() && file_size.unwrap() <= SMALL_FILE_THRESHOLD as u64 {
+        let writer_kind = STORAGE_THRESHOLDS.writer_kind(file_size);
+        let writer = if writer_kind == WriterKind::Small {
             // For small files, we simply write them as O_DSYNC and not O_DIRECT
             // to avoid the complexities of aligned I/O.
             match fs::OpenOptions::new()
@@ -1251,17 +1455,16 @@ impl XlStorage {
                 Ok(file) => Ok(FileWriterEnum::Left(file)),
             }
         } else {
-            match fs::OpenOptions::new()
-                .create_new(true)
-                .write(true)
-                .open_direct_io(&file_path)
-                .await
-            {
+            let mut open_options = fs::OpenOptions::new();
+            open_options.create_new(true).write(true);
+            match if self.supports_direct_io {
+                open_options.open_direct_io(&file_path).await
+            } else {
+                open_options.open(&file_path).await
+            } {
                 Err(err) => Err(err),
                 Ok(file) => {
-                    let buf_guard = if file_size.is_some()
-                        && file_size.unwrap() >= REALLY_LARGE_FILE_THRESHOLD as u64
-                    {
+                    let buf_guard = if writer_kind == WriterKind::ReallyLarge {
                         // Really large files.
                         utils::EitherGuard::Left(XL_POOL_REALLY_LARGE.get().await?)
                     } else {
@@ -1296,20 +1499,17 @@ impl XlStorage {
         };
 
         let volume = volume.to_owned();
-        let w = FileWriter {
+        let w = FileWriter::new(
             writer,
             file_size,
-            written: 0,
-            sync: None,
-            has_err: false,
-            cleanup: async move {
+            async move {
                 // If error, cleanup system meta tmp volume dir.
                 if &volume == crate::object::SYSTEM_META_TMP_BUCKET {
                     let _ = fs::reliable_remove_all(&volume_dir).await;
                 }
             }
-            .boxed_local(),
-        };
+            .boxed(),
+        );
         Ok(Box::new(w))
     }
 
@@ -1375,24 +1575,43 @@ impl XlStorage {
         Ok(buf.len() as u64)
     }
 
+    // Reads fail with `FaultyDisk` once `deadline` has passed, so that a stuck
+    // disk cannot hang the caller.
     pub async fn read_file_reader(
         &self,
         volume: &str,
         path: &str,
         offset: u64,
         size: u64,
+        deadline: Option<Instant>,
+    ) -> anyhow::Result<Box<dyn AsyncRead + Unpin + Send>> {
+        let reader =
+            with_deadline(self.open_file_reader(volume, path, offset, size), deadline).await?;
+        Ok(match deadline {
+            Some(deadline) => Box::new(DeadlineReader {
+                reader,
+                deadline: Box::pin(tokio::time::sleep_until(deadline)),
+            }),
+            None => reader,
+        })
+    }
+
+    async fn open_file_reader(
+        &self,
+        volume: &str,
+        path: &str,
+        offset: u64,
+        size: u64,
     ) -> anyhow::Result<Box<dyn AsyncRead + Unpin + Send>> {
         let volume_dir = self.get_volume_dir(volume)?;
         let file_path = path_join(&[&volume_dir, path]);
         check_path_length(&file_path)?;
 
+        // O_DIRECT only supported if `offset` is 0.
+        let direct_io = offset == 0 && self.read_direct_io();
         let mut open_options = fs::OpenOptions::new();
         open_options.read(true).no_atime();
-        let mut file = match if offset == 0
-            && &globals::GLOBALS.storage_class.guard().dma
-                == crate::config::storageclass::DMA_READ_WRITE
-        {
-            // O_DIRECT only supported if `offset` is 0.
+        let mut file = match if direct_io {
             open_options.open_direct_io(&file_path).await
         } else {
             open_options.open(&file_path).await
@@ -1428,10 +1647,7 @@ impl XlStorage {
             return Err(StorageError::IsNotRegular.into());
         }
 
-        if offset == 0
-            && &globals::GLOBALS.storage_class.guard().dma
-                == crate::config::storageclass::DMA_READ_WRITE
-        {
+        if direct_io {
             struct PoolGuard(
                 Option<TypedPoolGuard<'static, SmallAlignedBlock>>,
                 Option<TypedPoolGuard<'static, LargeAlignedBlock>>,
@@ -1455,7 +1671,7 @@ impl XlStorage {
                 }
             }
 
-            let pool_guard = if size <= SMALL_FILE_THRESHOLD as u64 {
+            let pool_guard = if size <= STORAGE_THRESHOLDS.small_file {
                 PoolGuard(Some(XL_POOL_SMALL.get().await?), None)
             } else {
                 PoolGuard(None, Some(XL_POOL_LARGE.get().await?))
@@ -1514,12 +1730,14 @@ impl XlStorage {
                 .await
                 {
                     Ok(xl_meta) => {
-                        let xl_meta = xl_meta.dump()?; // TODO
-                        let permit = tx.reserve().await?;
-                        permit.send(MetaCacheEntry::new(
-                            opts.base_dir.clone(),
-                            Arc::new(xl_meta),
-                        ));
+                        if walk_dir_matches(&opts, &opts.base_dir, &xl_meta) {
+                            let xl_meta = xl_meta.dump()?; // TODO
+                            let permit = tx.reserve().await?;
+                            permit.send(MetaCacheEntry::new(
+                                opts.base_dir.clone(),
+                                Arc::new(xl_meta),
+                            ));
+                        }
                     }
                     Err(_) => {
                         match fs::metadata(&path_join(&[
@@ -1540,7 +1758,10 @@ impl XlStorage {
                 }
             }
 
-            let forward = &opts.forward_to as &str;
+            let forward = opts
+                .forward_to
+                .strip_prefix(&opts.base_dir as &str)
+                .unwrap_or("");
             let cur_dir = Cow::Borrowed(&opts.base_dir as &str);
 
             self.walk_dir_inner(&opts, &volume_dir, &tx, forward, cur_dir)
@@ -1562,6 +1783,12 @@ impl XlStorage {
     ) -> Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send + Sync + 'a>>
 where {
         Box::pin(async move {
+            // Entries of this directory only compare with the first segment
+            // of the path to forward to.
+            let forward = match forward.find(globals::SLASH_SEPARATOR) {
+                Some(idx) if idx > 0 => &forward[..idx],
+                _ => forward,
+            };
             let mut entries = match self.list_dir(&opts.bucket, cur_dir.as_ref(), 0).await {
                 Err(err) => {
                     if opts.report_not_found && cur_dir.as_ref() == &opts.base_dir {
@@ -1575,10 +1802,13 @@ where {
             };
             let mut dir_objects = HashSet::new();
             for entry in entries.iter_mut() {
+                // Do not retain the entries filtered out.
                 if !opts.filter_prefix.is_empty() && !entry.starts_with(&opts.filter_prefix) {
+                    entry.clear();
                     continue;
                 }
                 if !forward.is_empty() && (entry as &str) < forward {
+                    entry.clear();
                     continue;
                 }
                 if entry.ends_with(globals::SLASH_SEPARATOR) {
@@ -1596,12 +1826,13 @@ where {
                     entry.remove(entry.len() - 1); // remove slash suffix
                     continue;
                 }
-                entry.clear();
+                // Do not retain the file, but keep its name for the check below.
+                let entry = std::mem::take(entry);
                 if entry.ends_with(XL_STORAGE_FORMAT_FILE) {
                     match XlMetaV2::load_from_file(&path_join(&[
                         volume_dir,
                         cur_dir.as_ref(),
-                        entry,
+                        &entry,
                     ]))
                     .await
                     {
@@ -1609,14 +1840,15 @@ where {
                             continue;
                         }
                         Ok(xl_meta) => {
-                            let xl_meta = xl_meta.dump()?; // TODO
-                            let name = entry
-                                .strip_suffix(XL_STORAGE_FORMAT_FILE)
-                                .unwrap()
-                                .strip_suffix(globals::SLASH_SEPARATOR)
-                                .unwrap();
+                            // If root was an object return it as such.
+                            let name = entry.strip_suffix(XL_STORAGE_FORMAT_FILE).unwrap();
+                            let name = name.strip_suffix(globals::SLASH_SEPARATOR).unwrap_or(name);
                             let name = path_join(&[cur_dir.as_ref(), name]);
                             let name = crate::object::decode_dir_object(&name);
+                            if !walk_dir_matches(opts, &name, &xl_meta) {
+                                return Ok(());
+                            }
+                            let xl_meta = xl_meta.dump()?; // TODO
                             let permit = tx.reserve().await?;
                             permit.send(MetaCacheEntry::new(name.into_owned(), Arc::new(xl_meta)));
                             return Ok(());
@@ -1651,10 +1883,7 @@ where {
 
                 let is_dir_obj = dir_objects.contains(&(entry as &str));
                 if is_dir_obj {
-                    name.replace_range(
-                        name.len() - 1..name.len(),
-                        globals::GLOBAL_DIR_SUFFIX_WITH_SLASH,
-                    );
+                    name = object::encode_dir_object(&name).into_owned() + globals::SLASH_SEPARATOR;
                 }
 
                 match XlMetaV2::load_from_file(&path_join(&[
@@ -1665,14 +1894,13 @@ where {
                 .await
                 {
                     Ok(xl_meta) => {
-                        let xl_meta = xl_meta.dump()?; // TODO
                         if is_dir_obj {
-                            name.replace_range(
-                                name.rfind(globals::GLOBAL_DIR_SUFFIX_WITH_SLASH).unwrap()
-                                    ..globals::GLOBAL_DIR_SUFFIX_WITH_SLASH.len(),
-                                globals::SLASH_SEPARATOR,
-                            );
+                            name = object::decode_dir_object(&name).into_owned();
+                        }
+                        if !walk_dir_matches(opts, &name, &xl_meta) {
+                            continue;
                         }
+                        let xl_meta = xl_meta.dump()?; // TODO
                         let permit = tx.reserve().await?;
                         permit.send(MetaCacheEntry::new(name, Arc::new(xl_meta)));
                     }
@@ -1718,7 +1946,11 @@ where {
         })
     }
 
-    pub async fn namespace_scanner(&self) -> anyhow::Result<()> {
+    // Scans the namespace of the disk, reporting to `progress`.
+    pub async fn namespace_scanner(
+        &self,
+        progress: &crate::admin::ScanState,
+    ) -> anyhow::Result<()> {
         todo!()
     }
 
@@ -1730,6 +1962,60 @@ where {
     }
 }
 
+// Reports whether a walked object passes the size and modification time
+// filters of `opts`. Objects whose metadata cannot be resolved are skipped.
+fn walk_dir_matches(
+    opts: &crate::metacache::WalkDirOptions,
+    name: &str,
+    xl_meta: &XlMetaV2,
+) -> bool {
+    if !opts.has_object_filters() {
+        return true;
+    }
+    match xl_meta.to_file_info(&opts.bucket, name, "") {
+        Ok(fi) => opts.matches_object(&fi),
+        Err(_) => false,
+    }
+}
+
+// Rejects inline data whose length does not match the bitrot protected
+// shard of the object, so a corrupted xl.meta cannot claim a huge blob.
+fn check_inline_data(fi: &FileInfo) -> anyhow::Result<()> {
+    let want_size = match &fi.erasure {
+        Some(erasure) if fi.size > 0 => {
+            // Inline data is a single part, hashed with the algorithm recorded for it.
+            let part_number = fi.parts.first().map_or(1, |part| part.number);
+            let checksum_info = erasure
+                .get_checksum_info(part_number)
+                .ok_or(StorageError::FileCorrupt)?;
+            crate::bitrot::bitrot_shard_file_size(
+                erasure.shard_file_size(fi.size),
+                erasure.shard_size(),
+                checksum_info.algorithm,
+            )
+        }
+        _ => fi.size,
+    };
+    if fi.data.len() as u64 != want_size {
+        return Err(StorageError::FileCorrupt.into());
+    }
+    Ok(())
+}
+
+// Runs `fut`, failing with `FaultyDisk` if it is not done by `deadline`.
+async fn with_deadline<T>(
+    fut: impl std::future::Future<Output = anyhow::Result<T>>,
+    deadline: Option<Instant>,
+) -> anyhow::Result<T> {
+    match deadline {
+        Some(deadline) => tokio::select! {
+            res = fut => res,
+            _ = tokio::time::sleep_until(deadline) => Err(StorageError::FaultyDisk.into()),
+        },
+        None => fut.await,
+    }
+}
+
 async fn read_all_data(
     volume_dir: &str,
     file_path: &str,
@@ -1834,7 +2120,7 @@ pub async fn get_valid_path(path: &str) -> anyhow::Result<Cow<'_, Path>> {
     Ok(path)
 }
 
-#[pin_project::pin_project]
+#[pin_project::pin_project(PinnedDrop)]
 struct FileWriter<G: utils::BufGuardMut + 'static> {
     #[pin]
     writer: FileWriterEnum<G>,
@@ -1842,7 +2128,42 @@ struct FileWriter<G: utils::BufGuardMut + 'static> {
     written: u64,
     sync: Option<futures_util::future::LocalBoxFuture<'static, std::io::Result<()>>>,
     has_err: bool,
-    cleanup: futures_util::future::LocalBoxFuture<'static, ()>,
+    // Taken once it has been driven to completion or handed off on drop.
+    cleanup: Option<futures_util::future::BoxFuture<'static, ()>>,
+}
+
+impl<G: utils::BufGuardMut + 'static> FileWriter<G> {
+    fn new(
+        writer: FileWriterEnum<G>,
+        file_size: Option<u64>,
+        cleanup: futures_util::future::BoxFuture<'static, ()>,
+    ) -> FileWriter<G> {
+        FileWriter {
+            writer,
+            file_size,
+            written: 0,
+            sync: None,
+            has_err: false,
+            cleanup: Some(cleanup),
+        }
+    }
+}
+
+#[pin_project::pinned_drop]
+impl<G: utils::BufGuardMut + 'static> PinnedDrop for FileWriter<G> {
+    fn drop(self: Pin<&mut Self>) {
+        let this = self.project();
+        if !*this.has_err {
+            return;
+        }
+        // The writer was dropped after an error without being flushed,
+        // so finish the cleanup in the background.
+        if let Some(cleanup) = this.cleanup.take() {
+            if let Ok(handle) = tokio::runtime::Handle::try_current() {
+                handle.spawn(cleanup);
+            }
+        }
+    }
 }
 
 #[pin_project::pin_project(project = FileWriterEnumProj)]
@@ -1880,6 +2201,9 @@ impl<G: utils::BufGuardMut + 'static> AsyncWrite for FileWriter<G> {
         let this = self.project();
 
         if let Some(file_size) = *this.file_size {
+            if *this.written != file_size {
+                *this.has_err = true;
+            }
             if *this.written < file_size {
                 return Poll::Ready(Err(std::io::Error::new(
                     ErrorKind::Other,
@@ -1913,7 +2237,10 @@ impl<G: utils::BufGuardMut + 'static> AsyncWrite for FileWriter<G> {
         }
 
         if *this.has_err {
-            ready!(this.cleanup.poll_unpin(cx));
+            if let Some(cleanup) = this.cleanup {
+                ready!(cleanup.poll_unpin(cx));
+                *this.cleanup = None;
+            }
         }
 
         Poll::Ready(Ok(()))
@@ -1923,3 +2250,718 @@ impl<G: utils::BufGuardMut + 'static> AsyncWrite for FileWriter<G> {
         self.poll_flush(cx)
     }
 }
+
+// Fails reads with `FaultyDisk` once the deadline has passed.
+struct DeadlineReader {
+    reader: Box<dyn AsyncRead + Unpin + Send>,
+    deadline: Pin<Box<tokio::time::Sleep>>,
+}
+
+impl AsyncRead for DeadlineReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        if let Poll::Ready(res) = Pin::new(&mut self.reader).poll_read(cx, buf) {
+            return Poll::Ready(res);
+        }
+        ready!(self.deadline.poll_unpin(cx));
+        Poll::Ready(Err(std::io::Error::new(
+            ErrorKind::Other,
+            StorageError::FaultyDisk,
+        )))
+    }
+}
+
+// Appends to a file opened with O_SYNC, flushing every write: the file
+// buffers writes in the background otherwise.
+struct AppendWriter {
+    file: File,
+    // Whether the flush of the last write could not complete yet.
+    flush_pending: bool,
+}
+
+impl AsyncWrite for AppendWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        // A write is only accepted once the previous one is flushed.
+        if self.flush_pending {
+            ready!(Pin::new(&mut self.file).poll_flush(cx))?;
+            self.flush_pending = false;
+        }
+        let n = ready!(Pin::new(&mut self.file).poll_write(cx, buf))?;
+        // The bytes are written, so `n` is returned even if the flush cannot
+        // complete now. It is then completed before the next write.
+        match Pin::new(&mut self.file).poll_flush(cx) {
+            Poll::Ready(res) => res?,
+            Poll::Pending => self.flush_pending = true,
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        ready!(Pin::new(&mut self.file).poll_flush(cx))?;
+        self.flush_pending = false;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.file).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches::assert_matches;
+
+    use super::*;
+    use crate::bitrot::BitrotAlgorithm;
+    use crate::metacache::{paginate, WalkDirOptions};
+    use crate::utils::assert::{assert_err, assert_ok};
+    use crate::utils::ChronoDuration;
+
+    fn new_xl_meta(size: u64, mod_time: utils::DateTime) -> XlMetaV2 {
+        let fi = FileInfo {
+            data_dir: uuid::Uuid::new_v4().to_string(),
+            mod_time,
+            size,
+            erasure: Some(ErasureInfo {
+                algorithm: ErasureAlgo::ReedSolomon.to_string(),
+                data_blocks: 4,
+                parity_blocks: 2,
+                block_size: 10000,
+                index: 1,
+                distribution: vec![1, 2, 3, 4, 5, 6],
+                checksums: vec![ChecksumInfo {
+                    part_number: 1,
+                    algorithm: BitrotAlgorithm::HighwayHash256,
+                    hash: Vec::new(),
+                }],
+            }),
+            ..Default::default()
+        };
+        let mut xl_meta = XlMetaV2::default();
+        xl_meta.add_version(&fi).unwrap();
+        xl_meta
+    }
+
+    fn new_test_storage(disk_path: &str) -> XlStorage {
+        XlStorage {
+            disk_path: disk_path.to_owned(),
+            endpoint: Endpoint::new(disk_path).unwrap(),
+            global_sync: false,
+            root_disk: false,
+            pool_index: -1,
+            set_index: -1,
+            disk_index: -1,
+            meta_cache: RwLock::new(None),
+            disk_info_cache: utils::TimedValue::new(None, None),
+            supports_direct_io: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_walk_dir_inner_xl_meta_only() {
+        let tmp_dir = tempfile::tempdir_in(".").unwrap();
+        let disk_path = tmp_dir.path().to_str().unwrap();
+        let object_dir = path_join(&[disk_path, "bucket", "dir", "object"]);
+        std::fs::create_dir_all(&object_dir).unwrap();
+        let xl_meta = new_xl_meta(10, utils::now()).dump().unwrap();
+        std::fs::write(path_join(&[&object_dir, XL_STORAGE_FORMAT_FILE]), xl_meta).unwrap();
+
+        let xl = new_test_storage(disk_path);
+        let opts = WalkDirOptions {
+            bucket: "bucket".to_owned(),
+            base_dir: "dir/object/".to_owned(),
+            ..Default::default()
+        };
+        let volume_dir = xl.get_volume_dir(&opts.bucket).unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::channel(10);
+        xl.walk_dir_inner(
+            &opts,
+            &volume_dir,
+            &tx,
+            "",
+            Cow::Borrowed(opts.base_dir.as_str()),
+        )
+        .await
+        .unwrap();
+        drop(tx);
+
+        let entry = rx.recv().await.unwrap();
+        assert_eq!(entry.name, "dir/object");
+        assert!(!entry.metadata.is_empty());
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_walk_dir_continuation() {
+        let tmp_dir = tempfile::tempdir_in(".").unwrap();
+        let disk_path = tmp_dir.path().to_str().unwrap();
+        for i in 0..50 {
+            let object_dir = path_join(&[disk_path, "bucket", &format!("object-{:02}", i)]);
+            std::fs::create_dir_all(&object_dir).unwrap();
+            let xl_meta = new_xl_meta(10, utils::now()).dump().unwrap();
+            std::fs::write(path_join(&[&object_dir, XL_STORAGE_FORMAT_FILE]), xl_meta).unwrap();
+        }
+        let xl = new_test_storage(disk_path);
+        let volume_dir = xl.get_volume_dir("bucket").unwrap();
+
+        let list_page = |token: Option<String>| {
+            let xl = &xl;
+            let volume_dir = &volume_dir;
+            async move {
+                let mut opts = WalkDirOptions {
+                    bucket: "bucket".to_owned(),
+                    ..Default::default()
+                };
+                let after = match token {
+                    Some(token) => opts.resume_from(&token).unwrap(),
+                    None => "".to_owned(),
+                };
+                let (tx, mut rx) = tokio::sync::mpsc::channel(100);
+                xl.walk_dir_inner(&opts, volume_dir, &tx, &opts.forward_to, Cow::Borrowed(""))
+                    .await
+                    .unwrap();
+                drop(tx);
+                let mut entries = Vec::new();
+                while let Some(entry) = rx.recv().await {
+                    entries.push(entry);
+                }
+                paginate(entries, &after, 10)
+            }
+        };
+
+        let mut names = Vec::new();
+        let mut token = None;
+        for i in 0..5 {
+            let page = list_page(token).await;
+            assert_eq!(page.entries.len(), 10);
+            names.extend(page.entries.into_iter().map(|entry| entry.name));
+            token = page.next_continuation_token;
+            assert_eq!(token.is_some(), i < 4);
+        }
+        let expected: Vec<_> = (0..50).map(|i| format!("object-{:02}", i)).collect();
+        assert_eq!(names, expected);
+
+        // The token key was deleted in between, resume at the next greater key.
+        let page = list_page(None).await;
+        let token = page.next_continuation_token.unwrap();
+        std::fs::remove_dir_all(path_join(&[disk_path, "bucket", "object-09"])).unwrap();
+        let page = list_page(Some(token)).await;
+        assert_eq!(page.entries[0].name, "object-10");
+        assert_eq!(page.entries.len(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_walk_dir_continuation_nested() {
+        let tmp_dir = tempfile::tempdir_in(".").unwrap();
+        let disk_path = tmp_dir.path().to_str().unwrap();
+        let mut expected = Vec::new();
+        for dir in &["dir-a", "dir-b"] {
+            for i in 0..10 {
+                let name = format!("{}/object-{:02}", dir, i);
+                let object_dir = path_join(&[disk_path, "bucket", &name]);
+                std::fs::create_dir_all(&object_dir).unwrap();
+                let xl_meta = new_xl_meta(10, utils::now()).dump().unwrap();
+                std::fs::write(path_join(&[&object_dir, XL_STORAGE_FORMAT_FILE]), xl_meta).unwrap();
+                expected.push(name);
+            }
+        }
+        let xl = new_test_storage(disk_path);
+        let volume_dir = xl.get_volume_dir("bucket").unwrap();
+
+        let mut names = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut opts = WalkDirOptions {
+                bucket: "bucket".to_owned(),
+                recursive: true,
+                ..Default::default()
+            };
+            let after = match &token {
+                Some(token) => opts.resume_from(token).unwrap(),
+                None => "".to_owned(),
+            };
+            let (tx, mut rx) = tokio::sync::mpsc::channel(100);
+            xl.walk_dir_inner(&opts, &volume_dir, &tx, &opts.forward_to, Cow::Borrowed(""))
+                .await
+                .unwrap();
+            drop(tx);
+            let mut entries = Vec::new();
+            while let Some(entry) = rx.recv().await {
+                // Skip the directory entries.
+                if !entry.metadata.is_empty() {
+                    entries.push(entry);
+                }
+            }
+            let page = paginate(entries, &after, 3);
+            names.extend(page.entries.into_iter().map(|entry| entry.name));
+            token = page.next_continuation_token;
+            if token.is_none() {
+                break;
+            }
+        }
+        assert_eq!(names, expected);
+    }
+
+    #[tokio::test]
+    async fn test_list_path_bad_object() {
+        let tmp_dir = tempfile::tempdir_in(".").unwrap();
+        let disk_path = tmp_dir.path().to_str().unwrap();
+        let objects = [
+            ("a", new_xl_meta(10, utils::now()).dump().unwrap()),
+            // No version.
+            ("b", XlMetaV2::default().dump().unwrap()),
+            ("c", new_xl_meta(10, utils::now()).dump().unwrap()),
+            ("d", b"corrupt".to_vec()),
+        ];
+        for (object, xl_meta) in &objects {
+            let object_dir = path_join(&[disk_path, "bucket", object]);
+            std::fs::create_dir_all(&object_dir).unwrap();
+            std::fs::write(path_join(&[&object_dir, XL_STORAGE_FORMAT_FILE]), xl_meta).unwrap();
+        }
+        let xl = new_test_storage(disk_path);
+
+        let (objects, prefixes) = xl.list_path("bucket", "", "/", 1000).await.unwrap();
+        let names: Vec<_> = objects.into_iter().map(|fi| fi.name).collect();
+        assert_eq!(names, vec!["a", "c"]);
+        assert!(prefixes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_path_delimiter() {
+        let tmp_dir = tempfile::tempdir_in(".").unwrap();
+        let disk_path = tmp_dir.path().to_str().unwrap();
+        for object in &[
+            "readme",
+            "photos/a.jpg",
+            "photos/2021/x.jpg",
+            "photos/2022/y.jpg",
+        ] {
+            let object_dir = path_join(&[disk_path, "bucket", object]);
+            std::fs::create_dir_all(&object_dir).unwrap();
+            let xl_meta = new_xl_meta(10, utils::now()).dump().unwrap();
+            std::fs::write(path_join(&[&object_dir, XL_STORAGE_FORMAT_FILE]), xl_meta).unwrap();
+        }
+        let xl = new_test_storage(disk_path);
+
+        let names = |objects: Vec<FileInfo>| -> Vec<String> {
+            objects.into_iter().map(|fi| fi.name).collect()
+        };
+
+        let (objects, prefixes) = xl.list_path("bucket", "", "/", 1000).await.unwrap();
+        assert_eq!(names(objects), vec!["readme"]);
+        assert_eq!(prefixes, vec!["photos/"]);
+
+        let (objects, prefixes) = xl.list_path("bucket", "photos/", "/", 1000).await.unwrap();
+        assert_eq!(names(objects), vec!["photos/a.jpg"]);
+        assert_eq!(prefixes, vec!["photos/2021/", "photos/2022/"]);
+
+        let (objects, prefixes) = xl.list_path("bucket", "photos/20", "/", 1).await.unwrap();
+        assert!(objects.is_empty());
+        assert_eq!(prefixes, vec!["photos/2021/"]);
+
+        let (objects, prefixes) = xl.list_path("bucket", "missing/", "/", 1000).await.unwrap();
+        assert!(objects.is_empty() && prefixes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_walk_dir_modified_before() {
+        let tmp_dir = tempfile::tempdir_in(".").unwrap();
+        let disk_path = tmp_dir.path().to_str().unwrap();
+        let now = utils::now();
+        let old = now - ChronoDuration::days(10);
+        for (name, mod_time) in [
+            ("photos/old", old),
+            ("photos/new", now),
+            ("videos/old", old),
+        ] {
+            let object_dir = path_join(&[disk_path, "bucket", name]);
+            std::fs::create_dir_all(&object_dir).unwrap();
+            let xl_meta = new_xl_meta(10, mod_time).dump().unwrap();
+            std::fs::write(path_join(&[&object_dir, XL_STORAGE_FORMAT_FILE]), xl_meta).unwrap();
+        }
+        let xl = new_test_storage(disk_path);
+        let opts = WalkDirOptions {
+            bucket: "bucket".to_owned(),
+            base_dir: "photos/".to_owned(),
+            modified_before: Some(now - ChronoDuration::days(1)),
+            ..Default::default()
+        };
+        let volume_dir = xl.get_volume_dir(&opts.bucket).unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(10);
+        xl.walk_dir_inner(
+            &opts,
+            &volume_dir,
+            &tx,
+            "",
+            Cow::Borrowed(opts.base_dir.as_str()),
+        )
+        .await
+        .unwrap();
+        drop(tx);
+        let mut walked = Vec::new();
+        while let Some(entry) = rx.recv().await {
+            walked.push(entry.name);
+        }
+        assert_eq!(walked, vec!["photos/old"]);
+    }
+
+    #[test]
+    fn test_walk_dir_matches_min_size() {
+        let now = utils::now();
+        let opts = WalkDirOptions {
+            min_size: Some(100),
+            ..Default::default()
+        };
+        assert!(!walk_dir_matches(&opts, "small", &new_xl_meta(10, now)));
+        assert!(walk_dir_matches(&opts, "large", &new_xl_meta(100, now)));
+
+        let opts = WalkDirOptions::default();
+        assert!(walk_dir_matches(&opts, "small", &new_xl_meta(10, now)));
+    }
+
+    #[tokio::test]
+    async fn test_file_writer_cleanup_on_drop() {
+        let tmp_dir = tempfile::tempdir_in(".").unwrap();
+        let volume_dir = path_join(&[tmp_dir.path().to_str().unwrap(), "tmp"]);
+        let file_path = path_join(&[&volume_dir, "part.1"]);
+        std::fs::create_dir_all(&volume_dir).unwrap();
+        std::fs::write(&file_path, b"").unwrap();
+
+        // Writing to a file opened read-only fails.
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .open(&file_path)
+            .await
+            .unwrap();
+        let cleanup_dir = volume_dir.clone();
+        let mut w = FileWriter::<TypedPoolGuard<'static, SmallAlignedBlock>>::new(
+            FileWriterEnum::Left(file),
+            Some(5),
+            async move {
+                let _ = fs::reliable_remove_all(&cleanup_dir).await;
+            }
+            .boxed(),
+        );
+        assert!(w.write(b"hello").await.is_err());
+        drop(w);
+
+        for _ in 0..100 {
+            if std::fs::metadata(&volume_dir).is_err() {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("temp volume dir {} was not removed", volume_dir);
+    }
+
+    #[tokio::test]
+    async fn test_create_file_writer_large_file_size() {
+        let tmp_dir = tempfile::tempdir_in(".").unwrap();
+        let disk_path = tmp_dir.path().to_str().unwrap();
+        let xl = new_test_storage(disk_path);
+
+        let size = SMALL_FILE_THRESHOLD + 123;
+        assert_eq!(
+            STORAGE_THRESHOLDS.writer_kind(Some(size as u64)),
+            WriterKind::Large
+        );
+        let data: Vec<u8> = (0..size).map(|i| i as u8).collect();
+        for (path, file_size) in [("sized", Some(size as u64)), ("unsized", None)] {
+            let mut w = xl
+                .create_file_writer("bucket", path, file_size)
+                .await
+                .unwrap();
+            w.write_all(&data).await.unwrap();
+            w.flush().await.unwrap();
+            drop(w);
+
+            let file_path = path_join(&[disk_path, "bucket", path]);
+            assert_eq!(
+                std::fs::metadata(&file_path).unwrap().len(),
+                size as u64,
+                "{}",
+                path
+            );
+            assert_eq!(std::fs::read(&file_path).unwrap(), data, "{}", path);
+        }
+    }
+
+    // Restores the global storage class config on drop.
+    struct StorageClassGuard(Arc<crate::config::storageclass::Config>);
+
+    impl StorageClassGuard {
+        fn set(config: crate::config::storageclass::Config) -> StorageClassGuard {
+            let guard = StorageClassGuard(globals::GLOBALS.storage_class.load_full());
+            globals::GLOBALS.storage_class.update(config);
+            guard
+        }
+    }
+
+    impl Drop for StorageClassGuard {
+        fn drop(&mut self) {
+            globals::GLOBALS.storage_class.store(self.0.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_without_direct_io_support() {
+        // tmpfs may not support O_DIRECT, reads must then skip it instead of
+        // failing with `UnsupportedDisk`.
+        let tmp_dir = match tempfile::tempdir_in("/dev/shm") {
+            Ok(tmp_dir) => tmp_dir,
+            Err(err) => {
+                eprintln!("skipped, /dev/shm is unavailable: {}", err);
+                return;
+            }
+        };
+        let disk_path = tmp_dir.path().to_str().unwrap();
+        std::fs::create_dir_all(path_join(&[disk_path, globals::SYSTEM_RESERVED_BUCKET])).unwrap();
+        std::fs::create_dir_all(path_join(&[disk_path, "bucket"])).unwrap();
+        let data: Vec<u8> = (0..10000).map(|i| i as u8).collect();
+        std::fs::write(path_join(&[disk_path, "bucket", "object"]), &data).unwrap();
+
+        // Direct I/O is only attempted when enabled for reads.
+        let _storage_class = StorageClassGuard::set(crate::config::storageclass::Config {
+            dma: crate::config::storageclass::DMA_READ_WRITE.to_owned(),
+            ..Default::default()
+        });
+
+        let xl = XlStorage::new(Endpoint::new(disk_path).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(xl.read_direct_io(), xl.supports_direct_io);
+        let mut unsupported = new_test_storage(disk_path);
+        unsupported.supports_direct_io = false;
+        assert!(!unsupported.read_direct_io());
+
+        for xl in [&xl, &unsupported] {
+            assert_eq!(xl.read_all("bucket", "object", None).await.unwrap(), data);
+            let mut r = xl
+                .read_file_reader("bucket", "object", 0, data.len() as u64, None)
+                .await
+                .unwrap();
+            let mut buf = Vec::new();
+            r.read_to_end(&mut buf).await.unwrap();
+            assert_eq!(buf, data);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_append_writer() {
+        let tmp_dir = tempfile::tempdir_in(".").unwrap();
+        let disk_path = tmp_dir.path().to_str().unwrap();
+        std::fs::create_dir_all(path_join(&[disk_path, "bucket"])).unwrap();
+        let xl = new_test_storage(disk_path);
+
+        let mut w = xl.create_append_writer("bucket", "log").await.unwrap();
+        let mut want = Vec::new();
+        for i in 0..100 {
+            let record = format!("record-{}\n", i);
+            w.write_all(record.as_bytes()).await.unwrap();
+            want.extend_from_slice(record.as_bytes());
+        }
+        w.flush().await.unwrap();
+        drop(w);
+        assert_eq!(xl.read_all("bucket", "log", None).await.unwrap(), want);
+
+        // Appends after the records of the previous writer.
+        xl.append_file("bucket", "log", b"last\n").await.unwrap();
+        want.extend_from_slice(b"last\n");
+        assert_eq!(xl.read_all("bucket", "log", None).await.unwrap(), want);
+
+        assert_matches!(
+            xl.create_append_writer("missing", "log")
+                .await
+                .err()
+                .unwrap()
+                .as_error::<StorageError>(),
+            Some(StorageError::VolumeNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_disk_id() {
+        let tmp_dir = tempfile::tempdir_in(".").unwrap();
+        let disk_path = tmp_dir.path().to_str().unwrap();
+        let xl = new_test_storage(disk_path);
+        assert_matches!(
+            xl.check_disk_id("disk-1")
+                .await
+                .unwrap_err()
+                .as_error::<StorageError>(),
+            Some(StorageError::UnformattedDisk)
+        );
+
+        let meta_dir = path_join(&[disk_path, object::SYSTEM_META_BUCKET]);
+        std::fs::create_dir_all(&meta_dir).unwrap();
+        let format = r#"{
+            "version": "1",
+            "format": "xl",
+            "id": "deployment",
+            "xl": {
+                "version": "3",
+                "this": "disk-1",
+                "sets": [["disk-1", "disk-2"]],
+                "distributionAlgo": "SIPMOD+PARITY"
+            }
+        }"#;
+        std::fs::write(
+            path_join(&[&meta_dir, crate::format::FORMAT_CONFIG_FILE]),
+            format,
+        )
+        .unwrap();
+
+        assert_ok!(xl.check_disk_id("disk-1").await);
+        let err = assert_err!(xl.check_disk_id("disk-2").await);
+        assert_eq!(
+            err.as_error::<StorageError>(),
+            Some(&StorageError::DriveIdMismatch(
+                "disk-2".to_owned(),
+                "disk-1".to_owned()
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_disk_info_metrics() {
+        let tmp_dir = tempfile::tempdir_in(".").unwrap();
+        let disk_path = tmp_dir.path().to_str().unwrap();
+        std::fs::create_dir_all(path_join(&[disk_path, "bucket"])).unwrap();
+        std::fs::write(path_join(&[disk_path, "bucket", "object"]), b"hello").unwrap();
+        let xl = XlStorageWithCheck::new(new_test_storage(disk_path));
+
+        for _ in 0..3 {
+            xl.read_all("bucket", "object", None).await.unwrap();
+        }
+        assert!(xl.read_all("bucket", "missing", None).await.is_err());
+        xl.read_file_reader("bucket", "object", 0, 5, None)
+            .await
+            .unwrap();
+
+        let metrics = xl.disk_info().await.unwrap().metrics.unwrap();
+        assert_eq!(metrics.api_calls["ReadAll"], 4);
+        assert_eq!(metrics.api_calls["ReadFileStream"], 1);
+        assert_eq!(metrics.api_calls["CreateFile"], 0);
+        assert_ne!(
+            metrics.api_latencies["ReadAll"],
+            format!("{:?}", utils::Duration::from_nanos(0))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_deadline() {
+        let tmp_dir = tempfile::tempdir_in(".").unwrap();
+        let disk_path = tmp_dir.path().to_str().unwrap();
+        std::fs::create_dir_all(path_join(&[disk_path, "bucket"])).unwrap();
+        std::fs::write(path_join(&[disk_path, "bucket", "object"]), b"hello").unwrap();
+        let xl = new_test_storage(disk_path);
+
+        let deadline = Some(Instant::now() + std::time::Duration::from_secs(10));
+        assert_eq!(
+            xl.read_all("bucket", "object", deadline).await.unwrap(),
+            b"hello"
+        );
+        let mut r = xl
+            .read_file_reader("bucket", "object", 0, 5, deadline)
+            .await
+            .unwrap();
+        let mut buf = Vec::new();
+        r.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"hello");
+
+        // A stuck read.
+        let deadline = Some(Instant::now() + std::time::Duration::from_millis(50));
+        let err = assert_err!(
+            with_deadline(std::future::pending::<anyhow::Result<()>>(), deadline).await
+        );
+        assert_matches!(
+            err.as_error::<StorageError>(),
+            Some(StorageError::FaultyDisk)
+        );
+
+        // A reader that never completes, its writer being kept open.
+        let (reader, _writer) = tokio::io::duplex(64);
+        let mut r = DeadlineReader {
+            reader: Box::new(reader),
+            deadline: Box::pin(tokio::time::sleep_until(deadline.unwrap())),
+        };
+        let err = r.read(&mut [0u8; 16]).await.unwrap_err();
+        assert_eq!(
+            err.into_inner().unwrap().downcast_ref::<StorageError>(),
+            Some(&StorageError::FaultyDisk)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rename_file_global_sync() {
+        let tmp_dir = tempfile::tempdir_in(".").unwrap();
+        let disk_path = tmp_dir.path().to_str().unwrap();
+        std::fs::create_dir_all(path_join(&[disk_path, "src-vol", "dir"])).unwrap();
+        std::fs::create_dir_all(path_join(&[disk_path, "dest-vol"])).unwrap();
+        std::fs::write(path_join(&[disk_path, "src-vol", "dir", "file"]), b"hello").unwrap();
+
+        let mut xl = new_test_storage(disk_path);
+        xl.global_sync = true;
+        xl.rename_file("src-vol", "dir/file", "dest-vol", "dir/file")
+            .await
+            .unwrap();
+
+        let data = std::fs::read(path_join(&[disk_path, "dest-vol", "dir", "file"])).unwrap();
+        assert_eq!(data, b"hello");
+    }
+
+    #[test]
+    fn test_check_inline_data() {
+        let mut fi = FileInfo {
+            size: 10,
+            data: vec![0; 10],
+            ..Default::default()
+        };
+        assert_ok!(check_inline_data(&fi));
+
+        // 10 bytes over 4 data blocks is a 3 bytes shard plus its checksum.
+        let xl_meta = new_xl_meta(10, utils::now());
+        let mut erasure = xl_meta
+            .to_file_info("bucket", "object", "")
+            .unwrap()
+            .erasure
+            .unwrap();
+        fi.data = vec![0; 32 + 3];
+        // The part has no recorded checksum.
+        fi.erasure = Some(erasure.clone());
+        let err = assert_err!(check_inline_data(&fi));
+        assert_matches!(
+            err.as_error::<StorageError>(),
+            Some(StorageError::FileCorrupt)
+        );
+        erasure.add_checksum_info(ChecksumInfo {
+            part_number: 1,
+            algorithm: BitrotAlgorithm::HighwayHash256,
+            hash: Vec::new(),
+        });
+        fi.erasure = Some(erasure);
+        assert_ok!(check_inline_data(&fi));
+
+        fi.data = vec![0; 1 << 20];
+        let err = assert_err!(check_inline_data(&fi));
+        assert_matches!(
+            err.as_error::<StorageError>(),
+            Some(StorageError::FileCorrupt)
+        );
+
+        fi.erasure = None;
+        let err = assert_err!(check_inline_data(&fi));
+        assert_matches!(
+            err.as_error::<StorageError>(),
+            Some(StorageError::FileCorrupt)
+        );
+    }
+}
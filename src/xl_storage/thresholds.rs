@@ -0,0 +1,134 @@
+use anyhow::ensure;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use super::{REALLY_LARGE_FILE_THRESHOLD, SMALL_FILE_THRESHOLD};
+use crate::config::{ENV_STORAGE_REALLY_LARGE_FILE_THRESHOLD, ENV_STORAGE_SMALL_FILE_THRESHOLD};
+
+// Thresholds select aligned I/O paths, so they must be multiples of this.
+const THRESHOLD_ALIGNMENT: u64 = 4096;
+
+lazy_static! {
+    pub(super) static ref STORAGE_THRESHOLDS: StorageThresholds = StorageThresholds::from_env()
+        .unwrap_or_else(|err| {
+            crate::error!("invalid storage thresholds, using defaults: {}", err);
+            StorageThresholds::default()
+        });
+}
+
+/// File size thresholds which select the read/write strategy of a disk.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct StorageThresholds {
+    /// Files up to this size are written with O_DSYNC instead of O_DIRECT,
+    /// and read inline with their metadata.
+    #[serde(with = "crate::serde::byte_size")]
+    pub small_file: u64,
+    /// Files of at least this size use the really large write buffers.
+    #[serde(with = "crate::serde::byte_size")]
+    pub really_large_file: u64,
+}
+
+impl Default for StorageThresholds {
+    fn default() -> Self {
+        StorageThresholds {
+            small_file: SMALL_FILE_THRESHOLD as u64,
+            really_large_file: REALLY_LARGE_FILE_THRESHOLD as u64,
+        }
+    }
+}
+
+impl StorageThresholds {
+    pub fn from_env() -> anyhow::Result<Self> {
+        Self::lookup(|key| std::env::var(key).ok())
+    }
+
+    /// Reads the thresholds with `get`, falling back to the defaults for unset keys.
+    pub fn lookup<F: Fn(&str) -> Option<String>>(get: F) -> anyhow::Result<Self> {
+        let defaults = Self::default();
+        let parse = |key: &str, default: u64| -> anyhow::Result<u64> {
+            let size = match get(key) {
+                Some(size) => byte_unit::Byte::from_str(&size)?.get_bytes() as u64,
+                None => return Ok(default),
+            };
+            ensure!(
+                size > 0 && size % THRESHOLD_ALIGNMENT == 0,
+                "{} must be a positive multiple of {}",
+                key,
+                THRESHOLD_ALIGNMENT
+            );
+            Ok(size)
+        };
+        let thresholds = StorageThresholds {
+            small_file: parse(ENV_STORAGE_SMALL_FILE_THRESHOLD, defaults.small_file)?,
+            really_large_file: parse(
+                ENV_STORAGE_REALLY_LARGE_FILE_THRESHOLD,
+                defaults.really_large_file,
+            )?,
+        };
+        ensure!(
+            thresholds.small_file < thresholds.really_large_file,
+            "{} must be smaller than {}",
+            ENV_STORAGE_SMALL_FILE_THRESHOLD,
+            ENV_STORAGE_REALLY_LARGE_FILE_THRESHOLD
+        );
+        Ok(thresholds)
+    }
+
+    pub(super) fn writer_kind(&self, file_size: Option<u64>) -> WriterKind {
+        match file_size {
+            Some(size) if size <= self.small_file => WriterKind::Small,
+            Some(size) if size >= self.really_large_file => WriterKind::ReallyLarge,
+            _ => WriterKind::Large,
+        }
+    }
+}
+
+/// Writer strategy selected by `create_file_writer`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum WriterKind {
+    /// O_DSYNC writes.
+    Small,
+    /// Aligned O_DIRECT writes with large buffers.
+    Large,
+    /// Aligned O_DIRECT writes with really large buffers.
+    ReallyLarge,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn lookup(vars: &[(&str, &str)]) -> anyhow::Result<StorageThresholds> {
+        let vars: HashMap<_, _> = vars.iter().cloned().collect();
+        StorageThresholds::lookup(|key| vars.get(key).map(|v| v.to_string()))
+    }
+
+    #[test]
+    fn test_storage_thresholds_lookup() {
+        assert_eq!(lookup(&[]).unwrap(), StorageThresholds::default());
+
+        let thresholds = lookup(&[(ENV_STORAGE_SMALL_FILE_THRESHOLD, "64KiB")]).unwrap();
+        assert_eq!(thresholds.small_file, 64 * 1024);
+
+        assert!(lookup(&[(ENV_STORAGE_SMALL_FILE_THRESHOLD, "1000")]).is_err());
+        assert!(lookup(&[(ENV_STORAGE_SMALL_FILE_THRESHOLD, "0")]).is_err());
+        assert!(lookup(&[(ENV_STORAGE_SMALL_FILE_THRESHOLD, "abc")]).is_err());
+        assert!(lookup(&[(ENV_STORAGE_SMALL_FILE_THRESHOLD, "128MiB")]).is_err());
+    }
+
+    #[test]
+    fn test_storage_thresholds_writer_kind() {
+        let size = Some(256 * 1024);
+        let defaults = StorageThresholds::default();
+        assert_eq!(defaults.writer_kind(size), WriterKind::Large);
+        assert_eq!(defaults.writer_kind(None), WriterKind::Large);
+
+        let thresholds = lookup(&[(ENV_STORAGE_SMALL_FILE_THRESHOLD, "512KiB")]).unwrap();
+        assert_eq!(thresholds.writer_kind(size), WriterKind::Small);
+
+        let thresholds = lookup(&[(ENV_STORAGE_REALLY_LARGE_FILE_THRESHOLD, "256KiB")]).unwrap();
+        assert_eq!(thresholds.writer_kind(size), WriterKind::ReallyLarge);
+    }
+}
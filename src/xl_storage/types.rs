@@ -60,7 +60,9 @@ impl ErasureInfo {
     }
 
     pub fn shard_file_size(&self, total_length: u64) -> u64 {
-        assert!(total_length > 0);
+        if total_length == 0 {
+            return 0;
+        }
         let shards_num = total_length / self.block_size;
         let last_block_size = total_length % self.block_size;
         let last_shard_size = utils::ceil_frac(last_block_size as u64, self.data_blocks as u64);
@@ -71,3 +73,71 @@ impl ErasureInfo {
         utils::ceil_frac(self.block_size as u64, self.data_blocks as u64)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_erasure_info(data_blocks: usize, block_size: u64) -> ErasureInfo {
+        ErasureInfo {
+            algorithm: "ReedSolomon".to_owned(),
+            data_blocks,
+            parity_blocks: data_blocks,
+            block_size,
+            index: 1,
+            distribution: vec![],
+            checksums: vec![],
+        }
+    }
+
+    #[test]
+    fn test_erasure_info_shard_size() {
+        // Exact multiple of the data blocks.
+        assert_eq!(new_erasure_info(4, 1024 * 1024).shard_size(), 256 * 1024);
+        // Remainder is rounded up.
+        assert_eq!(new_erasure_info(3, 1024 * 1024).shard_size(), 349526);
+        assert_eq!(new_erasure_info(5, 10).shard_size(), 2);
+        assert_eq!(new_erasure_info(6, 10).shard_size(), 2);
+    }
+
+    #[test]
+    fn test_erasure_info_shard_file_size() {
+        let block_size = 1024 * 1024;
+        let erasure = new_erasure_info(4, block_size);
+        let shard_size = erasure.shard_size();
+        let cases = [
+            (0, 0),
+            (1, 1),
+            (4, 1),
+            (5, 2),
+            // Exact multiples of the block size.
+            (block_size, shard_size),
+            (3 * block_size, 3 * shard_size),
+            // Remainder blocks.
+            (block_size + 1, shard_size + 1),
+            (block_size + 5, shard_size + 2),
+            (2 * block_size + 4096, 2 * shard_size + 1024),
+        ];
+        for &(total_length, want) in &cases {
+            assert_eq!(
+                erasure.shard_file_size(total_length),
+                want,
+                "total length {}",
+                total_length
+            );
+        }
+
+        // Sizes with the bitrot hash of each block, as checked by `bitrot_verify`.
+        let algo = bitrot::DEFAULT_BITROT_ALGORITHM;
+        let hash_size = algo.output_size() as u64;
+        let with_bitrot = |total_length| {
+            bitrot::bitrot_shard_file_size(erasure.shard_file_size(total_length), shard_size, algo)
+        };
+        assert_eq!(with_bitrot(5), 2 + hash_size);
+        assert_eq!(with_bitrot(block_size), shard_size + hash_size);
+        assert_eq!(
+            with_bitrot(2 * block_size + 4096),
+            2 * shard_size + 1024 + 3 * hash_size
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use std::ops::{Deref, DerefMut};
 use std::sync::atomic::AtomicU64;
 use std::sync::{Arc, RwLock};
 
@@ -38,7 +39,9 @@ enum StorageMetric {
     ReadAll,
 }
 
-pub(super) struct XlStorageWithCheck {
+/// Local storage recording the number and latency of its calls, which are
+/// reported with its disk info. Calls not recorded go to the storage itself.
+pub struct XlStorageWithCheck {
     storage: XlStorage,
     disk_id: Option<String>,
     api_latencies:
@@ -113,4 +116,72 @@ impl XlStorageWithCheck {
             }
         };
     }
+
+    pub async fn disk_info(&self) -> anyhow::Result<crate::storage::DiskInfo> {
+        let mut info = self.storage.disk_info().await?;
+        // Metrics are not cached with the disk info, they are cheap to get.
+        info.metrics = Some(self.get_metrics());
+        Ok(info)
+    }
+
+    pub async fn read_all(
+        &self,
+        volume: &str,
+        path: &str,
+        deadline: Option<Instant>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let paths = [volume, path];
+        let done = self.update_metrics(StorageMetric::ReadAll, &paths);
+        let res = self.storage.read_all(volume, path, deadline).await;
+        done();
+        res
+    }
+
+    pub async fn create_file_writer(
+        &self,
+        volume: &str,
+        path: &str,
+        file_size: Option<u64>,
+    ) -> anyhow::Result<Box<dyn AsyncWrite + Unpin>> {
+        let paths = [volume, path];
+        let done = self.update_metrics(StorageMetric::CreateFile, &paths);
+        let res = self
+            .storage
+            .create_file_writer(volume, path, file_size)
+            .await;
+        done();
+        res
+    }
+
+    pub async fn read_file_reader(
+        &self,
+        volume: &str,
+        path: &str,
+        offset: u64,
+        size: u64,
+        deadline: Option<Instant>,
+    ) -> anyhow::Result<Box<dyn AsyncRead + Unpin + Send>> {
+        let paths = [volume, path];
+        let done = self.update_metrics(StorageMetric::ReadFileStream, &paths);
+        let res = self
+            .storage
+            .read_file_reader(volume, path, offset, size, deadline)
+            .await;
+        done();
+        res
+    }
+}
+
+impl Deref for XlStorageWithCheck {
+    type Target = XlStorage;
+
+    fn deref(&self) -> &Self::Target {
+        &self.storage
+    }
+}
+
+impl DerefMut for XlStorageWithCheck {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.storage
+    }
 }